@@ -0,0 +1,33 @@
+use crate::error::AppError;
+use crate::middleware::authentication::api_key_configured;
+use crate::service::chat::chat_completion::ChatCompletionService;
+use crate::utils::config::get_config;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use serde_json::json;
+
+/// 诊断输出中替换密钥的占位符
+pub const REDACTED: &str = "***";
+
+/// 列出进行中的生成：生成ID、模型、已用时间（毫秒）与已生成的token数
+#[get("/generations")]
+pub async fn list_generations(service: web::Data<ChatCompletionService>) -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "object": "list",
+        "data": service.generations().list()
+    }))
+}
+
+/// 返回当前生效的应用配置（含默认值），便于确认运行时配置
+///
+/// 密钥不会原样返回：`auth.api_key` 在设置了API key时为 [`REDACTED`]，否则为 `null`
+#[get("/config")]
+pub async fn effective_config(req: HttpRequest) -> Result<HttpResponse, AppError> {
+    let mut config = serde_json::to_value(get_config())?;
+    config["auth"]["api_key"] = json!(api_key_configured(&req).then_some(REDACTED));
+    Ok(HttpResponse::Ok().json(config))
+}
+
+/// 管理接口不单独鉴权，由应用级的 `Authentication` 中间件要求API key
+pub fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_generations).service(effective_config);
+}
@@ -1,31 +1,12 @@
-use crate::entities::models::{DeepseekCoderModel, YiCoderModel};
+use crate::error::AppError;
+use crate::service::chat::chat_completion::{validate_n, ChatCompletionParams};
 use crate::service::chat::ChatService;
 use crate::service::models::ModelManager;
+use crate::utils::config::get_config;
 use actix_web::{post, web, HttpResponse, ResponseError};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::fmt;
-pub trait ModelResponseGenerator {
-    fn generate_response(
-        self,
-        input: &str,
-    ) -> impl std::future::Future<Output = anyhow::Result<String>> + Send;
-}
-
-impl ModelResponseGenerator for YiCoderModel {
-    async fn generate_response(self, input: &str) -> anyhow::Result<String> {
-        // TODO: Implement actual response generation
-        Ok(format!("Yi-Coder response for: {}", input))
-    }
-}
-
-impl ModelResponseGenerator for DeepseekCoderModel {
-    async fn generate_response(self, input: &str) -> anyhow::Result<String> {
-        // TODO: Implement actual response generation
-        Ok(format!("Deepseek-Coder response for: {}", input))
-    }
-}
-
 pub mod error {
     use super::*;
     #[derive(Debug)]
@@ -82,6 +63,7 @@ use error::ChatError;
 pub struct ChatRequest {
     pub model: String,
     pub messages: Vec<ChatMessage>,
+    pub n: Option<usize>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -95,39 +77,38 @@ pub async fn chat_completions(
     manager: web::Data<ModelManager>,
     _chat_service: web::Data<ChatService>,
     req: web::Json<ChatRequest>,
-) -> Result<HttpResponse, ChatError> {
+) -> Result<HttpResponse, actix_web::Error> {
+    validate_n(req.n, get_config().chat.max_n)?;
+
+    if manager.is_download_pending(&req.model) {
+        return Err(AppError::ModelDownloading(req.model.clone()).into());
+    }
+
     // Check if model is cached and enabled
     let model_status = manager.get_model_status(&req.model).await;
     if let Some(status) = model_status {
         if !status.is_cached || !status.is_enabled {
-            return Err(ChatError::ModelNotAvailable);
+            return Err(ChatError::ModelNotAvailable.into());
         }
     } else {
-        return Err(ChatError::ModelNotFound);
+        return Err(ChatError::ModelNotFound.into());
     }
 
     // Get and use the appropriate model based on the request
-    let response = match req.model.as_str() {
-        "yi-coder" => manager
-            .get_yi_coder()
-            .await
-            .ok_or(ChatError::ModelNotAvailable)?
-            .generate_response(&req.messages[0].content)
+    let model = manager.get_model(&req.model).await.ok_or(ChatError::ModelNotAvailable)?;
+    let mut responses = Vec::new();
+    for _ in 0..req.n.unwrap_or(1) {
+        let output = model
+            .generate(&req.messages[0].content, &ChatCompletionParams::default())
             .await
-            .map_err(|e| ChatError::OutputProcessingFailed(e.to_string()))?,
-        "deepseek-coder" => manager
-            .get_deepseek_coder()
-            .await
-            .ok_or(ChatError::ModelNotAvailable)?
-            .generate_response(&req.messages[0].content)
-            .await
-            .map_err(|e| ChatError::OutputProcessingFailed(e.to_string()))?,
-        _ => return Err(ChatError::ModelNotFound),
-    };
+            .map_err(|e| ChatError::OutputProcessingFailed(e.to_string()))?;
+        responses.push(output.text);
+    }
 
     Ok(HttpResponse::Ok().json(json!({
         "model": req.model,
-        "response": response
+        "response": responses[0],
+        "responses": responses
     })))
 }
 
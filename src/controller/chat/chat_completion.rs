@@ -1,30 +1,137 @@
+use crate::controller::chat::sse::event_stream;
+use crate::controller::chat::webhook::deliver;
+use crate::controller::json::{deserialize_bounded_vec, Validated};
 use crate::entities::chat_completion_message::ChatCompletionMessage;
-use crate::service::chat::chat_completion::{ChatCompletionParams, ChatCompletionService};
+use crate::entities::object;
+use crate::error::AppError;
+use crate::middleware::authentication::has_valid_api_key;
+use crate::middleware::compression::EVENT_STREAM;
+use crate::middleware::logging::RequestId;
+use crate::service::chat::chat_completion::{
+    ChatCompletionParams, ChatCompletionService, Completion, StreamToken,
+};
+use crate::service::chat::generation::GenerationHandle;
+use crate::service::metrics::{metrics, TOKEN_COUNT_BUCKETS};
+use crate::service::models::json_schema::ResponseFormat;
+use crate::service::models::overrides::ModelOverrides;
+use crate::service::models::sampling::SamplingConfig;
+use crate::service::models::scheduler::Priority;
+use crate::service::models::{GenerationOutput, TruncationStrategy};
 use crate::utils::config::get_config;
-use actix_web::{web, HttpResponse};
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use crate::utils::device::resolve_request_device;
+use actix_web::http::header::{self, TryIntoHeaderPair};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use chrono::Utc;
+use reqwest::Url;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::json;
+use tokio::sync::{mpsc, oneshot};
 use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ChatCompletionRequest {
     pub model: String,
+    /// 数量超过 `chat.max_messages` 时在解析过程中拒绝
+    #[serde(deserialize_with = "deserialize_messages")]
     pub messages: Vec<ChatCompletionMessage>,
     pub temperature: Option<f32>,
+    /// 每生成一个token temperature降低的幅度
+    pub temperature_decay: Option<f32>,
     pub top_p: Option<f32>,
+    pub top_k: Option<usize>,
+    pub presence_penalty: Option<f32>,
+    pub frequency_penalty: Option<f32>,
     pub n: Option<usize>,
+    pub min_tokens: Option<usize>,
     pub max_tokens: Option<usize>,
+    /// OpenAI新版的 `max_tokens` 字段名，两者同时设置时优先使用
+    pub max_completion_tokens: Option<usize>,
+    pub no_repeat_ngram_size: Option<usize>,
     pub stream: Option<bool>,
+    pub seed: Option<u64>,
+    pub truncation: Option<TruncationStrategy>,
+    pub response_format: Option<ResponseFormat>,
+    /// 调度优先级: low | normal | high
+    pub priority: Option<Priority>,
+    /// 服务端会话ID，设置时拼接该会话的历史消息并保存本轮对话
+    pub conversation_id: Option<String>,
+    /// 为 `true` 时回复以提示词开头
+    pub echo: Option<bool>,
+    /// 设置时将生成的token推送到该地址而不是返回SSE，地址须在 `chat.webhooks` 允许列表中
+    pub webhook_url: Option<String>,
+}
+
+fn deserialize_messages<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<ChatCompletionMessage>, D::Error> {
+    deserialize_bounded_vec(deserializer, get_config().chat.max_messages)
 }
 
 #[derive(Debug, Serialize)]
 pub struct ChatCompletionResponse {
     pub id: String,
-    pub object: String,
-    pub created: DateTime<Utc>,
+    /// 精简响应 (`X-Lean-Response: true`) 时省略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object: Option<String>,
+    /// 创建时间，Unix时间戳（秒），精简响应时省略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<i64>,
     pub model: String,
+    /// 生成结果所用模型配置与权重的指纹，同一个已加载的模型保持不变
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_fingerprint: Option<String>,
     pub choices: Vec<Choice>,
-    pub usage: Usage,
+    /// 精简响应时省略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    /// 生成因 `X-Max-Duration-Ms` 超时而返回部分结果时为 `true`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub x_timeout: bool,
+    /// 生成被 `POST /v1/chat/completions/{id}/cancel` 取消而返回部分结果时为 `true`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub x_cancelled: bool,
+    /// 请求的模型不可用、改用备用模型时为备用模型ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x_fallback_model: Option<String>,
+    /// 请求的 `max_tokens` 超过模型的 `max_output_tokens` 被降低时为实际使用的上限
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x_max_tokens: Option<usize>,
+    /// 请求头 `X-Return-Params: true` 时为实际使用的采样参数（填充默认值并按上限降低后）
+    ///
+    /// 只有 `max_tokens` 会被降低，其他参数超出范围时请求直接被拒绝
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x_params: Option<SamplingConfig>,
+}
+
+impl ChatCompletionResponse {
+    /// 由补全结果构造响应，`lean` 为 `true` 时省略 `object`、`created` 和 `usage`
+    pub(crate) fn from_completion(completion: Completion, created: i64, lean: bool) -> Self {
+        let Completion { outputs, model, fallback_model } = completion;
+        Self {
+            id: Uuid::new_v4().to_string(),
+            object: (!lean).then(|| object::CHAT_COMPLETION.to_string()),
+            created: (!lean).then_some(created),
+            model,
+            usage: (!lean).then(|| Usage::from_outputs(&outputs)),
+            x_timeout: outputs.iter().any(|output| output.timed_out),
+            x_cancelled: outputs.iter().any(|output| output.cancelled),
+            x_fallback_model: fallback_model,
+            x_max_tokens: outputs.iter().find_map(|output| output.clamped_max_tokens),
+            x_params: None,
+            system_fingerprint: outputs.iter().find_map(|output| output.system_fingerprint.clone()),
+            choices: outputs
+                .into_iter()
+                .map(|output| Choice {
+                    message: ChatCompletionMessage {
+                        role: "assistant".to_string(),
+                        content: output.text,
+                        name: None,
+                    },
+                    finish_reason: output.finish_reason.to_string(),
+                })
+                .collect(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -40,8 +147,69 @@ pub struct Usage {
     pub total_tokens: usize,
 }
 
-pub async fn chat_completion(req: web::Json<ChatCompletionRequest>) -> HttpResponse {
-    let request_id = Uuid::new_v4();
+impl Usage {
+    /// 汇总所有choice的token用量，提示词只计算一次
+    pub(crate) fn from_outputs(outputs: &[GenerationOutput]) -> Self {
+        let prompt_tokens = outputs.first().map(|output| output.prompt_tokens).unwrap_or(0);
+        let completion_tokens = outputs.iter().map(GenerationOutput::completion_tokens).sum();
+        Self { prompt_tokens, completion_tokens, total_tokens: prompt_tokens + completion_tokens }
+    }
+
+    /// 将本次请求的token数记入提示词与生成token数的直方图
+    pub(crate) fn record_metrics(&self) {
+        metrics().observe_histogram(
+            PROMPT_TOKENS_HISTOGRAM,
+            TOKEN_COUNT_BUCKETS,
+            self.prompt_tokens as f64,
+        );
+        metrics().observe_histogram(
+            COMPLETION_TOKENS_HISTOGRAM,
+            TOKEN_COUNT_BUCKETS,
+            self.completion_tokens as f64,
+        );
+    }
+}
+
+/// 指标：每个请求的提示词token数
+pub const PROMPT_TOKENS_HISTOGRAM: &str = "chat_completion_prompt_tokens";
+/// 指标：每个请求生成的token数（所有choice之和）
+pub const COMPLETION_TOKENS_HISTOGRAM: &str = "chat_completion_completion_tokens";
+
+/// 请求头：单个请求允许的最长生成时间（毫秒）
+pub const MAX_DURATION_HEADER: &str = "X-Max-Duration-Ms";
+
+/// 请求头：只对本次请求生效的模型参数覆盖（JSON），需要有效的API key
+///
+/// 可覆盖的字段见 [`ModelOverrides`]
+pub const MODEL_OVERRIDES_HEADER: &str = "X-Model-Overrides";
+
+/// 请求头：执行本次推理的设备，例如 `cuda:1`，需要有效的API key
+///
+/// 模型不在该设备上时使用（或加载）该设备上的副本
+pub const DEVICE_HEADER: &str = "X-Device";
+
+/// 请求头：为 `true` 时非流式响应省略 `object`、`created` 和 `usage`
+pub const LEAN_RESPONSE_HEADER: &str = "X-Lean-Response";
+
+/// 请求头：为 `true` 时在非流式响应的 `x_params` 中返回实际使用的采样参数
+pub const RETURN_PARAMS_HEADER: &str = "X-Return-Params";
+
+/// 响应头：服务端为本次生成分配的ID，可用于取消生成
+///
+/// 流式响应在生成开始时即返回该响应头，非流式响应在生成结束后返回
+pub const GENERATION_ID_HEADER: &str = "X-Generation-Id";
+
+pub async fn chat_completion(
+    service: web::Data<ChatCompletionService>,
+    http_req: HttpRequest,
+    req: web::Json<Validated<ChatCompletionRequest>>,
+) -> HttpResponse {
+    // 沿用日志中间件分配的请求ID，便于与网关日志关联
+    let request_id = http_req
+        .extensions()
+        .get::<RequestId>()
+        .map(|id| id.0.clone())
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
     let start_time = Utc::now();
 
     log::info!("[{}] Received chat completion request for model: {}", request_id, req.model);
@@ -51,31 +219,197 @@ pub async fn chat_completion(req: web::Json<ChatCompletionRequest>) -> HttpRespo
     // Validate required fields
     if req.model.is_empty() {
         log::warn!("Empty model field in request");
-        return HttpResponse::BadRequest().json("model field is required");
+        return AppError::ValidationError("model field is required".to_string()).error_response();
     }
     if req.messages.is_empty() {
         log::warn!("Empty messages field in request");
-        return HttpResponse::BadRequest().json("messages field cannot be empty");
+        return AppError::ValidationError("messages field cannot be empty".to_string())
+            .error_response();
+    }
+    // 分词前拒绝超大请求
+    if let Err(e) = service.prompt_limits().validate(&req.messages) {
+        log::warn!("[{}] Rejected oversized request: {}", request_id, e);
+        return e.error_response();
+    }
+    if let Err(e) = service.validate_turn_order(&req.messages) {
+        log::warn!("[{}] Rejected message order: {}", request_id, e);
+        return e.error_response();
     }
 
+    let max_duration = match http_req.headers().get(MAX_DURATION_HEADER) {
+        Some(value) => match value.to_str().ok().and_then(|v| v.trim().parse::<u64>().ok()) {
+            Some(ms) => Some(std::time::Duration::from_millis(ms)),
+            None => {
+                log::warn!("Invalid {} header: {:?}", MAX_DURATION_HEADER, value);
+                return AppError::InvalidParameter(format!(
+                    "{} must be a non-negative integer",
+                    MAX_DURATION_HEADER
+                ))
+                .error_response();
+            }
+        },
+        None => None,
+    };
+
+    let lean = match http_req.headers().get(LEAN_RESPONSE_HEADER) {
+        Some(value) => match value.to_str().ok().and_then(|v| v.trim().parse::<bool>().ok()) {
+            Some(lean) => lean,
+            None => {
+                log::warn!("Invalid {} header: {:?}", LEAN_RESPONSE_HEADER, value);
+                return AppError::InvalidParameter(format!(
+                    "{} must be true or false",
+                    LEAN_RESPONSE_HEADER
+                ))
+                .error_response();
+            }
+        },
+        None => false,
+    };
+
+    let return_params = match http_req.headers().get(RETURN_PARAMS_HEADER) {
+        Some(value) => match value.to_str().ok().and_then(|v| v.trim().parse::<bool>().ok()) {
+            Some(return_params) => return_params,
+            None => {
+                log::warn!("Invalid {} header: {:?}", RETURN_PARAMS_HEADER, value);
+                return AppError::InvalidParameter(format!(
+                    "{} must be true or false",
+                    RETURN_PARAMS_HEADER
+                ))
+                .error_response();
+            }
+        },
+        None => false,
+    };
+
+    // 覆盖模型参数只供持有API key的调用方实验使用
+    let model_overrides = match http_req.headers().get(MODEL_OVERRIDES_HEADER) {
+        Some(_) if !has_valid_api_key(&http_req) => {
+            log::warn!(
+                "[{}] Rejected {} without a valid API key",
+                request_id,
+                MODEL_OVERRIDES_HEADER
+            );
+            return AppError::Unauthorized.error_response();
+        }
+        Some(value) => match value
+            .to_str()
+            .map_err(|e| AppError::InvalidParameter(e.to_string()))
+            .and_then(ModelOverrides::parse)
+        {
+            Ok(overrides) => Some(overrides),
+            Err(e) => {
+                log::warn!("Invalid {} header: {}", MODEL_OVERRIDES_HEADER, e);
+                return e.error_response();
+            }
+        },
+        None => None,
+    };
+
+    // 指定设备可能加载新的模型副本，同样只对持有API key的调用方开放
+    let device = match http_req.headers().get(DEVICE_HEADER) {
+        Some(_) if !has_valid_api_key(&http_req) => {
+            log::warn!("[{}] Rejected {} without a valid API key", request_id, DEVICE_HEADER);
+            return AppError::Unauthorized.error_response();
+        }
+        Some(value) => match value
+            .to_str()
+            .map_err(|e| AppError::InvalidParameter(e.to_string()))
+            .and_then(resolve_request_device)
+        {
+            Ok(device) => Some(device),
+            Err(e) => {
+                log::warn!("Invalid {} header: {}", DEVICE_HEADER, e);
+                return e.error_response();
+            }
+        },
+        None => None,
+    };
+
+    let webhook_url = match &req.webhook_url {
+        Some(_) if req.stream == Some(true) => {
+            return AppError::InvalidParameter(
+                "webhook_url cannot be combined with stream".to_string(),
+            )
+            .error_response();
+        }
+        Some(_) if req.conversation_id.is_some() => {
+            return AppError::InvalidParameter(
+                "conversation_id is not supported with webhook_url".to_string(),
+            )
+            .error_response();
+        }
+        Some(url) => match service.webhooks().validate(url) {
+            Ok(url) => Some(url),
+            Err(e) => {
+                log::warn!("[{}] Rejected webhook_url: {}", request_id, e);
+                return e.error_response();
+            }
+        },
+        None => None,
+    };
+
     log::debug!("[{}] Request validation passed", request_id);
 
-    let service = ChatCompletionService::new();
+    // 生成ID由服务端分配，请求ID可能来自上游，不能作为取消凭据
+    let generation_id = Uuid::new_v4().to_string();
+    let Some(generation) = service.generations().register(&generation_id, &req.model) else {
+        log::error!("[{}] Generation {} is already registered", request_id, generation_id);
+        return AppError::Generic(format!("generation {} is already registered", generation_id))
+            .error_response();
+    };
+
     let config = get_config();
-    let chat_config = &config.chat;
+    let model_defaults = service.generation_defaults(&req.model);
 
     let params = ChatCompletionParams {
-        temperature: req.temperature.or(Some(chat_config.defaults.temperature)),
-        top_p: req.top_p.or(Some(chat_config.defaults.top_p)),
-        n: req.n.or(Some(chat_config.defaults.n)),
-        max_tokens: req.max_tokens.or(Some(chat_config.defaults.max_tokens)),
-        stream: req.stream.or(Some(chat_config.defaults.stream)),
-    };
+        temperature: req.temperature,
+        temperature_decay: req.temperature_decay,
+        top_p: req.top_p,
+        top_k: req.top_k,
+        presence_penalty: req.presence_penalty,
+        frequency_penalty: req.frequency_penalty,
+        n: req.n,
+        min_tokens: req.min_tokens,
+        max_tokens: req.max_completion_tokens.or(req.max_tokens),
+        no_repeat_ngram_size: req.no_repeat_ngram_size,
+        stream: req.stream,
+        seed: req.seed,
+        truncation: req.truncation,
+        response_format: req.response_format.clone(),
+        echo: req.echo,
+        priority: req.priority,
+        deadline: max_duration.map(|duration| std::time::Instant::now() + duration),
+        cancellation: Some(generation.cancellation.clone()),
+        progress: Some(generation.progress.clone()),
+        model_overrides,
+        device,
+        // 由服务按 `inference.absolute_max_tokens` 设置
+        absolute_max_tokens: None,
+    }
+    .with_defaults(&model_defaults, &config.chat.defaults);
 
     log::debug!("[{}] Using completion parameters: {:?}", request_id, params);
 
-    match service.complete(&req.model, req.messages.clone(), params).await {
-        Ok(messages) => {
+    if let Some(url) = webhook_url {
+        return webhook_completion(service, &req, params, generation, generation_id, url);
+    }
+    if params.stream == Some(true) {
+        return stream_completion(service, &req, params, generation, generation_id);
+    }
+
+    let result = match &req.conversation_id {
+        Some(conversation_id) => {
+            service
+                .complete_in_conversation(conversation_id, &req.model, req.messages.clone(), params)
+                .await
+        }
+        None => service.complete_with_fallback(&req.model, req.messages.clone(), params).await,
+    };
+    drop(generation);
+
+    match result {
+        Ok(completion) => {
+            Usage::from_outputs(&completion.outputs).record_metrics();
             let end_time = Utc::now();
             let duration = end_time - start_time;
             log::info!(
@@ -84,19 +418,26 @@ pub async fn chat_completion(req: web::Json<ChatCompletionRequest>) -> HttpRespo
                 req.model,
                 duration.num_milliseconds()
             );
-            let response = ChatCompletionResponse {
-                id: Uuid::new_v4().to_string(),
-                object: "chat.completion".to_string(),
-                created: Utc::now(),
-                model: req.model.clone(),
-                choices: messages
-                    .into_iter()
-                    .map(|message| Choice { message, finish_reason: "stop".to_string() })
-                    .collect(),
-                usage: Usage { prompt_tokens: 0, completion_tokens: 0, total_tokens: 0 },
-            };
+            // 同一请求的多个结果使用相同的采样参数
+            let applied_params = return_params
+                .then(|| completion.outputs.iter().find_map(|output| output.sampling.clone()))
+                .flatten();
+            let mut response =
+                ChatCompletionResponse::from_completion(completion, end_time.timestamp(), lean);
+            response.x_params = applied_params;
+            if response.x_timeout {
+                log::warn!(
+                    "[{}] Generation hit the {} limit, returning partial result",
+                    request_id,
+                    MAX_DURATION_HEADER
+                );
+            }
+            if response.x_cancelled {
+                log::warn!("[{}] Generation was cancelled, returning partial result", request_id);
+            }
+            log::debug!("[{}] Response created at: {}", request_id, end_time);
             log::debug!("[{}] Response details: {:?}", request_id, response);
-            HttpResponse::Ok().json(response)
+            HttpResponse::Ok().insert_header((GENERATION_ID_HEADER, generation_id)).json(response)
         }
         Err(e) => {
             let end_time = Utc::now();
@@ -107,7 +448,156 @@ pub async fn chat_completion(req: web::Json<ChatCompletionRequest>) -> HttpRespo
                 duration.num_milliseconds(),
                 e
             );
-            HttpResponse::InternalServerError().json(e.to_string())
+            let mut response = e.error_response();
+            if let Ok((name, value)) = (GENERATION_ID_HEADER, generation_id).try_into_pair() {
+                response.headers_mut().insert(name, value);
+            }
+            response
+        }
+    }
+}
+
+/// 流式生成时token通道的容量
+const STREAM_CHANNEL_CAPACITY: usize = 64;
+
+/// 在后台任务中生成，并以SSE逐token返回
+fn stream_completion(
+    service: web::Data<ChatCompletionService>,
+    req: &ChatCompletionRequest,
+    params: ChatCompletionParams,
+    generation: GenerationHandle,
+    generation_id: String,
+) -> HttpResponse {
+    if req.conversation_id.is_some() {
+        return AppError::InvalidParameter(
+            "conversation_id is not supported with stream".to_string(),
+        )
+        .error_response();
+    }
+
+    let model = service.resolve_model_id(&req.model).to_string();
+    let keepalive = service.stream_keepalive();
+    let (token_rx, result_rx) =
+        spawn_completion(service, req, params, generation, generation_id.clone());
+
+    let stream = event_stream(
+        token_rx,
+        result_rx,
+        keepalive,
+        Uuid::new_v4().to_string(),
+        model,
+        Utc::now().timestamp(),
+    );
+    HttpResponse::Ok()
+        .content_type(EVENT_STREAM)
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .insert_header((GENERATION_ID_HEADER, generation_id))
+        .streaming(stream)
+}
+
+/// 在后台任务中生成，并将token推送到 `webhook_url`，立即返回202
+fn webhook_completion(
+    service: web::Data<ChatCompletionService>,
+    req: &ChatCompletionRequest,
+    params: ChatCompletionParams,
+    generation: GenerationHandle,
+    generation_id: String,
+    url: Url,
+) -> HttpResponse {
+    let id = Uuid::new_v4().to_string();
+    let model = service.resolve_model_id(&req.model).to_string();
+    let timeout = service.webhooks().timeout;
+    let (token_rx, result_rx) =
+        spawn_completion(service, req, params, generation, generation_id.clone());
+
+    log::info!("[{}] Delivering generation {} to webhook {}", id, generation_id, url);
+    actix_web::rt::spawn(deliver(
+        url,
+        timeout,
+        token_rx,
+        result_rx,
+        id.clone(),
+        model.clone(),
+        Utc::now().timestamp(),
+    ));
+    HttpResponse::Accepted()
+        .insert_header((GENERATION_ID_HEADER, generation_id))
+        .json(json!({ "id": id, "object": object::CHAT_COMPLETION_WEBHOOK, "model": model }))
+}
+
+/// 在后台任务中生成，返回token通道与最终结果
+///
+/// 接收端关闭token通道时生成提前结束
+fn spawn_completion(
+    service: web::Data<ChatCompletionService>,
+    req: &ChatCompletionRequest,
+    params: ChatCompletionParams,
+    generation: GenerationHandle,
+    generation_id: String,
+) -> (mpsc::Receiver<StreamToken>, oneshot::Receiver<Result<Completion, AppError>>) {
+    let (token_tx, token_rx) = mpsc::channel(STREAM_CHANNEL_CAPACITY);
+    let (result_tx, result_rx) = oneshot::channel();
+
+    let task_model = req.model.clone();
+    let messages = req.messages.clone();
+    let task_generation_id = generation_id;
+    actix_web::rt::spawn(async move {
+        let result = service.complete_stream(&task_model, messages, params, &token_tx).await;
+        drop(generation);
+        // 提前结束的流仍记录已生成的token数，客户端断开时这是唯一的用量记录
+        if let Ok(Completion { outputs, .. }) = &result {
+            Usage::from_outputs(outputs).record_metrics();
+            let reason = if token_tx.is_closed() {
+                Some("client disconnected")
+            } else if outputs.iter().any(|output| output.cancelled) {
+                Some("cancelled")
+            } else if outputs.iter().any(|output| output.timed_out) {
+                Some("timed out")
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                let usage = Usage::from_outputs(outputs);
+                log::info!(
+                    "[{}] Streaming generation {}: prompt_tokens={}, completion_tokens={}",
+                    task_generation_id,
+                    reason,
+                    usage.prompt_tokens,
+                    usage.completion_tokens
+                );
+            }
         }
+        // 先关闭token通道，再发送最终结果
+        drop(token_tx);
+        let _ = result_tx.send(result);
+    });
+    (token_rx, result_rx)
+}
+
+/// 删除服务端保存的会话
+pub async fn delete_conversation(
+    service: web::Data<ChatCompletionService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let conversation_id = path.into_inner();
+    if !service.conversations().remove(&conversation_id) {
+        return Err(AppError::NotFound);
+    }
+    log::info!("Deleted conversation {}", conversation_id);
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// 取消进行中的生成，返回202；生成不存在或已结束时返回404
+///
+/// 被取消的请求返回已生成的部分，`x_cancelled` 为 `true`
+pub async fn cancel_completion(
+    service: web::Data<ChatCompletionService>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let generation_id = path.into_inner();
+    if !service.generations().cancel(&generation_id) {
+        return Err(AppError::NotFound);
     }
+    log::info!("Cancelled generation {}", generation_id);
+    Ok(HttpResponse::Accepted().finish())
 }
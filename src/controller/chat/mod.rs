@@ -1,6 +1,8 @@
 #[allow(clippy::module_inception)]
 pub mod chat;
 pub mod chat_completion;
+pub mod sse;
+pub mod webhook;
 
 pub use chat::*;
 pub use chat_completion::*;
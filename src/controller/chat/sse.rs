@@ -0,0 +1,184 @@
+//! 流式补全的SSE输出
+//!
+//! 每个token作为一个 `chat.completion.chunk` 事件发送，`n > 1` 时各choice的事件按生成顺序交错，
+//! 以 `index` 区分。所有choice结束后为每个choice发送带 `finish_reason` 的事件，最后发送
+//! `data: [DONE]`。第一个token生成之前，每隔固定间隔发送一条SSE注释 `: keepalive`，
+//! 避免代理因连接空闲而断开；token开始输出后不再发送。
+//! 生成被取消或超时而提前结束时，`[DONE]` 之前再发送一个 `choices` 为空、携带 `usage` 的事件。
+use crate::controller::chat::chat_completion::Usage;
+use crate::entities::object;
+use crate::error::AppError;
+use crate::service::chat::chat_completion::{Completion, StreamToken};
+use actix_web::web::Bytes;
+use actix_web::ResponseError;
+use futures::Stream;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+/// 第一个token之前发送的keepalive注释
+pub const KEEPALIVE_FRAME: &str = ": keepalive\n\n";
+/// 流结束标记
+pub const DONE_FRAME: &str = "data: [DONE]\n\n";
+
+#[derive(Debug, Serialize)]
+pub struct ChatCompletionChunk {
+    pub id: String,
+    pub object: String,
+    /// 创建时间，Unix时间戳（秒）
+    pub created: i64,
+    pub model: String,
+    pub choices: Vec<ChunkChoice>,
+    /// 提前结束时已生成的token用量
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChunkChoice {
+    pub index: usize,
+    pub delta: Delta,
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Delta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+fn data_frame(value: &impl Serialize) -> String {
+    format!("data: {}\n\n", serde_json::to_string(value).unwrap_or_default())
+}
+
+struct EventStream {
+    /// 生成结束后发送端被drop，接收到 `None`
+    tokens: Option<mpsc::Receiver<StreamToken>>,
+    result: Option<oneshot::Receiver<Result<Completion, AppError>>>,
+    keepalive: Duration,
+    /// 已经发送过token的choice
+    started: HashSet<usize>,
+    /// 生成结束后待发送的事件
+    pending: VecDeque<String>,
+    done: bool,
+    id: String,
+    model: String,
+    created: i64,
+}
+
+impl EventStream {
+    fn chunk(&self, index: usize, delta: Delta, finish_reason: Option<String>) -> String {
+        data_frame(&ChatCompletionChunk {
+            id: self.id.clone(),
+            object: object::CHAT_COMPLETION_CHUNK.to_string(),
+            created: self.created,
+            model: self.model.clone(),
+            choices: vec![ChunkChoice { index, delta, finish_reason }],
+            usage: None,
+        })
+    }
+
+    fn usage_chunk(&self, usage: Usage) -> String {
+        data_frame(&ChatCompletionChunk {
+            id: self.id.clone(),
+            object: object::CHAT_COMPLETION_CHUNK.to_string(),
+            created: self.created,
+            model: self.model.clone(),
+            choices: Vec::new(),
+            usage: Some(usage),
+        })
+    }
+
+    async fn next_frame(&mut self) -> Option<String> {
+        while let Some(tokens) = &mut self.tokens {
+            match tokio::time::timeout(self.keepalive, tokens.recv()).await {
+                Ok(Some(StreamToken { index, text })) => {
+                    // 每个choice的第一个事件携带role
+                    let role = self.started.insert(index).then(|| "assistant".to_string());
+                    return Some(self.chunk(index, Delta { role, content: Some(text) }, None));
+                }
+                Ok(None) => self.tokens = None,
+                Err(_) if self.started.is_empty() => return Some(KEEPALIVE_FRAME.to_string()),
+                Err(_) => {}
+            }
+        }
+        if let Some(result) = self.result.take() {
+            match result.await {
+                Ok(Ok(completion)) => {
+                    for (index, output) in completion.outputs.iter().enumerate() {
+                        let finish_reason = Some(output.finish_reason.to_string());
+                        // 没有生成任何token的choice在结束事件中补上role与空内容
+                        let delta = if self.started.contains(&index) {
+                            Delta::default()
+                        } else {
+                            Delta {
+                                role: Some("assistant".to_string()),
+                                content: Some(String::new()),
+                            }
+                        };
+                        self.pending.push_back(self.chunk(index, delta, finish_reason));
+                    }
+                    let outputs = &completion.outputs;
+                    if outputs.iter().any(|output| output.cancelled || output.timed_out) {
+                        self.pending.push_back(self.usage_chunk(Usage::from_outputs(outputs)));
+                    }
+                }
+                Ok(Err(e)) => {
+                    log::error!("Streaming completion failed: {}", e);
+                    self.pending.push_back(data_frame(&json!({
+                        "error": {
+                            "code": e.status_code().as_u16(),
+                            "error_code": e.error_code(),
+                            "message": e.to_string()
+                        }
+                    })));
+                }
+                Err(_) => self.pending.push_back(data_frame(&json!({
+                    "error": {
+                        "code": 500,
+                        "error_code": "internal_error",
+                        "message": "generation ended unexpectedly"
+                    }
+                }))),
+            }
+        }
+        if let Some(frame) = self.pending.pop_front() {
+            return Some(frame);
+        }
+        if !self.done {
+            self.done = true;
+            return Some(DONE_FRAME.to_string());
+        }
+        None
+    }
+}
+
+/// 将生成任务的token与最终结果转换为SSE字节流
+pub fn event_stream(
+    tokens: mpsc::Receiver<StreamToken>,
+    result: oneshot::Receiver<Result<Completion, AppError>>,
+    keepalive: Duration,
+    id: String,
+    model: String,
+    created: i64,
+) -> impl Stream<Item = Result<Bytes, actix_web::Error>> {
+    let state = EventStream {
+        tokens: Some(tokens),
+        result: Some(result),
+        keepalive,
+        started: HashSet::new(),
+        pending: VecDeque::new(),
+        done: false,
+        id,
+        model,
+        created,
+    };
+    futures::stream::unfold(state, |mut state| async move {
+        let frame = state.next_frame().await?;
+        Some((Ok(Bytes::from(frame)), state))
+    })
+}
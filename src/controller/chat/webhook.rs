@@ -0,0 +1,98 @@
+//! 将流式补全推送到webhook
+//!
+//! 每个token作为一个 `chat.completion.chunk` 事件POST到 `webhook_url`，格式与SSE事件相同；
+//! 生成结束后再POST一个 `chat.completion` 事件，包含完整回复、`finish_reason` 与用量。
+//! 生成失败时POST一个 `error` 事件。任何一次推送失败或返回非2xx都会停止推送并终止生成。
+use crate::controller::chat::chat_completion::ChatCompletionResponse;
+use crate::controller::chat::sse::{ChatCompletionChunk, ChunkChoice, Delta};
+use crate::entities::object;
+use crate::error::AppError;
+use crate::service::chat::chat_completion::{Completion, StreamToken};
+use actix_web::ResponseError;
+use reqwest::{redirect, Client, Url};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+/// 将生成任务的token与最终结果逐个POST到 `url`
+pub async fn deliver(
+    url: Url,
+    timeout: Duration,
+    mut tokens: mpsc::Receiver<StreamToken>,
+    result: oneshot::Receiver<Result<Completion, AppError>>,
+    id: String,
+    model: String,
+    created: i64,
+) {
+    // 不跟随重定向，避免绕过允许列表
+    let client = match Client::builder().timeout(timeout).redirect(redirect::Policy::none()).build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            log::error!("[{}] Failed to create webhook client: {}", id, e);
+            return;
+        }
+    };
+
+    let mut started = HashSet::new();
+    while let Some(StreamToken { index, text }) = tokens.recv().await {
+        // 每个choice的第一个事件携带role
+        let role = started.insert(index).then(|| "assistant".to_string());
+        let chunk = ChatCompletionChunk {
+            id: id.clone(),
+            object: object::CHAT_COMPLETION_CHUNK.to_string(),
+            created,
+            model: model.clone(),
+            choices: vec![ChunkChoice {
+                index,
+                delta: Delta { role, content: Some(text) },
+                finish_reason: None,
+            }],
+            usage: None,
+        };
+        if let Err(e) = post(&client, &url, &chunk).await {
+            // 关闭token通道，生成任务随之结束
+            log::warn!("[{}] Webhook delivery failed, stopping generation: {}", id, e);
+            return;
+        }
+    }
+
+    let event = match result.await {
+        Ok(Ok(completion)) => {
+            let response = ChatCompletionResponse::from_completion(completion, created, false);
+            serde_json::to_value(ChatCompletionResponse { id: id.clone(), ..response })
+                .unwrap_or_default()
+        }
+        Ok(Err(e)) => {
+            log::error!("[{}] Webhook completion failed: {}", id, e);
+            json!({
+                "id": id,
+                "object": object::ERROR,
+                "error": {
+                    "code": e.status_code().as_u16(),
+                    "error_code": e.error_code(),
+                    "message": e.to_string()
+                }
+            })
+        }
+        Err(_) => json!({
+            "id": id,
+            "object": object::ERROR,
+            "error": {
+                "code": 500,
+                "error_code": "internal_error",
+                "message": "generation ended unexpectedly"
+            }
+        }),
+    };
+    if let Err(e) = post(&client, &url, &event).await {
+        log::warn!("[{}] Failed to deliver the final webhook event: {}", id, e);
+    }
+}
+
+async fn post(client: &Client, url: &Url, body: &impl Serialize) -> Result<(), reqwest::Error> {
+    client.post(url.clone()).json(body).send().await?.error_for_status()?;
+    Ok(())
+}
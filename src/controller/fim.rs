@@ -0,0 +1,100 @@
+use crate::controller::chat::chat_completion::Usage;
+use crate::controller::json::Validated;
+use crate::entities::object;
+use crate::error::AppError;
+use crate::service::chat::chat_completion::{ChatCompletionParams, ChatCompletionService};
+use crate::service::models::scheduler::Priority;
+use crate::utils::config::get_config;
+use actix_web::{post, web, HttpResponse};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct FimCompletionRequest {
+    pub model: String,
+    /// 光标前的代码
+    pub prefix: String,
+    /// 光标后的代码，未设置时为空
+    #[serde(default)]
+    pub suffix: String,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub top_k: Option<usize>,
+    pub n: Option<usize>,
+    pub max_tokens: Option<usize>,
+    pub seed: Option<u64>,
+    /// 调度优先级: low | normal | high
+    pub priority: Option<Priority>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FimCompletionResponse {
+    pub id: String,
+    pub object: String,
+    /// 创建时间，Unix时间戳（秒）
+    pub created: i64,
+    pub model: String,
+    pub choices: Vec<FimChoice>,
+    pub usage: Usage,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FimChoice {
+    pub index: usize,
+    /// 生成的中间部分，不包含前缀与后缀
+    pub text: String,
+    pub finish_reason: String,
+}
+
+/// fill-in-the-middle补全：生成 `prefix` 与 `suffix` 之间的代码
+///
+/// 模型须配置 `models.<id>.fim` 哨兵token，否则返回400
+#[post("/completions")]
+pub async fn fim_completion(
+    service: web::Data<ChatCompletionService>,
+    req: web::Json<Validated<FimCompletionRequest>>,
+) -> Result<HttpResponse, AppError> {
+    let req = req.into_inner().into_inner();
+    if req.model.is_empty() {
+        return Err(AppError::ValidationError("model field is required".to_string()));
+    }
+    log::info!("Received FIM completion request for model: {}", req.model);
+
+    let params = ChatCompletionParams {
+        temperature: req.temperature,
+        top_p: req.top_p,
+        top_k: req.top_k,
+        n: req.n,
+        max_tokens: req.max_tokens,
+        seed: req.seed,
+        priority: req.priority,
+        stream: Some(false),
+        ..Default::default()
+    }
+    .with_defaults(&service.generation_defaults(&req.model), &get_config().chat.defaults);
+
+    let completion = service.complete_fim(&req.model, &req.prefix, &req.suffix, params).await?;
+    let usage = Usage::from_outputs(&completion.outputs);
+    Ok(HttpResponse::Ok().json(FimCompletionResponse {
+        id: format!("cmpl-{}", Uuid::new_v4()),
+        object: object::TEXT_COMPLETION.to_string(),
+        created: Utc::now().timestamp(),
+        model: completion.model,
+        usage,
+        choices: completion
+            .outputs
+            .into_iter()
+            .enumerate()
+            .map(|(index, output)| FimChoice {
+                index,
+                text: output.text,
+                finish_reason: output.finish_reason.to_string(),
+            })
+            .collect(),
+    }))
+}
+
+pub fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(fim_completion);
+}
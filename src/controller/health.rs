@@ -0,0 +1,40 @@
+use crate::service::models::ModelManager;
+use crate::utils::config::get_config;
+use crate::utils::device::{configured_device, device_label};
+use crate::utils::memory::{peak_memory_bytes, resident_memory_bytes};
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+
+/// 存活检查
+pub async fn health() -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "status": "ok" }))
+}
+
+/// 运维面板使用的详细状态：计算设备、已加载的模型与进程内存
+///
+/// 设备无法解析时 `device` 为 `null`，`device_error` 说明原因
+pub async fn health_detail(manager: web::Data<ModelManager>) -> HttpResponse {
+    let (device, device_error) = match configured_device() {
+        Ok(device) => (Some(device), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    let label = device.as_ref().map(device_label);
+
+    HttpResponse::Ok().json(json!({
+        "status": "ok",
+        "device": label,
+        "device_config": get_config().inference.device,
+        "device_error": device_error,
+        "cuda_active": device.as_ref().is_some_and(|device| device.is_cuda()),
+        "metal_active": device.as_ref().is_some_and(|device| device.is_metal()),
+        "loaded_models": manager.loaded_model_ids().await,
+        "memory": {
+            "resident_bytes": resident_memory_bytes(),
+            "peak_bytes": peak_memory_bytes()
+        }
+    }))
+}
+
+pub fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("", web::get().to(health)).route("/detail", web::get().to(health_detail));
+}
@@ -0,0 +1,121 @@
+//! JSON请求体解析
+//!
+//! 反序列化失败时返回统一错误格式的400，并在错误信息中给出出错字段的路径，
+//! 例如 `temperature: invalid type: string "hot", expected f32`。
+//! Content-Type不是 `application/json`（可带 `charset` 参数）时返回415。
+//! 请求体大小受 [`JSON_LIMIT`] 限制，超大的数组字段可以用 [`deserialize_bounded_vec`]
+//! 在解析过程中提前拒绝。
+use crate::error::AppError;
+use actix_web::error::JsonPayloadError;
+use actix_web::http::header;
+use actix_web::{web, HttpRequest};
+use serde::de::{Error as _, IgnoredAny, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+/// 请求体的最大字节数
+pub const JSON_LIMIT: usize = 32768 * 1024;
+
+/// 反序列化时记录字段路径的包装类型，错误信息以出错字段的路径开头
+#[derive(Debug)]
+pub struct Validated<T>(pub T);
+
+impl<T> Validated<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Validated<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Validated<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        serde_path_to_error::deserialize(deserializer).map(Validated).map_err(|err| {
+            let path = err.path().to_string();
+            // 根对象上的错误（如缺少必填字段）路径为 `.`，无需前缀
+            if path == "." {
+                err.into_inner()
+            } else {
+                D::Error::custom(format!("{}: {}", path, err.into_inner()))
+            }
+        })
+    }
+}
+
+/// 反序列化数组，元素数量超过 `max` 后剩余的元素只计数、不再构造和分配
+pub fn deserialize_bounded_vec<'de, D, T>(deserializer: D, max: usize) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    struct BoundedVecVisitor<T> {
+        max: usize,
+        marker: PhantomData<T>,
+    }
+
+    impl<'de, T: Deserialize<'de>> Visitor<'de> for BoundedVecVisitor<T> {
+        type Value = Vec<T>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "an array with at most {} entries", self.max)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<T>, A::Error> {
+            let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(self.max));
+            while let Some(value) = seq.next_element()? {
+                if values.len() == self.max {
+                    let mut len = values.len() + 1;
+                    while seq.next_element::<IgnoredAny>()?.is_some() {
+                        len += 1;
+                    }
+                    // 字段名由 `serde_path_to_error` 加在前面
+                    return Err(A::Error::custom(format!(
+                        "{} entries exceeds the limit of {}",
+                        len, self.max
+                    )));
+                }
+                values.push(value);
+            }
+            Ok(values)
+        }
+    }
+
+    deserializer.deserialize_seq(BoundedVecVisitor { max, marker: PhantomData })
+}
+
+/// 将JSON解析错误转换为 `AppError::ValidationError`
+///
+/// Content-Type不是 `application/json` 时返回415 (`AppError::UnsupportedMediaType`)
+pub fn json_error_handler(err: JsonPayloadError, req: &HttpRequest) -> actix_web::Error {
+    log::warn!("Rejected JSON request body: {}", err);
+    let message = match err {
+        JsonPayloadError::Deserialize(e) => e.to_string(),
+        JsonPayloadError::ContentType => {
+            let content_type = req
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or("none");
+            return AppError::UnsupportedMediaType(format!(
+                "Content-Type must be application/json, got {}",
+                content_type
+            ))
+            .into();
+        }
+        e => e.to_string(),
+    };
+    AppError::ValidationError(message).into()
+}
+
+/// 带统一错误处理的JSON提取器配置
+pub fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default().limit(JSON_LIMIT).error_handler(json_error_handler)
+}
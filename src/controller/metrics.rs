@@ -0,0 +1,7 @@
+use crate::service::metrics::metrics as registry;
+use actix_web::HttpResponse;
+
+/// 以Prometheus文本格式返回服务指标
+pub async fn metrics() -> HttpResponse {
+    HttpResponse::Ok().content_type("text/plain; version=0.0.4").body(registry().render())
+}
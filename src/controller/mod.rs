@@ -1,4 +1,9 @@
+pub mod admin;
 pub mod chat;
+pub mod fim;
+pub mod health;
+pub mod json;
+pub mod metrics;
 pub mod models;
 
 pub use chat::chat_completion;
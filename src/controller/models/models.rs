@@ -1,7 +1,9 @@
 use crate::error::AppError;
+use crate::service::models::benchmark::{run_benchmark, BenchmarkConfig};
+use crate::service::models::scheduler::Priority;
 use crate::service::models::yi_coder::loader::ModelLoader;
-use crate::service::models::{ModelManager, ModelStatus};
-use actix_web::{get, post, web, HttpResponse};
+use crate::service::models::ModelManager;
+use actix_web::{delete, get, post, web, HttpResponse};
 use anyhow::Result;
 use log::{debug, info};
 use serde::Deserialize;
@@ -19,16 +21,16 @@ pub async fn list_models(manager: web::Data<ModelManager>) -> HttpResponse {
     let response = models
         .into_iter()
         .map(|(id, name, description)| {
-            let status = status
-                .get(id)
-                .cloned()
-                .unwrap_or(ModelStatus { is_cached: false, is_enabled: false });
+            let status = status.get(id).cloned().unwrap_or_default();
             json!({
                 "id": id,
                 "name": name,
                 "description": description,
                 "is_cached": status.is_cached,
-                "is_enabled": status.is_enabled
+                "is_enabled": status.is_enabled,
+                "is_downloading": status.is_downloading,
+                "download_queue_position": status.download_queue_position,
+                "aliases": manager.aliases_of(id)
             })
         })
         .collect::<Vec<_>>();
@@ -36,6 +38,118 @@ pub async fn list_models(manager: web::Data<ModelManager>) -> HttpResponse {
     HttpResponse::Ok().json(json!({ "models": response }))
 }
 
+#[get("/{model_id}/files")]
+pub async fn list_model_files(
+    manager: web::Data<ModelManager>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let model_id = manager.resolve_model_id(&path).to_string();
+    debug!("{}", t!("logs.handling_request"));
+    let files = manager.get_model_files(&model_id).ok_or(AppError::NotFound)?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "model_id": model_id,
+        "files": files
+    })))
+}
+
+#[get("/{model_id}/generation_config")]
+pub async fn get_generation_config(
+    manager: web::Data<ModelManager>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let model_id = manager.resolve_model_id(&path).to_string();
+    debug!("{}", t!("logs.handling_request"));
+    let defaults = manager.get_generation_defaults(&model_id)?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "model_id": model_id,
+        "generation_config": defaults
+    })))
+}
+
+#[get("/{model_id}/special_tokens")]
+pub async fn get_special_tokens(
+    manager: web::Data<ModelManager>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let model_id = manager.resolve_model_id(&path).to_string();
+    debug!("{}", t!("logs.handling_request"));
+    let special_tokens = manager.get_special_tokens(&model_id)?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "model_id": model_id,
+        "special_tokens": special_tokens
+    })))
+}
+
+/// 在当前硬件上运行合成生成，返回吞吐量
+///
+/// 需要API key；以低优先级占用模型的推理许可，避免挤占正常请求
+#[post("/{model_id}/benchmark")]
+pub async fn benchmark_model(
+    manager: web::Data<ModelManager>,
+    path: web::Path<String>,
+    req: web::Json<BenchmarkConfig>,
+) -> Result<HttpResponse, AppError> {
+    let model_id = manager.resolve_model_id(&path).to_string();
+    debug!("{}", t!("logs.handling_request"));
+    if manager.get_model_status(&model_id).await.is_none() {
+        return Err(AppError::NotFound);
+    }
+    let model = manager.get_or_load_model(&model_id).await?;
+    let _permit = manager.acquire_permit(&model_id, Priority::Low).await?;
+    let result = run_benchmark(model.as_ref(), req.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+/// 卸载模型以释放内存，缓存的模型文件保持不变
+#[post("/{model_id}/unload")]
+pub async fn unload_model(
+    manager: web::Data<ModelManager>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let model_id = manager.resolve_model_id(&path).to_string();
+    debug!("{}", t!("logs.handling_request"));
+    let unloaded = manager.unload_model(&model_id).await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "model_id": model_id,
+        "unloaded": unloaded
+    })))
+}
+
+#[derive(Deserialize)]
+struct DeleteFilesQuery {
+    #[serde(default)]
+    confirm: bool,
+}
+
+/// 卸载模型并删除缓存的模型文件
+///
+/// 必须带上 `confirm=true` 查询参数，避免误删
+#[delete("/{model_id}/files")]
+pub async fn delete_model_files(
+    manager: web::Data<ModelManager>,
+    path: web::Path<String>,
+    query: web::Query<DeleteFilesQuery>,
+) -> Result<HttpResponse, AppError> {
+    let model_id = manager.resolve_model_id(&path).to_string();
+    debug!("{}", t!("logs.handling_request"));
+    if !query.confirm {
+        return Err(AppError::InvalidParameter(
+            "Deleting model files requires the confirm=true query parameter".to_string(),
+        ));
+    }
+    let deleted = manager.delete_model_files(&model_id).await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "model_id": model_id,
+        "deleted_files": deleted
+    })))
+}
+
 #[post("/download")]
 pub async fn download_model(
     _manager: web::Data<ModelManager>,
@@ -61,5 +175,12 @@ struct DownloadRequest {
 }
 
 pub fn routes(cfg: &mut actix_web::web::ServiceConfig) {
-    cfg.service(list_models).service(download_model);
+    cfg.service(list_models)
+        .service(list_model_files)
+        .service(get_generation_config)
+        .service(get_special_tokens)
+        .service(benchmark_model)
+        .service(unload_model)
+        .service(delete_model_files)
+        .service(download_model);
 }
@@ -1,7 +1,42 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatCompletionMessage {
     pub role: String,
+    /// 字符串，或OpenAI的内容片段数组 `[{"type": "text", "text": "..."}]`，见 [`deserialize_content`]
+    #[serde(deserialize_with = "deserialize_content")]
     pub content: String,
+    /// 消息发送者名称，例如多智能体对话中的参与者或工具名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Deserialize)]
+struct ContentPart {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+/// 将 `content` 统一为字符串
+///
+/// 内容片段数组中 `type` 为 `text` 的片段以换行连接，其他类型（如 `image_url`）暂时忽略
+fn deserialize_content<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(match MessageContent::deserialize(deserializer)? {
+        MessageContent::Text(text) => text,
+        MessageContent::Parts(parts) => parts
+            .into_iter()
+            .filter(|part| part.kind == "text")
+            .filter_map(|part| part.text)
+            .collect::<Vec<_>>()
+            .join("\n"),
+    })
 }
@@ -1,2 +1,3 @@
 pub mod chat_completion_message;
 pub mod models;
+pub mod object;
@@ -10,16 +10,15 @@ pub struct DeepseekCoderModel {
 
 impl DeepseekCoderModel {
     pub async fn new() -> Result<Self> {
-        let device = Device::cuda_if_available(0).unwrap_or(Device::Cpu);
         let config = crate::service::models::deepseek_coder::config::ModelConfig::from_file(
             "config/deepseek_coder.json",
         )?;
         let loader = crate::service::models::deepseek_coder::loader::DeepseekCoderLoader::new(
             config.clone(),
-        );
+        )?;
         let _tensors = loader.load_weights().await?;
 
-        Ok(DeepseekCoderModel { device })
+        Ok(DeepseekCoderModel { device: loader.device().clone() })
     }
 
     #[allow(dead_code)]
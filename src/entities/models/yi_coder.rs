@@ -10,13 +10,12 @@ pub struct YiCoderModel {
 
 impl YiCoderModel {
     pub async fn new(config_path: &str) -> Result<Self> {
-        let device = Device::cuda_if_available(0).unwrap_or(Device::Cpu);
         let loader =
             crate::service::models::yi_coder::loader::ModelLoader::new("yi-coder", config_path)
                 .await?;
-        let _tensors = loader.load()?;
+        let _tensors = loader.load().await?;
 
-        Ok(YiCoderModel { device })
+        Ok(YiCoderModel { device: loader.device().clone() })
     }
 
     #[allow(dead_code)]
@@ -0,0 +1,12 @@
+//! 响应中 `object` 字段的取值，与OpenAI接口保持一致
+
+/// 非流式补全响应，以及webhook的最终事件
+pub const CHAT_COMPLETION: &str = "chat.completion";
+/// 流式补全的SSE事件与webhook推送的分块
+pub const CHAT_COMPLETION_CHUNK: &str = "chat.completion.chunk";
+/// 使用 `webhook_url` 时立即返回的响应
+pub const CHAT_COMPLETION_WEBHOOK: &str = "chat.completion.webhook";
+/// webhook推送的错误事件
+pub const ERROR: &str = "error";
+/// fill-in-the-middle补全 (`POST /v1/fim/completions`) 的响应
+pub const TEXT_COMPLETION: &str = "text_completion";
@@ -39,14 +39,55 @@ pub enum AppError {
     TokenizerError(String),
     #[error("Invalid parameter: {0}")]
     InvalidParameter(String),
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+    #[error("Model is downloading: {0}")]
+    ModelDownloading(String),
+    #[error("Unsupported media type: {0}")]
+    UnsupportedMediaType(String),
     #[error("Generic error: {0}")]
     Generic(String),
 }
 
+/// 模型下载中时建议客户端重试的间隔（秒）
+pub const DOWNLOAD_RETRY_AFTER_SECS: u64 = 30;
+
 impl AppError {
     pub fn new(message: String) -> Self {
         AppError::Generic(message)
     }
+
+    /// 稳定的机器可读错误码，客户端可据此区分错误类型而不必解析 `message`
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            AppError::ValidationError(_) => "validation_error",
+            AppError::NotFound => "not_found",
+            AppError::Unauthorized => "unauthorized",
+            AppError::Forbidden => "forbidden",
+            AppError::Io(_) => "io_error",
+            AppError::Anyhow(_) => "internal_error",
+            AppError::Model(_) => "model_unavailable",
+            AppError::Candle(_) => "inference_error",
+            AppError::Chat(_) => "chat_error",
+            AppError::SafeTensor(_) => "weights_error",
+            AppError::InvalidModel(_) => "model_not_found",
+            AppError::ConfigError(_) => "config_error",
+            AppError::TokenizerError(_) => "tokenizer_error",
+            AppError::InvalidParameter(_) => "invalid_parameter",
+            AppError::ServiceUnavailable(_) => "service_unavailable",
+            AppError::ModelDownloading(_) => "model_downloading",
+            AppError::UnsupportedMediaType(_) => "unsupported_media_type",
+            AppError::Generic(_) => "internal_error",
+        }
+    }
+
+    /// 需要在响应中携带的 `Retry-After` 秒数
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            AppError::ModelDownloading(_) => Some(DOWNLOAD_RETRY_AFTER_SECS),
+            _ => None,
+        }
+    }
 }
 
 impl From<Box<dyn std::error::Error + Send + Sync>> for AppError {
@@ -65,6 +106,8 @@ impl From<serde_json::Error> for AppError {
 pub struct ErrorResponse {
     pub code: u32,
     pub status: String,
+    /// 见 `AppError::error_code`
+    pub error_code: String,
     pub message: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<serde_json::Value>,
@@ -93,6 +136,11 @@ impl ResponseError for AppError {
             AppError::NotFound => actix_web::http::StatusCode::NOT_FOUND,
             AppError::Unauthorized => actix_web::http::StatusCode::UNAUTHORIZED,
             AppError::Forbidden => actix_web::http::StatusCode::FORBIDDEN,
+            AppError::ServiceUnavailable(_) => actix_web::http::StatusCode::SERVICE_UNAVAILABLE,
+            AppError::ModelDownloading(_) => actix_web::http::StatusCode::SERVICE_UNAVAILABLE,
+            AppError::UnsupportedMediaType(_) => {
+                actix_web::http::StatusCode::UNSUPPORTED_MEDIA_TYPE
+            }
             AppError::Generic(_) => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -113,29 +161,42 @@ impl ResponseError for AppError {
             AppError::NotFound => (404, "Not Found"),
             AppError::Unauthorized => (401, "Unauthorized"),
             AppError::Forbidden => (403, "Forbidden"),
+            AppError::ServiceUnavailable(_) => (503, "Service Unavailable"),
+            AppError::ModelDownloading(_) => (503, "Service Unavailable"),
+            AppError::UnsupportedMediaType(_) => (415, "Unsupported Media Type"),
             AppError::Generic(_) => (500, "Internal Server Error"),
         };
 
         let response = ErrorResponse {
             code: code as u32,
             status: status.to_string(),
+            error_code: self.error_code().to_string(),
             message: self.to_string(),
             data: None,
         };
 
-        actix_web::HttpResponse::build(self.status_code()).json(response)
+        let mut builder = actix_web::HttpResponse::build(self.status_code());
+        if let Some(secs) = self.retry_after() {
+            builder.insert_header((actix_web::http::header::RETRY_AFTER, secs.to_string()));
+        }
+        builder.json(response)
     }
 }
 
 impl From<AppError> for std::io::Error {
     fn from(err: AppError) -> std::io::Error {
-        std::io::Error::new(std::io::ErrorKind::Other, err.to_string())
+        std::io::Error::other(err.to_string())
     }
 }
 
 impl From<crate::service::models::ModelError> for AppError {
     fn from(err: crate::service::models::ModelError) -> Self {
-        AppError::Model(err.to_string())
+        match err {
+            crate::service::models::ModelError::Busy(_) => {
+                AppError::ServiceUnavailable(err.to_string())
+            }
+            _ => AppError::Model(err.to_string()),
+        }
     }
 }
 
@@ -11,7 +11,7 @@
 //! - `utils`: 包含实用函数和辅助工具
 //!
 //! # 示例
-//! ```rust
+//! ```rust,no_run
 //! use coder_openapi::controller::chat::chat_completions;
 //! use actix_web::{web, App, HttpServer};
 //!
@@ -44,8 +44,14 @@ pub mod routes;
 pub mod service;
 pub mod utils {
     pub mod config;
+    pub mod device;
     pub mod download;
     pub mod init;
+    pub mod log_filter;
+    pub mod lru;
+    pub mod memory;
+    pub mod tls;
+    pub mod weights;
 }
 
 pub use controller::{chat, models};
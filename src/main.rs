@@ -1,9 +1,19 @@
+use actix_web::http::KeepAlive;
+use actix_web::middleware::Compress;
 use actix_web::{web, App, HttpServer};
 rust_i18n::i18n!("locales");
 use anyhow::Context;
+use coder_openapi::controller::json::json_config;
+use coder_openapi::middleware::authentication::Authentication;
+use coder_openapi::middleware::compression::EventStreamIdentity;
+use coder_openapi::middleware::cors::cors;
+use coder_openapi::middleware::draining::{DrainState, Draining};
+use coder_openapi::middleware::Logging;
 use coder_openapi::routes;
+use coder_openapi::service::chat::chat_completion::ChatCompletionService;
 use coder_openapi::set_locale;
 use coder_openapi::utils::init;
+use coder_openapi::utils::tls::Listener;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -11,26 +21,96 @@ async fn main() -> std::io::Result<()> {
     set_locale("zh");
 
     // 初始化应用配置和日志系统
-    let config = init::init()
+    let config = init::init().await.context("init failed").map_err(std::io::Error::other)?;
+    // 预加载的模型就绪后才开始接收请求
+    let model_manager = init::init_models(&config)
         .await
-        .context("init failed")
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        .context("preload failed")
+        .map_err(std::io::Error::other)?;
+    // 所有worker共用同一个服务
+    let chat_completion_service = web::Data::new(ChatCompletionService::new(model_manager.clone()));
 
     let server_config = config.clone();
     let host = server_config.server.host.clone();
     let port = server_config.server.port;
     let shutdown_timeout = server_config.server.shutdown_timeout;
+    let client_request_timeout = server_config.server.client_request_timeout();
+    let keep_alive = match server_config.server.keep_alive() {
+        Some(duration) => KeepAlive::Timeout(duration),
+        None => KeepAlive::Disabled,
+    };
+    let listener = Listener::from_config(&server_config.server)
+        .context("TLS config failed")
+        .map_err(std::io::Error::other)?;
 
-    HttpServer::new(move || {
+    // 收到关闭信号后先拒绝新的 /v1 请求，再等待进行中的请求完成
+    let drain = DrainState::new();
+    let app_drain = drain.clone();
+    let server = HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(server_config.clone()))
             .app_data(web::PayloadConfig::new(32768 * 1024)) // 32MB payload limit
+            .app_data(json_config())
             .wrap(coder_openapi::middleware::error_handler::error_handler())
-            .configure(routes::route::configure)
+            // 按Accept-Encoding压缩响应，SSE响应不压缩
+            .wrap(EventStreamIdentity)
+            .wrap(Compress::default())
+            // `auth.public_paths` 之外的请求都需要API key
+            .wrap(Authentication::from_config())
+            .wrap(Draining::new(app_drain.clone()))
+            // 在排空与鉴权之前应答预检请求
+            .wrap(cors(&server_config.cors))
+            // 最外层分配请求ID并记录请求耗时
+            .wrap(Logging::from_config())
+            .configure(|cfg| {
+                routes::route::configure_with_manager(
+                    cfg,
+                    model_manager.clone(),
+                    chat_completion_service.clone(),
+                )
+            })
     })
-    .client_request_timeout(std::time::Duration::from_secs(30)) // 客户端请求超时30秒
-    .bind((host, port))?
+    .client_request_timeout(client_request_timeout) // 客户端请求超时
+    .keep_alive(keep_alive)
     .shutdown_timeout(shutdown_timeout) // 优雅关闭等待时间
-    .run()
-    .await
+    .disable_signals(); // 由下面的任务处理关闭信号，先进入排空状态
+
+    let server = match listener {
+        Listener::Plaintext => server.bind((host, port))?,
+        // 通过ALPN协商HTTP/2
+        Listener::Tls(tls) => {
+            log::info!("TLS enabled, serving HTTPS with HTTP/2");
+            server.bind_rustls_0_23((host, port), *tls)?
+        }
+    };
+    let server = server.run();
+    let handle = server.handle();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        log::info!("收到关闭信号，拒绝新请求并等待进行中的请求完成");
+        drain.start();
+        handle.stop(true).await;
+    });
+    server.await
+}
+
+/// 等待Ctrl-C或SIGTERM
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => log::warn!("无法监听SIGTERM: {}", e),
+        }
+    }
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        log::error!("无法监听Ctrl-C: {}", e);
+    }
 }
@@ -1,16 +1,21 @@
+use crate::utils::config::get_config;
 use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
-use actix_web::Error;
+use actix_web::http::header::HeaderMap;
+use actix_web::{Error, HttpMessage, HttpRequest};
 use futures::future::{ok, Ready};
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 
 /// 身份验证中间件
 ///
+/// 路径匹配 `public_paths` 中任一前缀的请求无需API key，API key默认取自环境变量 `API_KEY`
+///
 /// # 示例
-/// ```rust
-/// use actix_web::{web, App, HttpServer};
-/// use coder_openapi::middleware::Authentication;
-/// use coder_openapi::routes::config;
+/// ```rust,no_run
+/// use actix_web::{App, HttpServer};
+/// use coder_openapi::middleware::authentication::Authentication;
+/// use coder_openapi::routes::configure;
 ///
 /// #[actix_web::main]
 /// async fn main() -> std::io::Result<()> {
@@ -18,15 +23,79 @@ use std::pin::Pin;
 ///     
 ///     HttpServer::new(|| {
 ///         App::new()
-///             .wrap(Authentication)
-///             .configure(config)
+///             .wrap(Authentication::from_config())
+///             .configure(configure)
 ///     })
 ///     .bind(("127.0.0.1", 8080))?
 ///     .run()
 ///     .await
 /// }
 /// ```
-pub struct Authentication;
+#[derive(Clone, Default)]
+pub struct Authentication {
+    public_paths: Arc<Vec<String>>,
+    api_key: Option<Arc<str>>,
+}
+
+impl Authentication {
+    /// 未设置API key时，非公开路径的请求一律返回500
+    pub fn new(public_paths: Vec<String>) -> Self {
+        Self { public_paths: Arc::new(public_paths), api_key: None }
+    }
+
+    /// 设置请求需要携带的API key
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(Arc::from(api_key));
+        self
+    }
+
+    /// 使用配置文件中的 `auth.public_paths` 与环境变量 `API_KEY`
+    pub fn from_config() -> Self {
+        let auth = Self::new(get_config().auth.public_paths.clone());
+        match std::env::var(API_KEY_ENV) {
+            Ok(api_key) => auth.with_api_key(&api_key),
+            Err(_) => auth,
+        }
+    }
+}
+
+/// 取出 `Authorization: Bearer <key>` 请求头中的API key
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "))
+}
+
+/// 保存API key的环境变量
+pub const API_KEY_ENV: &str = "API_KEY";
+
+/// `Authentication` 中间件记录在请求扩展中的鉴权结果，公开路径上的请求同样记录
+#[derive(Clone, Copy)]
+struct ApiKeyStatus {
+    configured: bool,
+    verified: bool,
+}
+
+/// 请求是否携带了有效的API key，由 `Authentication` 中间件校验
+///
+/// 用于可能位于公开路径下、需要额外鉴权的可选功能，例如 `X-Model-Overrides`
+pub fn has_valid_api_key(req: &HttpRequest) -> bool {
+    req.extensions().get::<ApiKeyStatus>().is_some_and(|status| status.verified)
+}
+
+/// 处理请求的 `Authentication` 中间件是否设置了API key
+pub fn api_key_configured(req: &HttpRequest) -> bool {
+    req.extensions().get::<ApiKeyStatus>().is_some_and(|status| status.configured)
+}
+
+/// 判断 `path` 是否位于 `prefix` 之下，按路径段匹配，`/health` 不匹配 `/healthz`
+fn matches_prefix(path: &str, prefix: &str) -> bool {
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/'),
+        None => false,
+    }
+}
 
 impl<S, B> Transform<S, ServiceRequest> for Authentication
 where
@@ -41,12 +110,18 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ok(AuthenticationMiddleware { service })
+        ok(AuthenticationMiddleware {
+            service,
+            public_paths: self.public_paths.clone(),
+            api_key: self.api_key.clone(),
+        })
     }
 }
 
 pub struct AuthenticationMiddleware<S> {
     service: S,
+    public_paths: Arc<Vec<String>>,
+    api_key: Option<Arc<str>>,
 }
 
 impl<S, B> Service<ServiceRequest> for AuthenticationMiddleware<S>
@@ -68,26 +143,26 @@ where
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
         // Extract API key from Authorization header
-        let api_key = req
-            .headers()
-            .get("Authorization")
-            .and_then(|h| h.to_str().ok())
-            .and_then(|s| s.strip_prefix("Bearer "));
+        let api_key = bearer_token(req.headers());
+        let verified =
+            matches!((api_key, &self.api_key), (Some(key), Some(expected)) if key == &**expected);
+        req.extensions_mut().insert(ApiKeyStatus { configured: self.api_key.is_some(), verified });
+
+        if verified || self.public_paths.iter().any(|prefix| matches_prefix(req.path(), prefix)) {
+            let fut = self.service.call(req);
+            return Box::pin(async move {
+                let res = fut.await?;
+                Ok(res)
+            });
+        }
 
         // Validate API key
-        match (api_key, std::env::var("API_KEY")) {
-            (Some(key), Ok(env_key)) if key == env_key => {
-                let fut = self.service.call(req);
-                Box::pin(async move {
-                    let res = fut.await?;
-                    Ok(res)
-                })
-            }
+        match (api_key, &self.api_key) {
             (None, _) => {
                 // Missing API key
                 Box::pin(async move { Err(actix_web::error::ErrorUnauthorized("Missing API key")) })
             }
-            (_, Err(_)) => {
+            (_, None) => {
                 // API key not configured
                 Box::pin(async move {
                     Err(actix_web::error::ErrorInternalServerError("Server configuration error"))
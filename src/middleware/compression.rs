@@ -0,0 +1,81 @@
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderValue, CONTENT_ENCODING, CONTENT_TYPE};
+use actix_web::Error;
+use futures::future::{ok, Ready};
+use std::future::Future;
+use std::pin::Pin;
+
+/// SSE响应的Content-Type
+pub const EVENT_STREAM: &str = "text/event-stream";
+
+/// 将SSE响应标记为 `Content-Encoding: identity`，使外层的 `Compress` 跳过压缩
+///
+/// 压缩会缓冲流式输出，客户端无法及时收到每个事件。
+/// 必须注册在 `Compress` 内层，即先于 `Compress` 调用 `wrap`。
+///
+/// # 示例
+/// ```
+/// use actix_web::{middleware::Compress, App};
+/// use coder_openapi::middleware::compression::EventStreamIdentity;
+///
+/// App::new()
+///     .wrap(EventStreamIdentity)
+///     .wrap(Compress::default());
+/// ```
+pub struct EventStreamIdentity;
+
+impl<S, B> Transform<S, ServiceRequest> for EventStreamIdentity
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = EventStreamIdentityMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(EventStreamIdentityMiddleware { service })
+    }
+}
+
+pub struct EventStreamIdentityMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for EventStreamIdentityMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(
+        &self,
+        ctx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let mut res = fut.await?;
+            let is_event_stream = res
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.starts_with(EVENT_STREAM));
+            if is_event_stream && !res.headers().contains_key(CONTENT_ENCODING) {
+                res.headers_mut().insert(CONTENT_ENCODING, HeaderValue::from_static("identity"));
+            }
+            Ok(res)
+        })
+    }
+}
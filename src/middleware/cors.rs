@@ -0,0 +1,30 @@
+use crate::utils::config::CorsConfig;
+use actix_cors::Cors;
+use actix_web::middleware::Condition;
+
+/// 按 `cors` 配置构造跨域中间件，未启用时不做任何处理
+///
+/// `allowed_origins` 为空时允许任意来源；设置了 `max_age_secs` 时预检响应带
+/// `Access-Control-Max-Age`，浏览器在该时间内复用预检结果，不再重复发送OPTIONS请求
+///
+/// # 示例
+/// ```
+/// use actix_web::App;
+/// use coder_openapi::middleware::cors::cors;
+/// use coder_openapi::utils::config::CorsConfig;
+///
+/// let config = CorsConfig { enabled: true, max_age_secs: Some(600), ..Default::default() };
+/// let app = App::new().wrap(cors(&config));
+/// ```
+pub fn cors(config: &CorsConfig) -> Condition<Cors> {
+    let mut cors =
+        Cors::default().allow_any_method().allow_any_header().max_age(config.max_age_secs);
+    if config.allowed_origins.is_empty() {
+        cors = cors.allow_any_origin();
+    } else {
+        for origin in &config.allowed_origins {
+            cors = cors.allowed_origin(origin);
+        }
+    }
+    Condition::new(config.enabled, cors)
+}
@@ -0,0 +1,107 @@
+use crate::error::AppError;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::Error;
+use futures::future::{ok, Ready};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// 排空期间拒绝的路径前缀，`/health` 等不受影响
+pub const DRAINED_PATH_PREFIX: &str = "/v1";
+
+/// 优雅关闭时的排空标记，各worker共享同一个实例
+#[derive(Clone, Default)]
+pub struct DrainState {
+    draining: Arc<AtomicBool>,
+}
+
+impl DrainState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 开始排空：之后的新请求返回503，进行中的请求不受影响
+    pub fn start(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+}
+
+/// 排空中间件：[`DrainState`] 开始排空后，`/v1` 下的新请求返回503
+///
+/// # 示例
+/// ```
+/// use actix_web::App;
+/// use coder_openapi::middleware::draining::{DrainState, Draining};
+///
+/// let drain = DrainState::new();
+/// App::new().wrap(Draining::new(drain.clone()));
+/// // 收到关闭信号时
+/// drain.start();
+/// ```
+pub struct Draining {
+    state: DrainState,
+}
+
+impl Draining {
+    pub fn new(state: DrainState) -> Self {
+        Self { state }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Draining
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = DrainingMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(DrainingMiddleware { service, state: self.state.clone() })
+    }
+}
+
+pub struct DrainingMiddleware<S> {
+    service: S,
+    state: DrainState,
+}
+
+impl<S, B> Service<ServiceRequest> for DrainingMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(
+        &self,
+        ctx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let path = req.path();
+        let drained = path == DRAINED_PATH_PREFIX
+            || path.strip_prefix(DRAINED_PATH_PREFIX).is_some_and(|rest| rest.starts_with('/'));
+        if drained && self.state.is_draining() {
+            log::warn!("Rejecting {} {} while draining for shutdown", req.method(), path);
+            return Box::pin(async move {
+                Err(AppError::ServiceUnavailable("server is shutting down".to_string()).into())
+            });
+        }
+        Box::pin(self.service.call(req))
+    }
+}
@@ -1,21 +1,65 @@
+use crate::utils::config::get_config;
 use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
-use actix_web::Error;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{Error, HttpMessage};
 use futures::future::{ok, Ready};
 use log;
 use std::future::Future;
 use std::pin::Pin;
+use uuid::Uuid;
+
+/// 请求/响应头：请求ID，网关传入时沿用，否则由服务端生成
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// 沿用的请求ID的最大长度
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// 当前请求的ID，由 [`Logging`] 写入请求的extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// 只沿用由字母、数字和 `-_.:` 组成、不超过128个字符的请求ID，避免日志注入
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
 
 /// 用于请求计时和日志记录的中间件
 ///
+/// 为每个请求分配请求ID：请求头 `X-Request-Id` 合法时沿用，否则生成UUID。
+/// 请求ID写入日志和响应头 `X-Request-Id`，处理函数可以从extensions中读取 [`RequestId`]
+///
 /// # 示例
 /// ```
 /// use actix_web::App;
 /// use coder_openapi::middleware::Logging;
 ///
 /// App::new()
-///     .wrap(Logging);
+///     .wrap(Logging::default());
 /// ```
-pub struct Logging;
+#[derive(Clone, Copy)]
+pub struct Logging {
+    trust_request_id: bool,
+}
+
+impl Default for Logging {
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+impl Logging {
+    /// `trust_request_id` 为 `false` 时忽略请求头中的请求ID
+    pub fn new(trust_request_id: bool) -> Self {
+        Self { trust_request_id }
+    }
+
+    /// 使用配置文件中的 `server.trust_request_id`
+    pub fn from_config() -> Self {
+        Self::new(get_config().server.trust_request_id)
+    }
+}
 
 impl<S, B> Transform<S, ServiceRequest> for Logging
 where
@@ -30,12 +74,13 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ok(LoggingMiddleware { service })
+        ok(LoggingMiddleware { service, trust_request_id: self.trust_request_id })
     }
 }
 
 pub struct LoggingMiddleware<S> {
     service: S,
+    trust_request_id: bool,
 }
 
 impl<S, B> Service<ServiceRequest> for LoggingMiddleware<S>
@@ -60,12 +105,29 @@ where
         let path = req.path().to_string();
         let method = req.method().to_string();
 
+        let incoming = req.headers().get(REQUEST_ID_HEADER).map(|value| value.to_str().ok());
+        let request_id = match incoming {
+            Some(Some(id)) if self.trust_request_id && is_valid_request_id(id) => id.to_string(),
+            Some(id) => {
+                if self.trust_request_id {
+                    log::debug!("Ignoring invalid {} header: {:?}", REQUEST_ID_HEADER, id);
+                }
+                Uuid::new_v4().to_string()
+            }
+            None => Uuid::new_v4().to_string(),
+        };
+        req.extensions_mut().insert(RequestId(request_id.clone()));
+
         let fut = self.service.call(req);
 
         Box::pin(async move {
-            let res = fut.await?;
+            let mut res = fut.await?;
             let duration = start.elapsed();
-            log::info!("{} {} - {}ms", method, path, duration.as_millis());
+            log::info!("[{}] {} {} - {}ms", request_id, method, path, duration.as_millis());
+            // 请求ID只包含合法的头部字符，转换不会失败
+            if let Ok(value) = HeaderValue::from_str(&request_id) {
+                res.headers_mut().insert(HeaderName::from_static("x-request-id"), value);
+            }
             Ok(res)
         })
     }
@@ -1,4 +1,7 @@
 pub mod authentication;
+pub mod compression;
+pub mod cors;
+pub mod draining;
 pub mod error_handler;
 pub mod logging;
 
@@ -6,3 +9,4 @@ pub use crate::middleware::error_handler::error_handler;
 pub use crate::middleware::error_handler::ErrorHandlerMiddleware;
 pub use logging::Logging;
 pub use logging::LoggingMiddleware;
+pub use logging::RequestId;
@@ -1,3 +1,6 @@
 pub mod route;
 
-pub use route::{chat_routes, configure, download_routes, model_routes};
+pub use route::{
+    chat_routes, configure, configure_with_manager, conversation_routes, download_routes,
+    model_routes,
+};
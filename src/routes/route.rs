@@ -1,3 +1,4 @@
+use crate::service::chat::chat_completion::ChatCompletionService;
 use crate::utils::config::load_route_config;
 use actix_web::web;
 
@@ -12,6 +13,11 @@ pub fn chat_routes() -> actix_web::Scope {
                 .route(web::post().to(crate::controller::chat::chat_completion::chat_completion))
                 .name("chat_completions"),
         )
+        .service(
+            web::resource("/completions/{generation_id}/cancel")
+                .route(web::post().to(crate::controller::chat::chat_completion::cancel_completion))
+                .name("chat_completion_cancel"),
+        )
 }
 
 pub fn model_routes() -> actix_web::Scope {
@@ -26,16 +32,54 @@ pub fn download_routes() -> actix_web::Scope {
         .route("", web::get().to(|| async move { "Download API" }))
 }
 
+pub fn conversation_routes() -> actix_web::Scope {
+    web::scope("/conversations").service(
+        web::resource("/{conversation_id}")
+            .route(web::delete().to(crate::controller::chat::chat_completion::delete_conversation))
+            .name("conversation"),
+    )
+}
+
+pub fn fim_routes() -> actix_web::Scope {
+    web::scope("/fim").configure(crate::controller::fim::routes)
+}
+
+pub fn admin_routes() -> actix_web::Scope {
+    web::scope("/admin").configure(crate::controller::admin::routes)
+}
+
 pub fn configure(cfg: &mut web::ServiceConfig) {
-    let chat_service = crate::service::chat::ChatService::new();
     let model_manager = crate::service::models::ModelManager::new();
+    let chat_completion_service = web::Data::new(ChatCompletionService::new(model_manager.clone()));
+    configure_with_manager(cfg, model_manager, chat_completion_service);
+}
 
+/// 使用共享的 `model_manager` 与 `chat_completion_service` 注册路由
+///
+/// 各worker共用已加载的模型与同一个服务，服务需在 `HttpServer::new` 之外创建
+pub fn configure_with_manager(
+    cfg: &mut web::ServiceConfig,
+    model_manager: crate::service::models::ModelManager,
+    chat_completion_service: web::Data<ChatCompletionService>,
+) {
+    let chat_service = crate::service::chat::ChatService::new();
+
+    cfg.route("/metrics", web::get().to(crate::controller::metrics::metrics));
+    cfg.service(
+        web::scope("/health")
+            .app_data(web::Data::new(model_manager.clone()))
+            .configure(crate::controller::health::routes),
+    );
     cfg.service(
         web::scope("/v1")
             .app_data(web::Data::new(chat_service))
             .app_data(web::Data::new(model_manager))
+            .app_data(chat_completion_service)
             .service(chat_routes())
             .service(model_routes())
-            .service(download_routes()),
+            .service(download_routes())
+            .service(conversation_routes())
+            .service(fim_routes())
+            .service(admin_routes()),
     );
 }
@@ -1,28 +1,352 @@
 use crate::entities::chat_completion_message::ChatCompletionMessage;
 use crate::error::AppError;
-use crate::service::models::deepseek_coder::DeepseekCoder;
-use crate::service::models::yi_coder::YiCoder;
+use crate::service::chat::conversation::ConversationStore;
+use crate::service::chat::generation::{ActiveGenerations, CancellationToken, GenerationProgress};
+use crate::service::chat::prompt::{assistant_prefill, dedupe_consecutive, render_fim_prompt};
+use crate::service::chat::prompt_cache::{is_cacheable, PromptCache, PromptCacheKey};
+use crate::service::chat::webhook::WebhookAllowlist;
+use crate::service::models::json_schema::ResponseFormat;
+use crate::service::models::overrides::ModelOverrides;
+use crate::service::models::sampling::SamplingConfig;
+use crate::service::models::scheduler::Priority;
+use crate::service::models::{
+    CompletionModel, GenerationDefaults, GenerationOutput, ModelManager, TruncationStrategy,
+};
+use crate::utils::config::{get_config, ChatDefaults};
+use candle_core::Device;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct ChatCompletionParams {
     pub temperature: Option<f32>,
+    /// 每生成一个token temperature降低的幅度，最低降到 [`MIN_ANNEALED_TEMPERATURE`]
+    ///
+    /// [`MIN_ANNEALED_TEMPERATURE`]: crate::service::models::sampling::MIN_ANNEALED_TEMPERATURE
+    pub temperature_decay: Option<f32>,
     pub top_p: Option<f32>,
+    /// 只从概率最高的 `top_k` 个token中采样
+    pub top_k: Option<usize>,
+    pub presence_penalty: Option<f32>,
+    pub frequency_penalty: Option<f32>,
     pub n: Option<usize>,
+    /// 生成至少这么多token后才允许生成EOS
+    pub min_tokens: Option<usize>,
     pub max_tokens: Option<usize>,
+    /// 禁止生成重复的该长度n-gram，避免模型陷入循环
+    pub no_repeat_ngram_size: Option<usize>,
     pub stream: Option<bool>,
+    /// 采样随机种子，相同种子与参数得到相同结果
+    pub seed: Option<u64>,
+    /// 生成截止时间，到达后停止生成并返回已生成的部分
+    pub deadline: Option<Instant>,
+    /// 取消标记，被取消后停止生成并返回已生成的部分
+    pub cancellation: Option<CancellationToken>,
+    /// 每生成一个token计数一次，供 `GET /v1/admin/generations` 查看进度
+    pub progress: Option<GenerationProgress>,
+    /// 达到模型并发上限时的排队优先级，默认 `normal`
+    pub priority: Option<Priority>,
+    /// 提示词超出上下文长度时的截断策略，未设置时不截断
+    pub truncation: Option<TruncationStrategy>,
+    /// 输出格式约束，`json_schema` 时只生成符合schema的JSON
+    pub response_format: Option<ResponseFormat>,
+    /// 为 `true` 时回复以渲染后的提示词开头，即OpenAI旧版接口的 `echo`
+    pub echo: Option<bool>,
+    /// 只对本次请求生效的模型参数覆盖，来自请求头 `X-Model-Overrides`
+    pub model_overrides: Option<ModelOverrides>,
+    /// 执行推理的设备，来自请求头 `X-Device`；未设置时使用模型加载时的设备
+    pub device: Option<Device>,
+    /// 服务端的生成token数绝对上限，由服务按 `inference.absolute_max_tokens` 设置
+    pub absolute_max_tokens: Option<usize>,
+}
+
+impl ChatCompletionParams {
+    /// 为请求中未设置的参数填入默认值
+    ///
+    /// 优先级从高到低：请求参数、模型 `generation_config.json` 中的值 (`model`)、
+    /// `config/app.yml` 的 `chat.defaults` (`app`)。`top_k` 与 `max_tokens` 只来自
+    /// `generation_config.json`，两处都未设置时保持 `None`；`n` 与 `stream` 只来自 `chat.defaults`
+    pub fn with_defaults(self, model: &GenerationDefaults, app: &ChatDefaults) -> Self {
+        Self {
+            temperature: self.temperature.or(model.temperature).or(Some(app.temperature)),
+            top_p: self.top_p.or(model.top_p).or(Some(app.top_p)),
+            top_k: self.top_k.or(model.top_k),
+            max_tokens: self.max_tokens.or(model.max_tokens),
+            n: self.n.or(Some(app.n)),
+            stream: self.stream.or(Some(app.stream)),
+            ..self
+        }
+    }
 }
 
-pub struct ChatCompletionService;
+/// 校验 `n` 在 `1..=max_n` 范围内
+pub fn validate_n(n: Option<usize>, max_n: usize) -> Result<(), AppError> {
+    match n {
+        Some(n) if n == 0 || n > max_n => {
+            Err(AppError::InvalidParameter(format!("n must be between 1 and {}, got {}", max_n, n)))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 校验user与assistant消息交替出现，其他角色（如system、tool）不参与检查
+///
+/// 最后一条为assistant时作为回复前缀续写，仍然允许
+pub fn validate_turn_order(messages: &[ChatCompletionMessage]) -> Result<(), AppError> {
+    let mut previous: Option<&str> = None;
+    for (index, message) in messages.iter().enumerate() {
+        let role = message.role.as_str();
+        if role != "user" && role != "assistant" {
+            continue;
+        }
+        if previous == Some(role) {
+            return Err(AppError::ValidationError(format!(
+                "messages[{}] is a second consecutive {} message; user and assistant messages must alternate",
+                index, role
+            )));
+        }
+        previous = Some(role);
+    }
+    Ok(())
+}
+
+/// 请求中消息数量与提示词长度的上限，避免超大请求占满tokenizer
+#[derive(Debug, Clone, Copy)]
+pub struct PromptLimits {
+    pub max_messages: usize,
+    /// 所有消息内容的字符数之和上限
+    pub max_prompt_chars: usize,
+}
+
+impl PromptLimits {
+    /// 校验消息数量与提示词字符数，超出时返回 `AppError::ValidationError`
+    pub fn validate(&self, messages: &[ChatCompletionMessage]) -> Result<(), AppError> {
+        if messages.len() > self.max_messages {
+            return Err(AppError::ValidationError(format!(
+                "messages has {} entries, exceeding the limit of {}",
+                messages.len(),
+                self.max_messages
+            )));
+        }
+        let prompt_chars: usize =
+            messages.iter().map(|message| message.content.chars().count()).sum();
+        if prompt_chars > self.max_prompt_chars {
+            return Err(AppError::ValidationError(format!(
+                "messages content has {} characters, exceeding the limit of {}",
+                prompt_chars, self.max_prompt_chars
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 流式补全中的一段文本
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamToken {
+    /// 所属choice的下标
+    pub index: usize,
+    pub text: String,
+}
+
+/// 每个choice的token通道容量
+const STREAM_CHOICE_CAPACITY: usize = 16;
+
+/// 补全结果
+#[derive(Debug)]
+pub struct Completion {
+    pub outputs: Vec<GenerationOutput>,
+    /// 请求的模型ID，别名已解析为实际模型ID
+    pub model: String,
+    /// 主模型不可用时实际使用的备用模型
+    pub fallback_model: Option<String>,
+}
+
+/// 主模型不可用、可以尝试备用模型的错误
+fn is_unavailable(error: &AppError) -> bool {
+    matches!(error, AppError::InvalidModel(_) | AppError::ModelDownloading(_) | AppError::Model(_))
+}
+
+pub struct ChatCompletionService {
+    model_manager: ModelManager,
+    prompt_cache: Option<PromptCache>,
+    conversations: ConversationStore,
+    generations: ActiveGenerations,
+    /// 模型ID到备用模型ID的映射
+    fallbacks: HashMap<String, String>,
+    /// 流式响应在第一个token之前发送keepalive注释的间隔
+    stream_keepalive: Duration,
+    prompt_limits: PromptLimits,
+    webhooks: WebhookAllowlist,
+    /// 是否要求user与assistant消息交替出现
+    strict_turn_order: bool,
+    /// 是否合并连续重复的消息
+    dedupe_consecutive: bool,
+    /// 单次生成token数的绝对上限
+    absolute_max_tokens: Option<usize>,
+}
 
 impl Default for ChatCompletionService {
     fn default() -> Self {
-        Self::new()
+        Self::new(ModelManager::new())
     }
 }
 
 impl ChatCompletionService {
-    pub fn new() -> Self {
-        Self
+    pub fn new(model_manager: ModelManager) -> Self {
+        let chat_config = &get_config().chat;
+        let cache_config = &chat_config.prompt_cache;
+        let prompt_cache = cache_config.enabled.then(|| PromptCache::new(cache_config.capacity));
+        let conversations = ConversationStore::new(chat_config.conversations.capacity);
+        let fallbacks = get_config()
+            .models
+            .iter()
+            .filter_map(|(model_id, model_config)| {
+                model_config.fallback.as_ref().map(|fallback| (model_id.clone(), fallback.clone()))
+            })
+            .collect();
+        Self {
+            model_manager,
+            prompt_cache,
+            conversations,
+            generations: ActiveGenerations::new(),
+            fallbacks,
+            stream_keepalive: Duration::from_millis(chat_config.stream_keepalive_ms),
+            prompt_limits: PromptLimits {
+                max_messages: chat_config.max_messages,
+                max_prompt_chars: chat_config.max_prompt_chars,
+            },
+            webhooks: WebhookAllowlist::from_config(&chat_config.webhooks),
+            strict_turn_order: chat_config.strict_turn_order,
+            dedupe_consecutive: chat_config.dedupe_consecutive,
+            absolute_max_tokens: get_config().inference.absolute_max_tokens,
+        }
+    }
+
+    /// 设置模型的备用模型，覆盖配置文件中的 `fallback`
+    pub fn with_fallback(mut self, model_id: &str, fallback: &str) -> Self {
+        self.fallbacks.insert(model_id.to_string(), fallback.to_string());
+        self
+    }
+
+    /// 设置流式响应的keepalive间隔，覆盖配置文件中的 `chat.stream_keepalive_ms`
+    pub fn with_stream_keepalive(mut self, interval: Duration) -> Self {
+        self.stream_keepalive = interval;
+        self
+    }
+
+    /// 设置单次生成token数的绝对上限，覆盖配置文件中的 `inference.absolute_max_tokens`
+    pub fn with_absolute_max_tokens(mut self, absolute_max_tokens: usize) -> Self {
+        self.absolute_max_tokens = Some(absolute_max_tokens);
+        self
+    }
+
+    /// 流式响应在第一个token之前发送keepalive注释的间隔
+    pub fn stream_keepalive(&self) -> Duration {
+        self.stream_keepalive
+    }
+
+    /// 设置消息数量与提示词字符数上限，覆盖配置文件中的 `chat.max_messages` 与 `chat.max_prompt_chars`
+    pub fn with_prompt_limits(mut self, max_messages: usize, max_prompt_chars: usize) -> Self {
+        self.prompt_limits = PromptLimits { max_messages, max_prompt_chars };
+        self
+    }
+
+    /// 消息数量与提示词字符数上限
+    pub fn prompt_limits(&self) -> PromptLimits {
+        self.prompt_limits
+    }
+
+    /// 设置是否要求user与assistant消息交替出现，覆盖配置文件中的 `chat.strict_turn_order`
+    pub fn with_strict_turn_order(mut self, strict: bool) -> Self {
+        self.strict_turn_order = strict;
+        self
+    }
+
+    /// 设置是否合并连续重复的消息，覆盖配置文件中的 `chat.dedupe_consecutive`
+    pub fn with_dedupe_consecutive(mut self, dedupe: bool) -> Self {
+        self.dedupe_consecutive = dedupe;
+        self
+    }
+
+    /// 开启 `dedupe_consecutive` 时合并role与content都相同的连续消息，见 [`dedupe_consecutive`]
+    pub fn dedupe_messages(
+        &self,
+        messages: Vec<ChatCompletionMessage>,
+    ) -> Vec<ChatCompletionMessage> {
+        if !self.dedupe_consecutive {
+            return messages;
+        }
+        let count = messages.len();
+        let messages = dedupe_consecutive(messages);
+        if messages.len() < count {
+            log::debug!("Dropped {} repeated consecutive messages", count - messages.len());
+        }
+        messages
+    }
+
+    /// 开启 `strict_turn_order` 时校验消息顺序，见 [`validate_turn_order`]
+    pub fn validate_turn_order(&self, messages: &[ChatCompletionMessage]) -> Result<(), AppError> {
+        if self.strict_turn_order {
+            // 重复的消息会在渲染前合并，不应因此被拒绝
+            if self.dedupe_consecutive {
+                validate_turn_order(&dedupe_consecutive(messages.to_vec()))?;
+            } else {
+                validate_turn_order(messages)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 设置允许的webhook地址，覆盖配置文件中的 `chat.webhooks`
+    pub fn with_webhook_allowlist(mut self, webhooks: WebhookAllowlist) -> Self {
+        self.webhooks = webhooks;
+        self
+    }
+
+    /// 允许的webhook地址
+    pub fn webhooks(&self) -> &WebhookAllowlist {
+        &self.webhooks
+    }
+
+    /// 将模型别名解析为实际模型ID
+    pub fn resolve_model_id<'a>(&'a self, model: &'a str) -> &'a str {
+        self.model_manager.resolve_model_id(model)
+    }
+
+    /// 模型 `generation_config.json` 中的采样默认值，模型未配置或文件尚未下载时为空
+    pub fn generation_defaults(&self, model: &str) -> GenerationDefaults {
+        let model_id = self.resolve_model_id(model);
+        match self.model_manager.get_generation_defaults(model_id) {
+            Ok(defaults) => defaults,
+            Err(AppError::NotFound) => GenerationDefaults::default(),
+            Err(e) => {
+                log::warn!("Ignoring generation_config.json of model {}: {}", model_id, e);
+                GenerationDefaults::default()
+            }
+        }
+    }
+
+    /// 启用指定容量的提示词缓存，覆盖配置文件中的 `chat.prompt_cache`
+    pub fn with_prompt_cache(mut self, capacity: usize) -> Self {
+        self.prompt_cache = Some(PromptCache::new(capacity));
+        self
+    }
+
+    /// 提示词缓存，未启用时为 `None`
+    pub fn prompt_cache(&self) -> Option<&PromptCache> {
+        self.prompt_cache.as_ref()
+    }
+
+    /// 服务端保存的会话
+    pub fn conversations(&self) -> &ConversationStore {
+        &self.conversations
+    }
+
+    /// 进行中、可以取消的生成
+    pub fn generations(&self) -> &ActiveGenerations {
+        &self.generations
     }
 
     pub async fn complete(
@@ -30,35 +354,315 @@ impl ChatCompletionService {
         model: &str,
         messages: Vec<ChatCompletionMessage>,
         params: ChatCompletionParams,
-    ) -> Result<Vec<ChatCompletionMessage>, AppError> {
+    ) -> Result<Vec<GenerationOutput>, AppError> {
+        self.complete_with_fallback(model, messages, params).await.map(|c| c.outputs)
+    }
+
+    /// 补全，主模型不可用时使用 `models.<id>.fallback` 配置的备用模型重试一次
+    ///
+    /// `model` 可以是 `models.aliases` 中的别名
+    pub async fn complete_with_fallback(
+        &self,
+        model: &str,
+        messages: Vec<ChatCompletionMessage>,
+        params: ChatCompletionParams,
+    ) -> Result<Completion, AppError> {
+        let requested = model;
+        let model = self.model_manager.resolve_model_id(requested);
+        if model != requested {
+            log::debug!("Resolved model alias {} to {}", requested, model);
+        }
         log::debug!("Starting completion for model: {}", model);
+        let messages = self.dedupe_messages(messages);
         log::debug!("Input messages count: {}", messages.len());
         log::debug!("Completion params: {:?}", params);
 
-        let result = match model {
-            "deepseek-coder" => {
-                log::info!("Initializing Deepseek Coder model");
-                let model = DeepseekCoder::new().await?;
-                log::info!("Starting Deepseek Coder inference");
-                model.infer(messages, params).await
-            }
-            "yi-coder" => {
-                log::info!("Initializing Yi Coder model");
-                let model = YiCoder::new().await?;
-                log::info!("Starting Yi Coder inference");
-                model.infer(messages, params).await
-            }
-            _ => {
-                log::error!("Invalid model requested: {}", model);
-                Err(AppError::InvalidModel(model.to_string()))
-            }
+        let params =
+            ChatCompletionParams { absolute_max_tokens: self.absolute_max_tokens, ..params };
+        validate_n(params.n, get_config().chat.max_n)?;
+        SamplingConfig::try_new(&params)?;
+        let result = match self.generate(model, &messages, &params).await {
+            Err(e) if is_unavailable(&e) => match self.fallbacks.get(model) {
+                // 只回退一次，不沿备用模型的fallback继续，避免循环
+                Some(fallback) => {
+                    log::warn!("Model {} unavailable ({}), falling back to {}", model, e, fallback);
+                    self.generate(fallback, &messages, &params).await.map(|outputs| Completion {
+                        outputs,
+                        model: model.to_string(),
+                        fallback_model: Some(fallback.clone()),
+                    })
+                }
+                None => Err(e),
+            },
+            result => result.map(|outputs| Completion {
+                outputs,
+                model: model.to_string(),
+                fallback_model: None,
+            }),
         };
 
         match &result {
-            Ok(messages) => log::debug!("Successfully generated {} messages", messages.len()),
+            Ok(completion) => {
+                log::debug!("Successfully generated {} choices", completion.outputs.len())
+            }
             Err(e) => log::error!("Error during completion: {}", e),
         }
 
         result
     }
+
+    /// 在会话中补全：在新消息前拼接会话历史，并将新消息与第一个choice的回复写回会话
+    ///
+    /// 新消息以assistant前缀结尾时，前缀已包含在回复中，不再单独保存
+    pub async fn complete_in_conversation(
+        &self,
+        conversation_id: &str,
+        model: &str,
+        messages: Vec<ChatCompletionMessage>,
+        params: ChatCompletionParams,
+    ) -> Result<Completion, AppError> {
+        // 回显的提示词已包含会话历史，写回会话会使历史重复
+        if params.echo.unwrap_or(false) {
+            return Err(AppError::InvalidParameter(
+                "echo is not supported with conversation_id".to_string(),
+            ));
+        }
+        let mut full_messages = self.conversations.history(conversation_id);
+        log::debug!("Conversation {} has {} prior messages", conversation_id, full_messages.len());
+        full_messages.extend(messages.iter().cloned());
+
+        let completion = self.complete_with_fallback(model, full_messages, params).await?;
+        if let Some(reply) = completion.outputs.first() {
+            let mut turn = messages;
+            if assistant_prefill(&turn).is_some() {
+                turn.pop();
+            }
+            turn.push(ChatCompletionMessage {
+                role: "assistant".to_string(),
+                content: reply.text.clone(),
+                name: None,
+            });
+            self.conversations.append(conversation_id, turn);
+        }
+        Ok(completion)
+    }
+
+    /// 流式补全：逐token通过 `sender` 发送生成的文本及其所属choice的下标
+    ///
+    /// `n > 1` 时各choice同时生成，token交错发送。不使用提示词缓存，也不回退到备用模型
+    pub async fn complete_stream(
+        &self,
+        model: &str,
+        messages: Vec<ChatCompletionMessage>,
+        params: ChatCompletionParams,
+        sender: &mpsc::Sender<StreamToken>,
+    ) -> Result<Completion, AppError> {
+        let model = self.model_manager.resolve_model_id(model);
+        log::debug!("Starting streaming completion for model: {}", model);
+        let messages = self.dedupe_messages(messages);
+        let params =
+            ChatCompletionParams { absolute_max_tokens: self.absolute_max_tokens, ..params };
+        validate_n(params.n, get_config().chat.max_n)?;
+        SamplingConfig::try_new(&params)?;
+        self.check_model(model).await?;
+
+        let completion_model = self.request_model(model, &params).await?;
+        let _permit =
+            self.model_manager.acquire_permit(model, params.priority.unwrap_or_default()).await?;
+        let prompt = completion_model.render_prompt(&messages);
+        let prefix = reply_prefix(&prompt, &messages, &params).filter(|prefix| !prefix.is_empty());
+        let n = params.n.unwrap_or(1).max(1);
+        let choices = (0..n).map(|index| {
+            let choice_params = ChatCompletionParams {
+                seed: params.seed.map(|seed| seed.wrapping_add(index as u64)),
+                ..params.clone()
+            };
+            let (choice_tx, mut choice_rx) = mpsc::channel(STREAM_CHOICE_CAPACITY);
+            let completion_model = completion_model.clone();
+            let prompt = &prompt;
+            let generate = async move {
+                // 续写或回显时先发送前缀，客户端拼接后得到完整回复
+                if let Some(prefix) = prefix {
+                    let _ = choice_tx.send(prefix.to_string()).await;
+                }
+                completion_model.generate_stream(prompt, &choice_params, Some(&choice_tx)).await
+            };
+            let forward = async move {
+                while let Some(text) = choice_rx.recv().await {
+                    if sender.send(StreamToken { index, text }).await.is_err() {
+                        break;
+                    }
+                }
+            };
+            async move { futures::join!(generate, forward).0 }
+        });
+        let mut outputs = Vec::with_capacity(n);
+        for output in futures::future::join_all(choices).await {
+            let mut output = output?;
+            if let Some(prefix) = prefix {
+                output.text.insert_str(0, prefix);
+            }
+            outputs.push(output);
+        }
+        Ok(Completion { outputs, model: model.to_string(), fallback_model: None })
+    }
+
+    /// fill-in-the-middle补全：用模型的FIM哨兵token包裹 `prefix` 与 `suffix`，生成两者之间的部分
+    ///
+    /// 模型未配置 `models.<id>.fim` 时返回 `AppError::InvalidParameter`。
+    /// 不使用提示词缓存，也不回退到备用模型
+    pub async fn complete_fim(
+        &self,
+        model: &str,
+        prefix: &str,
+        suffix: &str,
+        params: ChatCompletionParams,
+    ) -> Result<Completion, AppError> {
+        let model = self.model_manager.resolve_model_id(model);
+        log::debug!("Starting FIM completion for model: {}", model);
+        let params =
+            ChatCompletionParams { absolute_max_tokens: self.absolute_max_tokens, ..params };
+        validate_n(params.n, get_config().chat.max_n)?;
+        SamplingConfig::try_new(&params)?;
+        self.check_model(model).await?;
+
+        let completion_model = self.request_model(model, &params).await?;
+        let Some(tokens) = completion_model.fim_tokens() else {
+            return Err(AppError::InvalidParameter(format!(
+                "Model {} does not support fill-in-the-middle completion",
+                model
+            )));
+        };
+        let prompt = render_fim_prompt(prefix, suffix, &tokens);
+
+        let _permit =
+            self.model_manager.acquire_permit(model, params.priority.unwrap_or_default()).await?;
+        let n = params.n.unwrap_or(1).max(1);
+        let mut outputs = Vec::with_capacity(n);
+        for i in 0..n {
+            let choice_params = ChatCompletionParams {
+                seed: params.seed.map(|seed| seed.wrapping_add(i as u64)),
+                ..params.clone()
+            };
+            let output = completion_model.generate(&prompt, &choice_params).await?;
+            let stop = output.timed_out || output.cancelled;
+            outputs.push(output);
+            if stop {
+                break;
+            }
+        }
+        Ok(Completion { outputs, model: model.to_string(), fallback_model: None })
+    }
+
+    /// 获取本次请求使用的模型实例：设置了 `device` 时使用该设备上的副本，再应用 `model_overrides`
+    async fn request_model(
+        &self,
+        model: &str,
+        params: &ChatCompletionParams,
+    ) -> Result<Arc<dyn CompletionModel>, AppError> {
+        let completion_model = match &params.device {
+            Some(device) => self.model_manager.get_or_load_model_on(model, device).await?,
+            None => self.model_manager.get_or_load_model(model).await?,
+        };
+        apply_overrides(completion_model, params)
+    }
+
+    /// 检查模型存在且未在下载中
+    async fn check_model(&self, model: &str) -> Result<(), AppError> {
+        if self.model_manager.is_download_pending(model) {
+            log::warn!("Model {} is still downloading", model);
+            return Err(AppError::ModelDownloading(model.to_string()));
+        }
+        if self.model_manager.get_model_status(model).await.is_none() {
+            log::error!("Invalid model requested: {}", model);
+            return Err(AppError::InvalidModel(model.to_string()));
+        }
+        Ok(())
+    }
+
+    async fn generate(
+        &self,
+        model: &str,
+        messages: &[ChatCompletionMessage],
+        params: &ChatCompletionParams,
+    ) -> Result<Vec<GenerationOutput>, AppError> {
+        self.check_model(model).await?;
+
+        log::info!("Loading model: {}", model);
+        let completion_model = self.request_model(model, params).await?;
+        let prompt = completion_model.render_prompt(messages);
+
+        // 以模型实际分词的提示词为键，不同模型的角色标记不同
+        let cache_key = self
+            .prompt_cache
+            .as_ref()
+            .filter(|_| is_cacheable(params))
+            .map(|_| PromptCacheKey::new(model, &prompt, params));
+        if let (Some(cache), Some(key)) = (&self.prompt_cache, &cache_key) {
+            if let Some(outputs) = cache.get(key) {
+                log::debug!("Serving completion for model {} from prompt cache", model);
+                return Ok(outputs);
+            }
+        }
+
+        // 持有许可直到所有choice生成完毕
+        let _permit =
+            self.model_manager.acquire_permit(model, params.priority.unwrap_or_default()).await?;
+        let n = params.n.unwrap_or(1).max(1);
+        let mut outputs = Vec::with_capacity(n);
+        for i in 0..n {
+            log::info!("Starting {} inference", completion_model.model_id());
+            // 每个choice使用不同的种子，避免n个结果完全相同
+            let choice_params = ChatCompletionParams {
+                seed: params.seed.map(|seed| seed.wrapping_add(i as u64)),
+                ..params.clone()
+            };
+            let mut output = completion_model.generate(&prompt, &choice_params).await?;
+            // 续写时返回包含前缀的完整回复，回显时返回包含提示词的完整文本
+            if let Some(prefix) = reply_prefix(&prompt, messages, params) {
+                output.text.insert_str(0, prefix);
+            }
+            let (timed_out, cancelled) = (output.timed_out, output.cancelled);
+            outputs.push(output);
+            if timed_out {
+                log::warn!("Generation for model {} reached its deadline", model);
+                break;
+            }
+            if cancelled {
+                log::warn!("Generation for model {} was cancelled", model);
+                break;
+            }
+        }
+
+        // 超时或被取消的部分结果不写入缓存
+        let partial = outputs.iter().any(|output| output.timed_out || output.cancelled);
+        if let (Some(cache), Some(key), false) = (&self.prompt_cache, cache_key, partial) {
+            cache.insert(key, outputs.clone());
+        }
+        Ok(outputs)
+    }
+}
+
+/// 请求设置了 `model_overrides` 时换成应用覆盖后、只供本次请求使用的模型实例
+fn apply_overrides(
+    model: Arc<dyn CompletionModel>,
+    params: &ChatCompletionParams,
+) -> Result<Arc<dyn CompletionModel>, AppError> {
+    match &params.model_overrides {
+        Some(overrides) => model.with_overrides(overrides),
+        None => Ok(model),
+    }
+}
+
+/// 回复开头附加的文本：`echo` 时为提示词（已以续写前缀结尾），否则为续写前缀
+fn reply_prefix<'a>(
+    prompt: &'a str,
+    messages: &'a [ChatCompletionMessage],
+    params: &ChatCompletionParams,
+) -> Option<&'a str> {
+    if params.echo.unwrap_or(false) {
+        Some(prompt)
+    } else {
+        assistant_prefill(messages)
+    }
 }
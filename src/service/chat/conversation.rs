@@ -0,0 +1,52 @@
+//! 会话存储
+//!
+//! 请求携带 `conversation_id` 时，服务端保存该会话的历史消息，
+//! 后续请求只需发送新一轮的消息。会话数量超出容量时淘汰最久未使用的会话。
+//! 未携带 `conversation_id` 的请求不受影响。
+use crate::entities::chat_completion_message::ChatCompletionMessage;
+use crate::utils::lru::LruCache;
+use std::sync::Mutex;
+
+/// 固定容量的内存会话存储
+pub struct ConversationStore {
+    conversations: Mutex<LruCache<String, Vec<ChatCompletionMessage>>>,
+}
+
+impl ConversationStore {
+    pub fn new(capacity: usize) -> Self {
+        Self { conversations: Mutex::new(LruCache::new(capacity)) }
+    }
+
+    /// 会话的历史消息，会话不存在时为空
+    pub fn history(&self, conversation_id: &str) -> Vec<ChatCompletionMessage> {
+        self.conversations
+            .lock()
+            .unwrap()
+            .get(&conversation_id.to_string())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// 将消息追加到会话末尾，会话不存在时创建
+    pub fn append(&self, conversation_id: &str, messages: Vec<ChatCompletionMessage>) {
+        let mut conversations = self.conversations.lock().unwrap();
+        let key = conversation_id.to_string();
+        match conversations.get_mut(&key) {
+            Some(history) => history.extend(messages),
+            None => conversations.insert(key, messages),
+        }
+    }
+
+    /// 删除会话，会话不存在时返回 `false`
+    pub fn remove(&self, conversation_id: &str) -> bool {
+        self.conversations.lock().unwrap().remove(&conversation_id.to_string()).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.conversations.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,158 @@
+//! 进行中的生成
+//!
+//! 服务端为每个补全请求分配一个生成ID并登记取消标记，客户端可以通过
+//! `POST /v1/chat/completions/{id}/cancel` 中止生成，生成循环在每一步检查该标记。
+//! 登记中同时记录模型与已生成的token数，供 `GET /v1/admin/generations` 查看。
+//! 登记在 [`GenerationHandle`] 被drop时移除，请求处理被中途丢弃（例如客户端断开）时也不会残留。
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// 可在多个线程间共享的取消标记
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// 已生成的token计数，生成循环每生成一个token加一
+#[derive(Debug, Clone, Default)]
+pub struct GenerationProgress(Arc<AtomicUsize>);
+
+impl GenerationProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_token(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn tokens(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// 登记生成后返回给请求处理函数的句柄，drop时移除登记
+///
+/// 持有者应在生成结束前一直持有该句柄
+pub struct GenerationHandle {
+    pub cancellation: CancellationToken,
+    pub progress: GenerationProgress,
+    id: String,
+    generations: Arc<Mutex<HashMap<String, ActiveGeneration>>>,
+}
+
+impl Drop for GenerationHandle {
+    fn drop(&mut self) {
+        if let Ok(mut generations) = self.generations.lock() {
+            generations.remove(&self.id);
+        }
+    }
+}
+
+struct ActiveGeneration {
+    model: String,
+    started_at: Instant,
+    cancellation: CancellationToken,
+    progress: GenerationProgress,
+}
+
+/// 进行中的生成的概况
+#[derive(Debug, Clone, Serialize)]
+pub struct GenerationSummary {
+    pub id: String,
+    pub model: String,
+    pub elapsed_ms: u128,
+    /// 已生成的token数，`n` 大于1时为所有choice之和
+    pub tokens: usize,
+}
+
+/// 生成ID到取消标记与进度的映射
+#[derive(Default)]
+pub struct ActiveGenerations {
+    generations: Arc<Mutex<HashMap<String, ActiveGeneration>>>,
+}
+
+impl ActiveGenerations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记 `model` 的一个生成并返回其句柄，同一ID的生成仍在进行时返回 `None`
+    ///
+    /// 句柄被drop时移除登记
+    pub fn register(&self, generation_id: &str, model: &str) -> Option<GenerationHandle> {
+        let mut generations = self.generations.lock().unwrap();
+        if generations.contains_key(generation_id) {
+            return None;
+        }
+        let generation = ActiveGeneration {
+            model: model.to_string(),
+            started_at: Instant::now(),
+            cancellation: CancellationToken::new(),
+            progress: GenerationProgress::new(),
+        };
+        let handle = GenerationHandle {
+            cancellation: generation.cancellation.clone(),
+            progress: generation.progress.clone(),
+            id: generation_id.to_string(),
+            generations: self.generations.clone(),
+        };
+        generations.insert(generation_id.to_string(), generation);
+        Some(handle)
+    }
+
+    /// 取消生成，生成不存在或已结束时返回 `false`
+    pub fn cancel(&self, generation_id: &str) -> bool {
+        match self.generations.lock().unwrap().get(generation_id) {
+            Some(generation) => {
+                generation.cancellation.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 进行中的生成ID
+    pub fn ids(&self) -> Vec<String> {
+        self.generations.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// 进行中的生成，按开始时间排序
+    pub fn list(&self) -> Vec<GenerationSummary> {
+        let generations = self.generations.lock().unwrap();
+        let mut active: Vec<_> = generations.iter().collect();
+        active.sort_by_key(|(_, generation)| generation.started_at);
+        active
+            .into_iter()
+            .map(|(id, generation)| GenerationSummary {
+                id: id.clone(),
+                model: generation.model.clone(),
+                elapsed_ms: generation.started_at.elapsed().as_millis(),
+                tokens: generation.progress.tokens(),
+            })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.generations.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -1,4 +1,9 @@
 pub mod chat_completion;
+pub mod conversation;
+pub mod generation;
+pub mod prompt;
+pub mod prompt_cache;
+pub mod webhook;
 
 pub struct ChatService;
 
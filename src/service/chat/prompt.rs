@@ -0,0 +1,48 @@
+use crate::entities::chat_completion_message::ChatCompletionMessage;
+use crate::utils::config::{FimTokens, RoleMarkers};
+
+/// 将对话消息拼接为模型输入的提示词
+///
+/// 每条消息渲染为 `role: content`，带名称的消息渲染为 `role name=foo: content`，
+/// 消息之间以换行分隔。最后一条消息为assistant时，其内容作为回复的前缀，
+/// 提示词以该前缀结尾，模型从前缀处继续生成
+pub fn render_prompt(messages: &[ChatCompletionMessage]) -> String {
+    render_prompt_with(messages, &RoleMarkers::default())
+}
+
+/// 使用 `markers` 中配置的角色前缀渲染提示词
+///
+/// 配置了前缀的角色渲染为 `{marker}content`，带名称时为 `{marker}foo: content`；
+/// 其他角色使用 [`render_prompt`] 的默认格式
+pub fn render_prompt_with(messages: &[ChatCompletionMessage], markers: &RoleMarkers) -> String {
+    messages.iter().map(|message| render_message(message, markers)).collect::<Vec<_>>().join("\n")
+}
+
+fn render_message(message: &ChatCompletionMessage, markers: &RoleMarkers) -> String {
+    match (markers.marker(&message.role), &message.name) {
+        (Some(marker), Some(name)) => format!("{}{}: {}", marker, name, message.content),
+        (Some(marker), None) => format!("{}{}", marker, message.content),
+        (None, Some(name)) => format!("{} name={}: {}", message.role, name, message.content),
+        (None, None) => format!("{}: {}", message.role, message.content),
+    }
+}
+
+/// 最后一条消息为assistant时返回其内容，作为需要续写的回复前缀
+pub fn assistant_prefill(messages: &[ChatCompletionMessage]) -> Option<&str> {
+    messages.last().filter(|message| message.role == "assistant").map(|m| m.content.as_str())
+}
+
+/// 合并连续重复的消息：role与content都相同的相邻消息只保留第一条
+///
+/// 用于客户端意外重发同一条消息时避免上下文重复，见 `chat.dedupe_consecutive`
+pub fn dedupe_consecutive(mut messages: Vec<ChatCompletionMessage>) -> Vec<ChatCompletionMessage> {
+    messages.dedup_by(|next, prev| next.role == prev.role && next.content == prev.content);
+    messages
+}
+
+/// 使用 `tokens` 中的哨兵token拼接fill-in-the-middle提示词
+///
+/// 渲染为 `{prefix}前缀{suffix}后缀{middle}`，模型生成的文本即前缀与后缀之间的部分
+pub fn render_fim_prompt(prefix: &str, suffix: &str, tokens: &FimTokens) -> String {
+    format!("{}{}{}{}{}", tokens.prefix, prefix, tokens.suffix, suffix, tokens.middle)
+}
@@ -0,0 +1,131 @@
+//! 提示词缓存
+//!
+//! 对确定性请求（temperature≈0 或指定了seed）按 `(模型, 模型渲染后实际分词的提示词, 参数哈希)`
+//! 缓存生成结果，重复的请求直接返回缓存而不再执行前向传播。
+//! 流式请求和非确定性请求不会被缓存。
+use crate::service::chat::chat_completion::ChatCompletionParams;
+use crate::service::metrics::metrics;
+use crate::service::models::GenerationOutput;
+use crate::utils::lru::LruCache;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// 低于该temperature的采样视为确定性的
+pub const DETERMINISTIC_TEMPERATURE: f32 = 1e-3;
+
+/// 缓存键
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PromptCacheKey {
+    model: String,
+    prompt: String,
+    params_hash: u64,
+}
+
+impl PromptCacheKey {
+    pub fn new(model: &str, prompt: &str, params: &ChatCompletionParams) -> Self {
+        Self {
+            model: model.to_string(),
+            prompt: prompt.to_string(),
+            params_hash: params_hash(params),
+        }
+    }
+}
+
+/// 计算影响生成结果的参数的哈希值
+fn params_hash(params: &ChatCompletionParams) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    params.temperature.map(f32::to_bits).hash(&mut hasher);
+    params.temperature_decay.map(f32::to_bits).hash(&mut hasher);
+    params.top_p.map(f32::to_bits).hash(&mut hasher);
+    params.top_k.hash(&mut hasher);
+    params.presence_penalty.map(f32::to_bits).hash(&mut hasher);
+    params.frequency_penalty.map(f32::to_bits).hash(&mut hasher);
+    params.n.hash(&mut hasher);
+    params.min_tokens.hash(&mut hasher);
+    params.max_tokens.hash(&mut hasher);
+    params.no_repeat_ngram_size.hash(&mut hasher);
+    params.seed.hash(&mut hasher);
+    params.truncation.hash(&mut hasher);
+    params
+        .response_format
+        .as_ref()
+        .map(|f| serde_json::to_string(f).unwrap_or_default())
+        .hash(&mut hasher);
+    params.echo.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 判断请求的结果是否可以缓存
+pub fn is_cacheable(params: &ChatCompletionParams) -> bool {
+    // 覆盖模型参数的实验请求不与正常请求共用结果
+    if params.stream.unwrap_or(false) || params.model_overrides.is_some() {
+        return false;
+    }
+    params.seed.is_some()
+        || params.temperature.is_none_or(|temperature| temperature <= DETERMINISTIC_TEMPERATURE)
+}
+
+/// 固定容量的LRU缓存
+pub struct PromptCache {
+    entries: Mutex<LruCache<PromptCacheKey, Vec<GenerationOutput>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl PromptCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// 查询缓存，并更新命中率指标
+    pub fn get(&self, key: &PromptCacheKey) -> Option<Vec<GenerationOutput>> {
+        let cached = self.entries.lock().unwrap().get(key).cloned();
+        if cached.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            metrics().increment_counter("prompt_cache_hits_total");
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            metrics().increment_counter("prompt_cache_misses_total");
+        }
+        metrics().set_gauge("prompt_cache_hit_rate", self.hit_rate());
+        cached
+    }
+
+    /// 写入缓存，超出容量时淘汰最久未使用的条目
+    pub fn insert(&self, key: PromptCacheKey, outputs: Vec<GenerationOutput>) {
+        self.entries.lock().unwrap().insert(key, outputs);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// 缓存命中率，尚无查询时为0
+    pub fn hit_rate(&self) -> f64 {
+        let hits = self.hits();
+        let total = hits + self.misses();
+        if total == 0 {
+            0.0
+        } else {
+            hits as f64 / total as f64
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! 请求中 `webhook_url` 的校验
+//!
+//! 只允许配置中列出的scheme与主机，避免服务端被用来访问内网地址（SSRF）
+use crate::error::AppError;
+use crate::utils::config::WebhookConfig;
+use reqwest::Url;
+use std::time::Duration;
+
+/// 允许推送的webhook地址
+#[derive(Debug, Clone)]
+pub struct WebhookAllowlist {
+    schemes: Vec<String>,
+    hosts: Vec<String>,
+    /// 每次推送的超时时间
+    pub timeout: Duration,
+}
+
+impl WebhookAllowlist {
+    pub fn new(schemes: &[&str], hosts: &[&str]) -> Self {
+        Self {
+            schemes: schemes.iter().map(|scheme| scheme.to_ascii_lowercase()).collect(),
+            hosts: hosts.iter().map(|host| host.to_ascii_lowercase()).collect(),
+            timeout: Duration::from_millis(WebhookConfig::default().timeout_ms),
+        }
+    }
+
+    pub fn from_config(config: &WebhookConfig) -> Self {
+        let schemes: Vec<&str> = config.allowed_schemes.iter().map(String::as_str).collect();
+        let hosts: Vec<&str> = config.allowed_hosts.iter().map(String::as_str).collect();
+        Self { timeout: Duration::from_millis(config.timeout_ms), ..Self::new(&schemes, &hosts) }
+    }
+
+    /// 解析并校验webhook地址，scheme或主机不在允许列表中时返回 `AppError::ValidationError`
+    pub fn validate(&self, url: &str) -> Result<Url, AppError> {
+        let parsed = Url::parse(url)
+            .map_err(|e| AppError::ValidationError(format!("Invalid webhook_url: {}", e)))?;
+        if !self.schemes.iter().any(|scheme| scheme == parsed.scheme()) {
+            return Err(AppError::ValidationError(format!(
+                "webhook_url scheme {} is not allowed",
+                parsed.scheme()
+            )));
+        }
+        // 凭据可能被用来绕过主机校验的解析差异，一律拒绝
+        if !parsed.username().is_empty() || parsed.password().is_some() {
+            return Err(AppError::ValidationError(
+                "webhook_url must not contain credentials".to_string(),
+            ));
+        }
+        let host = parsed.host_str().unwrap_or_default().to_ascii_lowercase();
+        if !self.hosts.contains(&host) {
+            return Err(AppError::ValidationError(format!(
+                "webhook_url host {} is not allowed",
+                host
+            )));
+        }
+        Ok(parsed)
+    }
+}
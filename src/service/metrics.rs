@@ -0,0 +1,128 @@
+//! 服务运行指标
+//!
+//! 进程内的全局指标注册表，通过 `/metrics` 以Prometheus文本格式导出。
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Mutex, OnceLock};
+
+/// token数直方图的桶上限
+pub const TOKEN_COUNT_BUCKETS: &[f64] =
+    &[16.0, 64.0, 256.0, 512.0, 1024.0, 2048.0, 4096.0, 8192.0, 16384.0];
+
+/// 指标注册表，保存计数器、仪表盘数值和直方图
+#[derive(Default)]
+pub struct MetricsRegistry {
+    counters: Mutex<BTreeMap<String, u64>>,
+    gauges: Mutex<BTreeMap<String, f64>>,
+    histograms: Mutex<BTreeMap<String, Histogram>>,
+}
+
+/// 直方图，每个桶记录不超过其上限的观测值数量（累计）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    bounds: Vec<f64>,
+    bucket_counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &[f64]) -> Self {
+        Self { bounds: bounds.to_vec(), bucket_counts: vec![0; bounds.len()], sum: 0.0, count: 0 }
+    }
+
+    fn observe(&mut self, value: f64) {
+        for (bound, count) in self.bounds.iter().zip(self.bucket_counts.iter_mut()) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    /// 观测值总数
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// 观测值之和
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    /// 各桶的上限及不超过该上限的观测值数量
+    pub fn buckets(&self) -> impl Iterator<Item = (f64, u64)> + '_ {
+        self.bounds.iter().copied().zip(self.bucket_counts.iter().copied())
+    }
+}
+
+static METRICS: OnceLock<MetricsRegistry> = OnceLock::new();
+
+/// 获取全局指标注册表
+pub fn metrics() -> &'static MetricsRegistry {
+    METRICS.get_or_init(MetricsRegistry::default)
+}
+
+impl MetricsRegistry {
+    /// 计数器加1
+    pub fn increment_counter(&self, name: &str) {
+        self.add_counter(name, 1);
+    }
+
+    /// 计数器增加指定值
+    pub fn add_counter(&self, name: &str, value: u64) {
+        let mut counters = self.counters.lock().unwrap();
+        *counters.entry(name.to_string()).or_insert(0) += value;
+    }
+
+    /// 获取计数器当前值，未记录时为0
+    pub fn counter(&self, name: &str) -> u64 {
+        self.counters.lock().unwrap().get(name).copied().unwrap_or(0)
+    }
+
+    /// 设置仪表盘数值
+    pub fn set_gauge(&self, name: &str, value: f64) {
+        self.gauges.lock().unwrap().insert(name.to_string(), value);
+    }
+
+    /// 获取仪表盘当前值
+    pub fn gauge(&self, name: &str) -> Option<f64> {
+        self.gauges.lock().unwrap().get(name).copied()
+    }
+
+    /// 向直方图添加一个观测值，`bounds` 只在首次记录该直方图时使用
+    pub fn observe_histogram(&self, name: &str, bounds: &[f64], value: f64) {
+        let mut histograms = self.histograms.lock().unwrap();
+        histograms.entry(name.to_string()).or_insert_with(|| Histogram::new(bounds)).observe(value);
+    }
+
+    /// 获取直方图当前数据
+    pub fn histogram(&self, name: &str) -> Option<Histogram> {
+        self.histograms.lock().unwrap().get(name).cloned()
+    }
+
+    /// 以Prometheus文本格式导出所有指标
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        for (name, value) in self.counters.lock().unwrap().iter() {
+            let _ = writeln!(output, "# TYPE {} counter\n{} {}", name, name, value);
+        }
+        for (name, value) in self.gauges.lock().unwrap().iter() {
+            let _ = writeln!(output, "# TYPE {} gauge\n{} {}", name, name, value);
+        }
+        for (name, histogram) in self.histograms.lock().unwrap().iter() {
+            let _ = writeln!(output, "# TYPE {} histogram", name);
+            for (bound, count) in histogram.buckets() {
+                let _ = writeln!(output, "{}_bucket{{le=\"{}\"}} {}", name, bound, count);
+            }
+            let _ = writeln!(output, "{}_bucket{{le=\"+Inf\"}} {}", name, histogram.count);
+            let _ = writeln!(
+                output,
+                "{}_sum {}\n{}_count {}",
+                name, histogram.sum, name, histogram.count
+            );
+        }
+        output
+    }
+}
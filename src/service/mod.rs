@@ -5,6 +5,7 @@
 //!
 //! # 模块
 //! - `chat`: 处理聊天完成和对话管理
+//! - `metrics`: 记录服务运行指标
 //! - `models`: 管理模型操作和配置
 //!
 //! # 规范
@@ -14,4 +15,5 @@
 //! - 服务应该是可测试的
 
 pub mod chat;
+pub mod metrics;
 pub mod models;
@@ -0,0 +1,115 @@
+//! 模型吞吐量基准测试
+//!
+//! 使用固定的合成提示词测量当前硬件上的性能：先对整段提示词执行一次前向传播，
+//! 再贪心解码固定数量的token。生成过程中不会因EOS提前结束，结果只反映模型速度。
+use crate::error::AppError;
+use crate::service::models::sampling::greedy_token;
+use crate::service::models::CompletionModel;
+pub use crate::utils::memory::peak_memory_bytes;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// 默认的合成提示词长度
+pub const DEFAULT_BENCHMARK_PROMPT_TOKENS: usize = 128;
+/// 默认的生成token数
+pub const DEFAULT_BENCHMARK_MAX_TOKENS: usize = 128;
+
+/// 基准测试参数
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct BenchmarkConfig {
+    /// 合成提示词的token数
+    #[serde(default = "default_prompt_tokens")]
+    pub prompt_tokens: usize,
+    /// 生成的token数
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: usize,
+}
+
+fn default_prompt_tokens() -> usize {
+    DEFAULT_BENCHMARK_PROMPT_TOKENS
+}
+
+fn default_max_tokens() -> usize {
+    DEFAULT_BENCHMARK_MAX_TOKENS
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> Self {
+        Self { prompt_tokens: default_prompt_tokens(), max_tokens: default_max_tokens() }
+    }
+}
+
+/// 基准测试结果
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkResult {
+    pub model_id: String,
+    pub prompt_tokens: usize,
+    pub generated_tokens: usize,
+    /// 提示词处理速度 (tokens/s)
+    pub prompt_tokens_per_second: f64,
+    /// 生成速度 (tokens/s)
+    pub generation_tokens_per_second: f64,
+    /// 进程的内存峰值 (字节)，平台不支持时为 `None`
+    pub peak_memory_bytes: Option<u64>,
+}
+
+/// 对 `model` 运行一次基准测试
+///
+/// 两个token数都必须大于0，且合计不超过模型的上下文长度，否则返回 `AppError::InvalidParameter`
+pub async fn run_benchmark(
+    model: &dyn CompletionModel,
+    config: BenchmarkConfig,
+) -> Result<BenchmarkResult, AppError> {
+    if config.prompt_tokens == 0 || config.max_tokens == 0 {
+        return Err(AppError::InvalidParameter(
+            "Benchmark prompt_tokens and max_tokens must be greater than 0".to_string(),
+        ));
+    }
+    if let Some(context_length) = model.context_length() {
+        if config.prompt_tokens + config.max_tokens > context_length {
+            return Err(AppError::InvalidParameter(format!(
+                "Benchmark needs {} tokens, exceeding the context length of {}",
+                config.prompt_tokens + config.max_tokens,
+                context_length
+            )));
+        }
+    }
+
+    // 合成提示词按顺序循环使用词表中的token
+    let vocab_size = model.tokenizer().get_vocab_size(true).max(1);
+    let mut input_ids: Vec<u32> =
+        (0..config.prompt_tokens).map(|i| (i % vocab_size) as u32).collect();
+
+    let start = Instant::now();
+    let mut logits = model.forward_logits(&input_ids)?;
+    let prompt_elapsed = start.elapsed();
+
+    let start = Instant::now();
+    for _ in 0..config.max_tokens {
+        tokio::task::yield_now().await;
+        input_ids.push(greedy_token(&logits)?);
+        logits = model.forward_logits(&input_ids)?;
+    }
+    let generation_elapsed = start.elapsed();
+
+    let result = BenchmarkResult {
+        model_id: model.model_id().to_string(),
+        prompt_tokens: config.prompt_tokens,
+        generated_tokens: config.max_tokens,
+        prompt_tokens_per_second: tokens_per_second(config.prompt_tokens, prompt_elapsed),
+        generation_tokens_per_second: tokens_per_second(config.max_tokens, generation_elapsed),
+        peak_memory_bytes: peak_memory_bytes(),
+    };
+    log::info!(
+        "[{}] Benchmark: prompt {:.2} tokens/s, generation {:.2} tokens/s",
+        result.model_id,
+        result.prompt_tokens_per_second,
+        result.generation_tokens_per_second
+    );
+    Ok(result)
+}
+
+fn tokens_per_second(tokens: usize, elapsed: Duration) -> f64 {
+    // 计时精度不足时避免除以0
+    tokens as f64 / elapsed.as_secs_f64().max(1e-9)
+}
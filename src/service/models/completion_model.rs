@@ -0,0 +1,498 @@
+//! 代码补全模型抽象
+//!
+//! `CompletionModel` 统一了 YiCoder 与 DeepseekCoder 的推理接口。
+//! 各模型只需提供tokenizer和单步前向传播，
+//! 生成循环、采样以及流式输出由默认实现共享。
+use crate::entities::chat_completion_message::ChatCompletionMessage;
+use crate::error::AppError;
+use crate::service::chat::chat_completion::ChatCompletionParams;
+use crate::service::chat::prompt;
+use crate::service::models::fingerprint::fingerprint;
+use crate::service::models::input_buffer::InputBuffer;
+use crate::service::models::json_schema::JsonSchemaConstraint;
+use crate::service::models::overrides::ModelOverrides;
+use crate::service::models::sampler::sampler_for;
+use crate::service::models::sampling::{
+    apply_penalties, mask_repeated_ngrams, mask_token, sanitize_logits, NanPolicy, SamplingConfig,
+};
+use crate::service::models::stream_decoder::{invalid_utf8_tokens, StreamDecoder};
+use crate::utils::config::{get_config, FimTokens};
+use crate::utils::device::device_label;
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+use tokenizers::Tokenizer;
+use tokio::sync::mpsc;
+
+/// 未指定max_tokens时生成长度上限 `chat.default_max_tokens` 的默认值
+pub const DEFAULT_MAX_TOKENS: usize = 2048;
+
+/// 生成结束原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FinishReason {
+    /// 生成了EOS token
+    Stop,
+    /// 达到max_tokens上限
+    Length,
+}
+
+impl fmt::Display for FinishReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FinishReason::Stop => write!(f, "stop"),
+            FinishReason::Length => write!(f, "length"),
+        }
+    }
+}
+
+/// 提示词超出上下文长度时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TruncationStrategy {
+    /// 丢弃最前面的token，保留提示词末尾
+    Left,
+    /// 丢弃最后面的token，保留提示词开头
+    Right,
+    /// 不截断，返回400
+    Error,
+}
+
+/// 请求的max_tokens超过模型的 `max_output_tokens` 时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MaxOutputTokensPolicy {
+    /// 降低到 `max_output_tokens`，响应中附带 `x_max_tokens`
+    #[default]
+    Clamp,
+    /// 返回400
+    Reject,
+}
+
+/// 将提示词token序列截断到 `budget` 以内
+///
+/// # 返回值
+/// * `Ok(Vec<u32>)` - 长度不超过 `budget` 的token序列
+/// * `Err(AppError::InvalidParameter)` - 超长且策略为 `Error`
+pub fn truncate_prompt(
+    mut input_ids: Vec<u32>,
+    budget: usize,
+    strategy: TruncationStrategy,
+) -> Result<Vec<u32>, AppError> {
+    if input_ids.len() <= budget {
+        return Ok(input_ids);
+    }
+    if budget == 0 {
+        return Err(AppError::InvalidParameter(
+            "max_tokens leaves no room for the prompt in the model context".to_string(),
+        ));
+    }
+    match strategy {
+        TruncationStrategy::Left => Ok(input_ids.split_off(input_ids.len() - budget)),
+        TruncationStrategy::Right => {
+            input_ids.truncate(budget);
+            Ok(input_ids)
+        }
+        TruncationStrategy::Error => Err(AppError::InvalidParameter(format!(
+            "Prompt has {} tokens, exceeding the {} tokens available after reserving max_tokens",
+            input_ids.len(),
+            budget
+        ))),
+    }
+}
+
+/// 校验tokenizer的词表大小与模型配置的 `vocab_size` 是否匹配
+///
+/// 词表大于 `vocab_size` 时部分token ID超出embedding范围，说明模型文件不匹配，
+/// 返回 `AppError::ConfigError`。embedding按硬件对齐填充得比词表大是正常的；
+/// `vocab_size` 为0表示配置中未声明，不做校验
+pub fn check_vocab_size(tokenizer: &Tokenizer, vocab_size: usize) -> Result<(), AppError> {
+    let tokenizer_vocab_size = tokenizer.get_vocab_size(true);
+    if vocab_size > 0 && tokenizer_vocab_size > vocab_size {
+        return Err(AppError::ConfigError(format!(
+            "Tokenizer has {} tokens, exceeding the model vocab_size of {}",
+            tokenizer_vocab_size, vocab_size
+        )));
+    }
+    Ok(())
+}
+
+/// 单次生成的结果
+#[derive(Debug, Clone)]
+pub struct GenerationOutput {
+    /// 生成的文本
+    pub text: String,
+    /// 生成的token序列（不含提示词）
+    pub token_ids: Vec<u32>,
+    /// 提示词token数量
+    pub prompt_tokens: usize,
+    /// 结束原因
+    pub finish_reason: FinishReason,
+    /// 是否因到达截止时间而提前结束
+    pub timed_out: bool,
+    /// 是否因被取消而提前结束
+    pub cancelled: bool,
+    /// 请求的max_tokens被降低到 `max_output_tokens` 时为实际使用的上限
+    pub clamped_max_tokens: Option<usize>,
+    /// 生成该结果的模型指纹，见 [`CompletionModel::system_fingerprint`]
+    pub system_fingerprint: Option<String>,
+    /// 填充默认值并按上限降低后实际使用的采样参数
+    pub sampling: Option<SamplingConfig>,
+}
+
+impl GenerationOutput {
+    /// 生成的token数量
+    pub fn completion_tokens(&self) -> usize {
+        self.token_ids.len()
+    }
+}
+
+#[async_trait]
+pub trait CompletionModel: Send + Sync {
+    /// 模型ID，例如 `yi-coder`
+    fn model_id(&self) -> &str;
+
+    /// 模型使用的tokenizer
+    fn tokenizer(&self) -> &Tokenizer;
+
+    /// EOS token，生成该token时停止
+    fn eos_token_id(&self) -> Option<u32>;
+
+    /// 生成其中任一token时停止，默认只有 [`eos_token_id`](Self::eos_token_id)
+    ///
+    /// 模型的 `generation_config.json` 中 `eos_token_id` 可以是列表
+    fn stop_token_ids(&self) -> Vec<u32> {
+        self.eos_token_id().into_iter().collect()
+    }
+
+    /// 模型的最大上下文长度 (`max_position_embeddings`)，未知时为 `None`
+    fn context_length(&self) -> Option<usize> {
+        None
+    }
+
+    /// logits中出现NaN/Inf时的处理方式，默认使用 `inference.nan_policy`
+    fn nan_policy(&self) -> NanPolicy {
+        get_config().inference.nan_policy
+    }
+
+    /// 编码提示词时是否添加BOS等特殊token，默认使用 `models.<id>.add_special_tokens`
+    fn add_special_tokens(&self) -> bool {
+        get_config().models.get(self.model_id()).is_none_or(|config| config.add_special_tokens)
+    }
+
+    /// 单次生成的token数上限，默认使用 `models.<id>.max_output_tokens`
+    fn max_output_tokens(&self) -> Option<usize> {
+        get_config().models.get(self.model_id()).and_then(|config| config.max_output_tokens)
+    }
+
+    /// 请求的max_tokens超过上限时的处理方式，默认使用 `models.<id>.max_output_tokens_policy`
+    fn max_output_tokens_policy(&self) -> MaxOutputTokensPolicy {
+        get_config()
+            .models
+            .get(self.model_id())
+            .map(|config| config.max_output_tokens_policy)
+            .unwrap_or_default()
+    }
+
+    /// 对完整的输入token序列执行前向传播，返回最后一个位置的logits `(vocab,)`
+    fn forward_logits(&self, input_ids: &[u32]) -> Result<Tensor, AppError>;
+
+    /// 模型输入所在的设备
+    ///
+    /// 返回 `Some` 时生成循环使用该设备上的 [`InputBuffer`] 复用输入张量，
+    /// 并通过 [`forward_input`](Self::forward_input) 执行前向传播
+    fn input_device(&self) -> Option<&Device> {
+        None
+    }
+
+    /// 对 `(seq_len,)` 的U32输入张量执行前向传播，返回最后一个位置的logits `(vocab,)`
+    ///
+    /// 默认将张量复制回CPU后调用 [`forward_logits`](Self::forward_logits)
+    fn forward_input(&self, input: &Tensor) -> Result<Tensor, AppError> {
+        self.forward_logits(&input.to_vec1::<u32>()?)
+    }
+
+    /// 将对话消息渲染为提示词，默认使用 `models.<id>.role_markers` 中的角色前缀
+    fn render_prompt(&self, messages: &[ChatCompletionMessage]) -> String {
+        match get_config().models.get(self.model_id()) {
+            Some(config) => prompt::render_prompt_with(messages, &config.role_markers),
+            None => prompt::render_prompt(messages),
+        }
+    }
+
+    /// fill-in-the-middle补全的哨兵token，默认使用 `models.<id>.fim`，未配置时为 `None`
+    fn fim_tokens(&self) -> Option<FimTokens> {
+        get_config().models.get(self.model_id()).and_then(|config| config.fim.clone())
+    }
+
+    /// 响应中的 `system_fingerprint`，同一个已加载的模型保持不变
+    ///
+    /// 默认由模型ID计算，加载了真实权重的模型应包含配置与权重文件的信息
+    fn system_fingerprint(&self) -> String {
+        fingerprint([self.model_id().as_bytes()])
+    }
+
+    /// 应用单个请求的参数覆盖 (`X-Model-Overrides`)，返回只供本次请求使用的模型实例
+    ///
+    /// 默认不支持覆盖，返回 `AppError::InvalidParameter`
+    fn with_overrides(
+        &self,
+        _overrides: &ModelOverrides,
+    ) -> Result<Arc<dyn CompletionModel>, AppError> {
+        Err(AppError::InvalidParameter(format!(
+            "Model {} does not support model overrides",
+            self.model_id()
+        )))
+    }
+
+    /// 返回位于 `device` 上的模型副本，供请求头 `X-Device` 指定设备的请求使用
+    ///
+    /// 默认不支持，返回 `AppError::InvalidParameter`
+    async fn to_device(&self, device: &Device) -> Result<Arc<dyn CompletionModel>, AppError> {
+        Err(AppError::InvalidParameter(format!(
+            "Model {} cannot be moved to device {}",
+            self.model_id(),
+            device_label(device)
+        )))
+    }
+
+    /// 将提示词编码为token序列
+    fn encode_prompt(&self, prompt: &str) -> Result<Vec<u32>, AppError> {
+        let encoding = self
+            .tokenizer()
+            .encode(prompt, self.add_special_tokens())
+            .map_err(|e| AppError::TokenizerError(e.to_string()))?;
+        Ok(encoding.get_ids().to_vec())
+    }
+
+    /// 根据提示词生成补全
+    async fn generate(
+        &self,
+        prompt: &str,
+        params: &ChatCompletionParams,
+    ) -> Result<GenerationOutput, AppError> {
+        self.generate_stream(prompt, params, None).await
+    }
+
+    /// 根据提示词生成补全，并在提供 `sender` 时逐token发送解码后的文本
+    async fn generate_stream(
+        &self,
+        prompt: &str,
+        params: &ChatCompletionParams,
+        sender: Option<&mpsc::Sender<String>>,
+    ) -> Result<GenerationOutput, AppError> {
+        let sampling = SamplingConfig::try_new(params)?;
+        let mut input_ids = self.encode_prompt(prompt)?;
+        if let (Some(strategy), Some(context_length)) = (params.truncation, self.context_length()) {
+            let budget = context_length.saturating_sub(sampling.max_tokens());
+            if input_ids.len() > budget {
+                log::warn!(
+                    "[{}] Prompt has {} tokens, budget is {}, applying {:?} truncation",
+                    self.model_id(),
+                    input_ids.len(),
+                    budget,
+                    strategy
+                );
+            }
+            input_ids = truncate_prompt(input_ids, budget, strategy)?;
+        }
+        let prompt_tokens = input_ids.len();
+        let ceiling = self.max_output_tokens();
+        let mut clamped_max_tokens = None;
+        // 服务端的绝对上限先于模型上限生效，不受 `max_output_tokens_policy` 影响
+        let requested = match (params.max_tokens, params.absolute_max_tokens) {
+            (Some(max_tokens), Some(cap)) if max_tokens > cap => {
+                log::info!(
+                    "[{}] Clamping max_tokens from {} to absolute_max_tokens {}",
+                    self.model_id(),
+                    max_tokens,
+                    cap
+                );
+                clamped_max_tokens = Some(cap);
+                Some(cap)
+            }
+            (max_tokens, _) => max_tokens,
+        };
+        let max_tokens = match (requested, ceiling) {
+            (Some(max_tokens), Some(ceiling)) if max_tokens > ceiling => {
+                match self.max_output_tokens_policy() {
+                    MaxOutputTokensPolicy::Clamp => {
+                        log::info!(
+                            "[{}] Clamping max_tokens from {} to max_output_tokens {}",
+                            self.model_id(),
+                            max_tokens,
+                            ceiling
+                        );
+                        clamped_max_tokens = Some(ceiling);
+                        ceiling
+                    }
+                    MaxOutputTokensPolicy::Reject => {
+                        return Err(AppError::InvalidParameter(format!(
+                            "max_tokens {} exceeds the max_output_tokens of {} for model {}",
+                            max_tokens,
+                            ceiling,
+                            self.model_id()
+                        )));
+                    }
+                }
+            }
+            (Some(max_tokens), _) => max_tokens,
+            // 未指定时生成到上下文用尽为止，不超过 `chat.default_max_tokens`、`max_output_tokens`
+            // 和 `absolute_max_tokens`
+            (None, _) => {
+                let cap = get_config()
+                    .chat
+                    .default_max_tokens
+                    .min(ceiling.unwrap_or(usize::MAX))
+                    .min(params.absolute_max_tokens.unwrap_or(usize::MAX));
+                self.context_length().map_or(cap, |context_length| {
+                    context_length.saturating_sub(prompt_tokens).min(cap)
+                })
+            }
+        };
+        let stop_token_ids = self.stop_token_ids();
+        log::debug!(
+            "[{}] Generating up to {} tokens from {} prompt tokens",
+            self.model_id(),
+            max_tokens,
+            prompt_tokens
+        );
+
+        let sampling = sampling.with_max_tokens(max_tokens);
+        let sampler = sampler_for(&sampling);
+
+        let mut constraint = match params.response_format.as_ref().and_then(|f| f.schema()) {
+            Some(schema) => Some(JsonSchemaConstraint::new(schema, self.tokenizer())?),
+            None => None,
+        };
+
+        // 上下文长度未知时先按默认的生成上限预留，不足时缓冲区会扩容
+        let mut input_buffer = self.input_device().map(|device| {
+            let headroom = max_tokens.min(self.context_length().unwrap_or(DEFAULT_MAX_TOKENS));
+            InputBuffer::with_capacity(device, input_ids.len() + headroom)
+        });
+
+        let mut decoder = StreamDecoder::new(self.tokenizer());
+        let mut token_ids = Vec::new();
+        let mut finish_reason = FinishReason::Length;
+        let mut timed_out = false;
+        let mut cancelled = false;
+        while token_ids.len() < max_tokens {
+            // 让出执行权，使同一worker上的取消请求有机会执行
+            tokio::task::yield_now().await;
+            if params.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                timed_out = true;
+                break;
+            }
+            if params.cancellation.as_ref().is_some_and(|token| token.is_cancelled()) {
+                cancelled = true;
+                break;
+            }
+            // 没有EOS的模型在输出满足schema后直接结束
+            if stop_token_ids.is_empty() && constraint.as_ref().is_some_and(|c| c.is_complete()) {
+                finish_reason = FinishReason::Stop;
+                break;
+            }
+            let logits = match &mut input_buffer {
+                Some(buffer) => self.forward_input(&buffer.update(&input_ids)?)?,
+                None => self.forward_logits(&input_ids)?,
+            };
+            let mut logits = sanitize_logits(&logits, self.nan_policy())?;
+            logits = apply_penalties(
+                &logits,
+                &token_ids,
+                sampling.presence_penalty(),
+                sampling.frequency_penalty(),
+            )?;
+            // 未达到min_tokens前屏蔽EOS
+            if token_ids.len() < sampling.min_tokens() {
+                for &stop in &stop_token_ids {
+                    logits = mask_token(&logits, stop)?;
+                }
+            }
+            if let Some(constraint) = &constraint {
+                logits = constraint.mask_logits(&logits, &stop_token_ids)?;
+            }
+            // 只检查已生成的token，提示词中的重复不受限制
+            if let Some(ngram_size) = sampling.no_repeat_ngram_size() {
+                match mask_repeated_ngrams(&logits, &token_ids, ngram_size)? {
+                    Some(masked) => logits = masked,
+                    None => {
+                        finish_reason = FinishReason::Stop;
+                        break;
+                    }
+                }
+            }
+            let next_token = sampler.sample(&logits, &token_ids)?;
+            if stop_token_ids.contains(&next_token) {
+                finish_reason = FinishReason::Stop;
+                break;
+            }
+            if let Some(constraint) = &mut constraint {
+                constraint.advance(next_token);
+            }
+            token_ids.push(next_token);
+            input_ids.push(next_token);
+            if let Some(progress) = &params.progress {
+                progress.record_token();
+            }
+
+            if let Some(sender) = sender {
+                // 多字节字符被拆成多个token时，等字符完整后再发送
+                let Some(token_text) = decoder.push(next_token)? else {
+                    continue;
+                };
+                if let Err(e) = sender.send(token_text).await {
+                    log::warn!("{} {}", t!("errors.stream_response.failed"), e);
+                    break;
+                }
+            }
+        }
+        // 发送剩余的不完整字符，客户端已断开时不再发送
+        let sender = sender.filter(|sender| !sender.is_closed());
+        if let (Some(sender), Some(token_text)) = (sender, decoder.finish()?) {
+            if let Err(e) = sender.send(token_text).await {
+                log::warn!("{} {}", t!("errors.stream_response.failed"), e);
+            }
+        }
+
+        // 第一个token即为EOS时返回空回复，不对空序列解码
+        let text = if token_ids.is_empty() {
+            String::new()
+        } else {
+            self.tokenizer().decode(&token_ids, true)?
+        };
+        if get_config().inference.validate_output_encoding {
+            let invalid = invalid_utf8_tokens(self.tokenizer(), &token_ids, &text)?;
+            if !invalid.is_empty() {
+                log::warn!(
+                    "[{}] Generated text contains replacement characters decoded from tokens {:?}",
+                    self.model_id(),
+                    invalid
+                );
+            }
+        }
+        log::debug!(
+            "[{}] Generated {} tokens, finish reason: {}, timed out: {}, cancelled: {}",
+            self.model_id(),
+            token_ids.len(),
+            finish_reason,
+            timed_out,
+            cancelled
+        );
+        Ok(GenerationOutput {
+            text,
+            token_ids,
+            prompt_tokens,
+            finish_reason,
+            timed_out,
+            cancelled,
+            clamped_max_tokens,
+            system_fingerprint: Some(self.system_fingerprint()),
+            sampling: Some(sampling),
+        })
+    }
+}
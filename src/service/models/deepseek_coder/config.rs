@@ -1,3 +1,4 @@
+use crate::utils::config::ArchitectureConfig;
 use serde::Deserialize;
 use std::path::Path;
 
@@ -33,7 +34,8 @@ pub struct ModelConfig {
     pub num_attention_heads: usize,
     #[serde(default)]
     pub intermediate_size: usize,
-    #[serde(default)]
+    /// HuggingFace的 `config.json` 中为 `num_hidden_layers`
+    #[serde(default, alias = "num_hidden_layers")]
     pub num_layers: usize,
     #[serde(default)]
     pub layer_norm_eps: f64,
@@ -69,4 +71,18 @@ impl ModelConfig {
         let config: Self = serde_json::from_str(&config_str)?;
         Ok(config)
     }
+
+    /// 使用 `models.<id>.architecture` 中设置的字段覆盖 `config.json` 的值
+    pub fn with_architecture(mut self, architecture: &ArchitectureConfig) -> Self {
+        self.hidden_size = architecture.hidden_size.unwrap_or(self.hidden_size);
+        self.num_attention_heads =
+            architecture.num_attention_heads.unwrap_or(self.num_attention_heads);
+        self.intermediate_size = architecture.intermediate_size.unwrap_or(self.intermediate_size);
+        self.num_layers = architecture.num_hidden_layers.unwrap_or(self.num_layers);
+        self.layer_norm_eps = architecture.layer_norm_eps.unwrap_or(self.layer_norm_eps);
+        self.vocab_size = architecture.vocab_size.unwrap_or(self.vocab_size);
+        self.max_position_embeddings =
+            architecture.max_position_embeddings.unwrap_or(self.max_position_embeddings);
+        self
+    }
 }
@@ -2,21 +2,29 @@ use super::config::ModelConfig;
 use super::inference::DeepSeekCoderInference;
 use super::loader::DeepseekCoderLoader;
 use super::transformer::DeepseekCoderTransformer;
-use crate::entities::chat_completion_message::ChatCompletionMessage;
 use crate::error::AppError;
-use crate::service::chat::chat_completion::ChatCompletionParams;
-use candle_core::Tensor;
+use crate::service::models::completion_model::{check_vocab_size, CompletionModel};
+use crate::service::models::fingerprint::model_fingerprint;
+use crate::service::models::read_stop_token_ids;
+use crate::service::models::sampling::last_position_logits;
+use crate::utils::config::{get_config, TokenizerSettings};
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
 use candle_nn::Module;
-use rand::{thread_rng, Rng};
+use std::path::{Path, PathBuf};
+use tokenizers::Tokenizer;
 
 /// DeepseekCoder 代码生成模型
 /// 基于 DeepSeek AI 的代码生成模型实现
 /// 包含配置、加载器、转换器和推理模块
 pub struct DeepseekCoder {
     _config: ModelConfig,                   // 模型配置
+    stop_token_ids: Vec<u32>,               // 来自generation_config.json的EOS token
     _loader: DeepseekCoderLoader,           // 模型加载器
     _transformer: DeepseekCoderTransformer, // 转换器模块
     _inference: DeepSeekCoderInference,     // 推理模块
+    tokenizer: Tokenizer,                   // 分词器
+    system_fingerprint: String,             // 由模型配置与权重文件计算的指纹
 }
 
 impl DeepseekCoder {
@@ -25,145 +33,84 @@ impl DeepseekCoder {
     pub async fn new() -> Result<Self, AppError> {
         // 从配置文件加载模型配置
         log::debug!("Loading model configuration from config/deepseek_coder.json");
-        let config = ModelConfig::from_file("config/deepseek_coder.json")?;
+        let mut config = ModelConfig::from_file("config/deepseek_coder.json")?;
+        let mut tokenizer_settings = TokenizerSettings::default();
+        if let Some(model_config) = get_config().models.get("deepseek-coder") {
+            config = config.with_architecture(&model_config.architecture);
+            tokenizer_settings = model_config.tokenizer.clone();
+        }
         // 初始化模型加载器
-        let loader = DeepseekCoderLoader::new(config.clone());
+        let loader = DeepseekCoderLoader::new(config.clone())?;
         // 初始化转换器
-        let transformer = DeepseekCoderTransformer::new(&config, loader.get_var_builder()?)?;
+        let transformer = DeepseekCoderTransformer::new(&config, loader.get_var_builder().await?)?;
         // 初始化推理模块
-        let inference = DeepSeekCoderInference::new(&config);
+        let inference = DeepSeekCoderInference::new(&config, loader.device());
+        // 加载分词器
+        let tokenizer = loader.get_tokenizer(&tokenizer_settings).await?;
+        check_vocab_size(&tokenizer, config.vocab_size)?;
+        let model_dir = Path::new(&config.models_cache_dir).join(&config.hf_hub_id);
+        // 读取generation_config.json中声明的所有EOS token
+        let stop_token_ids = read_stop_token_ids(
+            &model_dir.join(&config.model_files.generation_config),
+            config.eos_token_id as u32,
+        );
+        let weights: Vec<PathBuf> =
+            config.model_files.weights.iter().map(|file| model_dir.join(file)).collect();
+        let system_fingerprint = model_fingerprint(&config, &weights);
 
         Ok(Self {
             _config: config,
+            stop_token_ids,
             _loader: loader,
             _transformer: transformer,
             _inference: inference,
+            tokenizer,
+            system_fingerprint,
         })
     }
-    /// 执行推理
-    /// 参数:
-    ///   - messages: 聊天消息列表
-    ///   - temperature: 采样温度
-    ///   - top_p: top-p 采样参数
-    ///   - n: 生成结果数量
-    ///   - max_tokens: 最大token数
-    ///   - stream: 是否流式输出
-    /// 返回 Result<Vec<ChatCompletionMessage>, AppError>
-    pub async fn infer(
-        &self,
-        messages: Vec<ChatCompletionMessage>,
-        params: ChatCompletionParams,
-    ) -> Result<Vec<ChatCompletionMessage>, AppError> {
-        // 1. 使用tokenizer将输入消息转换为token序列
-        log::debug!("Initializing tokenizer for Deepseek Coder model");
-        let tokenizer = self._loader.get_tokenizer().await?;
-        let mut input_ids: Vec<u32> = Vec::with_capacity(1024);
-        for message in &messages {
-            let encoding = tokenizer
-                .encode(message.content.clone(), false)
-                .map_err(|e| AppError::TokenizerError(e.to_string()))?;
-            input_ids.extend(encoding.get_ids().iter().copied());
-        }
-
-        // 2. 将token序列输入transformer模型进行处理
-        let input_tensor =
-            Tensor::from_slice(&input_ids, &[input_ids.len()], self._transformer.device())?;
-
-        // 使用contiguous()确保内存布局优化
-        let input_tensor = input_tensor.contiguous()?;
-
-        // 处理输入序列，添加batch维度
-        let input_tensor = input_tensor.unsqueeze(0)?;
-
-        let logits = self._transformer.forward(&input_tensor)?;
-
-        // 移除batch维度
-        let mut logits = logits.squeeze(0)?;
-
-        // 3. 根据temperature和top_p参数进行采样
-        let next_token = if let Some(temp) = params.temperature {
-            let logits = logits.squeeze(0)?;
-            let temp_tensor: Tensor =
-                Tensor::from_slice(&[temp], &[1], self._transformer.device())?;
-            let scaled_logits: Tensor = logits.div(&temp_tensor)?;
-            let probs: Tensor = candle_nn::ops::softmax(&scaled_logits, 0)?;
-            let mut rng = thread_rng();
-            let random_val: f32 = rng.gen();
-            let mut cumulative = 0.0;
-            let probs_vec: Vec<f32> = probs.to_vec1()?;
-            for (i, &p) in probs_vec.iter().enumerate() {
-                cumulative += p;
-                if cumulative >= random_val {
-                    return Ok(vec![ChatCompletionMessage {
-                        role: "assistant".to_string(),
-                        content: tokenizer.decode(&[i as u32], true)?,
-                    }]);
-                }
-            }
-
-            probs.argmax(0)?.to_scalar::<u32>()?
-        } else {
-            logits.argmax(1)?.to_scalar::<u32>()?
-        };
+}
 
-        // 4. 将生成的token序列转换回文本
-        let output_text = tokenizer.decode(&[next_token], true)?;
+#[async_trait]
+impl CompletionModel for DeepseekCoder {
+    fn model_id(&self) -> &str {
+        "deepseek-coder"
+    }
 
-        // 5. 处理流式输出（如果stream参数为true）
-        if params.stream.unwrap_or(false) {
-            let mut stream_output = String::new();
-            let mut generated_tokens = 0;
-            let max_tokens = params.max_tokens.unwrap_or(2048);
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
 
-            while generated_tokens < max_tokens {
-                // 生成下一个token
-                let next_token = if let Some(temp) = params.temperature {
-                    let logits = logits.squeeze(0)?;
-                    let _shape = [1];
-                    let temp_tensor =
-                        Tensor::from_slice(&[temp], &[1], self._transformer.device())?;
-                    let scaled_logits = logits.div(&temp_tensor)?;
-                    let probs = candle_nn::ops::softmax(&scaled_logits, 0)?;
-                    let mut rng = thread_rng();
-                    let random_val: f32 = rng.gen();
-                    let mut cumulative = 0.0;
-                    let probs_vec: Vec<f32> = probs.to_vec1()?;
-                    for (i, &p) in probs_vec.iter().enumerate() {
-                        cumulative += p;
-                        if cumulative >= random_val {
-                            return Ok(vec![ChatCompletionMessage {
-                                role: "assistant".to_string(),
-                                content: tokenizer.decode(&[i as u32], true)?,
-                            }]);
-                        }
-                    }
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(self._config.eos_token_id as u32)
+    }
 
-                    probs.argmax(0)?.to_scalar::<u32>()?
-                } else {
-                    logits.argmax(1)?.to_scalar::<u32>()?
-                };
+    fn stop_token_ids(&self) -> Vec<u32> {
+        self.stop_token_ids.clone()
+    }
 
-                // 解码token并添加到输出
-                let token_text = tokenizer.decode(&[next_token], true)?;
-                stream_output.push_str(&token_text);
-                generated_tokens += 1;
+    fn context_length(&self) -> Option<usize> {
+        // 0表示配置中未声明
+        Some(self._config.max_position_embeddings).filter(|&len| len > 0)
+    }
 
-                // 发送部分响应
-                let _message =
-                    ChatCompletionMessage { role: "assistant".to_string(), content: token_text };
-                // TODO: 实现实际的流式输出机制
+    fn system_fingerprint(&self) -> String {
+        self.system_fingerprint.clone()
+    }
 
-                // 更新输入序列
-                input_ids.push(next_token);
-                let input_tensor =
-                    Tensor::from_slice(&input_ids, (input_ids.len(),), self._transformer.device())?;
-                logits = self._transformer.forward(&input_tensor)?;
-            }
+    fn forward_logits(&self, input_ids: &[u32]) -> Result<Tensor, AppError> {
+        let input_tensor =
+            Tensor::from_slice(input_ids, &[input_ids.len()], self._transformer.device())?;
+        self.forward_input(&input_tensor)
+    }
 
-            return Ok(vec![]);
-        }
+    fn input_device(&self) -> Option<&Device> {
+        Some(self._transformer.device())
+    }
 
-        // 6. 返回生成的聊天消息列表
-        Ok(vec![ChatCompletionMessage { role: "assistant".to_string(), content: output_text }])
+    fn forward_input(&self, input_tensor: &Tensor) -> Result<Tensor, AppError> {
+        // 处理输入序列，添加batch维度
+        let input_tensor = input_tensor.contiguous()?.unsqueeze(0)?;
+        let logits = self._transformer.forward(&input_tensor)?;
+        last_position_logits(&logits)
     }
 }
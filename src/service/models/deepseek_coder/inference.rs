@@ -7,10 +7,9 @@ pub struct DeepSeekCoderInference {
 }
 
 impl DeepSeekCoderInference {
-    pub fn new(_config: &super::config::ModelConfig) -> Self {
-        let device = Device::cuda_if_available(0).unwrap_or(Device::Cpu);
+    pub fn new(_config: &super::config::ModelConfig, device: &Device) -> Self {
         log::info!("Initializing Deepseek Coder with device: {:?}", device);
-        Self { _device: device }
+        Self { _device: device.clone() }
     }
 
     pub async fn infer(
@@ -31,6 +30,7 @@ impl DeepSeekCoderInference {
         let response = ChatCompletionMessage {
             role: "assistant".to_string(),
             content: "DeepSeek Coder response".to_string(),
+            name: None,
         };
 
         log::debug!("Generated response: {:?}", response);
@@ -1,5 +1,10 @@
 use super::config::ModelConfig;
 use crate::error::AppError;
+use crate::service::models::tokenizer_file::load_tokenizer_file;
+use crate::service::models::tokenizer_settings::apply_tokenizer_settings;
+use crate::utils::config::{get_config, TokenizerSettings};
+use crate::utils::device::configured_device;
+use crate::utils::weights::verify_safetensors_file;
 use candle_core::DType;
 use candle_core::{Device, Tensor};
 use safetensors::SafeTensors;
@@ -12,15 +17,16 @@ pub struct DeepseekCoderLoader {
 }
 
 impl DeepseekCoderLoader {
-    pub fn new(config: ModelConfig) -> Self {
-        Self {
-            config,
-            device: Device::cuda_if_available(0).unwrap_or(Device::Cpu),
-            tokenizer: None,
-        }
+    pub fn new(config: ModelConfig) -> Result<Self, AppError> {
+        Ok(Self { config, device: configured_device()?, tokenizer: None })
+    }
+
+    /// 获取加载器使用的计算设备
+    pub fn device(&self) -> &Device {
+        &self.device
     }
 
-    pub fn get_var_builder(&self) -> Result<candle_nn::VarBuilder, AppError> {
+    pub async fn get_var_builder(&self) -> Result<candle_nn::VarBuilder<'_>, AppError> {
         let mut tensors = std::collections::HashMap::new();
         let _zeros_data = vec![0.0f32; self.config.hidden_size];
         let shape = vec![self.config.hidden_size];
@@ -29,13 +35,14 @@ impl DeepseekCoderLoader {
         Ok(candle_nn::VarBuilder::from_tensors(tensors, DType::F32, &self.device))
     }
 
-    pub async fn get_tokenizer(&self) -> Result<Tokenizer, AppError> {
+    /// 加载tokenizer，并应用 `models.<id>.tokenizer` 中的截断与填充参数
+    pub async fn get_tokenizer(&self, settings: &TokenizerSettings) -> Result<Tokenizer, AppError> {
         let tokenizer_path = format!(
             "{}/{}/{}",
             self.config.models_cache_dir, self.config.hf_hub_id, self.config.model_files.tokenizer
         );
-        let tokenizer = Tokenizer::from_file(tokenizer_path)
-            .map_err(|e| AppError::TokenizerError(e.to_string()))?;
+        let mut tokenizer = load_tokenizer_file(tokenizer_path).await?;
+        apply_tokenizer_settings(&mut tokenizer, settings)?;
         Ok(tokenizer)
     }
 
@@ -44,6 +51,14 @@ impl DeepseekCoderLoader {
             "{}/{}/{}",
             self.config.models_cache_dir, self.config.hf_hub_id, self.config.model_files.weights[0]
         );
+        if get_config().inference.verify_weights {
+            let path = weights_path.clone();
+            tokio::task::spawn_blocking(move || {
+                verify_safetensors_file(std::path::Path::new(&path))
+            })
+            .await
+            .map_err(|e| AppError::Generic(e.to_string()))??;
+        }
         let data = tokio::fs::read(weights_path).await?;
         let safetensors = SafeTensors::deserialize(&data)?;
 
@@ -3,5 +3,6 @@ pub mod inference;
 pub mod loader;
 pub mod transformer;
 
+#[allow(clippy::module_inception)]
 mod deepseek_coder;
 pub use deepseek_coder::DeepseekCoder;
@@ -48,9 +48,9 @@ impl Module for MultiHeadAttention {
 
         let attention = query.matmul(&key.t()?)?;
         let dim = attention.rank() - 1;
-        let max = attention.max(dim)?;
-        let exp = (attention - max)?.exp()?;
-        let sum = exp.sum(dim)?;
+        let max = attention.max_keepdim(dim)?;
+        let exp = attention.broadcast_sub(&max)?.exp()?;
+        let sum = exp.sum_keepdim(dim)?;
         let attention = exp.broadcast_div(&sum)?;
         let context = attention.matmul(&value)?;
 
@@ -82,7 +82,7 @@ struct PositionWiseFeedForward {
 
 impl DeepseekCoderTransformer {
     pub fn new(config: &super::config::ModelConfig, vb: VarBuilder) -> Result<Self, AppError> {
-        let device = Device::cuda_if_available(0).unwrap_or(Device::Cpu);
+        let device = vb.device().clone();
 
         let mut layers = Vec::new();
         for i in 0..config.num_layers {
@@ -112,7 +112,7 @@ impl TransformerLayer {
         intermediate_size: usize,
         vb: VarBuilder,
     ) -> Result<Self, AppError> {
-        let device = Device::cuda_if_available(0).unwrap_or(Device::Cpu);
+        let device = vb.device().clone();
         let _attention = MultiHeadAttention::new(num_heads, hidden_size, vb.pp("attention"))?;
         let _feed_forward =
             PositionWiseFeedForward::new(hidden_size, intermediate_size, vb.pp("ffn"))?;
@@ -127,12 +127,7 @@ impl TransformerLayer {
             1e-5,
         );
 
-        Ok(Self {
-            _attention,
-            _feed_forward,
-            _norm1,
-            _norm2,
-        })
+        Ok(Self { _attention, _feed_forward, _norm1, _norm2 })
     }
 }
 
@@ -0,0 +1,130 @@
+//! 回显模型
+//!
+//! 内置的伪模型 `echo` 将最后一条user消息的内容原样作为回复，支持流式输出，
+//! 便于下游应用在没有模型权重的情况下测试集成。`ModelManager` 总是注册该模型。
+//!
+//! 回显模型没有真实词表：按空白切分文本，每个词（连同其前面的空白）计为一个token。
+use crate::entities::chat_completion_message::ChatCompletionMessage;
+use crate::error::AppError;
+use crate::service::chat::chat_completion::ChatCompletionParams;
+use crate::service::models::mock::word_level_tokenizer;
+use crate::service::models::sampling::SamplingConfig;
+use crate::service::models::{CompletionModel, FinishReason, GenerationOutput};
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use std::time::Instant;
+use tokenizers::Tokenizer;
+use tokio::sync::mpsc;
+
+/// 回显模型的ID
+pub const ECHO_MODEL_ID: &str = "echo";
+
+/// 按空白切分文本，每段包含一个词及其前面的空白，拼接后等于原文
+pub fn split_tokens(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut previous_is_whitespace = true;
+    for (i, c) in text.char_indices() {
+        if c.is_whitespace() && !previous_is_whitespace {
+            tokens.push(&text[start..i]);
+            start = i;
+        }
+        previous_is_whitespace = c.is_whitespace();
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+pub struct EchoModel {
+    tokenizer: Tokenizer,
+}
+
+impl EchoModel {
+    pub fn new() -> Result<Self, AppError> {
+        Ok(Self { tokenizer: word_level_tokenizer(&["<eos>", "<unk>"])? })
+    }
+}
+
+#[async_trait]
+impl CompletionModel for EchoModel {
+    fn model_id(&self) -> &str {
+        ECHO_MODEL_ID
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    /// 提示词即最后一条user消息的内容
+    fn render_prompt(&self, messages: &[ChatCompletionMessage]) -> String {
+        messages
+            .iter()
+            .rev()
+            .find(|message| message.role == "user")
+            .map(|message| message.content.clone())
+            .unwrap_or_default()
+    }
+
+    fn forward_logits(&self, _input_ids: &[u32]) -> Result<Tensor, AppError> {
+        Ok(Tensor::new(&[0f32, f32::NEG_INFINITY], &Device::Cpu)?)
+    }
+
+    async fn generate_stream(
+        &self,
+        prompt: &str,
+        params: &ChatCompletionParams,
+        sender: Option<&mpsc::Sender<String>>,
+    ) -> Result<GenerationOutput, AppError> {
+        let sampling = SamplingConfig::try_new(params)?;
+        let tokens = split_tokens(prompt);
+        let mut text = String::new();
+        let mut generated = 0;
+        let mut finish_reason = FinishReason::Stop;
+        let mut timed_out = false;
+        let mut cancelled = false;
+        for token in &tokens {
+            if generated == sampling.max_tokens() {
+                finish_reason = FinishReason::Length;
+                break;
+            }
+            if params.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                timed_out = true;
+                finish_reason = FinishReason::Length;
+                break;
+            }
+            if params.cancellation.as_ref().is_some_and(|token| token.is_cancelled()) {
+                cancelled = true;
+                finish_reason = FinishReason::Length;
+                break;
+            }
+            if let Some(sender) = sender {
+                if sender.send(token.to_string()).await.is_err() {
+                    break;
+                }
+            }
+            text.push_str(token);
+            generated += 1;
+            if let Some(progress) = &params.progress {
+                progress.record_token();
+            }
+        }
+        Ok(GenerationOutput {
+            text,
+            // 没有真实词表，token ID仅用于计数
+            token_ids: vec![1; generated],
+            prompt_tokens: tokens.len(),
+            finish_reason,
+            timed_out,
+            cancelled,
+            clamped_max_tokens: None,
+            system_fingerprint: Some(self.system_fingerprint()),
+            sampling: Some(sampling),
+        })
+    }
+}
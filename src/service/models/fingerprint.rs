@@ -0,0 +1,44 @@
+//! 模型指纹
+//!
+//! 对应OpenAI响应中的 `system_fingerprint`，标识生成结果所用的模型配置与权重。
+//! 同一个已加载的模型在各请求间保持不变，配置或权重文件变化后随之改变。
+use std::fmt;
+use std::path::PathBuf;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// 计算 `parts` 的指纹，格式为 `fp_` 加16位十六进制数
+///
+/// 使用FNV-1a哈希，结果不随进程或Rust版本变化
+pub fn fingerprint<'a>(parts: impl IntoIterator<Item = &'a [u8]>) -> String {
+    let mut hash = FNV_OFFSET_BASIS;
+    let mut feed = |bytes: &[u8]| {
+        for &byte in bytes {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    };
+    for part in parts {
+        // 加入长度，避免 ["ab", "c"] 与 ["a", "bc"] 得到相同的指纹
+        feed(&(part.len() as u64).to_le_bytes());
+        feed(part);
+    }
+    format!("fp_{:016x}", hash)
+}
+
+/// 由模型配置与模型文件计算指纹：配置取其 `Debug` 输出，文件取文件名与大小
+///
+/// 无法读取的文件按大小0计算
+pub fn model_fingerprint(config: &impl fmt::Debug, files: &[PathBuf]) -> String {
+    let config = format!("{:?}", config);
+    let files: Vec<String> = files
+        .iter()
+        .map(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let size = std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+            format!("{}:{}", name, size)
+        })
+        .collect();
+    fingerprint(std::iter::once(config.as_bytes()).chain(files.iter().map(|file| file.as_bytes())))
+}
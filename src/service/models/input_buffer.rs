@@ -0,0 +1,72 @@
+//! 生成循环的输入缓冲区
+//!
+//! 每一步的输入是上一步的输入加上新生成的token。缓冲区预先分配足够容量的张量，
+//! 每步只写入与上次不同的部分并返回前缀视图，避免每步用 `Tensor::from_slice`
+//! 重新分配整段输入。容量不足时按两倍扩容。
+use crate::error::AppError;
+use candle_core::{DType, Device, Tensor};
+
+pub struct InputBuffer {
+    device: Device,
+    storage: Option<Tensor>,
+    capacity: usize,
+    /// 已写入 `storage` 的token
+    written: Vec<u32>,
+    allocations: usize,
+}
+
+impl InputBuffer {
+    /// 创建容量为 `capacity` 个token的缓冲区，张量在第一次 [`update`](Self::update) 时分配
+    pub fn with_capacity(device: &Device, capacity: usize) -> Self {
+        Self {
+            device: device.clone(),
+            storage: None,
+            capacity,
+            written: Vec::with_capacity(capacity),
+            allocations: 0,
+        }
+    }
+
+    /// 写入 `input_ids` 并返回形状为 `(input_ids.len(),)` 的U32张量
+    ///
+    /// 返回的张量与缓冲区共享存储，只在下一次调用 `update` 之前有效
+    pub fn update(&mut self, input_ids: &[u32]) -> Result<Tensor, AppError> {
+        let storage = match &self.storage {
+            Some(storage) if input_ids.len() <= self.capacity => storage.clone(),
+            storage => {
+                if storage.is_some() {
+                    self.capacity = self.capacity.saturating_mul(2);
+                }
+                self.capacity = self.capacity.max(input_ids.len()).max(1);
+                let storage = Tensor::zeros(self.capacity, DType::U32, &self.device)?;
+                self.allocations += 1;
+                self.written.clear();
+                self.storage = Some(storage.clone());
+                storage
+            }
+        };
+
+        // 只写入与上次不同的部分，通常只有新生成的一个token
+        let unchanged =
+            self.written.iter().zip(input_ids).take_while(|(written, id)| written == id).count();
+        if unchanged < input_ids.len() {
+            let changed = &input_ids[unchanged..];
+            let src = Tensor::from_slice(changed, changed.len(), &self.device)?;
+            storage.slice_set(&src, 0, unchanged)?;
+        }
+        self.written.truncate(unchanged);
+        self.written.extend_from_slice(&input_ids[unchanged..]);
+
+        Ok(storage.narrow(0, 0, input_ids.len())?)
+    }
+
+    /// 当前容量（token数）
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// 分配缓冲区张量的次数
+    pub fn allocations(&self) -> usize {
+        self.allocations
+    }
+}
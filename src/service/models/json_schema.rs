@@ -0,0 +1,344 @@
+//! JSON schema约束解码
+//!
+//! 将schema编译为由字面量和值占位符组成的线性序列，逐字符检查已生成的文本
+//! 是否仍是符合schema的JSON前缀，并在每一步屏蔽会破坏该前缀的token。
+//!
+//! 只支持schema的一个子集：`object`（按属性名顺序输出全部属性）、`string`、
+//! `number`、`integer` 以及字符串 `enum`。输出为不含空白的紧凑JSON。
+use crate::error::AppError;
+use candle_core::{DType, Tensor};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokenizers::Tokenizer;
+
+/// 请求的 `response_format`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// 不约束输出
+    Text,
+    /// 输出符合 `json_schema.schema` 的JSON
+    JsonSchema { json_schema: JsonSchemaFormat },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonSchemaFormat {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub schema: Value,
+}
+
+impl ResponseFormat {
+    /// 需要约束输出时返回schema
+    pub fn schema(&self) -> Option<&Value> {
+        match self {
+            ResponseFormat::Text => None,
+            ResponseFormat::JsonSchema { json_schema } => Some(&json_schema.schema),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    String,
+    Number {
+        integer: bool,
+    },
+    /// JSON编码后的候选值
+    Enum(Vec<String>),
+}
+
+/// 数字的词法状态，对应 `-?(0|[1-9]\d*)(\.\d+)?([eE][+-]?\d+)?`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NumberState {
+    Start,
+    Sign,
+    Zero,
+    Int,
+    Dot,
+    Frac,
+    Exp,
+    ExpSign,
+    ExpDigits,
+}
+
+impl NumberState {
+    fn next(self, c: char, integer: bool) -> Option<Self> {
+        use NumberState::*;
+        match (self, c) {
+            (Start, '-') => Some(Sign),
+            (Start | Sign, '0') => Some(Zero),
+            (Start | Sign, '1'..='9') => Some(Int),
+            (Int, '0'..='9') => Some(Int),
+            (Zero | Int, '.') if !integer => Some(Dot),
+            (Dot | Frac, '0'..='9') => Some(Frac),
+            (Zero | Int | Frac, 'e' | 'E') if !integer => Some(Exp),
+            (Exp, '+' | '-') => Some(ExpSign),
+            (Exp | ExpSign | ExpDigits, '0'..='9') => Some(ExpDigits),
+            _ => None,
+        }
+    }
+
+    fn is_accepting(self) -> bool {
+        matches!(
+            self,
+            NumberState::Zero | NumberState::Int | NumberState::Frac | NumberState::ExpDigits
+        )
+    }
+}
+
+/// 当前片段内的进度
+#[derive(Debug, Clone, PartialEq)]
+enum State {
+    Fresh,
+    Literal(usize),
+    InString,
+    Escape,
+    Unicode(u8),
+    Number(NumberState),
+    Enum(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Cursor {
+    index: usize,
+    state: State,
+}
+
+impl Cursor {
+    fn advance(&mut self) {
+        self.index += 1;
+        self.state = State::Fresh;
+    }
+}
+
+fn unsupported(message: impl Into<String>) -> AppError {
+    AppError::InvalidParameter(format!("Unsupported json_schema: {}", message.into()))
+}
+
+fn push_literal(segments: &mut Vec<Segment>, literal: &str) {
+    match segments.last_mut() {
+        Some(Segment::Literal(last)) => last.push_str(literal),
+        _ => segments.push(Segment::Literal(literal.to_string())),
+    }
+}
+
+fn compile(schema: &Value, segments: &mut Vec<Segment>) -> Result<(), AppError> {
+    if let Some(values) = schema.get("enum") {
+        let values = values.as_array().ok_or_else(|| unsupported("enum must be an array"))?;
+        let encoded = values
+            .iter()
+            .map(|value| match value {
+                Value::String(_) => Ok(value.to_string()),
+                _ => Err(unsupported("only string enum values are supported")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if encoded.is_empty() {
+            return Err(unsupported("enum must not be empty"));
+        }
+        segments.push(Segment::Enum(encoded));
+        return Ok(());
+    }
+    match schema.get("type").and_then(Value::as_str) {
+        Some("string") => segments.push(Segment::String),
+        Some("number") => segments.push(Segment::Number { integer: false }),
+        Some("integer") => segments.push(Segment::Number { integer: true }),
+        Some("object") => {
+            let properties = schema
+                .get("properties")
+                .and_then(Value::as_object)
+                .ok_or_else(|| unsupported("object schema requires properties"))?;
+            push_literal(segments, "{");
+            for (i, (name, property)) in properties.iter().enumerate() {
+                if i > 0 {
+                    push_literal(segments, ",");
+                }
+                push_literal(segments, &format!("{}:", Value::String(name.clone())));
+                compile(property, segments)?;
+            }
+            push_literal(segments, "}");
+        }
+        other => return Err(unsupported(format!("type {:?} is not supported", other))),
+    }
+    Ok(())
+}
+
+/// 按schema逐字符校验输出前缀
+#[derive(Debug, Clone)]
+struct SchemaMatcher {
+    segments: Vec<Segment>,
+}
+
+impl SchemaMatcher {
+    fn new(schema: &Value) -> Result<Self, AppError> {
+        let mut segments = Vec::new();
+        compile(schema, &mut segments)?;
+        Ok(Self { segments })
+    }
+
+    /// 输入一个字符，字符不符合schema时返回 `false`
+    fn feed(&self, cursor: &mut Cursor, c: char) -> bool {
+        loop {
+            let Some(segment) = self.segments.get(cursor.index) else {
+                return false;
+            };
+            match (segment, &cursor.state) {
+                (Segment::Literal(literal), State::Fresh | State::Literal(_)) => {
+                    let offset = match cursor.state {
+                        State::Literal(offset) => offset,
+                        _ => 0,
+                    };
+                    if !literal[offset..].starts_with(c) {
+                        return false;
+                    }
+                    let offset = offset + c.len_utf8();
+                    if offset == literal.len() {
+                        cursor.advance();
+                    } else {
+                        cursor.state = State::Literal(offset);
+                    }
+                    return true;
+                }
+                (Segment::String, State::Fresh) => {
+                    cursor.state = State::InString;
+                    return c == '"';
+                }
+                (Segment::String, State::InString) => {
+                    match c {
+                        '"' => cursor.advance(),
+                        '\\' => cursor.state = State::Escape,
+                        c if c < ' ' => return false,
+                        _ => {}
+                    }
+                    return true;
+                }
+                (Segment::String, State::Escape) => {
+                    cursor.state = match c {
+                        'u' => State::Unicode(4),
+                        '"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't' => State::InString,
+                        _ => return false,
+                    };
+                    return true;
+                }
+                (Segment::String, State::Unicode(remaining)) => {
+                    if !c.is_ascii_hexdigit() {
+                        return false;
+                    }
+                    cursor.state = match remaining {
+                        1 => State::InString,
+                        n => State::Unicode(n - 1),
+                    };
+                    return true;
+                }
+                (Segment::Number { integer }, State::Fresh | State::Number(_)) => {
+                    let number = match cursor.state {
+                        State::Number(number) => number,
+                        _ => NumberState::Start,
+                    };
+                    match number.next(c, *integer) {
+                        Some(next) => {
+                            cursor.state = State::Number(next);
+                            return true;
+                        }
+                        // 数字没有结束符，遇到无法延续的字符时交给下一个片段处理
+                        None if number.is_accepting() => cursor.advance(),
+                        None => return false,
+                    }
+                }
+                (Segment::Enum(values), State::Fresh | State::Enum(_)) => {
+                    let mut prefix = match &cursor.state {
+                        State::Enum(prefix) => prefix.clone(),
+                        _ => String::new(),
+                    };
+                    prefix.push(c);
+                    if !values.iter().any(|value| value.starts_with(&prefix)) {
+                        return false;
+                    }
+                    if values.contains(&prefix) {
+                        cursor.advance();
+                    } else {
+                        cursor.state = State::Enum(prefix);
+                    }
+                    return true;
+                }
+                _ => return false,
+            }
+        }
+    }
+
+    fn is_complete(&self, cursor: &Cursor) -> bool {
+        match (self.segments.get(cursor.index), &cursor.state) {
+            (None, _) => true,
+            (Some(Segment::Number { .. }), State::Number(number)) => {
+                cursor.index + 1 == self.segments.len() && number.is_accepting()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 单次生成的schema约束
+pub struct JsonSchemaConstraint {
+    matcher: SchemaMatcher,
+    cursor: Cursor,
+    /// 每个token解码后的文本，特殊token为空字符串
+    vocab: Vec<String>,
+}
+
+impl JsonSchemaConstraint {
+    pub fn new(schema: &Value, tokenizer: &Tokenizer) -> Result<Self, AppError> {
+        let matcher = SchemaMatcher::new(schema)?;
+        let vocab = (0..tokenizer.get_vocab_size(true) as u32)
+            .map(|id| tokenizer.decode(&[id], true).unwrap_or_default())
+            .collect();
+        Ok(Self { matcher, cursor: Cursor { index: 0, state: State::Fresh }, vocab })
+    }
+
+    /// 输出是否已经是完整的、符合schema的JSON
+    pub fn is_complete(&self) -> bool {
+        self.matcher.is_complete(&self.cursor)
+    }
+
+    fn accepts(&self, token_id: u32) -> Option<Cursor> {
+        let text = self.vocab.get(token_id as usize).filter(|text| !text.is_empty())?;
+        let mut cursor = self.cursor.clone();
+        text.chars().all(|c| self.matcher.feed(&mut cursor, c)).then_some(cursor)
+    }
+
+    /// 屏蔽会破坏schema的token；输出完整后只允许 `stop_token_ids` 中的token
+    ///
+    /// 没有任何token可以延续输出时返回 `AppError::Chat`
+    pub fn mask_logits(&self, logits: &Tensor, stop_token_ids: &[u32]) -> Result<Tensor, AppError> {
+        let complete = self.is_complete();
+        let mut values = logits.to_dtype(DType::F32)?.to_vec1::<f32>()?;
+        let mut any_allowed = false;
+        for (id, value) in values.iter_mut().enumerate() {
+            let id = id as u32;
+            let allowed = if stop_token_ids.contains(&id) {
+                complete
+            } else {
+                !complete && self.accepts(id).is_some()
+            };
+            if allowed && *value != f32::NEG_INFINITY {
+                any_allowed = true;
+            } else {
+                *value = f32::NEG_INFINITY;
+            }
+        }
+        if !any_allowed {
+            return Err(AppError::Chat(
+                "response_format json_schema cannot be satisfied by the model vocabulary"
+                    .to_string(),
+            ));
+        }
+        Ok(Tensor::new(values, logits.device())?)
+    }
+
+    /// 记录已生成的token
+    pub fn advance(&mut self, token_id: u32) {
+        if let Some(cursor) = self.accepts(token_id) {
+            self.cursor = cursor;
+        }
+    }
+}
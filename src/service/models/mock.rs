@@ -0,0 +1,133 @@
+//! 无需权重的确定性模拟模型
+//!
+//! 设置环境变量 `CODER_MOCK_MODEL=1` 时，`ModelManager` 会注册ID为 `mock-model` 的模型，
+//! 使集成测试无需下载权重即可端到端调用 `/v1/chat/completions`。
+//! logits由输入token序列与种子的哈希确定，相同输入总是得到相同结果。
+use crate::error::AppError;
+use crate::service::models::CompletionModel;
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use tokenizers::Tokenizer;
+
+/// 模拟模型的ID
+pub const MOCK_MODEL_ID: &str = "mock-model";
+/// 设为 `1` 时注册模拟模型
+pub const MOCK_MODEL_ENV: &str = "CODER_MOCK_MODEL";
+/// 模拟模型的种子，未设置时为0
+pub const MOCK_MODEL_SEED_ENV: &str = "CODER_MOCK_MODEL_SEED";
+
+const VOCAB: [&str; 22] = [
+    "<eos>",
+    "<unk>",
+    "user",
+    "assistant",
+    "system",
+    ":",
+    "fn",
+    "main",
+    "(",
+    ")",
+    "{",
+    "}",
+    "let",
+    "x",
+    "=",
+    "1",
+    ";",
+    "return",
+    "print",
+    "hello",
+    "world",
+    "def",
+];
+const EOS_TOKEN_ID: u32 = 0;
+const UNK_TOKEN_ID: u32 = 1;
+
+/// 是否通过 `CODER_MOCK_MODEL=1` 启用了模拟模型
+pub fn mock_model_enabled() -> bool {
+    std::env::var(MOCK_MODEL_ENV).is_ok_and(|value| value == "1")
+}
+
+pub struct MockModel {
+    tokenizer: Tokenizer,
+    seed: u64,
+}
+
+impl MockModel {
+    pub fn new(seed: u64) -> Result<Self, AppError> {
+        Ok(Self { tokenizer: word_level_tokenizer(&VOCAB)?, seed })
+    }
+
+    /// 使用 `CODER_MOCK_MODEL_SEED` 指定的种子创建模拟模型
+    pub fn from_env() -> Result<Self, AppError> {
+        let seed = match std::env::var(MOCK_MODEL_SEED_ENV) {
+            Ok(seed) => seed.trim().parse().map_err(|_| {
+                AppError::ConfigError(format!(
+                    "{} must be an integer: {}",
+                    MOCK_MODEL_SEED_ENV, seed
+                ))
+            })?,
+            Err(_) => 0,
+        };
+        Self::new(seed)
+    }
+}
+
+/// 基于空格分词的WordLevel tokenizer，token ID即其在 `vocab` 中的下标
+///
+/// `vocab` 中必须包含 `<unk>`
+pub(crate) fn word_level_tokenizer(vocab: &[&str]) -> Result<Tokenizer, AppError> {
+    let vocab: serde_json::Map<String, serde_json::Value> =
+        vocab.iter().enumerate().map(|(id, token)| (token.to_string(), id.into())).collect();
+    let json = serde_json::json!({
+        "version": "1.0",
+        "truncation": null,
+        "padding": null,
+        "added_tokens": [],
+        "normalizer": null,
+        "pre_tokenizer": {"type": "Whitespace"},
+        "post_processor": null,
+        "decoder": null,
+        "model": {
+            "type": "WordLevel",
+            "vocab": vocab,
+            "unk_token": "<unk>"
+        }
+    });
+    Tokenizer::from_str(&json.to_string()).map_err(|e| AppError::TokenizerError(e.to_string()))
+}
+
+#[async_trait]
+impl CompletionModel for MockModel {
+    fn model_id(&self) -> &str {
+        MOCK_MODEL_ID
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(EOS_TOKEN_ID)
+    }
+
+    fn context_length(&self) -> Option<usize> {
+        Some(4096)
+    }
+
+    fn forward_logits(&self, input_ids: &[u32]) -> Result<Tensor, AppError> {
+        let mut hasher = DefaultHasher::new();
+        self.seed.hash(&mut hasher);
+        input_ids.hash(&mut hasher);
+        let mut rng = StdRng::seed_from_u64(hasher.finish());
+        let logits: Vec<f32> = (0..VOCAB.len() as u32)
+            .map(|id| if id == UNK_TOKEN_ID { f32::NEG_INFINITY } else { rng.gen_range(-4.0..4.0) })
+            .collect();
+        Ok(Tensor::new(logits, &Device::Cpu)?)
+    }
+}
@@ -6,7 +6,7 @@
 //! - 模型配置管理
 //!
 //! # 示例
-//! ```rust
+//! ```rust,no_run
 //! use coder_openapi::service::models::ModelManager;
 //!
 //! #[tokio::main]
@@ -16,15 +16,44 @@
 //! }
 //! ```
 
+pub mod benchmark;
+pub mod completion_model;
 pub mod deepseek_coder;
+pub mod echo;
+pub mod fingerprint;
+pub mod input_buffer;
+pub mod json_schema;
+pub mod mock;
+pub mod overrides;
+pub mod sampler;
+pub mod sampling;
+pub mod scheduler;
+pub mod special_tokens;
+pub mod stream_decoder;
+pub mod tokenizer_file;
+pub mod tokenizer_settings;
 pub mod yi_coder;
 
-use crate::entities::models::{DeepseekCoderModel, YiCoderModel};
+pub use completion_model::{CompletionModel, FinishReason, GenerationOutput, TruncationStrategy};
+pub use special_tokens::{read_special_tokens, SpecialToken, SpecialTokens};
+
+use crate::error::AppError;
+use crate::utils::config::{get_config, ModelFiles};
+use crate::utils::device::device_label;
+use candle_core::Device;
+use deepseek_coder::DeepseekCoder;
+use echo::{EchoModel, ECHO_MODEL_ID};
+use futures::future::BoxFuture;
+use mock::{mock_model_enabled, MockModel, MOCK_MODEL_ID};
+use scheduler::{Priority, PriorityLimiter, PriorityPermit};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
-use tokio::sync::RwLock;
+use tokio::sync::{OnceCell, RwLock, Semaphore};
+use yi_coder::YiCoder;
 
 // Model weights file path
 #[allow(dead_code)]
@@ -38,19 +67,169 @@ pub enum ModelError {
     UnknownModel(String),
     #[error("Model initialization failed: {0}")]
     InitializationFailed(String),
+    #[error("Model busy: {0}")]
+    Busy(String),
 }
 
 #[derive(Clone)]
 pub struct ModelManager {
-    yi_coder: Arc<RwLock<Option<YiCoderModel>>>,
-    deepseek_coder: Arc<RwLock<Option<DeepseekCoderModel>>>,
+    models: Arc<RwLock<HashMap<String, Arc<dyn CompletionModel>>>>,
     model_status: Arc<RwLock<HashMap<String, ModelStatus>>>,
+    /// 按 `models.<id>.max_concurrent` 配置的并发限制
+    concurrency_limits: Arc<HashMap<String, Arc<PriorityLimiter>>>,
+    queue_timeout: Duration,
+    /// 正在下载的模型，独立于 `model_status` 以便下载期间也能查询
+    downloading: Arc<std::sync::Mutex<HashSet<String>>>,
+    /// 按 `download.max_concurrent_models` 配置的同时下载数量限制
+    download_limiter: Option<Arc<Semaphore>>,
+    /// 等待下载许可的模型，按排队先后顺序
+    download_queue: Arc<std::sync::Mutex<VecDeque<String>>>,
+    /// 模型别名到实际模型ID的映射
+    aliases: Arc<HashMap<String, String>>,
+    /// 每个模型的初始化结果，并发的首次请求只会构造一次模型
+    initializers: Arc<std::sync::Mutex<HashMap<String, Arc<OnceCell<()>>>>>,
+    /// 自定义的模型构造函数，优先于内置模型
+    loaders: Arc<HashMap<String, ModelLoaderFn>>,
+    /// 模型文件的缓存目录，默认为配置中的 `models_cache_dir`
+    models_cache_dir: PathBuf,
+    /// 模型空闲超过该时间后自动卸载，未设置时一直驻留内存
+    idle_ttl: Option<Duration>,
+    /// 每个已加载模型最近一次被请求的时间
+    last_used: Arc<std::sync::Mutex<HashMap<String, Instant>>>,
+    /// 请求指定设备时创建的模型副本，键为 (模型ID, 设备名称)
+    replicas: Arc<RwLock<ReplicaMap>>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Copy)]
+/// 模型副本，键为 (模型ID, 设备名称)
+type ReplicaMap = HashMap<(String, String), Arc<dyn CompletionModel>>;
+
+/// 自定义的模型构造函数
+pub type ModelLoaderFn =
+    Arc<dyn Fn() -> BoxFuture<'static, Result<Arc<dyn CompletionModel>, ModelError>> + Send + Sync>;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
 pub struct ModelStatus {
     pub is_cached: bool,
     pub is_enabled: bool,
+    /// 模型文件正在下载，只需从缓存初始化或排队等待下载许可时为 `false`
+    #[serde(default)]
+    pub is_downloading: bool,
+    /// 等待下载许可时在队列中的位置，从1开始；未排队时为 `None`
+    #[serde(default)]
+    pub download_queue_position: Option<usize>,
+}
+
+/// 模型下载标记，drop时清除下载状态
+pub struct DownloadGuard {
+    model_id: String,
+    downloading: Arc<std::sync::Mutex<HashSet<String>>>,
+}
+
+impl Drop for DownloadGuard {
+    fn drop(&mut self) {
+        self.downloading.lock().unwrap().remove(&self.model_id);
+    }
+}
+
+/// 下载排队标记，获得许可或等待被取消时移出队列
+struct QueuedDownload {
+    model_id: String,
+    queue: Arc<std::sync::Mutex<VecDeque<String>>>,
+}
+
+impl Drop for QueuedDownload {
+    fn drop(&mut self) {
+        let mut queue = self.queue.lock().unwrap();
+        if let Some(position) = queue.iter().position(|queued| *queued == self.model_id) {
+            queue.remove(position);
+        }
+    }
+}
+
+/// 模型文件在缓存目录中的状态
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModelFileStatus {
+    pub name: String,
+    pub present: bool,
+    pub size_bytes: u64,
+}
+
+/// 检查模型所需文件在缓存目录中是否存在
+///
+/// # 参数
+/// * `model_dir` - 模型缓存目录，例如 `models_cache/01-ai/Yi-Coder-1.5B-Chat`
+/// * `files` - 配置中声明的模型文件
+pub fn scan_model_files(model_dir: &Path, files: &ModelFiles) -> Vec<ModelFileStatus> {
+    files
+        .all()
+        .into_iter()
+        .map(|name| {
+            let metadata =
+                std::fs::metadata(model_dir.join(name)).ok().filter(|metadata| metadata.is_file());
+            ModelFileStatus {
+                name: name.to_string(),
+                present: metadata.is_some(),
+                size_bytes: metadata.map(|metadata| metadata.len()).unwrap_or(0),
+            }
+        })
+        .collect()
+}
+
+/// `generation_config.json` 中的采样默认值
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct GenerationDefaults {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<usize>,
+    #[serde(default, alias = "max_new_tokens", skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<usize>,
+}
+
+/// 读取模型的 `generation_config.json`，忽略其中与采样无关的字段
+pub fn read_generation_defaults(path: &Path) -> Result<GenerationDefaults, AppError> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// `generation_config.json` 中的 `eos_token_id`，可以是单个ID或列表
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EosTokenIds {
+    One(u32),
+    Many(Vec<u32>),
+}
+
+#[derive(Deserialize)]
+struct GenerationStopTokens {
+    #[serde(default)]
+    eos_token_id: Option<EosTokenIds>,
+}
+
+/// 读取 `generation_config.json` 中声明的所有EOS token
+///
+/// 文件不存在、无法解析或未声明 `eos_token_id` 时返回 `[fallback]`
+pub fn read_stop_token_ids(path: &Path, fallback: u32) -> Vec<u32> {
+    let parsed = std::fs::read_to_string(path)
+        .map_err(AppError::from)
+        .and_then(|content| Ok(serde_json::from_str::<GenerationStopTokens>(&content)?));
+    match parsed {
+        Ok(GenerationStopTokens { eos_token_id: Some(EosTokenIds::One(id)) }) => vec![id],
+        Ok(GenerationStopTokens { eos_token_id: Some(EosTokenIds::Many(ids)) })
+            if !ids.is_empty() =>
+        {
+            ids
+        }
+        Ok(_) => vec![fallback],
+        Err(e) => {
+            if path.exists() {
+                log::warn!("Failed to read stop tokens from {}: {}", path.display(), e);
+            }
+            vec![fallback]
+        }
+    }
 }
 
 impl Default for ModelManager {
@@ -62,71 +241,198 @@ impl Default for ModelManager {
 impl ModelManager {
     /// 创建一个新的ModelManager实例
     pub fn new() -> Self {
-        let manager = Self {
-            yi_coder: Arc::new(RwLock::new(None)),
-            deepseek_coder: Arc::new(RwLock::new(None)),
-            model_status: Arc::new(RwLock::new(HashMap::new())),
-        };
+        let config = get_config();
+        let concurrency_limits = config
+            .models
+            .iter()
+            .filter_map(|(model_id, model_config)| {
+                model_config
+                    .max_concurrent
+                    .map(|limit| (model_id.clone(), PriorityLimiter::new(limit)))
+            })
+            .collect();
+
+        let mut models: HashMap<String, Arc<dyn CompletionModel>> = HashMap::new();
         // Initialize status from disk
-        let _ = manager.refresh_status_from_disk();
-        manager
+        let models_cache_dir = PathBuf::from(&config.models_cache_dir);
+        let mut model_status = Self::scan_status_from_disk(&models_cache_dir);
+        // 回显模型总是可用
+        match EchoModel::new() {
+            Ok(model) => {
+                models.insert(ECHO_MODEL_ID.to_string(), Arc::new(model));
+                model_status.insert(
+                    ECHO_MODEL_ID.to_string(),
+                    ModelStatus { is_cached: true, is_enabled: true, ..Default::default() },
+                );
+            }
+            Err(e) => log::error!("Failed to create {}: {}", ECHO_MODEL_ID, e),
+        }
+        // 测试用的模拟模型，无需下载权重
+        if mock_model_enabled() {
+            match MockModel::from_env() {
+                Ok(model) => {
+                    log::info!("Registering {} for testing", MOCK_MODEL_ID);
+                    models.insert(MOCK_MODEL_ID.to_string(), Arc::new(model));
+                    model_status.insert(
+                        MOCK_MODEL_ID.to_string(),
+                        ModelStatus { is_cached: true, is_enabled: true, ..Default::default() },
+                    );
+                }
+                Err(e) => log::error!("Failed to create {}: {}", MOCK_MODEL_ID, e),
+            }
+        }
+
+        Self {
+            models: Arc::new(RwLock::new(models)),
+            model_status: Arc::new(RwLock::new(model_status)),
+            concurrency_limits: Arc::new(concurrency_limits),
+            queue_timeout: Duration::from_secs(config.inference.queue_timeout_secs),
+            downloading: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            download_limiter: config
+                .download
+                .max_concurrent_models
+                .map(|limit| Arc::new(Semaphore::new(limit))),
+            download_queue: Arc::new(std::sync::Mutex::new(VecDeque::new())),
+            aliases: Arc::new(config.models.aliases.clone()),
+            initializers: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            loaders: Arc::new(HashMap::new()),
+            models_cache_dir,
+            idle_ttl: config.models.idle_ttl_secs.map(Duration::from_secs),
+            last_used: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            replicas: Arc::new(RwLock::new(HashMap::new())),
+        }
     }
 
-    /// Refresh model status from disk
-    async fn refresh_status_from_disk(&self) -> Result<(), ModelError> {
-        let mut status = self.model_status.write().await;
+    /// 设置模型文件的缓存目录，覆盖配置文件中的 `models_cache_dir`
+    pub fn with_models_cache_dir(mut self, models_cache_dir: impl Into<PathBuf>) -> Self {
+        self.models_cache_dir = models_cache_dir.into();
+        self.model_status =
+            Arc::new(RwLock::new(Self::scan_status_from_disk(&self.models_cache_dir)));
+        self
+    }
 
-        // Check yi-coder files
-        let yi_coder_dir = "models_cache/01-ai/Yi-Coder-1.5B-Chat".to_string();
-        let yi_coder_files = [
-            "model.safetensors",
-            "config.json",
-            "tokenizer.model",
-            "tokenizer_config.json",
-            "tokenizer.json",
-            "generation_config.json",
-        ];
-        let yi_coder_any_exists = yi_coder_files
-            .iter()
-            .any(|file| std::path::Path::new(&format!("{}/{}", yi_coder_dir, file)).exists());
-        let yi_coder_all_exists = yi_coder_files
-            .iter()
-            .all(|file| std::path::Path::new(&format!("{}/{}", yi_coder_dir, file)).exists());
-        status.insert(
-            "yi-coder".to_string(),
-            ModelStatus { is_cached: yi_coder_any_exists, is_enabled: yi_coder_all_exists },
-        );
+    /// 设置模型的构造函数，首次请求该模型时调用，替代内置的下载与加载
+    pub fn with_model_loader(mut self, model_id: &str, loader: ModelLoaderFn) -> Self {
+        Arc::make_mut(&mut self.loaders).insert(model_id.to_string(), loader);
+        self
+    }
 
-        // Check deepseek-coder files
-        let deepseek_coder_dir =
-            "models_cache/deepseek-ai/DeepSeek-Coder-V2-Lite-Instruct".to_string();
-        let deepseek_coder_files = [
-            "model-00001-of-000004.safetensors",
-            "model-00002-of-000004.safetensors",
-            "model-00003-of-000004.safetensors",
-            "model-00004-of-000004.safetensors",
-            "config.json",
-            "tokenizer.json",
-            "tokenizer_config.json",
-            "generation_config.json",
-        ];
-        let deepseek_coder_any_exists = deepseek_coder_files
-            .iter()
-            .any(|file| std::path::Path::new(&format!("{}/{}", deepseek_coder_dir, file)).exists());
-        let deepseek_coder_all_exists = deepseek_coder_files
+    /// 设置同时下载的模型数量上限，覆盖配置文件中的 `download.max_concurrent_models`
+    pub fn with_max_concurrent_downloads(mut self, max_concurrent: usize) -> Self {
+        self.download_limiter = Some(Arc::new(Semaphore::new(max_concurrent)));
+        self
+    }
+
+    /// 设置模型的空闲卸载时间，覆盖配置文件中的 `models.idle_ttl_secs`
+    pub fn with_idle_ttl(mut self, idle_ttl: Duration) -> Self {
+        self.idle_ttl = Some(idle_ttl);
+        self
+    }
+
+    /// 设置模型别名，覆盖配置文件中的 `models.aliases`
+    pub fn with_alias(mut self, alias: &str, model_id: &str) -> Self {
+        Arc::make_mut(&mut self.aliases).insert(alias.to_string(), model_id.to_string());
+        self
+    }
+
+    /// 将别名解析为实际模型ID，不是别名时原样返回
+    pub fn resolve_model_id<'a>(&'a self, model_id: &'a str) -> &'a str {
+        self.aliases.get(model_id).map(String::as_str).unwrap_or(model_id)
+    }
+
+    /// 指向该模型的所有别名，按名称排序
+    pub fn aliases_of(&self, model_id: &str) -> Vec<String> {
+        let mut aliases: Vec<String> = self
+            .aliases
             .iter()
-            .all(|file| std::path::Path::new(&format!("{}/{}", deepseek_coder_dir, file)).exists());
-        status.insert(
-            "deepseek-coder".to_string(),
-            ModelStatus {
-                is_cached: deepseek_coder_any_exists,
-                is_enabled: deepseek_coder_all_exists,
-            },
+            .filter(|(_, target)| target.as_str() == model_id)
+            .map(|(alias, _)| alias.clone())
+            .collect();
+        aliases.sort();
+        aliases
+    }
+
+    /// 设置模型的并发上限，覆盖配置文件中的 `max_concurrent`
+    pub fn with_concurrency_limit(mut self, model_id: &str, max_concurrent: usize) -> Self {
+        Arc::make_mut(&mut self.concurrency_limits)
+            .insert(model_id.to_string(), PriorityLimiter::new(max_concurrent));
+        self
+    }
+
+    /// 设置达到并发上限时的最长等待时间
+    pub fn with_queue_timeout(mut self, queue_timeout: Duration) -> Self {
+        self.queue_timeout = queue_timeout;
+        self
+    }
+
+    /// 获取模型的推理许可
+    ///
+    /// 许可在推理结束后释放；模型未配置并发上限时返回 `Ok(None)`。
+    /// 达到并发上限时按 `priority` 排队，高优先级请求先获得许可
+    ///
+    /// # 返回值
+    /// * `Ok(Some(PriorityPermit))` - 获得许可
+    /// * `Err(ModelError::Busy)` - 在 `queue_timeout` 内未能获得许可
+    pub async fn acquire_permit(
+        &self,
+        model_id: &str,
+        priority: Priority,
+    ) -> Result<Option<PriorityPermit>, ModelError> {
+        let Some(limiter) = self.concurrency_limits.get(model_id) else {
+            return Ok(None);
+        };
+        match tokio::time::timeout(self.queue_timeout, limiter.acquire(priority)).await {
+            Ok(permit) => Ok(Some(permit)),
+            Err(_) => {
+                log::warn!(
+                    "Timed out after {:?} waiting for a free slot on model {}",
+                    self.queue_timeout,
+                    model_id
+                );
+                Err(ModelError::Busy(format!(
+                    "{}: concurrency limit reached, waited {:?}",
+                    model_id, self.queue_timeout
+                )))
+            }
+        }
+    }
+
+    /// 注册一个已初始化的模型实例
+    pub async fn register_model(&self, model_id: &str, model: Arc<dyn CompletionModel>) {
+        self.models.write().await.insert(model_id.to_string(), model);
+        self.model_status.write().await.insert(
+            model_id.to_string(),
+            ModelStatus { is_cached: true, is_enabled: true, ..Default::default() },
         );
+    }
 
+    /// Refresh model status from disk
+    async fn refresh_status_from_disk(&self) -> Result<(), ModelError> {
+        let scanned = Self::scan_status_from_disk(&self.models_cache_dir);
+        let mut status = self.model_status.write().await;
+        status.extend(scanned);
         Ok(())
     }
 
+    /// Scan the models cache directory and compute each model's status
+    fn scan_status_from_disk(models_cache_dir: &Path) -> HashMap<String, ModelStatus> {
+        let config = get_config();
+        config
+            .models
+            .iter()
+            .map(|(model_id, model_config)| {
+                let model_dir = models_cache_dir.join(&model_config.hf_hub_id);
+                let files = scan_model_files(&model_dir, &model_config.model_files);
+                let status = ModelStatus {
+                    is_cached: files.iter().any(|file| file.present),
+                    is_enabled: files.iter().all(|file| file.present),
+                    ..Default::default()
+                };
+                (model_id.clone(), status)
+            })
+            .collect()
+    }
+
     /// 下载并初始化模型
     ///
     /// # 参数
@@ -152,30 +458,95 @@ impl ModelManager {
         model_id: &str,
         config_path: &str,
     ) -> Result<(), ModelError> {
-        let mut status = self.model_status.write().await;
-        if let Some(model_status) = status.get_mut(model_id) {
-            match model_id {
-                "yi-coder" => {
-                    let mut model = self.yi_coder.write().await;
-                    *model = Some(YiCoderModel::new(config_path).await.map_err(|e| {
-                        ModelError::InitializationFailed(format!("Yi-Coder: {}", e))
-                    })?);
-                    model_status.is_cached = true;
-                    model_status.is_enabled = true;
-                }
-                "deepseek-coder" => {
-                    let mut model = self.deepseek_coder.write().await;
-                    *model = Some(DeepseekCoderModel::new().await.map_err(|e| {
-                        ModelError::InitializationFailed(format!("Deepseek-Coder: {}", e))
-                    })?);
-                    model_status.is_cached = true;
-                    model_status.is_enabled = true;
+        let initializer =
+            self.initializers.lock().unwrap().entry(model_id.to_string()).or_default().clone();
+        // 同一模型只有一个初始化在运行，其余调用等待其结果；失败时下次调用重试
+        initializer
+            .get_or_try_init(|| async {
+                let loader = self.loaders.get(model_id);
+                let status = self.get_model_status(model_id).await;
+                if loader.is_none() && status.is_none() {
+                    return Err(ModelError::UnknownModel(model_id.to_string()));
                 }
-                _ => return Err(ModelError::UnsupportedModel(model_id.to_string())),
+                // 缓存中的文件完整时只需初始化，并发的请求等待初始化完成而不是返回503
+                let needs_download = status.is_none_or(|status| !status.is_enabled);
+                let (_permit, _download) = if needs_download {
+                    // 排队期间只记录队列位置，获得许可后才标记为下载中
+                    let permit = self.acquire_download_permit(model_id).await;
+                    (permit, Some(self.begin_download(model_id)))
+                } else {
+                    (None, None)
+                };
+                let model = match loader {
+                    Some(loader) => loader().await?,
+                    None => Self::load_model(model_id, config_path).await?,
+                };
+                self.register_model(model_id, model).await;
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// 将模型标记为下载中，直到返回的 `DownloadGuard` 被drop
+    pub fn begin_download(&self, model_id: &str) -> DownloadGuard {
+        self.downloading.lock().unwrap().insert(model_id.to_string());
+        DownloadGuard { model_id: model_id.to_string(), downloading: self.downloading.clone() }
+    }
+
+    /// 获取下载许可，达到 `download.max_concurrent_models` 上限时按先后顺序排队
+    ///
+    /// 未配置上限时返回 `None`
+    async fn acquire_download_permit(
+        &self,
+        model_id: &str,
+    ) -> Option<tokio::sync::OwnedSemaphorePermit> {
+        let limiter = self.download_limiter.clone()?;
+        self.download_queue.lock().unwrap().push_back(model_id.to_string());
+        let _queued =
+            QueuedDownload { model_id: model_id.to_string(), queue: self.download_queue.clone() };
+        if limiter.available_permits() == 0 {
+            log::info!("Download of model {} queued behind other downloads", model_id);
+        }
+        // tokio的Semaphore按请求顺序分配许可，与队列顺序一致
+        limiter.acquire_owned().await.ok()
+    }
+
+    /// 模型在下载队列中的位置，从1开始；未排队时返回 `None`
+    pub fn download_queue_position(&self, model_id: &str) -> Option<usize> {
+        let queue = self.download_queue.lock().unwrap();
+        queue.iter().position(|queued| queued == model_id).map(|position| position + 1)
+    }
+
+    /// 检查模型是否正在下载
+    pub fn is_downloading(&self, model_id: &str) -> bool {
+        self.downloading.lock().unwrap().contains(model_id)
+    }
+
+    /// 检查模型是否正在下载或排队等待下载
+    pub fn is_download_pending(&self, model_id: &str) -> bool {
+        self.is_downloading(model_id) || self.download_queue_position(model_id).is_some()
+    }
+
+    /// 构造模型实例
+    async fn load_model(
+        model_id: &str,
+        config_path: &str,
+    ) -> Result<Arc<dyn CompletionModel>, ModelError> {
+        match model_id {
+            "yi-coder" => {
+                let model = YiCoder::load(config_path)
+                    .await
+                    .map_err(|e| ModelError::InitializationFailed(format!("Yi-Coder: {}", e)))?;
+                Ok(Arc::new(model))
+            }
+            "deepseek-coder" => {
+                let model = DeepseekCoder::new().await.map_err(|e| {
+                    ModelError::InitializationFailed(format!("Deepseek-Coder: {}", e))
+                })?;
+                Ok(Arc::new(model))
             }
-            Ok(())
-        } else {
-            Err(ModelError::UnknownModel(model_id.to_string()))
+            _ => Err(ModelError::UnsupportedModel(model_id.to_string())),
         }
     }
 
@@ -207,27 +578,229 @@ impl ModelManager {
     /// ```
     pub async fn get_model_status(&self, model_id: &str) -> Option<ModelStatus> {
         let status = self.model_status.read().await;
-        status.get(model_id).cloned()
+        status.get(model_id).map(|status| ModelStatus {
+            is_downloading: self.is_downloading(model_id),
+            download_queue_position: self.download_queue_position(model_id),
+            ..*status
+        })
     }
 
-    /// 获取Yi-Coder模型实例
+    /// 获取已加载的模型实例
     ///
     /// # 返回值
-    /// * `Some(YiCoderModel)` - 如果模型已加载
+    /// * `Some(Arc<dyn CompletionModel>)` - 如果模型已加载
     /// * `None` - 如果模型未加载
-    pub async fn get_yi_coder(&self) -> Option<YiCoderModel> {
-        let model = self.yi_coder.read().await;
-        model.clone()
+    pub async fn get_model(&self, model_id: &str) -> Option<Arc<dyn CompletionModel>> {
+        let models = self.models.read().await;
+        models.get(model_id).cloned()
+    }
+
+    /// 已加载的模型ID，按名称排序
+    pub async fn loaded_model_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.models.read().await.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// 获取模型实例，未加载时先下载并初始化
+    pub async fn get_or_load_model(
+        &self,
+        model_id: &str,
+    ) -> Result<Arc<dyn CompletionModel>, ModelError> {
+        let model = match self.get_model(model_id).await {
+            Some(model) => model,
+            None => {
+                self.download_model(model_id, "config/app.yml").await?;
+                self.get_model(model_id)
+                    .await
+                    .ok_or_else(|| ModelError::UnknownModel(model_id.to_string()))?
+            }
+        };
+        self.last_used.lock().unwrap().insert(model_id.to_string(), Instant::now());
+        Ok(model)
+    }
+
+    /// 获取位于 `device` 上的模型实例，供请求头 `X-Device` 指定设备的请求使用
+    ///
+    /// 模型本身就在该设备上时直接返回，否则使用该设备上的副本，首次请求时创建。
+    /// 副本随模型一起卸载
+    pub async fn get_or_load_model_on(
+        &self,
+        model_id: &str,
+        device: &Device,
+    ) -> Result<Arc<dyn CompletionModel>, AppError> {
+        let model = self.get_or_load_model(model_id).await?;
+        if model.input_device().is_some_and(|current| current.location() == device.location()) {
+            return Ok(model);
+        }
+        let key = (model_id.to_string(), device_label(device));
+        if let Some(replica) = self.replicas.read().await.get(&key) {
+            return Ok(replica.clone());
+        }
+        log::info!("Loading replica of model {} on {}", model_id, key.1);
+        let replica = model.to_device(device).await?;
+        // 并发的首次请求可能各自创建了副本，只保留先写入的一个
+        Ok(self.replicas.write().await.entry(key).or_insert(replica).clone())
+    }
+
+    /// 卸载空闲超过 `idle_ttl` 的模型，返回被卸载的模型ID
+    ///
+    /// 只卸载可以重新加载的模型，下次请求时透明地重新加载。
+    /// 进行中的生成持有模型实例，不受卸载影响
+    pub async fn unload_idle_models(&self) -> Vec<String> {
+        let Some(idle_ttl) = self.idle_ttl else {
+            return Vec::new();
+        };
+        let idle: Vec<String> = self
+            .last_used
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, last_used)| last_used.elapsed() >= idle_ttl)
+            .map(|(model_id, _)| model_id.clone())
+            .collect();
+        let mut unloaded = Vec::new();
+        for model_id in idle {
+            match self.unload_model(&model_id).await {
+                Ok(true) => {
+                    log::info!("Model {} idle for over {:?}, unloaded", model_id, idle_ttl);
+                    unloaded.push(model_id);
+                }
+                Ok(false) => {}
+                // 内置模型无法卸载，不再检查
+                Err(e) => {
+                    log::debug!("Skipping idle model {}: {}", model_id, e);
+                    self.last_used.lock().unwrap().remove(&model_id);
+                }
+            }
+        }
+        unloaded
     }
 
-    /// 获取Deepseek-Coder模型实例
+    /// 启动后台任务，定期卸载空闲的模型；未设置 `idle_ttl` 时返回 `None`
+    ///
+    /// 检查间隔为 `idle_ttl` 的一半，模型最多在空闲1.5倍 `idle_ttl` 后被卸载
+    pub fn spawn_idle_unloader(&self) -> Option<tokio::task::JoinHandle<()>> {
+        let idle_ttl = self.idle_ttl?;
+        let manager = self.clone();
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval((idle_ttl / 2).max(Duration::from_millis(1)));
+            loop {
+                interval.tick().await;
+                manager.unload_idle_models().await;
+            }
+        }))
+    }
+
+    /// 获取模型所需文件的列表及其状态
     ///
     /// # 返回值
-    /// * `Some(DeepseekCoderModel)` - 如果模型已加载
-    /// * `None` - 如果模型未加载
-    pub async fn get_deepseek_coder(&self) -> Option<DeepseekCoderModel> {
-        let model = self.deepseek_coder.read().await;
-        model.clone()
+    /// * `Some(Vec<ModelFileStatus>)` - 如果模型存在于配置中
+    /// * `None` - 如果模型不存在
+    pub fn get_model_files(&self, model_id: &str) -> Option<Vec<ModelFileStatus>> {
+        let config = get_config();
+        let model_config = config.models.get(model_id)?;
+        let model_dir = self.models_cache_dir.join(&model_config.hf_hub_id);
+        Some(scan_model_files(&model_dir, &model_config.model_files))
+    }
+
+    /// 卸载模型实例以释放内存，缓存目录中的文件保持不变
+    ///
+    /// 之后的请求会重新从缓存加载模型
+    ///
+    /// # 返回值
+    /// * `Ok(true)` - 模型已加载并被卸载
+    /// * `Ok(false)` - 模型尚未加载
+    /// * `Err(AppError::NotFound)` - 模型不存在
+    /// * `Err(AppError::InvalidParameter)` - 内置模型无法重新加载，不允许卸载
+    pub async fn unload_model(&self, model_id: &str) -> Result<bool, AppError> {
+        if self.get_model_status(model_id).await.is_none() {
+            return Err(AppError::NotFound);
+        }
+        let in_config = get_config().models.get(model_id).is_some();
+        // 内置的回显、模拟模型卸载后无法重新加载
+        if !in_config && !self.loaders.contains_key(model_id) {
+            return Err(AppError::InvalidParameter(format!(
+                "Model {} cannot be unloaded",
+                model_id
+            )));
+        }
+        let unloaded = self.models.write().await.remove(model_id).is_some();
+        self.replicas.write().await.retain(|(replica_id, _), _| replica_id != model_id);
+        self.last_used.lock().unwrap().remove(model_id);
+        // 清除初始化结果，下次请求时重新加载
+        self.initializers.lock().unwrap().remove(model_id);
+        if let Some(scanned) = Self::scan_status_from_disk(&self.models_cache_dir).remove(model_id)
+        {
+            self.model_status.write().await.insert(model_id.to_string(), scanned);
+        }
+        if unloaded {
+            log::info!("Unloaded model {}", model_id);
+        }
+        Ok(unloaded)
+    }
+
+    /// 卸载模型并删除缓存目录中的模型文件
+    ///
+    /// 只删除配置中声明的文件，目录中的其他文件保持不变
+    ///
+    /// # 返回值
+    /// * `Ok(Vec<String>)` - 被删除的文件名
+    /// * `Err(AppError::NotFound)` - 模型不存在于配置中
+    /// * `Err(AppError::ServiceUnavailable)` - 模型正在下载
+    pub async fn delete_model_files(&self, model_id: &str) -> Result<Vec<String>, AppError> {
+        let config = get_config();
+        let model_config = config.models.get(model_id).ok_or(AppError::NotFound)?;
+        if self.is_downloading(model_id) {
+            return Err(ModelError::Busy(format!("{}: download in progress", model_id)).into());
+        }
+        self.unload_model(model_id).await?;
+
+        let model_dir = self.models_cache_dir.join(&model_config.hf_hub_id);
+        let mut deleted = Vec::new();
+        for file in scan_model_files(&model_dir, &model_config.model_files) {
+            if file.present {
+                std::fs::remove_file(model_dir.join(&file.name))?;
+                deleted.push(file.name);
+            }
+        }
+        self.model_status.write().await.insert(model_id.to_string(), ModelStatus::default());
+        log::info!("Deleted {} cached files of model {}", deleted.len(), model_id);
+        Ok(deleted)
+    }
+
+    /// 获取模型的采样默认值
+    ///
+    /// # 返回值
+    /// * `Ok(GenerationDefaults)` - 读取成功
+    /// * `Err(AppError::NotFound)` - 模型不存在于配置中或尚未下载 `generation_config.json`
+    pub fn get_generation_defaults(&self, model_id: &str) -> Result<GenerationDefaults, AppError> {
+        let config = get_config();
+        let model_config = config.models.get(model_id).ok_or(AppError::NotFound)?;
+        let path = self
+            .models_cache_dir
+            .join(&model_config.hf_hub_id)
+            .join(&model_config.model_files.generation_config);
+        if !path.is_file() {
+            return Err(AppError::NotFound);
+        }
+        read_generation_defaults(&path)
+    }
+
+    /// 获取模型的特殊token
+    ///
+    /// # 返回值
+    /// * `Ok(SpecialTokens)` - 读取成功
+    /// * `Err(AppError::NotFound)` - 模型不存在于配置中或尚未下载tokenizer配置
+    pub fn get_special_tokens(&self, model_id: &str) -> Result<SpecialTokens, AppError> {
+        let config = get_config();
+        let model_config = config.models.get(model_id).ok_or(AppError::NotFound)?;
+        let model_dir = self.models_cache_dir.join(&model_config.hf_hub_id);
+        let config_path = model_dir.join(&model_config.model_files.tokenizer_config);
+        if !config_path.is_file() {
+            return Err(AppError::NotFound);
+        }
+        read_special_tokens(&config_path, &model_dir.join(&model_config.model_files.tokenizer))
     }
 
     /// 获取所有模型的状态
@@ -238,6 +811,16 @@ impl ModelManager {
         // Refresh status from disk before returning
         let _ = self.refresh_status_from_disk().await;
         let status = self.model_status.read().await;
-        status.clone()
+        status
+            .iter()
+            .map(|(model_id, status)| {
+                let status = ModelStatus {
+                    is_downloading: self.is_downloading(model_id),
+                    download_queue_position: self.download_queue_position(model_id),
+                    ..*status
+                };
+                (model_id.clone(), status)
+            })
+            .collect()
     }
 }
@@ -0,0 +1,67 @@
+//! 单个请求的模型参数覆盖
+//!
+//! 请求头 `X-Model-Overrides` 携带JSON对象，只在本次请求中覆盖模型的部分参数，
+//! 便于实验而无需修改配置文件。只允许覆盖数值稳定性相关的字段，其他字段在解析时拒绝。
+use crate::error::AppError;
+use crate::utils::config::NumericalStabilityConfig;
+use serde::Deserialize;
+
+/// 允许覆盖的模型参数，未设置的字段沿用模型配置
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelOverrides {
+    /// 最终LayerNorm的epsilon
+    pub layer_norm_eps: Option<f64>,
+    /// 词嵌入输出的截断范围，设置很大的值相当于关闭截断
+    pub embedding_clamp: Option<f32>,
+    /// 注意力中Q、K矩阵的截断范围
+    pub qk_clamp: Option<f32>,
+    /// 缩放后注意力分数的截断范围
+    pub attention_score_clamp: Option<f32>,
+    /// 最终LayerNorm输入与输出的截断范围
+    pub hidden_clamp: Option<f32>,
+}
+
+impl ModelOverrides {
+    /// 解析 `X-Model-Overrides` 的值，未知字段或非正数返回 `AppError::InvalidParameter`
+    pub fn parse(value: &str) -> Result<Self, AppError> {
+        let overrides: Self = serde_json::from_str(value)
+            .map_err(|e| AppError::InvalidParameter(format!("Invalid model overrides: {}", e)))?;
+        if overrides.layer_norm_eps.is_some_and(|eps| !(eps.is_finite() && eps > 0.0)) {
+            return Err(AppError::InvalidParameter(
+                "layer_norm_eps must be a positive number".to_string(),
+            ));
+        }
+        let clamps = [
+            ("embedding_clamp", overrides.embedding_clamp),
+            ("qk_clamp", overrides.qk_clamp),
+            ("attention_score_clamp", overrides.attention_score_clamp),
+            ("hidden_clamp", overrides.hidden_clamp),
+        ];
+        for (name, clamp) in clamps {
+            if clamp.is_some_and(|clamp| !(clamp.is_finite() && clamp > 0.0)) {
+                return Err(AppError::InvalidParameter(format!(
+                    "{} must be a positive number",
+                    name
+                )));
+            }
+        }
+        Ok(overrides)
+    }
+
+    /// 将设置的截断范围写入 `stability`
+    pub fn apply_to_stability(&self, stability: &mut NumericalStabilityConfig) {
+        if let Some(clamp) = self.embedding_clamp {
+            stability.embedding_clamp = clamp;
+        }
+        if let Some(clamp) = self.qk_clamp {
+            stability.qk_clamp = clamp;
+        }
+        if let Some(clamp) = self.attention_score_clamp {
+            stability.attention_score_clamp = clamp;
+        }
+        if let Some(clamp) = self.hidden_clamp {
+            stability.hidden_clamp = clamp;
+        }
+    }
+}
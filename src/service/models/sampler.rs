@@ -0,0 +1,135 @@
+//! 可替换的解码策略
+//!
+//! 生成循环按采样参数通过 [`sampler_for`] 选择 [`Sampler`]，各实现只负责从处理后的
+//! logits中选出下一个token；惩罚、EOS屏蔽与schema约束仍由生成循环处理。
+//! 设置了 `temperature_decay` 时，采样器以 `history` 的长度作为当前步数计算退火后的temperature。
+use crate::error::AppError;
+use crate::service::models::sampling::{
+    annealed_temperature, greedy_token, sample_next_token, softmax, SamplingConfig,
+};
+use candle_core::{DType, Device, Tensor};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::sync::Mutex;
+
+/// 解码策略
+pub trait Sampler: Send + Sync {
+    /// 从一维logits中选出下一个token，`history` 为已生成的token
+    fn sample(&self, logits: &Tensor, history: &[u32]) -> Result<u32, AppError>;
+}
+
+/// 按采样参数选择解码策略
+///
+/// * 未设置temperature（或 `<= 0`）时使用 [`GreedySampler`]
+/// * 设置了 `top_k` 或 `top_p < 1` 时使用 [`TopKTopPSampler`]
+/// * 否则使用 [`TemperatureSampler`]
+pub fn sampler_for(config: &SamplingConfig) -> Box<dyn Sampler> {
+    let Some(temperature) = config.temperature() else {
+        return Box::new(GreedySampler);
+    };
+    let decay = config.temperature_decay();
+    if config.top_k().is_some() || config.top_p() < 1.0 {
+        Box::new(
+            TopKTopPSampler::new(temperature, config.top_k(), config.top_p(), config.seed())
+                .with_temperature_decay(decay),
+        )
+    } else {
+        Box::new(TemperatureSampler::new(temperature, config.seed()).with_temperature_decay(decay))
+    }
+}
+
+/// 指定seed时使用固定种子，保证相同请求得到相同结果
+fn seeded_rng(seed: Option<u64>) -> Mutex<StdRng> {
+    Mutex::new(match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    })
+}
+
+/// 贪心解码：总是选择logit最大的token
+pub struct GreedySampler;
+
+impl Sampler for GreedySampler {
+    fn sample(&self, logits: &Tensor, _history: &[u32]) -> Result<u32, AppError> {
+        greedy_token(&logits.to_device(&Device::Cpu)?.to_dtype(DType::F32)?)
+    }
+}
+
+/// 按temperature缩放后在全部token上采样
+pub struct TemperatureSampler {
+    temperature: f32,
+    temperature_decay: f32,
+    rng: Mutex<StdRng>,
+}
+
+impl TemperatureSampler {
+    pub fn new(temperature: f32, seed: Option<u64>) -> Self {
+        Self { temperature, temperature_decay: 0.0, rng: seeded_rng(seed) }
+    }
+
+    /// 每生成一个token temperature降低 `decay`
+    pub fn with_temperature_decay(mut self, decay: f32) -> Self {
+        self.temperature_decay = decay;
+        self
+    }
+}
+
+impl Sampler for TemperatureSampler {
+    fn sample(&self, logits: &Tensor, history: &[u32]) -> Result<u32, AppError> {
+        let temperature =
+            annealed_temperature(self.temperature, self.temperature_decay, history.len());
+        sample_next_token(logits, Some(temperature), &mut *self.rng.lock().unwrap())
+    }
+}
+
+/// 只在概率最高的 `top_k` 个token中、累计概率达到 `top_p` 的最少token中采样
+pub struct TopKTopPSampler {
+    temperature: f32,
+    temperature_decay: f32,
+    top_k: Option<usize>,
+    top_p: f32,
+    rng: Mutex<StdRng>,
+}
+
+impl TopKTopPSampler {
+    pub fn new(temperature: f32, top_k: Option<usize>, top_p: f32, seed: Option<u64>) -> Self {
+        Self { temperature, temperature_decay: 0.0, top_k, top_p, rng: seeded_rng(seed) }
+    }
+
+    /// 每生成一个token temperature降低 `decay`
+    pub fn with_temperature_decay(mut self, decay: f32) -> Self {
+        self.temperature_decay = decay;
+        self
+    }
+}
+
+impl Sampler for TopKTopPSampler {
+    fn sample(&self, logits: &Tensor, history: &[u32]) -> Result<u32, AppError> {
+        let logits = logits.to_device(&Device::Cpu)?.to_dtype(DType::F32)?;
+        let temperature =
+            annealed_temperature(self.temperature, self.temperature_decay, history.len());
+        let probs: Vec<f32> = softmax(&(logits / temperature as f64)?, 0)?.to_vec1()?;
+
+        let mut candidates: Vec<(usize, f32)> = probs.into_iter().enumerate().collect();
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+        if let Some(top_k) = self.top_k {
+            candidates.truncate(top_k);
+        }
+        // top_k截断后重新归一化，保留累计概率达到top_p的最少token，至少保留一个
+        let total: f32 = candidates.iter().map(|(_, p)| p).sum();
+        let mut cumulative = 0.0;
+        let keep = candidates
+            .iter()
+            .position(|(_, p)| {
+                cumulative += p / total;
+                cumulative >= self.top_p
+            })
+            .map_or(candidates.len(), |index| index + 1);
+        candidates.truncate(keep.max(1));
+
+        let dist = WeightedIndex::new(candidates.iter().map(|(_, p)| *p))
+            .map_err(|e| AppError::new(format!("WeightedIndex error: {}", e)))?;
+        Ok(candidates[dist.sample(&mut *self.rng.lock().unwrap())].0 as u32)
+    }
+}
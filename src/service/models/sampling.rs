@@ -0,0 +1,408 @@
+//! 共享的采样工具
+//!
+//! YiCoder 与 DeepseekCoder 共用的 logits 处理和 token 采样逻辑。
+use crate::error::AppError;
+use crate::service::chat::chat_completion::ChatCompletionParams;
+use crate::service::models::completion_model::DEFAULT_MAX_TOKENS;
+use candle_core::{DType, Device, IndexOp, Tensor};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// `Skip` 策略下无效logit相对最小有效logit的差值，使其采样概率可忽略
+const SKIP_LOGIT_GAP: f32 = 1e4;
+
+/// logits中出现NaN/Inf时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NanPolicy {
+    /// 不做处理，由采样阶段报错并终止请求
+    #[default]
+    Error,
+    /// NaN和-Inf替换为有效logits的最小值，+Inf替换为最大值
+    Clamp,
+    /// 将NaN/Inf对应的token排除在采样之外
+    Skip,
+}
+
+/// `temperature_decay` 退火后temperature的下限
+pub const MIN_ANNEALED_TEMPERATURE: f32 = 0.1;
+
+/// 第 `step` 个生成token（从0开始）使用的temperature
+///
+/// 从 `temperature` 起每个token线性降低 `decay`，不低于 [`MIN_ANNEALED_TEMPERATURE`]；
+/// 初始值本身低于下限时保持不变
+pub fn annealed_temperature(temperature: f32, decay: f32, step: usize) -> f32 {
+    let floor = MIN_ANNEALED_TEMPERATURE.min(temperature);
+    (temperature - decay * step as f32).max(floor)
+}
+
+/// temperature的上限，更高的值按该值采样
+pub const MAX_TEMPERATURE: f32 = 2.0;
+
+/// presence_penalty 与 frequency_penalty 的取值范围
+const PENALTY_RANGE: std::ops::RangeInclusive<f32> = -2.0..=2.0;
+
+/// 经过校验和归一化的采样参数
+///
+/// 只能通过 [`SamplingConfig::try_new`] 构造，持有该类型即表示参数合法。
+/// 序列化结果即响应中的 `x_params`，`temperature` 为 `null` 表示贪心解码，
+/// 超出范围的 `temperature` 与 `top_p` 为截断后的值
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SamplingConfig {
+    temperature: Option<f32>,
+    temperature_decay: f32,
+    top_p: f32,
+    top_k: Option<usize>,
+    presence_penalty: f32,
+    frequency_penalty: f32,
+    min_tokens: usize,
+    max_tokens: usize,
+    no_repeat_ngram_size: Option<usize>,
+    seed: Option<u64>,
+}
+
+impl SamplingConfig {
+    /// 校验请求参数并填充默认值
+    ///
+    /// `temperature` 截断到 [`MAX_TEMPERATURE`]，`top_p` 截断到 `[0, 1]`
+    ///
+    /// # 返回值
+    /// * `Ok(SamplingConfig)` - 所有参数合法
+    /// * `Err(AppError::InvalidParameter)` - 第一个不合法的参数及其取值
+    pub fn try_new(params: &ChatCompletionParams) -> Result<Self, AppError> {
+        let temperature = match params.temperature {
+            Some(temp) if !temp.is_finite() => {
+                return Err(invalid(format!("temperature must be a finite number, got {}", temp)))
+            }
+            // temperature <= 0 使用贪心解码
+            Some(temp) if temp > 0.0 => Some(temp.min(MAX_TEMPERATURE)),
+            _ => None,
+        };
+        let temperature_decay = match params.temperature_decay {
+            Some(decay) if !(decay.is_finite() && decay >= 0.0) => {
+                return Err(invalid(format!(
+                    "temperature_decay must be a non-negative number, got {}",
+                    decay
+                )))
+            }
+            Some(decay) => decay,
+            None => 0.0,
+        };
+        let top_p = match params.top_p {
+            Some(top_p) if top_p.is_nan() => {
+                return Err(invalid(format!("top_p must be a number, got {}", top_p)))
+            }
+            // top_p为0时只保留概率最高的token
+            Some(top_p) => top_p.clamp(0.0, 1.0),
+            None => 1.0,
+        };
+        if params.top_k == Some(0) {
+            return Err(invalid("top_k must be at least 1, got 0".to_string()));
+        }
+        let presence_penalty = penalty("presence_penalty", params.presence_penalty)?;
+        let frequency_penalty = penalty("frequency_penalty", params.frequency_penalty)?;
+        let max_tokens = match params.max_tokens {
+            Some(0) => return Err(invalid("max_tokens must be at least 1, got 0".to_string())),
+            Some(max_tokens) => max_tokens,
+            None => DEFAULT_MAX_TOKENS,
+        };
+        let min_tokens = params.min_tokens.unwrap_or(0);
+        if min_tokens > max_tokens {
+            return Err(invalid(format!(
+                "min_tokens must not exceed max_tokens ({}), got {}",
+                max_tokens, min_tokens
+            )));
+        }
+
+        if params.no_repeat_ngram_size == Some(0) {
+            return Err(invalid("no_repeat_ngram_size must be at least 1, got 0".to_string()));
+        }
+
+        Ok(Self {
+            temperature,
+            temperature_decay,
+            top_p,
+            top_k: params.top_k,
+            presence_penalty,
+            frequency_penalty,
+            min_tokens,
+            max_tokens,
+            no_repeat_ngram_size: params.no_repeat_ngram_size,
+            seed: params.seed,
+        })
+    }
+
+    /// 采样温度，`None` 表示贪心解码
+    pub fn temperature(&self) -> Option<f32> {
+        self.temperature
+    }
+
+    /// 每生成一个token temperature降低的幅度，未设置时为0
+    pub fn temperature_decay(&self) -> f32 {
+        self.temperature_decay
+    }
+
+    /// 第 `step` 个生成token使用的temperature，`None` 表示贪心解码
+    pub fn temperature_at(&self, step: usize) -> Option<f32> {
+        self.temperature
+            .map(|temperature| annealed_temperature(temperature, self.temperature_decay, step))
+    }
+
+    pub fn top_p(&self) -> f32 {
+        self.top_p
+    }
+
+    pub fn top_k(&self) -> Option<usize> {
+        self.top_k
+    }
+
+    pub fn presence_penalty(&self) -> f32 {
+        self.presence_penalty
+    }
+
+    pub fn frequency_penalty(&self) -> f32 {
+        self.frequency_penalty
+    }
+
+    /// 生成至少这么多token之前不会生成EOS
+    pub fn min_tokens(&self) -> usize {
+        self.min_tokens
+    }
+
+    pub fn max_tokens(&self) -> usize {
+        self.max_tokens
+    }
+
+    /// 将生成上限降低为实际使用的 `max_tokens`，`min_tokens` 随之不超过该上限
+    pub(crate) fn with_max_tokens(self, max_tokens: usize) -> Self {
+        Self { max_tokens, min_tokens: self.min_tokens.min(max_tokens), ..self }
+    }
+
+    /// 禁止重复的n-gram长度
+    pub fn no_repeat_ngram_size(&self) -> Option<usize> {
+        self.no_repeat_ngram_size
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+}
+
+fn invalid(message: String) -> AppError {
+    AppError::InvalidParameter(message)
+}
+
+/// 校验惩罚系数在 `[-2, 2]` 内，未设置时为0
+fn penalty(name: &str, value: Option<f32>) -> Result<f32, AppError> {
+    match value {
+        Some(value) if !PENALTY_RANGE.contains(&value) => {
+            Err(invalid(format!("{} must be between -2 and 2, got {}", name, value)))
+        }
+        Some(value) => Ok(value),
+        None => Ok(0.0),
+    }
+}
+
+/// softmax(x_i) = exp(x_i - max(x)) / Σ(exp(x_j - max(x)))
+pub fn softmax(tensor: &Tensor, dim: usize) -> Result<Tensor, candle_core::Error> {
+    log::debug!("Softmax input tensor shape: {:?}", tensor.shape());
+
+    // Check tensor dimensions
+    if tensor.shape().dims().is_empty() {
+        return Err(candle_core::Error::msg(AppError::new(
+            "Empty tensor provided to softmax".to_string(),
+        )));
+    }
+
+    // Validate input tensor before conversion, -inf marks masked tokens
+    let values = tensor.to_vec1::<f32>()?;
+    if values.iter().any(|&x| x.is_nan() || x == f32::INFINITY) {
+        return Err(candle_core::Error::msg(AppError::new(
+            "Input tensor contains NaN or infinite values before conversion".to_string(),
+        )));
+    }
+
+    // Convert to f64 for better numerical stability
+    let tensor = tensor.to_dtype(DType::F64)?;
+
+    // Subtract max for numerical stability
+    let max = tensor.max_keepdim(dim)?;
+    let max = max.broadcast_as(tensor.shape())?;
+    let diff = tensor.sub(&max)?;
+
+    // Clip values to prevent overflow in exp calculation
+    let diff = diff.clamp(-100.0, 100.0)?;
+    let exp = diff.exp()?;
+
+    // Use larger epsilon value (1e-6) for better stability
+    let sum = exp.sum_keepdim(dim)?;
+    let epsilon = Tensor::new(1e-6, tensor.device())?.broadcast_as(sum.shape())?;
+    let sum = sum.add(&epsilon)?.broadcast_as(exp.shape())?;
+
+    // Convert back to f32
+    let probs = exp.div(&sum)?.to_dtype(DType::F32)?;
+
+    // Validate probabilities
+    let values = probs.to_vec1::<f32>()?;
+    if values.iter().any(|&x| x.is_nan() || x.is_infinite() || x < 0.0) {
+        return Err(candle_core::Error::msg(AppError::new(
+            "Invalid values detected in softmax output".to_string(),
+        )));
+    }
+
+    Ok(probs)
+}
+
+/// 取出最后一个位置的logits
+///
+/// 模型输出形如 `(batch, seq_len, vocab)` 或 `(seq_len, vocab)`，
+/// 返回最后一个位置的一维logits `(vocab,)`
+pub fn last_position_logits(output: &Tensor) -> Result<Tensor, AppError> {
+    let logits = match output.rank() {
+        0 => return Err(AppError::new("Model output is a scalar, expected logits".to_string())),
+        1 => output.clone(),
+        rank => {
+            let rows = output.flatten_to(rank - 2)?;
+            rows.i(rows.dim(0)? - 1)?
+        }
+    };
+    Ok(logits.to_dtype(DType::F32)?)
+}
+
+/// 按 `policy` 处理一维logits中的NaN/Inf
+///
+/// `Error` 策略原样返回logits；其他策略在所有logits都无效时返回错误
+pub fn sanitize_logits(logits: &Tensor, policy: NanPolicy) -> Result<Tensor, AppError> {
+    if policy == NanPolicy::Error {
+        return Ok(logits.clone());
+    }
+    let values = logits.to_device(&Device::Cpu)?.to_dtype(DType::F32)?.to_vec1::<f32>()?;
+    let invalid = values.iter().filter(|x| !x.is_finite()).count();
+    if invalid == 0 {
+        return Ok(logits.clone());
+    }
+    let (min, max) = values
+        .iter()
+        .filter(|x| x.is_finite())
+        .fold(None, |range: Option<(f32, f32)>, &x| match range {
+            Some((min, max)) => Some((min.min(x), max.max(x))),
+            None => Some((x, x)),
+        })
+        .ok_or_else(|| AppError::new("All logits are NaN or infinite".to_string()))?;
+    log::warn!("Replacing {} NaN/Inf logits with {:?} policy", invalid, policy);
+
+    let sanitized: Vec<f32> = values
+        .into_iter()
+        .map(|x| match policy {
+            _ if x.is_finite() => x,
+            NanPolicy::Clamp if x == f32::INFINITY => max,
+            NanPolicy::Clamp => min,
+            _ => min - SKIP_LOGIT_GAP,
+        })
+        .collect();
+    Ok(Tensor::new(sanitized, &Device::Cpu)?)
+}
+
+/// 将一维logits中 `token_id` 的logit置为-inf，使其不会被采样
+pub fn mask_token(logits: &Tensor, token_id: u32) -> Result<Tensor, AppError> {
+    let mut values = logits.to_dtype(DType::F32)?.to_vec1::<f32>()?;
+    if let Some(value) = values.get_mut(token_id as usize) {
+        *value = f32::NEG_INFINITY;
+    }
+    Ok(Tensor::new(values, logits.device())?)
+}
+
+/// 按OpenAI的定义对已生成的token施加惩罚
+///
+/// 每个token的logit减去 `frequency_penalty * 出现次数`，出现过的token再减去
+/// `presence_penalty`。与乘性的repetition penalty不同，惩罚是加性的，在softmax之前生效
+pub fn apply_penalties(
+    logits: &Tensor,
+    tokens: &[u32],
+    presence_penalty: f32,
+    frequency_penalty: f32,
+) -> Result<Tensor, AppError> {
+    if tokens.is_empty() || (presence_penalty == 0.0 && frequency_penalty == 0.0) {
+        return Ok(logits.clone());
+    }
+    let mut counts = HashMap::new();
+    for &token_id in tokens {
+        *counts.entry(token_id).or_insert(0usize) += 1;
+    }
+    let mut values = logits.to_dtype(DType::F32)?.to_vec1::<f32>()?;
+    for (token_id, count) in counts {
+        if let Some(value) = values.get_mut(token_id as usize) {
+            *value -= frequency_penalty * count as f32 + presence_penalty;
+        }
+    }
+    Ok(Tensor::new(values, logits.device())?)
+}
+
+/// 返回接在 `tokens` 之后会组成已出现过的 `ngram_size` 元组的token
+pub fn repeated_ngram_tokens(tokens: &[u32], ngram_size: usize) -> Vec<u32> {
+    if ngram_size == 0 || tokens.len() < ngram_size {
+        return Vec::new();
+    }
+    let prefix = &tokens[tokens.len() + 1 - ngram_size..];
+    tokens
+        .windows(ngram_size)
+        .filter(|window| &window[..ngram_size - 1] == prefix)
+        .map(|window| window[ngram_size - 1])
+        .collect()
+}
+
+/// 屏蔽会重复已生成n-gram的token，没有token可选时返回 `None`
+pub fn mask_repeated_ngrams(
+    logits: &Tensor,
+    tokens: &[u32],
+    ngram_size: usize,
+) -> Result<Option<Tensor>, AppError> {
+    let banned = repeated_ngram_tokens(tokens, ngram_size);
+    if banned.is_empty() {
+        return Ok(Some(logits.clone()));
+    }
+    let mut values = logits.to_dtype(DType::F32)?.to_vec1::<f32>()?;
+    for token_id in banned {
+        if let Some(value) = values.get_mut(token_id as usize) {
+            *value = f32::NEG_INFINITY;
+        }
+    }
+    if values.iter().all(|value| *value == f32::NEG_INFINITY) {
+        return Ok(None);
+    }
+    Ok(Some(Tensor::new(values, logits.device())?))
+}
+
+/// 根据temperature从logits中采样下一个token
+///
+/// * `temperature` 为 `None` 或 `<= 0` 时使用argmax贪心解码，结果确定且不消耗随机数
+/// * `rng` 随机数生成器，使用固定种子的生成器可得到可复现的结果
+///
+/// 采样在CPU上进行：softmax使用F64计算，而Metal不支持F64
+pub fn sample_next_token<R: Rng + ?Sized>(
+    logits: &Tensor,
+    temperature: Option<f32>,
+    rng: &mut R,
+) -> Result<u32, AppError> {
+    let logits = logits.to_device(&Device::Cpu)?.to_dtype(DType::F32)?;
+    match temperature {
+        Some(temp) if temp.is_nan() || temp.is_infinite() => Err(AppError::InvalidParameter(
+            format!("Invalid temperature value: {} (must be a finite number)", temp),
+        )),
+        Some(temp) if temp > 0.0 => {
+            let scaled_logits = (logits / temp as f64)?;
+            let probs_vec: Vec<f32> = softmax(&scaled_logits, 0)?.to_vec1()?;
+            let dist = WeightedIndex::new(&probs_vec)
+                .map_err(|e| AppError::new(format!("WeightedIndex error: {}", e)))?;
+            Ok(dist.sample(rng) as u32)
+        }
+        _ => greedy_token(&logits),
+    }
+}
+
+/// 贪心解码：返回logits最大的token
+pub fn greedy_token(logits: &Tensor) -> Result<u32, AppError> {
+    Ok(logits.argmax(0)?.to_scalar::<u32>()?)
+}
@@ -0,0 +1,129 @@
+//! 按优先级排队的推理并发限制
+//!
+//! 与 `tokio::sync::Semaphore` 类似，但等待中的请求按优先级出队：
+//! 高优先级请求先于更早入队的低优先级请求获得许可，同一优先级内先到先得。
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// 请求的调度优先级
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+struct Waiter {
+    priority: Priority,
+    /// 入队序号，同一优先级内序号小的先出队
+    seq: u64,
+    sender: oneshot::Sender<PriorityPermit>,
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.cmp(&other.priority).then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+struct State {
+    available: usize,
+    next_seq: u64,
+    waiters: BinaryHeap<Waiter>,
+}
+
+/// 固定数量许可的优先级队列
+pub struct PriorityLimiter {
+    state: Mutex<State>,
+}
+
+impl PriorityLimiter {
+    pub fn new(permits: usize) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(State {
+                available: permits,
+                next_seq: 0,
+                waiters: BinaryHeap::new(),
+            }),
+        })
+    }
+
+    /// 当前可用的许可数量
+    pub fn available_permits(&self) -> usize {
+        self.state.lock().unwrap().available
+    }
+
+    /// 获取许可，没有可用许可时按 `priority` 排队等待
+    ///
+    /// 等待中的future被drop（例如超时）后自动退出队列
+    pub async fn acquire(self: &Arc<Self>, priority: Priority) -> PriorityPermit {
+        let receiver = {
+            let mut state = self.state.lock().unwrap();
+            if state.available > 0 && state.waiters.is_empty() {
+                state.available -= 1;
+                return PriorityPermit { limiter: Some(self.clone()) };
+            }
+            let (sender, receiver) = oneshot::channel();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.waiters.push(Waiter { priority, seq, sender });
+            receiver
+        };
+        // 许可只会在发送后被持有，发送端不会在未发送时被drop
+        receiver.await.expect("priority limiter dropped a waiter")
+    }
+}
+
+/// 推理许可，drop时交给优先级最高的等待者
+pub struct PriorityPermit {
+    /// 归还许可时为 `None`
+    limiter: Option<Arc<PriorityLimiter>>,
+}
+
+impl Drop for PriorityPermit {
+    fn drop(&mut self) {
+        let Some(limiter) = self.limiter.take() else {
+            return;
+        };
+        let mut permit = PriorityPermit { limiter: Some(limiter.clone()) };
+        loop {
+            let waiter = {
+                let mut state = limiter.state.lock().unwrap();
+                match state.waiters.pop() {
+                    Some(waiter) => waiter,
+                    None => {
+                        state.available += 1;
+                        permit.limiter = None;
+                        return;
+                    }
+                }
+            };
+            // 等待者已放弃时取回许可，交给下一个等待者
+            match waiter.sender.send(permit) {
+                Ok(()) => return,
+                Err(returned) => permit = returned,
+            }
+        }
+    }
+}
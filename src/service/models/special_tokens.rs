@@ -0,0 +1,78 @@
+//! 模型的特殊token
+//!
+//! 从 `tokenizer_config.json` 读取BOS/EOS/PAD/UNK的字符串，再通过tokenizer查询其ID，
+//! 用于调试对话模板。
+use crate::error::AppError;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+use tokenizers::Tokenizer;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct SpecialToken {
+    /// token的ID，tokenizer中找不到时为 `None`
+    pub id: Option<u32>,
+    pub content: String,
+}
+
+/// `tokenizer_config.json` 中未声明的token为 `None`
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct SpecialTokens {
+    pub bos: Option<SpecialToken>,
+    pub eos: Option<SpecialToken>,
+    pub pad: Option<SpecialToken>,
+    pub unk: Option<SpecialToken>,
+}
+
+/// 读取 `tokenizer_config.json` 中的特殊token
+///
+/// # 参数
+/// * `config_path` - `tokenizer_config.json` 的路径
+/// * `tokenizer_path` - `tokenizer.json` 的路径，无法加载时从 `added_tokens_decoder` 查询ID
+pub fn read_special_tokens(
+    config_path: &Path,
+    tokenizer_path: &Path,
+) -> Result<SpecialTokens, AppError> {
+    let content = std::fs::read_to_string(config_path)?;
+    let config: Value = serde_json::from_str(&content)?;
+    let tokenizer = match Tokenizer::from_file(tokenizer_path) {
+        Ok(tokenizer) => Some(tokenizer),
+        Err(e) => {
+            log::debug!("Failed to load {}: {}", tokenizer_path.display(), e);
+            None
+        }
+    };
+
+    let token = |key: &str| {
+        let content = token_content(config.get(key)?)?;
+        let id = match &tokenizer {
+            Some(tokenizer) => tokenizer.token_to_id(&content),
+            None => added_token_id(&config, &content),
+        };
+        Some(SpecialToken { id, content })
+    };
+    Ok(SpecialTokens {
+        bos: token("bos_token"),
+        eos: token("eos_token"),
+        pad: token("pad_token"),
+        unk: token("unk_token"),
+    })
+}
+
+/// token可以是字符串，也可以是带 `content` 字段的AddedToken对象
+fn token_content(value: &Value) -> Option<String> {
+    match value {
+        Value::String(content) => Some(content.clone()),
+        Value::Object(token) => token.get("content")?.as_str().map(str::to_string),
+        _ => None,
+    }
+}
+
+fn added_token_id(config: &Value, content: &str) -> Option<u32> {
+    config
+        .get("added_tokens_decoder")?
+        .as_object()?
+        .iter()
+        .find(|(_, token)| token_content(token).as_deref() == Some(content))
+        .and_then(|(id, _)| id.parse().ok())
+}
@@ -0,0 +1,74 @@
+//! 流式输出的增量解码
+//!
+//! 字节级tokenizer（如ByteFallback的 `<0xE4>`）会把一个多字节字符拆成多个token，
+//! 单独解码其中任何一个都只能得到替换字符 `U+FFFD`。解码器暂存这些token，
+//! 直到它们拼成完整的UTF-8字符后再输出。
+use crate::error::AppError;
+use tokenizers::Tokenizer;
+
+/// UTF-8字符最多4个字节，暂存的token超过该数量时说明替换字符确实存在于输出中
+const MAX_PENDING_TOKENS: usize = 4;
+
+pub struct StreamDecoder<'a> {
+    tokenizer: &'a Tokenizer,
+    /// 尚未输出的token，解码结果以不完整的UTF-8序列结尾
+    pending: Vec<u32>,
+}
+
+impl<'a> StreamDecoder<'a> {
+    pub fn new(tokenizer: &'a Tokenizer) -> Self {
+        Self { tokenizer, pending: Vec::new() }
+    }
+
+    /// 输入一个token，返回可以输出的完整文本；字符尚不完整时返回 `None`
+    pub fn push(&mut self, token_id: u32) -> Result<Option<String>, AppError> {
+        self.pending.push(token_id);
+        let text = self.tokenizer.decode(&self.pending, true)?;
+        if text.ends_with(char::REPLACEMENT_CHARACTER) && self.pending.len() < MAX_PENDING_TOKENS {
+            return Ok(None);
+        }
+        self.pending.clear();
+        Ok(Some(text).filter(|text| !text.is_empty()))
+    }
+
+    /// 生成结束时输出剩余的token，不完整的字节按替换字符输出
+    pub fn finish(&mut self) -> Result<Option<String>, AppError> {
+        if self.pending.is_empty() {
+            return Ok(None);
+        }
+        let text = self.tokenizer.decode(&self.pending, true)?;
+        self.pending.clear();
+        Ok(Some(text).filter(|text| !text.is_empty()))
+    }
+}
+
+/// 找出解码后无法组成合法UTF-8字符的token
+///
+/// Rust字符串总是合法的UTF-8，解码失败的字节会被替换为 `U+FFFD`。`text` 为 `token_ids`
+/// 的完整解码结果，不含替换字符时直接返回空列表；否则逐token增量解码，
+/// 返回输出中含替换字符的那些token
+pub fn invalid_utf8_tokens(
+    tokenizer: &Tokenizer,
+    token_ids: &[u32],
+    text: &str,
+) -> Result<Vec<u32>, AppError> {
+    if !text.contains(char::REPLACEMENT_CHARACTER) {
+        return Ok(Vec::new());
+    }
+    let mut decoder = StreamDecoder::new(tokenizer);
+    let mut invalid = Vec::new();
+    let mut chunk = Vec::new();
+    for &token_id in token_ids {
+        chunk.push(token_id);
+        if let Some(text) = decoder.push(token_id)? {
+            if text.contains(char::REPLACEMENT_CHARACTER) {
+                invalid.append(&mut chunk);
+            }
+            chunk.clear();
+        }
+    }
+    if decoder.finish()?.is_some_and(|text| text.contains(char::REPLACEMENT_CHARACTER)) {
+        invalid.append(&mut chunk);
+    }
+    Ok(invalid)
+}
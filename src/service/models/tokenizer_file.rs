@@ -0,0 +1,70 @@
+//! 从文件加载tokenizer
+//!
+//! 模型文件可能仍在被下载或复制，此时 `tokenizer.json` 只写了一部分，解析会失败。
+//! 解析错误在短暂等待后重试，文件不存在则立即返回错误。
+use crate::error::AppError;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokenizers::Tokenizer;
+
+/// 默认的最大加载次数（含第一次）
+pub const DEFAULT_TOKENIZER_LOAD_ATTEMPTS: usize = 3;
+/// 默认的重试间隔
+pub const DEFAULT_TOKENIZER_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// 使用默认的重试次数与间隔加载 `path`
+pub async fn load_tokenizer_file(path: impl Into<PathBuf>) -> Result<Tokenizer, AppError> {
+    load_tokenizer_file_with_retry(
+        path,
+        DEFAULT_TOKENIZER_LOAD_ATTEMPTS,
+        DEFAULT_TOKENIZER_RETRY_DELAY,
+    )
+    .await
+}
+
+/// 加载 `path`，解析失败时每隔 `delay` 重试，最多加载 `attempts` 次
+///
+/// 文件不存在时不重试，返回 `AppError::InvalidParameter`；
+/// 重试次数用尽后返回最后一次的 `AppError::TokenizerError`
+pub async fn load_tokenizer_file_with_retry(
+    path: impl Into<PathBuf>,
+    attempts: usize,
+    delay: Duration,
+) -> Result<Tokenizer, AppError> {
+    let path = path.into();
+    let attempts = attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        if !tokio::fs::try_exists(&path).await? {
+            return Err(AppError::InvalidParameter(format!(
+                "Tokenizer file not found at path: {}",
+                path.display()
+            )));
+        }
+        let load_path = path.clone();
+        let result = tokio::task::spawn_blocking(move || Tokenizer::from_file(load_path))
+            .await
+            .map_err(|e| AppError::Generic(e.to_string()))?;
+        match result {
+            Ok(tokenizer) => return Ok(tokenizer),
+            Err(e) if attempt < attempts => {
+                log::warn!(
+                    "Failed to load tokenizer from {} (attempt {}/{}): {}, retrying",
+                    path.display(),
+                    attempt,
+                    attempts,
+                    e
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => {
+                return Err(AppError::TokenizerError(format!(
+                    "Failed to load tokenizer from {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        }
+    }
+}
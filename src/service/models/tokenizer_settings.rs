@@ -0,0 +1,36 @@
+//! tokenizer的截断与填充
+//!
+//! 加载tokenizer后按 `models.<id>.tokenizer` 设置截断与填充的方向和长度，
+//! 批量推理时左填充与右填充的差异会影响生成结果。
+use crate::error::AppError;
+use crate::utils::config::{TokenizerSettings, TokenizerSide};
+use tokenizers::{PaddingDirection, Tokenizer, TruncationDirection};
+
+/// 将 `settings` 应用到 `tokenizer`，未设置的参数保留 `tokenizer.json` 中的值
+///
+/// 设置了 `max_length` 时启用截断，设置了 `padding_side` 时启用填充
+pub fn apply_tokenizer_settings(
+    tokenizer: &mut Tokenizer,
+    settings: &TokenizerSettings,
+) -> Result<(), AppError> {
+    if let Some(max_length) = settings.max_length {
+        let mut truncation = tokenizer.get_truncation().cloned().unwrap_or_default();
+        truncation.max_length = max_length;
+        truncation.direction = match settings.truncation_side {
+            TokenizerSide::Left => TruncationDirection::Left,
+            TokenizerSide::Right => TruncationDirection::Right,
+        };
+        tokenizer
+            .with_truncation(Some(truncation))
+            .map_err(|e| AppError::TokenizerError(e.to_string()))?;
+    }
+    if let Some(padding_side) = settings.padding_side {
+        let mut padding = tokenizer.get_padding().cloned().unwrap_or_default();
+        padding.direction = match padding_side {
+            TokenizerSide::Left => PaddingDirection::Left,
+            TokenizerSide::Right => PaddingDirection::Right,
+        };
+        tokenizer.with_padding(Some(padding));
+    }
+    Ok(())
+}
@@ -1,3 +1,5 @@
+use crate::service::models::overrides::ModelOverrides;
+use crate::utils::config::{ArchitectureConfig, NumericalStabilityConfig};
 use serde::Deserialize;
 use std::path::Path;
 
@@ -21,12 +23,18 @@ pub struct ModelConfig {
     pub num_attention_heads: usize,
     #[serde(default)]
     pub intermediate_size: usize,
-    #[serde(default)]
+    /// HuggingFace的 `config.json` 中为 `num_hidden_layers`
+    #[serde(default, alias = "num_hidden_layers")]
     pub num_layers: usize,
     #[serde(default)]
     pub layer_norm_eps: f64,
     #[serde(default)]
     pub vocab_size: usize,
+    #[serde(default)]
+    pub max_position_embeddings: usize,
+    /// 来自 `models.<id>.numerical_stability`，不在 `config.json` 中
+    #[serde(skip)]
+    pub numerical_stability: NumericalStabilityConfig,
 }
 
 impl ModelConfig {
@@ -35,4 +43,34 @@ impl ModelConfig {
         let config: Self = serde_json::from_str(&config_str)?;
         Ok(config)
     }
+
+    /// 使用 `models.<id>.architecture` 中设置的字段覆盖 `config.json` 的值
+    pub fn with_architecture(mut self, architecture: &ArchitectureConfig) -> Self {
+        self.hidden_size = architecture.hidden_size.unwrap_or(self.hidden_size);
+        self.num_attention_heads =
+            architecture.num_attention_heads.unwrap_or(self.num_attention_heads);
+        self.intermediate_size = architecture.intermediate_size.unwrap_or(self.intermediate_size);
+        self.num_layers = architecture.num_hidden_layers.unwrap_or(self.num_layers);
+        self.layer_norm_eps = architecture.layer_norm_eps.unwrap_or(self.layer_norm_eps);
+        self.vocab_size = architecture.vocab_size.unwrap_or(self.vocab_size);
+        self.max_position_embeddings =
+            architecture.max_position_embeddings.unwrap_or(self.max_position_embeddings);
+        self
+    }
+
+    /// 使用 `models.<id>.numerical_stability` 中的数值稳定性参数
+    pub fn with_numerical_stability(
+        mut self,
+        numerical_stability: &NumericalStabilityConfig,
+    ) -> Self {
+        self.numerical_stability = numerical_stability.clone();
+        self
+    }
+
+    /// 应用单个请求的参数覆盖，见 [`ModelOverrides`]
+    pub fn with_overrides(mut self, overrides: &ModelOverrides) -> Self {
+        self.layer_norm_eps = overrides.layer_norm_eps.unwrap_or(self.layer_norm_eps);
+        overrides.apply_to_stability(&mut self.numerical_stability);
+        self
+    }
 }
@@ -10,9 +10,9 @@ pub struct YiCoderInference {
 }
 
 impl YiCoderInference {
-    pub fn new(_config: &super::config::ModelConfig) -> Self {
-        log::info!("Initializing Yi Coder with CPU device");
-        Self { _device: Device::Cpu, sender: Arc::new(Mutex::new(None)) }
+    pub fn new(_config: &super::config::ModelConfig, device: &Device) -> Self {
+        log::info!("Initializing Yi Coder with device: {:?}", device);
+        Self { _device: device.clone(), sender: Arc::new(Mutex::new(None)) }
     }
 
     pub fn set_stream_sender(&self, sender: mpsc::Sender<ChatCompletionMessage>) {
@@ -42,7 +42,7 @@ impl YiCoderInference {
         log::debug!("Validating inference parameters");
         let temperature = temperature.unwrap_or(0.7);
         log::debug!("Using temperature: {:.2}", temperature);
-        if temperature <= 0.0 || temperature > 2.0 {
+        if !(0.0..=2.0).contains(&temperature) {
             return Err(AppError::InvalidParameter(
                 t!("errors.validation.temperature_range").to_string(),
             ));
@@ -99,16 +99,13 @@ impl YiCoderInference {
                     "Streaming response (temp: {:.2}, top_p: {:.2}, n: {}, max_tokens: {})...",
                     temperature, top_p, n, max_tokens
                 ),
+                name: None,
             };
 
             log::debug!("Sending streaming response");
             sender.send(message.clone()).await.map_err(|e| {
                 log::error!("Failed to send streaming response: {}", e);
-                AppError::Generic(format!(
-                    "{}: {}",
-                    t!("errors.stream_response.failed").to_string(),
-                    e
-                ))
+                AppError::Generic(format!("{}: {}", t!("errors.stream_response.failed"), e))
             })?;
 
             log::debug!("Streaming response sent successfully");
@@ -122,37 +119,32 @@ impl YiCoderInference {
                     "Processed prompt (temp: {:.2}, top_p: {:.2}, n: {}, max_tokens: {}):\n{}",
                     temperature, top_p, n, max_tokens, prompt
                 ),
+                name: None,
             };
             log::debug!("Generated response: {:?}", response);
             Ok(vec![response])
         }
     }
 
-    pub fn send_stream_response(&self, message: &ChatCompletionMessage) -> Result<(), AppError> {
+    /// 发送一条流式消息，缓冲区已满时等待消费者读取，从而使生成速度与消费者保持一致
+    pub async fn send_stream_response(
+        &self,
+        message: &ChatCompletionMessage,
+    ) -> Result<(), AppError> {
         log::debug!("Attempting to send stream response");
-        if let Some(sender) = self
+        // 先取出sender，避免在等待期间持有锁
+        let sender = self
             .sender
             .lock()
             .map_err(|_| AppError::Generic(t!("errors.stream.lock_failed").to_string()))?
-            .as_ref()
-        {
-            log::debug!("Sending stream response message");
-            match sender.try_send(message.clone()) {
-                Ok(_) => Ok(()),
-                Err(e) => {
-                    log::error!("Failed to send stream response: {:?}", e);
-                    match e {
-                        mpsc::error::TrySendError::Full(_) => {
-                            Err(AppError::Generic(t!("errors.stream.buffer_full").to_string()))
-                        }
-                        mpsc::error::TrySendError::Closed(_) => {
-                            Err(AppError::Generic(t!("errors.stream.channel_closed").to_string()))
-                        }
-                    }
-                }
-            }
-        } else {
-            Err(AppError::Generic(t!("errors.stream.sender_not_initialized").to_string()))
-        }
+            .clone()
+            .ok_or_else(|| {
+                AppError::Generic(t!("errors.stream.sender_not_initialized").to_string())
+            })?;
+        log::debug!("Sending stream response message");
+        sender.send(message.clone()).await.map_err(|e| {
+            log::error!("Failed to send stream response: {:?}", e);
+            AppError::Generic(t!("errors.stream.channel_closed").to_string())
+        })
     }
 }
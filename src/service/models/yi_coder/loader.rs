@@ -1,5 +1,12 @@
 use crate::error::AppError;
-use crate::utils::{config::AppConfig, download::ModelDownloader};
+use crate::service::models::tokenizer_file::load_tokenizer_file;
+use crate::service::models::tokenizer_settings::apply_tokenizer_settings;
+use crate::utils::weights::verify_safetensors_file;
+use crate::utils::{
+    config::{AppConfig, TokenizerSettings},
+    device::{resolve_device, supported_dtype},
+    download::ModelDownloader,
+};
 use anyhow;
 use candle_core::{DType, Device, Tensor};
 use candle_nn::VarBuilder;
@@ -14,8 +21,12 @@ const BYTES_PER_GB: f64 = 1024.0 * 1024.0 * 1024.0;
 
 pub struct ModelLoader {
     model_paths: Vec<PathBuf>,
+    /// 模型文件所在目录: `{models_cache_dir}/{hf_hub_id}`
+    model_dir: PathBuf,
     device: Device,
     config_path: PathBuf,
+    verify_weights: bool,
+    strict_validation: bool,
 }
 
 impl ModelLoader {
@@ -25,8 +36,8 @@ impl ModelLoader {
 
         // 创建要下载的文件列表
         // 如果缓存目录不存在则创建
-        let cache_dir = format!("models_cache/{}", model_config.hf_hub_id);
-        std::fs::create_dir_all(&cache_dir)?;
+        let cache_dir = format!("{}/{}", config.models_cache_dir, model_config.hf_hub_id);
+        tokio::fs::create_dir_all(&cache_dir).await?;
 
         // 检查哪些文件需要下载
         let mut files_to_download = Vec::new();
@@ -38,17 +49,17 @@ impl ModelLoader {
                 continue;
             }
             let file_path = format!("{}/{}", cache_dir, weight_file);
-            if !std::path::Path::new(&file_path).exists() {
+            if !tokio::fs::try_exists(&file_path).await? {
                 files_to_download.push(weight_file.as_str());
             }
             model_paths.push(PathBuf::from(file_path));
         }
 
-        // 检查tokenizer文件
+        // 检查tokenizer文件 (sentencepiece的 `.model` 或 `tokenizer.json`)
         let tokenizer_file = &model_config.model_files.tokenizer;
-        if tokenizer_file.ends_with(".model") {
+        if tokenizer_file.ends_with(".model") || tokenizer_file.ends_with(".json") {
             let file_path = format!("{}/{}", cache_dir, tokenizer_file);
-            if !std::path::Path::new(&file_path).exists() {
+            if !tokio::fs::try_exists(&file_path).await? {
                 files_to_download.push(tokenizer_file.as_str());
             }
             model_paths.push(PathBuf::from(file_path));
@@ -62,7 +73,7 @@ impl ModelLoader {
 
         for file in config_files {
             let file_path = format!("{}/{}", cache_dir, file);
-            if !std::path::Path::new(&file_path).exists() {
+            if !tokio::fs::try_exists(&file_path).await? {
                 files_to_download.push(file.as_str());
             }
             model_paths.push(PathBuf::from(file_path));
@@ -80,62 +91,43 @@ impl ModelLoader {
 
         Ok(Self {
             model_paths,
-            device: Device::cuda_if_available(0)
-                .map_err(|e| AppError::Generic(format!("Failed to get CUDA device: {}", e)))?,
+            model_dir: PathBuf::from(cache_dir),
+            device: resolve_device(&config.inference.device)?,
             config_path: PathBuf::from(config_path),
+            verify_weights: config.inference.verify_weights,
+            strict_validation: config.inference.strict_validation,
         })
     }
 
-    pub fn load(&self) -> anyhow::Result<std::collections::HashMap<String, Tensor>> {
-        let mut model_tensors = std::collections::HashMap::new();
+    /// 加载全部 `.safetensors` 权重，见 [`load_safetensors`]
+    pub async fn load(&self) -> anyhow::Result<std::collections::HashMap<String, Tensor>> {
+        load_safetensors(self.model_paths.clone(), self.device.clone(), self.verify_weights).await
+    }
 
-        // 只加载.safetensors文件
-        for model_path in &self.model_paths {
-            if !model_path.to_string_lossy().ends_with(".safetensors") {
-                continue;
-            }
+    /// 改用指定的计算设备，覆盖配置中的 `inference.device`
+    pub fn with_device(mut self, device: Device) -> Self {
+        self.device = device;
+        self
+    }
 
-            let mmap =
-                unsafe { memmap2::MmapOptions::new().map(&std::fs::File::open(model_path)?)? };
-            let tensors = SafeTensors::deserialize(&mmap)?;
-
-            let mut total_bytes = 0;
-            for (name, _tensor_info) in tensors.tensors() {
-                let data = tensors.tensor(&name)?;
-                let tensor = Tensor::from_raw_buffer(
-                    data.data(),
-                    data.dtype().try_into()?,
-                    data.shape(),
-                    &self.device,
-                )?;
-
-                // Calculate tensor size in bytes
-                let tensor_size = data.data().len();
-                total_bytes += tensor_size;
-
-                // Debug log tensor info with proper unit conversion
-                log::debug!(
-                    "Loaded tensor: {}, shape: {:?}, dtype: {:?}, size: {:.2} MB ({:.2} GB)",
-                    name,
-                    data.shape(),
-                    data.dtype(),
-                    tensor_size as f64 / BYTES_PER_MB,
-                    tensor_size as f64 / BYTES_PER_GB
-                );
-
-                model_tensors.insert(name.to_string(), tensor);
-            }
+    /// 获取加载器使用的计算设备
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
 
-            // Log total size for this file in GB and MB
-            log::debug!(
-                "Total loaded size for {}: {:.2} GB ({:.2} MB)",
-                model_path.display(),
-                total_bytes as f64 / BYTES_PER_GB,
-                total_bytes as f64 / BYTES_PER_MB
-            );
-        }
+    /// 前向传播中是否逐元素检查NaN/Inf，来自 `inference.strict_validation`
+    pub fn strict_validation(&self) -> bool {
+        self.strict_validation
+    }
+
+    /// 获取已下载的模型文件路径
+    pub fn model_paths(&self) -> &[PathBuf] {
+        &self.model_paths
+    }
 
-        Ok(model_tensors)
+    /// 获取模型文件所在目录
+    pub fn model_dir(&self) -> &PathBuf {
+        &self.model_dir
     }
 
     pub fn get_config_path(&self) -> &PathBuf {
@@ -154,12 +146,15 @@ impl ModelLoader {
         Ok(config.get_model_config(model_id)?.clone())
     }
 
-    pub fn get_var_builder(&self) -> anyhow::Result<VarBuilder> {
-        let model_tensors = self.load()?;
+    pub async fn get_var_builder(&self) -> anyhow::Result<VarBuilder<'_>> {
+        let model_tensors = self.load().await?;
         Ok(VarBuilder::from_tensors(model_tensors, DType::F32, &self.device))
     }
 
-    pub async fn get_tokenizer(&self) -> anyhow::Result<Tokenizer> {
+    /// 加载 `tokenizer.json`，并应用 `models.<id>.tokenizer` 中的截断与填充参数
+    ///
+    /// 文件不存在时返回 `AppError::InvalidParameter`
+    pub async fn get_tokenizer(&self, settings: &TokenizerSettings) -> Result<Tokenizer, AppError> {
         // 查找tokenizer文件
         let tokenizer_path = self
             .model_paths
@@ -169,19 +164,94 @@ impl ModelLoader {
                 path.ends_with("tokenizer.json")
             })
             .ok_or_else(|| {
-                anyhow::anyhow!("Tokenizer file not found. Expected format: tokenizer.json")
+                AppError::InvalidParameter(
+                    "Tokenizer file not found. Expected format: tokenizer.json".to_string(),
+                )
             })?;
         log::debug!("Loading tokenizer from: {:?}", tokenizer_path);
-        if !tokenizer_path.exists() {
-            log::error!("Tokenizer file does not exist at path: {:?}", tokenizer_path);
-            return Err(anyhow::anyhow!("Tokenizer file not found at path: {:?}", tokenizer_path));
-        }
 
-        // 使用tokenizers::Tokenizer加载tokenizer
-        let tokenizer = Tokenizer::from_file(&tokenizer_path)
-            .map_err(|e| anyhow::anyhow!("Failed to load tokenizer: {:?}", e))?;
+        // 文件尚未写完时解析失败，短暂等待后重试
+        let mut tokenizer = load_tokenizer_file(tokenizer_path.clone()).await?;
+        apply_tokenizer_settings(&mut tokenizer, settings)?;
         log::debug!("Tokenizer loaded successfully");
 
         Ok(tokenizer)
     }
 }
+
+/// 在blocking线程池中加载 `model_paths` 中的 `.safetensors` 文件，其他文件被忽略
+///
+/// mmap和反序列化是同步操作，放到 `spawn_blocking` 中执行以免占用运行时的工作线程
+pub async fn load_safetensors(
+    model_paths: Vec<PathBuf>,
+    device: Device,
+    verify_weights: bool,
+) -> anyhow::Result<std::collections::HashMap<String, Tensor>> {
+    tokio::task::spawn_blocking(move || {
+        load_safetensors_blocking(&model_paths, &device, verify_weights)
+    })
+    .await?
+}
+
+fn load_safetensors_blocking(
+    model_paths: &[PathBuf],
+    device: &Device,
+    verify_weights: bool,
+) -> anyhow::Result<std::collections::HashMap<String, Tensor>> {
+    let mut model_tensors = std::collections::HashMap::new();
+
+    // 只加载.safetensors文件
+    for model_path in model_paths {
+        if !model_path.to_string_lossy().ends_with(".safetensors") {
+            continue;
+        }
+
+        if verify_weights {
+            verify_safetensors_file(model_path)?;
+        }
+
+        let mmap = unsafe { memmap2::MmapOptions::new().map(&std::fs::File::open(model_path)?)? };
+        let tensors = SafeTensors::deserialize(&mmap)?;
+
+        let mut total_bytes = 0;
+        for (name, _tensor_info) in tensors.tensors() {
+            let data = tensors.tensor(&name)?;
+            let dtype: DType = data.dtype().try_into()?;
+            let target_dtype = supported_dtype(dtype, device);
+            let tensor = if target_dtype == dtype {
+                Tensor::from_raw_buffer(data.data(), dtype, data.shape(), device)?
+            } else {
+                // 设备不支持该类型时先在CPU上转换
+                Tensor::from_raw_buffer(data.data(), dtype, data.shape(), &Device::Cpu)?
+                    .to_dtype(target_dtype)?
+                    .to_device(device)?
+            };
+
+            // Calculate tensor size in bytes
+            let tensor_size = data.data().len();
+            total_bytes += tensor_size;
+
+            // Debug log tensor info with proper unit conversion
+            log::debug!(
+                "Loaded tensor: {}, shape: {:?}, dtype: {:?}, size: {:.2} MB ({:.2} GB)",
+                name,
+                data.shape(),
+                data.dtype(),
+                tensor_size as f64 / BYTES_PER_MB,
+                tensor_size as f64 / BYTES_PER_GB
+            );
+
+            model_tensors.insert(name.to_string(), tensor);
+        }
+
+        // Log total size for this file in GB and MB
+        log::debug!(
+            "Total loaded size for {}: {:.2} GB ({:.2} MB)",
+            model_path.display(),
+            total_bytes as f64 / BYTES_PER_GB,
+            total_bytes as f64 / BYTES_PER_MB
+        );
+    }
+
+    Ok(model_tensors)
+}
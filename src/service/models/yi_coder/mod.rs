@@ -3,5 +3,6 @@ pub mod inference;
 pub mod loader;
 pub mod transformer;
 
+#[allow(clippy::module_inception)]
 mod yi_coder;
 pub use yi_coder::YiCoder;
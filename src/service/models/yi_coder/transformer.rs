@@ -1,4 +1,5 @@
 use crate::error::AppError;
+use crate::utils::config::NumericalStabilityConfig;
 use candle_core::{Device, Module, Result, Tensor};
 use candle_nn::{linear, ops::softmax, Embedding, LayerNorm, VarBuilder};
 use std::fmt;
@@ -78,7 +79,7 @@ fn validate_shape(tensor: &Tensor, expected: &[usize], context: &str) -> Result<
 /// YiCoder Transformer模型
 /// 实现用于代码生成的Transformer架构
 /// 包含多个Transformer层和最终的LayerNorm
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct YiCoderTransformer {
     /// Word embeddings layer
     embeddings: Embedding,
@@ -90,11 +91,15 @@ pub struct YiCoderTransformer {
     device: Device,
     /// Configuration parameters
     _config: super::config::ModelConfig,
+    /// 数值稳定性参数
+    stability: NumericalStabilityConfig,
+    /// 前向传播中是否逐元素检查NaN/Inf，见 [`Self::with_strict_validation`]
+    strict_validation: bool,
 }
 
 /// 单个Transformer层结构
 /// 包含多头注意力机制和前馈网络
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct TransformerLayer {
     /// 多头注意力机制
     attention: MultiHeadAttention,
@@ -108,7 +113,7 @@ struct TransformerLayer {
 
 /// 多头注意力机制结构
 /// 实现公式：Attention(Q,K,V) = softmax(QK^T/√d_k)V
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct MultiHeadAttention {
     /// 查询矩阵线性变换
     query: linear::Linear,
@@ -122,11 +127,15 @@ struct MultiHeadAttention {
     num_heads: usize,
     /// 每个注意力头的维度
     head_dim: usize,
+    /// Q、K矩阵的截断范围
+    qk_clamp: f32,
+    /// 缩放后注意力分数的截断范围
+    score_clamp: f32,
 }
 
 /// 位置前馈网络结构
 /// 实现公式：FFN(x) = max(0, xW1 + b1)W2 + b2
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct PositionWiseFeedForward {
     /// 第一个全连接层
     fc1: linear::Linear,
@@ -145,7 +154,7 @@ impl YiCoderTransformer {
     /// Result<Self> - 新的transformer实例
     pub fn new(config: &super::config::ModelConfig, vb: VarBuilder) -> Result<Self> {
         let config = config.clone();
-        let device = Device::cuda_if_available(0).unwrap_or(Device::Cpu);
+        let device = vb.device().clone();
         log::debug!("Selected computation device: {:?}", device);
 
         // Initialize Transformer layers
@@ -157,6 +166,7 @@ impl YiCoderTransformer {
                 config.hidden_size,
                 config.num_attention_heads,
                 config.intermediate_size,
+                &config.numerical_stability,
                 vb.pp(format!("layer_{}", i)),
             )
             .map_err(|e| {
@@ -171,7 +181,7 @@ impl YiCoderTransformer {
         let weight = vb.get((config.hidden_size,), "model.norm.weight")?;
         let bias = vb.get((config.hidden_size,), "model.norm.bias").unwrap_or_else(|_| {
             log::warn!("model.norm.bias not found, using zero tensor");
-            Tensor::zeros((config.hidden_size,), weight.dtype(), &weight.device()).unwrap()
+            Tensor::zeros((config.hidden_size,), weight.dtype(), weight.device()).unwrap()
         });
 
         validate_tensor(&weight, "Final layer norm weight")?;
@@ -196,13 +206,72 @@ impl YiCoderTransformer {
             config.hidden_size,
         );
 
-        Ok(Self { embeddings, layers, norm, device, _config: config.clone() })
+        let stability = config.numerical_stability.clone();
+        Ok(Self {
+            embeddings,
+            layers,
+            norm,
+            device,
+            _config: config,
+            stability,
+            strict_validation: true,
+        })
+    }
+
+    /// 使用 `config` 中的最终LayerNorm epsilon与数值稳定性参数创建副本，权重与原实例共享
+    ///
+    /// 用于单个请求的参数覆盖，其他结构参数须与原配置一致
+    pub fn with_config(&self, config: &super::config::ModelConfig) -> Self {
+        let norm = match self.norm.bias() {
+            Some(bias) => {
+                LayerNorm::new(self.norm.weight().clone(), bias.clone(), config.layer_norm_eps)
+            }
+            None => LayerNorm::new_no_bias(self.norm.weight().clone(), config.layer_norm_eps),
+        };
+        let stability = config.numerical_stability.clone();
+        let layers = self
+            .layers
+            .iter()
+            .cloned()
+            .map(|mut layer| {
+                layer.attention.qk_clamp = stability.qk_clamp;
+                layer.attention.score_clamp = stability.attention_score_clamp;
+                layer
+            })
+            .collect();
+        Self {
+            embeddings: self.embeddings.clone(),
+            layers,
+            norm,
+            device: self.device.clone(),
+            _config: config.clone(),
+            stability,
+            strict_validation: self.strict_validation,
+        }
+    }
+
+    /// 设置前向传播中是否逐元素检查隐藏状态的NaN/Inf，默认开启
+    ///
+    /// 每次检查都要把整个张量展开复制到CPU，关闭后只保留形状检查；
+    /// 加载时的权重检查不受影响
+    pub fn with_strict_validation(mut self, strict_validation: bool) -> Self {
+        self.strict_validation = strict_validation;
+        self
+    }
+
+    /// 开启严格检查时验证张量值，见 [`validate_tensor`]
+    fn validate(&self, tensor: &Tensor, context: &str) -> Result<()> {
+        if self.strict_validation {
+            validate_tensor(tensor, context)?;
+        }
+        Ok(())
     }
 
     /// 执行Transformer前向传播
     /// 参数:
     /// - input: 输入张量
     /// - attention_mask: 注意力掩码（可选）
+    ///
     /// 返回: Result<Tensor>
     pub async fn transform(&self, input: Tensor, attention_mask: Option<Tensor>) -> Result<Tensor> {
         let mut hidden_states = input;
@@ -222,9 +291,15 @@ impl YiCoderTransformer {
         &self.device
     }
 
+    /// Transformer层数
+    pub fn num_layers(&self) -> usize {
+        self.layers.len()
+    }
+
     /// 执行Transformer前向传播
     /// 参数:
     /// - input: 输入张量
+    ///
     /// 返回: Result<Tensor>
     pub fn forward(&self, input: &Tensor) -> Result<Tensor> {
         log::debug!("[Transformer] Starting forward pass");
@@ -237,28 +312,47 @@ impl YiCoderTransformer {
         // Validate and convert input tensor
         log::debug!("[Transformer] Validating input tensor");
 
-        // Convert input to i64 for Embedding layer
-        let input_i64 = if input.dtype() != candle_core::DType::I64 {
-            log::warn!("[Transformer] Converting input dtype from {:?} to I64", input.dtype());
-            input.to_dtype(candle_core::DType::I64)?
+        // Convert input to i64 for Embedding layer; Metal lacks most I64 kernels, keep U32 there
+        // U32输入在所有设备上都可以直接用于Embedding，不做转换以免每步复制输入
+        let index_dtype = if input.device().is_metal() {
+            candle_core::DType::U32
+        } else {
+            candle_core::DType::I64
+        };
+        let input_i64 = if input.dtype() != index_dtype && input.dtype() != candle_core::DType::U32
+        {
+            log::warn!(
+                "[Transformer] Converting input dtype from {:?} to {:?}",
+                input.dtype(),
+                index_dtype
+            );
+            input.to_dtype(index_dtype)?
         } else {
             input.clone()
         };
 
-        // Validate integer values
-        let min_value = input_i64.min(0)?.to_scalar::<i64>()?;
-        if min_value < 0 {
-            log::error!("[Transformer] Input contains negative values");
-            return Err(candle_core::Error::msg(AppError::new(
-                "Input tensor contains negative values which are invalid for embeddings"
-                    .to_string(),
-            )));
+        // Validate integer values; U32输入不可能为负
+        if input_i64.dtype() != candle_core::DType::U32 {
+            let min_value = input_i64
+                .flatten_all()?
+                .to_device(&candle_core::Device::Cpu)?
+                .to_dtype(candle_core::DType::I64)?
+                .min(0)?
+                .to_scalar::<i64>()?;
+            if min_value < 0 {
+                log::error!("[Transformer] Input contains negative values");
+                return Err(candle_core::Error::msg(AppError::new(
+                    "Input tensor contains negative values which are invalid for embeddings"
+                        .to_string(),
+                )));
+            }
         }
 
-        // Apply embeddings with i64 input
+        // Apply embeddings with integer input
         log::debug!("[Transformer] Applying embeddings");
         let mut hidden_states = self.embeddings.forward(&input_i64)?;
-        hidden_states = hidden_states.clamp(-1e4, 1e4)?;
+        let embedding_clamp = self.stability.embedding_clamp;
+        hidden_states = hidden_states.clamp(-embedding_clamp, embedding_clamp)?;
 
         // Convert embeddings output to F32 for subsequent layers
         hidden_states = hidden_states.to_dtype(candle_core::DType::F32)?;
@@ -269,7 +363,7 @@ impl YiCoderTransformer {
         );
 
         // Validate embeddings output
-        validate_tensor(&hidden_states, "Embeddings output")?;
+        self.validate(&hidden_states, "Embeddings output")?;
 
         if hidden_states.dtype() != candle_core::DType::F32 {
             log::error!(
@@ -285,7 +379,7 @@ impl YiCoderTransformer {
         // Handle tensor dimensions
         log::debug!("[Transformer] Handling tensor dimensions");
         match hidden_states.dims() {
-            &[1] => {
+            [1] => {
                 // Special case for single-element tensor
                 log::debug!("[Transformer] Handling single-element tensor");
                 hidden_states = hidden_states.unsqueeze(0)?.unsqueeze(0)?;
@@ -294,7 +388,7 @@ impl YiCoderTransformer {
                     hidden_states.shape()
                 );
             }
-            &[_batch_size] => {
+            [_batch_size] => {
                 // General rank 1 case
                 log::debug!("[Transformer] Adding batch dimension");
                 hidden_states = hidden_states.unsqueeze(0)?;
@@ -303,10 +397,10 @@ impl YiCoderTransformer {
                     hidden_states.shape()
                 );
             }
-            &[_batch_size, _seq_len] => {
+            [_batch_size, _seq_len] => {
                 log::debug!("[Transformer] Input has correct dimensions");
             }
-            &[1, _batch_size, _seq_len] => {
+            [1, _batch_size, _seq_len] => {
                 log::debug!("[Transformer] Removing extra dimension");
                 hidden_states = hidden_states.squeeze(0)?;
                 log::debug!(
@@ -323,7 +417,9 @@ impl YiCoderTransformer {
             }
         }
         // Process through transformer layers
+        // 注意力层的输入为 (batch, seq_len, hidden)，处理完后去掉batch维度
         log::debug!("[Transformer] Processing through layers");
+        let mut hidden_states = hidden_states.unsqueeze(0)?;
         for (i, layer) in self.layers.iter().enumerate() {
             log::debug!("[Transformer] Processing layer {}", i);
             hidden_states = layer.forward(&hidden_states, None)?;
@@ -335,10 +431,11 @@ impl YiCoderTransformer {
             );
 
             // Validate layer output
-            validate_tensor(&hidden_states, &format!("Layer {} output", i))?;
+            self.validate(&hidden_states, &format!("Layer {} output", i))?;
         }
+        let hidden_states = hidden_states.squeeze(0)?;
         // Validate input to final layer norm
-        validate_tensor(&hidden_states, "Final layer norm input")?;
+        self.validate(&hidden_states, "Final layer norm input")?;
 
         // Ensure proper input type (F32)
         let hidden_states = if hidden_states.dtype() != candle_core::DType::F32 {
@@ -349,51 +446,54 @@ impl YiCoderTransformer {
         };
 
         // Add more aggressive numerical stability checks
-        let mut hidden_states = hidden_states.clamp(-1e3, 1e3)?;
+        let hidden_clamp = self.stability.hidden_clamp;
+        let mut hidden_states = hidden_states.clamp(-hidden_clamp, hidden_clamp)?;
 
         // Check for NaN/Inf values before layer norm
-        let values = hidden_states.flatten_all()?.to_vec1::<f32>()?;
-        let nan_count = values.iter().filter(|&x| x.is_nan()).count();
-        let inf_count = values.iter().filter(|&x| x.is_infinite()).count();
+        if self.strict_validation {
+            let values = hidden_states.flatten_all()?.to_vec1::<f32>()?;
+            let nan_count = values.iter().filter(|&x| x.is_nan()).count();
+            let inf_count = values.iter().filter(|&x| x.is_infinite()).count();
 
-        if nan_count > 0 || inf_count > 0 {
-            log::error!(
-                "NaN/Inf detected before final layer norm - NaN: {}, Inf: {}",
-                nan_count,
-                inf_count
-            );
-            return Err(candle_core::Error::msg(AppError::new(
-                "NaN/Inf values detected before final layer norm".to_string(),
-            )));
+            if nan_count > 0 || inf_count > 0 {
+                log::error!(
+                    "NaN/Inf detected before final layer norm - NaN: {}, Inf: {}",
+                    nan_count,
+                    inf_count
+                );
+                return Err(candle_core::Error::msg(AppError::new(
+                    "NaN/Inf values detected before final layer norm".to_string(),
+                )));
+            }
         }
 
         // Add robust variance stability check with more aggressive stabilization
         let variance = hidden_states.var(1)?;
         let min_variance = variance.min(0)?.to_scalar::<f32>()?;
-        let stability_factor = if min_variance < 1e-20 {
+        if min_variance < self.stability.very_low_variance_threshold {
             log::warn!(
                 "Extremely low variance detected: {}. Adding larger stability factor.",
                 min_variance
             );
-            1e-5f32 // Increased stability factor for very low variance
-        } else if min_variance < 1e-8 {
+        } else if min_variance < self.stability.low_variance_threshold {
             log::warn!("Low variance detected: {}. Adding stability factor.", min_variance);
-            1e-6f32 // Increased stability factor
-        } else {
-            1e-8f32 // Always add small stability factor
-        };
+        }
+        let stability_factor = self.stability.stability_factor_for(min_variance);
 
         // Add stability factor and clamp values
         hidden_states = hidden_states
             .broadcast_add(&Tensor::new(stability_factor, &self.device)?)?
-            .clamp(-1e3, 1e3)?;
+            .clamp(-hidden_clamp, hidden_clamp)?;
 
         // Recompute variance after stabilization
         let _variance = hidden_states.var(1)?.clamp(1e-10, f32::MAX)?;
 
         // Apply layer norm with additional stability
         log::debug!("[Transformer] Applying final layer norm");
-        log::debug!("[Transformer] self.norm.forward xs input hidden_states values {:?}", values);
+        log::debug!(
+            "[Transformer] self.norm.forward xs input hidden_states values {:?}",
+            hidden_states.flatten_all()?.to_vec1::<f32>()?
+        );
         log::debug!(
             "[Transformer] Input mean: {:?}, variance: {:?}",
             hidden_states.mean(1)?.to_vec1::<f32>()?,
@@ -405,19 +505,19 @@ impl YiCoderTransformer {
         let mut output = Vec::new();
         for chunk in hidden_states.chunk(chunk_size, 0)? {
             // Add additional clamping and validation before layer norm
-            let chunk = chunk.clamp(-1e3, 1e3)?;
-            validate_tensor(&chunk, "Layer norm input chunk")?;
+            let chunk = chunk.clamp(-hidden_clamp, hidden_clamp)?;
+            self.validate(&chunk, "Layer norm input chunk")?;
 
             // Apply layer norm with additional stability
             let normed_chunk = self.norm.forward(&chunk)?;
 
             // Validate and clamp output
-            validate_tensor(&normed_chunk, "Layer norm chunk output")?;
-            let normed_chunk = normed_chunk.clamp(-1e3, 1e3)?;
+            self.validate(&normed_chunk, "Layer norm chunk output")?;
+            let normed_chunk = normed_chunk.clamp(-hidden_clamp, hidden_clamp)?;
 
             output.push(normed_chunk);
         }
-        let hidden_states = Tensor::cat(&output, 0)?.clamp(-1e3, 1e3)?;
+        let hidden_states = Tensor::cat(&output, 0)?.clamp(-hidden_clamp, hidden_clamp)?;
 
         log::debug!(
             "[Transformer] Output mean: {:?}, variance: {:?}",
@@ -431,7 +531,7 @@ impl YiCoderTransformer {
         );
 
         // Validate output
-        validate_tensor(&hidden_states, "Final layer norm output")?;
+        self.validate(&hidden_states, "Final layer norm output")?;
 
         log::debug!(
             "[Transformer] Final output - shape: {:?}, dtype: {:?}",
@@ -439,7 +539,7 @@ impl YiCoderTransformer {
             hidden_states.dtype()
         );
         // Validate final output
-        validate_tensor(&hidden_states, "Final output")?;
+        self.validate(&hidden_states, "Final output")?;
 
         log::debug!("[Transformer] Forward pass completed successfully");
         Ok(hidden_states)
@@ -452,16 +552,20 @@ impl TransformerLayer {
     /// - hidden_size: 隐藏层大小
     /// - num_heads: 注意力头数量
     /// - intermediate_size: 前馈网络中间层大小
+    /// - stability: 数值稳定性参数
     /// - vb: 变量构建器
+    ///
     /// 返回: Result<Self>
     fn new(
         hidden_size: usize,
         num_heads: usize,
         intermediate_size: usize,
+        stability: &NumericalStabilityConfig,
         vb: VarBuilder,
     ) -> Result<Self> {
         // 初始化多头注意力机制
-        let attention = MultiHeadAttention::new(hidden_size, num_heads, vb.pp("attention"))?;
+        let attention =
+            MultiHeadAttention::new(hidden_size, num_heads, stability, vb.pp("attention"))?;
 
         // 初始化前馈网络
         let feed_forward =
@@ -495,6 +599,7 @@ impl TransformerLayer {
     /// 参数:
     /// - input: 输入张量
     /// - attention_mask: 注意力掩码（可选）
+    ///
     /// 返回: Result<Tensor>
     fn forward(&self, input: &Tensor, attention_mask: Option<&Tensor>) -> Result<Tensor> {
         // 多头注意力机制
@@ -516,9 +621,16 @@ impl MultiHeadAttention {
     /// 参数:
     /// - hidden_size: 隐藏层大小
     /// - num_heads: 注意力头数量
+    /// - stability: 数值稳定性参数
     /// - vb: 变量构建器
+    ///
     /// 返回: Result<Self>
-    fn new(hidden_size: usize, num_heads: usize, vb: VarBuilder) -> Result<Self> {
+    fn new(
+        hidden_size: usize,
+        num_heads: usize,
+        stability: &NumericalStabilityConfig,
+        vb: VarBuilder,
+    ) -> Result<Self> {
         let head_dim = hidden_size / num_heads;
         // 初始化线性变换层
         let query = linear(hidden_size, hidden_size, vb.pp("query"))?;
@@ -526,7 +638,16 @@ impl MultiHeadAttention {
         let value = linear(hidden_size, hidden_size, vb.pp("value"))?;
         let out = linear(hidden_size, hidden_size, vb.pp("out"))?;
 
-        Ok(Self { query, key, value, out, num_heads, head_dim })
+        Ok(Self {
+            query,
+            key,
+            value,
+            out,
+            num_heads,
+            head_dim,
+            qk_clamp: stability.qk_clamp,
+            score_clamp: stability.attention_score_clamp,
+        })
     }
 
     /// 多头注意力机制前向传播
@@ -536,6 +657,7 @@ impl MultiHeadAttention {
     /// - key: 键张量
     /// - value: 值张量
     /// - attention_mask: 注意力掩码（可选）
+    ///
     /// 返回: Result<Tensor>
     fn forward(
         &self,
@@ -562,10 +684,16 @@ impl MultiHeadAttention {
         log::debug!("Key shape before reshape: {:?}", key.shape());
         log::debug!("Value shape before reshape: {:?}", value.shape());
 
-        // 重塑为多头形式
-        let query = query.reshape((batch_size, seq_len, self.num_heads, self.head_dim))?;
-        let key = key.reshape((batch_size, seq_len, self.num_heads, self.head_dim))?;
-        let value = value.reshape((batch_size, seq_len, self.num_heads, self.head_dim))?;
+        // 重塑为多头形式: (batch, num_heads, seq_len, head_dim)
+        let split_heads = |tensor: Tensor| -> Result<Tensor> {
+            tensor
+                .reshape((batch_size, seq_len, self.num_heads, self.head_dim))?
+                .transpose(1, 2)?
+                .contiguous()
+        };
+        let query = split_heads(query)?;
+        let key = split_heads(key)?;
+        let value = split_heads(value)?;
 
         // 打印调试信息
         log::debug!("Query shape after reshape: {:?}", query.shape());
@@ -577,8 +705,8 @@ impl MultiHeadAttention {
         log::debug!("Key shape before matmul: {:?}", key.shape());
 
         // Add numerical stability checks
-        let query = query.clamp(-1e4, 1e4)?;
-        let key = key.clamp(-1e4, 1e4)?;
+        let query = query.clamp(-self.qk_clamp, self.qk_clamp)?;
+        let key = key.clamp(-self.qk_clamp, self.qk_clamp)?;
 
         let mut attention_scores = query.matmul(&key.t()?)?.to_dtype(candle_core::DType::F32)?;
         log::debug!("Attention scores shape: {:?}", attention_scores.shape());
@@ -602,7 +730,7 @@ impl MultiHeadAttention {
         attention_scores = attention_scores.broadcast_div(&scale_factor)?;
 
         // Clamp attention scores to prevent overflow
-        attention_scores = attention_scores.clamp(-50.0, 50.0)?;
+        attention_scores = attention_scores.clamp(-self.score_clamp, self.score_clamp)?;
 
         // 应用注意力掩码
         if let Some(mask) = attention_mask {
@@ -638,22 +766,9 @@ impl MultiHeadAttention {
         // Softmax normalization
         let dim = dims.len() - 1;
 
-        // Add numerical stability to softmax
-        let max_values = attention_scores.max(dim)?;
-
-        // Ensure max_values is rank 0
-        let max_scalar = if max_values.dims().is_empty() {
-            max_values.to_scalar::<f32>()?
-        } else {
-            log::warn!(
-                "Max values tensor has rank {}, converting to scalar",
-                max_values.dims().len()
-            );
-            max_values.flatten_all()?.to_scalar::<f32>()?
-        };
-
-        let max_tensor = Tensor::new(max_scalar, attention_scores.device())?;
-        let stable_scores = (attention_scores.clone() - max_tensor)?;
+        // Add numerical stability to softmax: 每行减去该行的最大值
+        let max_values = attention_scores.max_keepdim(dim)?;
+        let stable_scores = attention_scores.broadcast_sub(&max_values)?;
 
         // Validate stable scores shape
         validate_shape(&stable_scores, dims, "Stable attention scores")?;
@@ -680,7 +795,11 @@ impl MultiHeadAttention {
         log::debug!("Context shape before reshape: {:?}", context.shape());
         log::debug!("Context dtype: {:?}", context.dtype());
         // 重塑回原始形状
-        let context = context.reshape((batch_size, seq_len, self.num_heads * self.head_dim))?;
+        let context = context.transpose(1, 2)?.contiguous()?.reshape((
+            batch_size,
+            seq_len,
+            self.num_heads * self.head_dim,
+        ))?;
         log::debug!("Context shape after reshape: {:?}", context.shape());
 
         // 输出线性变换
@@ -695,6 +814,7 @@ impl PositionWiseFeedForward {
     /// - hidden_size: 隐藏层大小
     /// - intermediate_size: 中间层大小
     /// - vb: 变量构建器
+    ///
     /// 返回: Result<Self>
     fn new(hidden_size: usize, intermediate_size: usize, vb: VarBuilder) -> Result<Self> {
         // 初始化全连接层
@@ -708,6 +828,7 @@ impl PositionWiseFeedForward {
     /// 实现公式: FFN(x) = GELU(xW1 + b1)W2 + b2
     /// 参数:
     /// - input: 输入张量
+    ///
     /// 返回: Result<Tensor>
     fn forward(&self, input: &Tensor) -> Result<Tensor> {
         // 第一层全连接 + GELU激活
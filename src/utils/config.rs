@@ -1,6 +1,11 @@
-use serde::Deserialize;
+use crate::service::models::completion_model::{MaxOutputTokensPolicy, DEFAULT_MAX_TOKENS};
+use crate::service::models::sampling::NanPolicy;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::ops::Deref;
+use std::path::PathBuf;
 use std::sync::OnceLock;
+use std::time::Duration;
 
 #[derive(Debug, Deserialize)]
 pub struct RouteConfig {
@@ -19,40 +24,397 @@ pub struct V1Routes {
     pub download: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Chat {
     pub defaults: ChatDefaults,
+    /// 单个请求允许的最大 `n`
+    #[serde(default = "default_max_n")]
+    pub max_n: usize,
+    #[serde(default)]
+    pub prompt_cache: PromptCacheConfig,
+    #[serde(default)]
+    pub conversations: ConversationConfig,
+    /// 单个请求允许的最大消息数量
+    #[serde(default = "default_max_messages")]
+    pub max_messages: usize,
+    /// 单个请求所有消息内容的最大字符数
+    #[serde(default = "default_max_prompt_chars")]
+    pub max_prompt_chars: usize,
+    /// 未指定 `max_tokens` 时生成长度的上限，实际长度不超过模型剩余的上下文长度
+    #[serde(default = "default_max_tokens")]
+    pub default_max_tokens: usize,
+    /// 流式响应在第一个token之前发送keepalive注释的间隔（毫秒）
+    #[serde(default = "default_stream_keepalive_ms")]
+    pub stream_keepalive_ms: u64,
+    #[serde(default)]
+    pub webhooks: WebhookConfig,
+    /// 要求user与assistant消息交替出现，连续两条相同角色的消息返回400
+    #[serde(default)]
+    pub strict_turn_order: bool,
+    /// 渲染提示词前合并role与content都相同的连续消息
+    #[serde(default)]
+    pub dedupe_consecutive: bool,
 }
 
-#[derive(Debug, Deserialize)]
+/// 请求中 `webhook_url` 的限制，避免服务端被用来访问任意地址
+#[derive(Debug, Deserialize, Serialize)]
+pub struct WebhookConfig {
+    /// 允许的URL scheme
+    #[serde(default = "default_webhook_schemes")]
+    pub allowed_schemes: Vec<String>,
+    /// 允许的主机名或IP，为空时不允许使用webhook
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    /// 每次推送的超时时间（毫秒）
+    #[serde(default = "default_webhook_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_webhook_schemes() -> Vec<String> {
+    vec!["https".to_string()]
+}
+
+fn default_webhook_timeout_ms() -> u64 {
+    10_000
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            allowed_schemes: default_webhook_schemes(),
+            allowed_hosts: Vec::new(),
+            timeout_ms: default_webhook_timeout_ms(),
+        }
+    }
+}
+
+fn default_max_messages() -> usize {
+    1024
+}
+
+fn default_max_prompt_chars() -> usize {
+    1_000_000
+}
+
+fn default_max_tokens() -> usize {
+    DEFAULT_MAX_TOKENS
+}
+
+fn default_stream_keepalive_ms() -> u64 {
+    15_000
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PromptCacheConfig {
+    /// 是否缓存确定性请求的生成结果
+    #[serde(default)]
+    pub enabled: bool,
+    /// 最多缓存的条目数
+    #[serde(default = "default_prompt_cache_capacity")]
+    pub capacity: usize,
+}
+
+fn default_max_n() -> usize {
+    16
+}
+
+fn default_prompt_cache_capacity() -> usize {
+    256
+}
+
+impl Default for PromptCacheConfig {
+    fn default() -> Self {
+        Self { enabled: false, capacity: default_prompt_cache_capacity() }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ConversationConfig {
+    /// 最多保存的会话数，超出时淘汰最久未使用的会话
+    #[serde(default = "default_conversation_capacity")]
+    pub capacity: usize,
+}
+
+fn default_conversation_capacity() -> usize {
+    1024
+}
+
+impl Default for ConversationConfig {
+    fn default() -> Self {
+        Self { capacity: default_conversation_capacity() }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ChatDefaults {
     pub temperature: f32,
     pub top_p: f32,
     pub n: usize,
-    pub max_tokens: usize,
     pub stream: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub shutdown_timeout: u64,
+    /// 读取请求头的超时时间（秒），0表示不限制
+    #[serde(default = "default_client_request_timeout_secs")]
+    pub client_request_timeout_secs: u64,
+    /// 空闲长连接保持时间（秒），0表示关闭keep-alive
+    #[serde(default = "default_keep_alive_secs")]
+    pub keep_alive_secs: u64,
+    /// 是否沿用网关传入的 `X-Request-Id`，为 `false` 时总是生成新的请求ID
+    #[serde(default = "default_true")]
+    pub trust_request_id: bool,
+    /// 设置时监听HTTPS并支持HTTP/2，未设置时监听明文HTTP/1
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
 }
 
-#[derive(Debug, Deserialize)]
+/// `server.tls` 中的证书链与私钥文件，均为PEM格式
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct TlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+fn default_client_request_timeout_secs() -> u64 {
+    30
+}
+
+fn default_keep_alive_secs() -> u64 {
+    5
+}
+
+impl ServerConfig {
+    pub fn client_request_timeout(&self) -> Duration {
+        Duration::from_secs(self.client_request_timeout_secs)
+    }
+
+    /// `None` 表示关闭keep-alive
+    pub fn keep_alive(&self) -> Option<Duration> {
+        (self.keep_alive_secs > 0).then(|| Duration::from_secs(self.keep_alive_secs))
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct LocalesConfig {
     pub path: String,
     pub default: String,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ModelConfig {
     pub hf_hub_id: String,
     pub model_files: ModelFiles,
+    /// 同时运行的推理请求上限，未设置时不限制；为0时加载配置失败
+    #[serde(default)]
+    pub max_concurrent: Option<usize>,
+    /// 模型不可用时改用的备用模型ID，只回退一次
+    #[serde(default)]
+    pub fallback: Option<String>,
+    /// 编码提示词时是否添加BOS等特殊token
+    #[serde(default = "default_true")]
+    pub add_special_tokens: bool,
+    /// 模型结构参数，覆盖权重目录中 `config.json` 的同名字段
+    #[serde(default)]
+    pub architecture: ArchitectureConfig,
+    /// 渲染提示词时各角色消息的前缀
+    #[serde(default)]
+    pub role_markers: RoleMarkers,
+    /// fill-in-the-middle补全使用的哨兵token，未设置时模型不支持 `POST /v1/fim/completions`
+    #[serde(default)]
+    pub fim: Option<FimTokens>,
+    /// 单次请求最多生成的token数，未设置时不限制
+    #[serde(default)]
+    pub max_output_tokens: Option<usize>,
+    /// 请求的max_tokens超过 `max_output_tokens` 时降低 (`clamp`) 还是拒绝 (`reject`)
+    #[serde(default)]
+    pub max_output_tokens_policy: MaxOutputTokensPolicy,
+    /// 前向传播中数值稳定性处理的截断范围与稳定因子
+    #[serde(default)]
+    pub numerical_stability: NumericalStabilityConfig,
+    /// 加载tokenizer后设置的截断与填充参数
+    #[serde(default)]
+    pub tokenizer: TokenizerSettings,
 }
 
-#[derive(Debug, Deserialize)]
+/// `models.<id>.tokenizer` 中的截断与填充参数，覆盖 `tokenizer.json` 中的设置
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct TokenizerSettings {
+    /// 超过 `max_length` 时截断的一侧，默认 `right`
+    #[serde(default)]
+    pub truncation_side: TokenizerSide,
+    /// 批量编码时填充的一侧，未设置时不填充
+    #[serde(default)]
+    pub padding_side: Option<TokenizerSide>,
+    /// 编码结果的最大token数，未设置时不截断
+    #[serde(default)]
+    pub max_length: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenizerSide {
+    Left,
+    #[default]
+    Right,
+}
+
+/// `models.<id>.role_markers` 中各角色消息的前缀
+///
+/// 未设置的角色使用默认格式 `role: content`
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct RoleMarkers {
+    #[serde(default)]
+    pub system: Option<String>,
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub assistant: Option<String>,
+}
+
+/// `models.<id>.fim` 中fill-in-the-middle提示词的哨兵token
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct FimTokens {
+    /// 放在前缀（光标前的代码）之前
+    pub prefix: String,
+    /// 放在后缀（光标后的代码）之前
+    pub suffix: String,
+    /// 放在提示词末尾，模型从这里开始生成中间部分
+    pub middle: String,
+}
+
+impl RoleMarkers {
+    /// 角色配置的前缀，其他角色或未配置时为 `None`
+    pub fn marker(&self, role: &str) -> Option<&str> {
+        match role {
+            "system" => self.system.as_deref(),
+            "user" => self.user.as_deref(),
+            "assistant" => self.assistant.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// `models.<id>.architecture` 中的模型结构参数，未设置的字段使用 `config.json` 中的值
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct ArchitectureConfig {
+    #[serde(default)]
+    pub hidden_size: Option<usize>,
+    #[serde(default)]
+    pub num_attention_heads: Option<usize>,
+    #[serde(default)]
+    pub intermediate_size: Option<usize>,
+    #[serde(default)]
+    pub num_hidden_layers: Option<usize>,
+    #[serde(default)]
+    pub layer_norm_eps: Option<f64>,
+    #[serde(default)]
+    pub vocab_size: Option<usize>,
+    #[serde(default)]
+    pub max_position_embeddings: Option<usize>,
+}
+
+/// `models.<id>.numerical_stability` 中前向传播的数值稳定性参数
+///
+/// 截断范围均为对称区间 `[-x, x]`。默认值针对F32推理，BF16等低精度下可以适当收紧
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct NumericalStabilityConfig {
+    /// 词嵌入输出的截断范围
+    #[serde(default = "default_embedding_clamp")]
+    pub embedding_clamp: f32,
+    /// 注意力中Q、K矩阵的截断范围
+    #[serde(default = "default_qk_clamp")]
+    pub qk_clamp: f32,
+    /// 缩放后注意力分数的截断范围
+    #[serde(default = "default_attention_score_clamp")]
+    pub attention_score_clamp: f32,
+    /// 最终LayerNorm输入与输出的截断范围
+    #[serde(default = "default_hidden_clamp")]
+    pub hidden_clamp: f32,
+    /// 最终LayerNorm前总是加上的稳定因子
+    #[serde(default = "default_stability_factor")]
+    pub stability_factor: f32,
+    /// 方差低于该值时改用 `low_variance_stability_factor`
+    #[serde(default = "default_low_variance_threshold")]
+    pub low_variance_threshold: f32,
+    #[serde(default = "default_low_variance_stability_factor")]
+    pub low_variance_stability_factor: f32,
+    /// 方差低于该值时改用 `very_low_variance_stability_factor`
+    #[serde(default = "default_very_low_variance_threshold")]
+    pub very_low_variance_threshold: f32,
+    #[serde(default = "default_very_low_variance_stability_factor")]
+    pub very_low_variance_stability_factor: f32,
+}
+
+fn default_embedding_clamp() -> f32 {
+    1e4
+}
+
+fn default_qk_clamp() -> f32 {
+    1e4
+}
+
+fn default_attention_score_clamp() -> f32 {
+    50.0
+}
+
+fn default_hidden_clamp() -> f32 {
+    1e3
+}
+
+fn default_stability_factor() -> f32 {
+    1e-8
+}
+
+fn default_low_variance_threshold() -> f32 {
+    1e-8
+}
+
+fn default_low_variance_stability_factor() -> f32 {
+    1e-6
+}
+
+fn default_very_low_variance_threshold() -> f32 {
+    1e-20
+}
+
+fn default_very_low_variance_stability_factor() -> f32 {
+    1e-5
+}
+
+impl Default for NumericalStabilityConfig {
+    fn default() -> Self {
+        Self {
+            embedding_clamp: default_embedding_clamp(),
+            qk_clamp: default_qk_clamp(),
+            attention_score_clamp: default_attention_score_clamp(),
+            hidden_clamp: default_hidden_clamp(),
+            stability_factor: default_stability_factor(),
+            low_variance_threshold: default_low_variance_threshold(),
+            low_variance_stability_factor: default_low_variance_stability_factor(),
+            very_low_variance_threshold: default_very_low_variance_threshold(),
+            very_low_variance_stability_factor: default_very_low_variance_stability_factor(),
+        }
+    }
+}
+
+impl NumericalStabilityConfig {
+    /// 按最小方差选择最终LayerNorm前的稳定因子
+    pub fn stability_factor_for(&self, min_variance: f32) -> f32 {
+        if min_variance < self.very_low_variance_threshold {
+            self.very_low_variance_stability_factor
+        } else if min_variance < self.low_variance_threshold {
+            self.low_variance_stability_factor
+        } else {
+            self.stability_factor
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ModelFiles {
     pub weights: Vec<String>,
     pub config: String,
@@ -61,6 +423,20 @@ pub struct ModelFiles {
     pub generation_config: String,
 }
 
+impl ModelFiles {
+    /// 模型所需的全部文件名
+    pub fn all(&self) -> Vec<&str> {
+        let mut files: Vec<&str> = self.weights.iter().map(String::as_str).collect();
+        files.extend([
+            self.config.as_str(),
+            self.tokenizer.as_str(),
+            self.tokenizer_config.as_str(),
+            self.generation_config.as_str(),
+        ]);
+        files
+    }
+}
+
 impl Clone for ModelFiles {
     fn clone(&self) -> Self {
         Self {
@@ -73,13 +449,173 @@ impl Clone for ModelFiles {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InferenceConfig {
+    /// 计算设备: auto | cpu | cuda | cuda:N | metal | metal:N
+    #[serde(default = "default_device")]
+    pub device: String,
+    /// 加载权重前校验safetensors文件是否完整
+    #[serde(default = "default_true")]
+    pub verify_weights: bool,
+    /// 达到模型并发上限时请求的最长等待时间（秒），超时返回503
+    #[serde(default = "default_queue_timeout_secs")]
+    pub queue_timeout_secs: u64,
+    /// logits中出现NaN/Inf时的处理方式: error | clamp | skip
+    #[serde(default)]
+    pub nan_policy: NanPolicy,
+    /// 前向传播中逐元素检查隐藏状态是否含NaN/Inf；关闭后只保留形状检查
+    #[serde(default = "default_true")]
+    pub strict_validation: bool,
+    /// 生成结束后检查输出是否含解码产生的替换字符，有则记录产生它的token ID
+    #[serde(default = "default_true")]
+    pub validate_output_encoding: bool,
+    /// 单次生成token数的绝对上限，优先于请求参数与模型的 `max_output_tokens`；未设置时不限制
+    #[serde(default)]
+    pub absolute_max_tokens: Option<usize>,
+}
+
+fn default_device() -> String {
+    "auto".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_queue_timeout_secs() -> u64 {
+    30
+}
+
+impl Default for InferenceConfig {
+    fn default() -> Self {
+        Self {
+            device: default_device(),
+            verify_weights: true,
+            queue_timeout_secs: default_queue_timeout_secs(),
+            nan_policy: NanPolicy::default(),
+            strict_validation: true,
+            validate_output_encoding: true,
+            absolute_max_tokens: None,
+        }
+    }
+}
+
+/// `models` 配置：各模型的配置，以及可选的 `aliases` 别名映射
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ModelsConfig {
+    /// 模型别名到实际模型ID的映射，例如 `gpt-3.5-turbo: yi-coder`
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+    /// 启动时预先加载的模型ID，服务在这些模型加载完成后才开始接收请求
+    #[serde(default)]
+    pub preload: Vec<String>,
+    /// 预加载失败时的处理方式
+    #[serde(default)]
+    pub preload_failure: PreloadFailure,
+    /// 模型超过该秒数未被请求时自动卸载以释放内存，下次请求时重新加载；未设置时不卸载
+    #[serde(default)]
+    pub idle_ttl_secs: Option<u64>,
+    #[serde(flatten)]
+    pub models: HashMap<String, ModelConfig>,
+}
+
+/// `models.preload` 中的模型加载失败时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PreloadFailure {
+    /// 启动失败
+    #[default]
+    Fatal,
+    /// 记录警告并继续启动，模型在首次请求时再加载
+    Warn,
+}
+
+impl Deref for ModelsConfig {
+    type Target = HashMap<String, ModelConfig>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.models
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct AppConfig {
     pub server: ServerConfig,
     pub locales: LocalesConfig,
-    pub models: HashMap<String, ModelConfig>,
+    pub models: ModelsConfig,
     pub models_cache_dir: String,
     pub chat: Chat,
+    #[serde(default)]
+    pub inference: InferenceConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub download: DownloadConfig,
+    #[serde(default)]
+    pub tokenizer: TokenizerConfig,
+    #[serde(default)]
+    pub cors: CorsConfig,
+}
+
+/// 跨域资源共享 (CORS) 设置
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct CorsConfig {
+    /// 是否启用CORS，默认关闭
+    #[serde(default)]
+    pub enabled: bool,
+    /// 允许的来源，例如 `https://example.com`；为空时允许任意来源
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    /// 预检结果的缓存时间（秒），作为 `Access-Control-Max-Age` 返回；未设置时不返回该头
+    #[serde(default)]
+    pub max_age_secs: Option<usize>,
+}
+
+/// 所有模型共用的tokenizer设置
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TokenizerConfig {
+    /// 编码时是否使用tokenizers的rayon线程池并行，关闭可避免与candle争用CPU线程
+    #[serde(default = "default_true")]
+    pub parallelism: bool,
+}
+
+impl Default for TokenizerConfig {
+    fn default() -> Self {
+        Self { parallelism: true }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct DownloadConfig {
+    /// 同时下载的模型数量上限，其余下载排队等待；未设置时不限制
+    #[serde(default)]
+    pub max_concurrent_models: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct LoggingConfig {
+    /// `RUST_LOG` 风格的过滤指令，例如 `info,coder_openapi::service::models::yi_coder=warn`
+    #[serde(default)]
+    pub filters: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AuthConfig {
+    /// 无需API key即可访问的路径前缀
+    #[serde(default = "default_public_paths")]
+    pub public_paths: Vec<String>,
+}
+
+fn default_public_paths() -> Vec<String> {
+    vec!["/health".to_string(), "/metrics".to_string()]
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self { public_paths: default_public_paths() }
+    }
 }
 
 pub static CONFIG: OnceLock<AppConfig> = OnceLock::new();
@@ -100,9 +636,26 @@ impl AppConfig {
     pub fn load(config_path: &str) -> anyhow::Result<Self> {
         let config_file = std::fs::File::open(config_path)?;
         let config: Self = serde_yaml::from_reader(config_file)?;
+        config.validate()?;
         Ok(config)
     }
 
+    /// 校验反序列化无法表达的约束
+    fn validate(&self) -> anyhow::Result<()> {
+        let mut model_ids: Vec<_> = self.models.keys().collect();
+        model_ids.sort();
+        for model_id in model_ids {
+            // 没有许可的限流器会让每个请求都排队到超时
+            if self.models[model_id].max_concurrent == Some(0) {
+                anyhow::bail!(
+                    "models.{}.max_concurrent must be at least 1, omit it for no limit",
+                    model_id
+                );
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_model_config(&self, model_id: &str) -> anyhow::Result<ModelConfig> {
         self.models
             .get(model_id)
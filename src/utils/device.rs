@@ -0,0 +1,127 @@
+//! 计算设备选择
+//!
+//! 根据配置项 `inference.device` 解析candle计算设备。
+//! 只有 `auto` 模式会在CUDA/Metal不可用时静默回退到CPU；
+//! 显式指定的设备不可用时返回 `AppError::ConfigError`。
+//! Metal 设备需要启用 `metal` feature 编译。
+use crate::error::AppError;
+use crate::utils::config::get_config;
+use candle_core::{DType, Device, DeviceLocation};
+use std::str::FromStr;
+
+/// 配置中的设备选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceSpec {
+    /// 自动选择：依次尝试 `cuda:0`、`metal:0`，都不可用时使用CPU
+    Auto,
+    /// 强制使用CPU
+    Cpu,
+    /// 使用指定序号的CUDA设备，例如 `cuda:1`
+    Cuda(usize),
+    /// 使用指定序号的Metal设备 (Apple Silicon)，例如 `metal:0`
+    Metal(usize),
+}
+
+impl FromStr for DeviceSpec {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "auto" => Ok(DeviceSpec::Auto),
+            "cpu" => Ok(DeviceSpec::Cpu),
+            "cuda" => Ok(DeviceSpec::Cuda(0)),
+            "metal" => Ok(DeviceSpec::Metal(0)),
+            other => {
+                let parse_ordinal =
+                    |prefix: &str| other.strip_prefix(prefix).and_then(|n| n.parse::<usize>().ok());
+                parse_ordinal("cuda:")
+                    .map(DeviceSpec::Cuda)
+                    .or_else(|| parse_ordinal("metal:").map(DeviceSpec::Metal))
+                    .ok_or_else(|| AppError::ConfigError(format!("Invalid device: {}", s)))
+            }
+        }
+    }
+}
+
+impl DeviceSpec {
+    /// 将设备选项解析为candle设备
+    ///
+    /// # 返回值
+    /// * `Ok(Device)` - 解析成功
+    /// * `Err(AppError::ConfigError)` - 显式指定的设备不可用
+    pub fn resolve(&self) -> Result<Device, AppError> {
+        match self {
+            DeviceSpec::Auto => {
+                if candle_core::utils::cuda_is_available() {
+                    match Device::new_cuda(0) {
+                        Ok(device) => return Ok(device),
+                        Err(e) => log::warn!("CUDA unavailable: {}", e),
+                    }
+                }
+                if candle_core::utils::metal_is_available() {
+                    match Device::new_metal(0) {
+                        Ok(device) => return Ok(device),
+                        Err(e) => log::warn!("Metal unavailable: {}", e),
+                    }
+                }
+                log::warn!("No GPU device available, falling back to CPU");
+                Ok(Device::Cpu)
+            }
+            DeviceSpec::Cpu => Ok(Device::Cpu),
+            DeviceSpec::Cuda(ordinal) => Device::new_cuda(*ordinal).map_err(|e| {
+                AppError::ConfigError(format!("Failed to get CUDA device cuda:{}: {}", ordinal, e))
+            }),
+            DeviceSpec::Metal(ordinal) => Device::new_metal(*ordinal).map_err(|e| {
+                AppError::ConfigError(format!(
+                    "Failed to get Metal device metal:{}: {}",
+                    ordinal, e
+                ))
+            }),
+        }
+    }
+}
+
+/// 返回设备支持的数据类型
+///
+/// Metal 不支持F64，此类张量回退为F32；其他设备保持原类型
+pub fn supported_dtype(dtype: DType, device: &Device) -> DType {
+    match (dtype, device) {
+        (DType::F64, Device::Metal(_)) => DType::F32,
+        _ => dtype,
+    }
+}
+
+/// 设备的显示名称，格式与配置相同：`cpu`、`cuda:N` 或 `metal:N`
+pub fn device_label(device: &Device) -> String {
+    match device.location() {
+        DeviceLocation::Cpu => "cpu".to_string(),
+        DeviceLocation::Cuda { gpu_id } => format!("cuda:{}", gpu_id),
+        DeviceLocation::Metal { gpu_id } => format!("metal:{}", gpu_id),
+    }
+}
+
+/// 解析设备字符串 (`auto` | `cpu` | `cuda` | `cuda:N` | `metal` | `metal:N`)
+pub fn resolve_device(spec: &str) -> Result<Device, AppError> {
+    spec.parse::<DeviceSpec>()?.resolve()
+}
+
+/// 解析请求指定的设备 (`cpu` | `cuda` | `cuda:N` | `metal` | `metal:N`)
+///
+/// 不接受 `auto`；格式错误或设备不存在时返回 `AppError::InvalidParameter`
+pub fn resolve_request_device(spec: &str) -> Result<Device, AppError> {
+    let invalid = |e: AppError| match e {
+        AppError::ConfigError(message) => AppError::InvalidParameter(message),
+        e => e,
+    };
+    match spec.parse::<DeviceSpec>().map_err(invalid)? {
+        DeviceSpec::Auto => {
+            Err(AppError::InvalidParameter("Device must be explicit, got auto".to_string()))
+        }
+        spec => spec.resolve().map_err(invalid),
+    }
+}
+
+/// 按应用配置 `inference.device` 解析设备
+pub fn configured_device() -> Result<Device, AppError> {
+    resolve_device(&get_config().inference.device)
+}
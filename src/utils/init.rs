@@ -1,16 +1,37 @@
-use crate::utils::config::AppConfig;
-use log::info;
+use crate::error::AppError;
+use crate::service::models::ModelManager;
+use crate::utils::config::{AppConfig, PreloadFailure, TokenizerConfig};
+use crate::utils::device::resolve_device;
+use crate::utils::log_filter::LogFilters;
+use log::{error, info, warn};
 use log4rs;
+use log4rs::config::{Config, Deserializers, Logger, RawConfig, Root};
 use std::sync::Arc;
+use std::time::Instant;
 
-pub async fn init() -> crate::error::Result<Arc<AppConfig>> {
-    // 初始化日志系统
-    log4rs::init_file("config/log4rs.yml", Default::default())?;
+/// 覆盖 `logging.filters` 的环境变量
+pub const LOG_FILTER_ENV: &str = "RUST_LOG";
 
+pub async fn init() -> crate::error::Result<Arc<AppConfig>> {
     // 加载应用配置
     let config = AppConfig::load("config/app.yml")?;
+
+    // 初始化日志系统，`RUST_LOG` 优先于配置文件中的 `logging.filters`
+    let filters = std::env::var(LOG_FILTER_ENV).ok().or_else(|| config.logging.filters.clone());
+    match filters {
+        Some(spec) => init_logging("config/log4rs.yml", &LogFilters::parse(&spec)?)?,
+        None => log4rs::init_file("config/log4rs.yml", Default::default())?,
+    }
     info!("应用配置加载完成");
 
+    // 在任何tokenizer被使用之前设置并行方式
+    apply_tokenizer_parallelism(&config.tokenizer);
+    info!("tokenizer并行: {}", config.tokenizer.parallelism);
+
+    // 校验计算设备配置，显式指定的设备不可用时启动失败
+    let device = resolve_device(&config.inference.device)?;
+    info!("计算设备: {} -> {:?}", config.inference.device, device);
+
     // 初始化本地化系统
     info!("使用本地化文件路径: {}, 默认语言: {}", config.locales.path, config.locales.default);
 
@@ -21,3 +42,93 @@ pub async fn init() -> crate::error::Result<Arc<AppConfig>> {
 
     Ok(Arc::new(config))
 }
+
+/// 按 `tokenizer.parallelism` 设置tokenizers的并行方式
+///
+/// 等同于设置 `TOKENIZERS_PARALLELISM` 环境变量，配置优先于启动前已设置的环境变量
+pub fn apply_tokenizer_parallelism(config: &TokenizerConfig) {
+    tokenizers::utils::parallelism::set_parallelism(config.parallelism);
+    // 同步环境变量，使读取环境变量的代码与配置一致
+    std::env::set_var("TOKENIZERS_PARALLELISM", config.parallelism.to_string());
+}
+
+/// 创建模型管理器，并加载 `models.preload` 中的模型
+pub async fn init_models(config: &AppConfig) -> crate::error::Result<ModelManager> {
+    let manager = ModelManager::new();
+    preload_models(&manager, &config.models.preload, config.models.preload_failure).await?;
+    if manager.spawn_idle_unloader().is_some() {
+        info!("空闲模型将在 {} 秒后自动卸载", config.models.idle_ttl_secs.unwrap_or_default());
+    }
+    Ok(manager)
+}
+
+/// 依次加载并预热 `model_ids` 中的模型
+///
+/// 预热对一段短提示词执行一次前向传播。加载失败时按 `on_failure` 返回错误或只记录警告
+pub async fn preload_models(
+    manager: &ModelManager,
+    model_ids: &[String],
+    on_failure: PreloadFailure,
+) -> crate::error::Result<()> {
+    for model_id in model_ids {
+        let model_id = manager.resolve_model_id(model_id);
+        let start = Instant::now();
+        match warm_up(manager, model_id).await {
+            Ok(()) => info!("模型 {} 预加载完成，耗时 {:?}", model_id, start.elapsed()),
+            Err(e) if on_failure == PreloadFailure::Warn => {
+                warn!("模型 {} 预加载失败，将在首次请求时加载: {}", model_id, e)
+            }
+            Err(e) => {
+                error!("模型 {} 预加载失败: {}", model_id, e);
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn warm_up(manager: &ModelManager, model_id: &str) -> crate::error::Result<()> {
+    let model = manager.get_or_load_model(model_id).await?;
+    let input_ids = model.encode_prompt("warmup")?;
+    if !input_ids.is_empty() {
+        model.forward_logits(&input_ids)?;
+    }
+    Ok(())
+}
+
+/// 使用log4rs配置文件初始化日志，并按 `filters` 覆盖root和各模块的级别
+///
+/// 与 `log4rs::init_file` 不同，配置文件修改后不会自动重新加载
+pub fn init_logging(config_path: &str, filters: &LogFilters) -> crate::error::Result<()> {
+    let file = std::fs::File::open(config_path)?;
+    let raw: RawConfig = serde_yaml::from_reader(file)
+        .map_err(|e| AppError::ConfigError(format!("Invalid log4rs config: {}", e)))?;
+    let (appenders, mut errors) = raw.appenders_lossy(&Deserializers::default());
+    // 无法构造的appender由log4rs输出到stderr，其余appender照常使用
+    errors.handle();
+
+    let root = raw.root();
+    let root = match filters.default_level() {
+        Some(level) => Root::builder().appenders(root.appenders().iter().cloned()).build(level),
+        None => root,
+    };
+    // 过滤指令中出现的target替换配置文件中的同名logger
+    let loggers = raw
+        .loggers()
+        .into_iter()
+        .filter(|logger| filters.directives().iter().all(|(target, _)| target != logger.name()))
+        .chain(
+            filters
+                .directives()
+                .iter()
+                .map(|(target, level)| Logger::builder().build(target.clone(), *level)),
+        );
+
+    let config = Config::builder()
+        .appenders(appenders)
+        .loggers(loggers)
+        .build(root)
+        .map_err(|e| AppError::ConfigError(format!("Invalid log filters: {}", e)))?;
+    log4rs::init_config(config).map_err(|e| AppError::ConfigError(e.to_string()))?;
+    Ok(())
+}
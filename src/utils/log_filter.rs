@@ -0,0 +1,66 @@
+//! `RUST_LOG` 风格的日志过滤指令
+//!
+//! 指令以逗号分隔：`target=level` 设置某个模块及其子模块的级别，
+//! 单独的 `level` 设置默认级别，例如
+//! `info,coder_openapi::service::models::yi_coder::transformer=warn`。
+use crate::error::AppError;
+use log::{Level, LevelFilter};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LogFilters {
+    default_level: Option<LevelFilter>,
+    directives: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilters {
+    /// 解析过滤指令，级别名不合法时返回 `AppError::ConfigError`
+    pub fn parse(spec: &str) -> Result<Self, AppError> {
+        let mut filters = Self::default();
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    filters.directives.push((target.trim().to_string(), parse_level(level)?))
+                }
+                None => filters.default_level = Some(parse_level(directive)?),
+            }
+        }
+        Ok(filters)
+    }
+
+    /// 未指定target的默认级别
+    pub fn default_level(&self) -> Option<LevelFilter> {
+        self.default_level
+    }
+
+    /// 按target设置的级别
+    pub fn directives(&self) -> &[(String, LevelFilter)] {
+        &self.directives
+    }
+
+    /// `target` 生效的级别，取最长匹配的指令，没有匹配时使用默认级别
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.directives
+            .iter()
+            .filter(|(prefix, _)| {
+                target == prefix
+                    || target
+                        .strip_prefix(prefix.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, level)| *level)
+            .or(self.default_level)
+            .unwrap_or(LevelFilter::Trace)
+    }
+
+    /// 判断 `target` 上 `level` 级别的日志是否输出
+    pub fn enabled(&self, target: &str, level: Level) -> bool {
+        level <= self.level_for(target)
+    }
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, AppError> {
+    LevelFilter::from_str(level.trim())
+        .map_err(|_| AppError::ConfigError(format!("Invalid log level in filter: {}", level)))
+}
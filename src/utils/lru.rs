@@ -0,0 +1,72 @@
+//! 固定容量的LRU映射
+//!
+//! 非线程安全，调用方需自行加锁。
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+
+pub struct LruCache<K, V> {
+    capacity: usize,
+    values: HashMap<K, V>,
+    /// 最近使用的键在队尾
+    order: VecDeque<K>,
+}
+
+impl<K: Eq + Hash + Clone, V> LruCache<K, V> {
+    /// 创建容量为 `capacity` 的缓存，容量至少为1
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity: capacity.max(1), values: HashMap::new(), order: VecDeque::new() }
+    }
+
+    /// 获取条目并将其标记为最近使用
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        if self.values.contains_key(key) {
+            self.touch(key);
+        }
+        self.values.get(key)
+    }
+
+    /// 获取可变条目并将其标记为最近使用
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        if self.values.contains_key(key) {
+            self.touch(key);
+        }
+        self.values.get_mut(key)
+    }
+
+    /// 写入条目，超出容量时淘汰最久未使用的条目
+    pub fn insert(&mut self, key: K, value: V) {
+        if self.values.insert(key.clone(), value).is_some() {
+            self.touch(&key);
+            return;
+        }
+        self.order.push_back(key);
+        while self.order.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.values.remove(&evicted);
+            }
+        }
+    }
+
+    /// 删除条目，返回被删除的值
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let value = self.values.remove(key)?;
+        self.order.retain(|k| k != key);
+        Some(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    fn touch(&mut self, key: &K) {
+        if let Some(position) = self.order.iter().position(|k| k == key) {
+            if let Some(key) = self.order.remove(position) {
+                self.order.push_back(key);
+            }
+        }
+    }
+}
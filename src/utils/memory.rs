@@ -0,0 +1,22 @@
+//! 进程内存用量
+//!
+//! 从 `/proc/self/status` 读取，非Linux平台返回 `None`。
+
+/// 进程当前的常驻内存 (`VmRSS`)，单位字节
+pub fn resident_memory_bytes() -> Option<u64> {
+    proc_status_bytes("VmRSS:")
+}
+
+/// 进程启动以来的常驻内存峰值 (`VmHWM`)，单位字节
+pub fn peak_memory_bytes() -> Option<u64> {
+    proc_status_bytes("VmHWM:")
+}
+
+/// 读取 `/proc/self/status` 中以kB为单位的字段
+fn proc_status_bytes(field: &str) -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with(field))?;
+    let kb: u64 =
+        line.trim_start_matches(field).trim().trim_end_matches("kB").trim().parse().ok()?;
+    Some(kb * 1024)
+}
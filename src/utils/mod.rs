@@ -1,8 +1,14 @@
 pub mod config;
+pub mod device;
 pub mod download;
 pub mod error;
 pub mod init;
+pub mod log_filter;
+pub mod lru;
+pub mod memory;
 pub mod time;
+pub mod tls;
+pub mod weights;
 
 pub use config::AppConfig;
 pub use download::ModelDownloader;
@@ -0,0 +1,63 @@
+//! HTTPS监听
+//!
+//! 配置了 `server.tls` 时使用rustls监听HTTPS，并通过ALPN协商HTTP/2；
+//! 未配置时监听明文HTTP/1。
+use crate::utils::config::{ServerConfig, TlsConfig};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+/// 服务的监听方式
+#[derive(Debug)]
+pub enum Listener {
+    /// 明文HTTP/1
+    Plaintext,
+    /// HTTPS，ALPN协商HTTP/2与HTTP/1.1
+    Tls(Box<rustls::ServerConfig>),
+}
+
+impl Listener {
+    /// 按 `server.tls` 选择监听方式，证书或私钥无法读取时返回错误
+    pub fn from_config(server: &ServerConfig) -> io::Result<Self> {
+        match &server.tls {
+            Some(tls) => Ok(Self::Tls(Box::new(load_rustls_config(tls)?))),
+            None => Ok(Self::Plaintext),
+        }
+    }
+}
+
+/// 读取PEM格式的证书链与私钥，构造rustls服务端配置
+pub fn load_rustls_config(tls: &TlsConfig) -> io::Result<rustls::ServerConfig> {
+    let certs = rustls_pemfile::certs(&mut open(&tls.cert)?)
+        .collect::<Result<Vec<CertificateDer<'static>>, _>>()?;
+    if certs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("no certificate found in {}", tls.cert.display()),
+        ));
+    }
+    let key: PrivateKeyDer<'static> = rustls_pemfile::private_key(&mut open(&tls.key)?)?
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("no private key found in {}", tls.key.display()),
+            )
+        })?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(io::Error::other)?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(config)
+}
+
+fn open(path: &Path) -> io::Result<BufReader<File>> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+}
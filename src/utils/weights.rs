@@ -0,0 +1,91 @@
+//! 模型权重文件校验
+//!
+//! safetensors文件格式: 8字节小端序头部长度N + N字节JSON头部 + 张量数据。
+//! 头部中每个张量的 `data_offsets` 是相对数据区起始位置的字节范围，
+//! 在构建VarBuilder之前检查这些范围是否都落在文件内，
+//! 以便在分片被截断时给出明确的错误，而不是在前向传播时才失败。
+use crate::error::AppError;
+use safetensors::SafeTensorError;
+use std::io::Read;
+use std::path::Path;
+
+/// 头部长度上限，与safetensors库保持一致 (100MB)
+const MAX_HEADER_SIZE: u64 = 100_000_000;
+
+/// 校验safetensors文件头部声明的张量字节范围与实际文件长度一致
+///
+/// # 返回值
+/// * `Ok(())` - 文件完整
+/// * `Err(AppError::SafeTensor)` - 头部无效或文件被截断，错误信息包含文件路径
+pub fn verify_safetensors_file(path: &Path) -> Result<(), AppError> {
+    let mut file = std::fs::File::open(path)?;
+    let file_len = file.metadata()?.len();
+
+    let mut header_len_bytes = [0u8; 8];
+    file.read_exact(&mut header_len_bytes).map_err(|_| {
+        invalid_header(path, SafeTensorError::HeaderTooSmall, "file is shorter than 8 bytes")
+    })?;
+    let header_len = u64::from_le_bytes(header_len_bytes);
+    if header_len > MAX_HEADER_SIZE {
+        return Err(invalid_header(
+            path,
+            SafeTensorError::HeaderTooLarge,
+            &format!("header length {} exceeds {} bytes", header_len, MAX_HEADER_SIZE),
+        ));
+    }
+    if 8 + header_len > file_len {
+        return Err(invalid_header(
+            path,
+            SafeTensorError::InvalidHeaderLength,
+            &format!("header length {} exceeds file length {}", header_len, file_len),
+        ));
+    }
+
+    let mut header = vec![0u8; header_len as usize];
+    file.read_exact(&mut header)?;
+    let header: serde_json::Map<String, serde_json::Value> = serde_json::from_slice(&header)
+        .map_err(|e| {
+            invalid_header(path, SafeTensorError::InvalidHeaderDeserialization, &e.to_string())
+        })?;
+
+    let data_len = file_len - 8 - header_len;
+    for (name, info) in header.iter().filter(|(name, _)| name.as_str() != "__metadata__") {
+        let end = info
+            .get("data_offsets")
+            .and_then(|offsets| offsets.get(1))
+            .and_then(|end| end.as_u64())
+            .ok_or_else(|| {
+                SafeTensorError::InvalidOffset(format!("{}: {}", path.display(), name))
+            })?;
+        if end > data_len {
+            log::error!(
+                "Truncated safetensors file {}: tensor {} ends at byte {}, but only {} data bytes present",
+                path.display(),
+                name,
+                end,
+                data_len
+            );
+            return Err(SafeTensorError::InvalidOffset(format!(
+                "{}: tensor {} ends at byte {}, but only {} data bytes present",
+                path.display(),
+                name,
+                end,
+                data_len
+            ))
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// 头部无效的错误
+///
+/// safetensors的头部错误不带任何信息，改用 `InvalidData` 的IO错误携带文件路径与原因
+fn invalid_header(path: &Path, kind: SafeTensorError, reason: &str) -> AppError {
+    SafeTensorError::IoError(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("{}: {:?}, {}", path.display(), kind, reason),
+    ))
+    .into()
+}
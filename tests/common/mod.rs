@@ -0,0 +1,125 @@
+//! 集成测试共享的辅助函数与测试模型
+#![allow(dead_code)]
+
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use coder_openapi::error::AppError;
+use coder_openapi::service::models::CompletionModel;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokenizers::Tokenizer;
+
+/// 构造一个基于空格分词的WordLevel tokenizer，token ID即其在 `vocab` 中的下标
+///
+/// `vocab` 中必须包含 `<unk>`
+pub fn word_level_tokenizer(vocab: &[&str]) -> Tokenizer {
+    let vocab: serde_json::Map<String, serde_json::Value> =
+        vocab.iter().enumerate().map(|(id, token)| (token.to_string(), id.into())).collect();
+    let json = serde_json::json!({
+        "version": "1.0",
+        "truncation": null,
+        "padding": null,
+        "added_tokens": [],
+        "normalizer": null,
+        "pre_tokenizer": {"type": "Whitespace"},
+        "post_processor": null,
+        "decoder": null,
+        "model": {
+            "type": "WordLevel",
+            "vocab": vocab,
+            "unk_token": "<unk>"
+        }
+    });
+    Tokenizer::from_str(&json.to_string()).unwrap()
+}
+
+/// [`FakeModel`] 使用的词表，ID 0为EOS
+pub const VOCAB: [&str; 6] = ["<eos>", "user", ":", "hi", "pong", "<unk>"];
+
+/// 可复用的测试模型：每步都以最高分输出同一个token，并记录前向传播次数
+pub struct FakeModel {
+    model_id: String,
+    tokenizer: Tokenizer,
+    /// 每步输出的token ID
+    reply: u32,
+    /// 输出一次 `reply` 后是否生成EOS
+    stop_after_reply: bool,
+    step_delay: Option<Duration>,
+    context_length: Option<usize>,
+    steps: AtomicUsize,
+}
+
+impl FakeModel {
+    fn new(model_id: &str, reply: &str, stop_after_reply: bool) -> Self {
+        let reply = VOCAB.iter().position(|token| *token == reply).expect("reply not in VOCAB");
+        Self {
+            model_id: model_id.to_string(),
+            tokenizer: word_level_tokenizer(&VOCAB),
+            reply: reply as u32,
+            stop_after_reply,
+            step_delay: None,
+            context_length: None,
+            steps: AtomicUsize::new(0),
+        }
+    }
+
+    /// 从不生成EOS，一直输出 `hi`，直到达到 `max_tokens`、被取消或超时
+    pub fn endless(model_id: &str) -> Self {
+        Self::new(model_id, "hi", false)
+    }
+
+    /// 输出一次 `reply` 后生成EOS，`reply` 为 `<eos>` 时第一个token就结束
+    pub fn fixed_reply(model_id: &str, reply: &str) -> Self {
+        Self::new(model_id, reply, true)
+    }
+
+    /// 每次前向传播阻塞 `delay`，模拟慢速模型
+    pub fn with_step_delay(mut self, delay: Duration) -> Self {
+        self.step_delay = Some(delay);
+        self
+    }
+
+    pub fn with_context_length(mut self, context_length: usize) -> Self {
+        self.context_length = Some(context_length);
+        self
+    }
+
+    /// 已执行的前向传播次数
+    pub fn steps(&self) -> usize {
+        self.steps.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl CompletionModel for FakeModel {
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    fn context_length(&self) -> Option<usize> {
+        self.context_length
+    }
+
+    fn forward_logits(&self, input_ids: &[u32]) -> Result<Tensor, AppError> {
+        self.steps.fetch_add(1, Ordering::SeqCst);
+        if let Some(delay) = self.step_delay {
+            std::thread::sleep(delay);
+        }
+        let next = match input_ids.last() {
+            Some(&last) if self.stop_after_reply && last == self.reply => 0,
+            _ => self.reply as usize,
+        };
+        let mut logits = [0f32; VOCAB.len()];
+        logits[next] = 10.0;
+        Ok(Tensor::new(&logits, &Device::Cpu)?)
+    }
+}
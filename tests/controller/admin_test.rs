@@ -0,0 +1,121 @@
+#[path = "../common/mod.rs"]
+mod common;
+
+use actix_web::{test, web, App};
+use coder_openapi::controller::admin::{routes, REDACTED};
+use coder_openapi::controller::chat::chat_completion::GENERATION_ID_HEADER;
+use coder_openapi::middleware::authentication::Authentication;
+use coder_openapi::routes::route::configure_with_manager;
+use coder_openapi::service::chat::chat_completion::ChatCompletionService;
+use coder_openapi::service::models::ModelManager;
+use common::FakeModel;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+const MODEL_ID: &str = "endless-model";
+const API_KEY: &str = "admin-key";
+
+fn auth() -> Authentication {
+    Authentication::new(vec![]).with_api_key(API_KEY)
+}
+
+#[actix_web::test]
+async fn test_lists_the_active_generation_across_workers() {
+    let model = Arc::new(FakeModel::endless(MODEL_ID));
+    let manager = ModelManager::new();
+    manager.register_model(MODEL_ID, model.clone()).await;
+    let service = web::Data::new(ChatCompletionService::new(manager.clone()));
+    // 与main.rs一样，每个worker各自构建App并共用同一个服务
+    let worker = || {
+        App::new().wrap(auth()).configure(|cfg| {
+            configure_with_manager(cfg, manager.clone(), service.clone());
+        })
+    };
+    let first = test::init_service(worker()).await;
+    let second = test::init_service(worker()).await;
+
+    let completion = async {
+        let req = test::TestRequest::post()
+            .uri("/v1/chat/completions")
+            .insert_header(("Authorization", format!("Bearer {}", API_KEY)))
+            .set_json(json!({
+                "model": MODEL_ID,
+                "messages": [{"role": "user", "content": "hi"}],
+                "temperature": 0.0,
+                "max_tokens": 100000
+            }))
+            .to_request();
+        test::call_service(&first, req).await
+    };
+    let inspect = async {
+        // 等待生成产生几个token后再查询
+        while model.steps() < 3 {
+            tokio::task::yield_now().await;
+        }
+        let req = test::TestRequest::get()
+            .uri("/v1/admin/generations")
+            .insert_header(("Authorization", format!("Bearer {}", API_KEY)))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&second, req).await;
+        let id = body["data"][0]["id"].as_str().unwrap();
+        let req = test::TestRequest::post()
+            .uri(&format!("/v1/chat/completions/{}/cancel", id))
+            .insert_header(("Authorization", format!("Bearer {}", API_KEY)))
+            .to_request();
+        assert_eq!(test::call_service(&second, req).await.status(), 202);
+        body
+    };
+    let (resp, body) = futures::join!(completion, inspect);
+    assert_eq!(resp.status(), 200);
+
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 1);
+    assert_eq!(resp.headers().get(GENERATION_ID_HEADER).unwrap(), data[0]["id"].as_str().unwrap());
+    assert_eq!(data[0]["model"], MODEL_ID);
+    assert!(data[0]["elapsed_ms"].is_u64());
+    assert!(data[0]["tokens"].as_u64().unwrap() >= 2);
+    assert!(service.generations().list().is_empty());
+}
+
+#[actix_web::test]
+async fn test_requires_api_key() {
+    let service = web::Data::new(ChatCompletionService::new(ModelManager::new()));
+    let app = test::init_service(
+        App::new()
+            .wrap(auth())
+            .app_data(service)
+            .service(web::scope("/v1/admin").configure(routes)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/v1/admin/generations").to_request();
+    let err = test::try_call_service(&app, req).await.unwrap_err();
+
+    assert_eq!(err.as_response_error().status_code(), 401);
+}
+
+#[actix_web::test]
+async fn test_config_redacts_api_key() {
+    let service = web::Data::new(ChatCompletionService::new(ModelManager::new()));
+    let app = test::init_service(
+        App::new()
+            .wrap(auth())
+            .app_data(service)
+            .service(web::scope("/v1/admin").configure(routes)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/v1/admin/config")
+        .insert_header(("Authorization", format!("Bearer {}", API_KEY)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+
+    assert_eq!(body["auth"]["api_key"], REDACTED);
+    assert!(!body.to_string().contains(API_KEY));
+    // 未在配置文件中出现的字段也以默认值输出
+    assert!(body["inference"]["queue_timeout_secs"].is_u64());
+    assert!(body["server"]["port"].is_u64());
+}
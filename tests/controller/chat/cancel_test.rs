@@ -0,0 +1,127 @@
+#[path = "../../common/mod.rs"]
+mod common;
+
+use actix_web::{test, web, App};
+use coder_openapi::controller::chat::chat_completion::{
+    cancel_completion, chat_completion, GENERATION_ID_HEADER,
+};
+use coder_openapi::service::chat::chat_completion::ChatCompletionService;
+use coder_openapi::service::models::ModelManager;
+use common::FakeModel;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+const MODEL_ID: &str = "endless-model";
+
+async fn service() -> (web::Data<ChatCompletionService>, Arc<FakeModel>) {
+    let model = Arc::new(FakeModel::endless(MODEL_ID));
+    let manager = ModelManager::new();
+    manager.register_model(MODEL_ID, model.clone()).await;
+    (web::Data::new(ChatCompletionService::new(manager)), model)
+}
+
+fn request(max_tokens: usize) -> test::TestRequest {
+    test::TestRequest::post().uri("/v1/chat/completions").set_json(json!({
+        "model": MODEL_ID,
+        "messages": [{"role": "user", "content": "hi"}],
+        "temperature": 0.0,
+        "max_tokens": max_tokens
+    }))
+}
+
+#[actix_web::test]
+async fn test_cancel_stops_generation_and_returns_partial_result() {
+    let (service, model) = service().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(service.clone())
+            .route("/v1/chat/completions", web::post().to(chat_completion))
+            .route(
+                "/v1/chat/completions/{generation_id}/cancel",
+                web::post().to(cancel_completion),
+            ),
+    )
+    .await;
+
+    let completion = test::call_service(&app, request(100000).to_request());
+    let cancel = async {
+        // 等待生成开始并产生几个token后再取消
+        while model.steps() < 3 {
+            tokio::task::yield_now().await;
+        }
+        let generation_id = service.generations().ids().pop().unwrap();
+        let req = test::TestRequest::post()
+            .uri(&format!("/v1/chat/completions/{}/cancel", generation_id))
+            .to_request();
+        (test::call_service(&app, req).await.status(), generation_id)
+    };
+    let (resp, (cancel_status, generation_id)) = futures::join!(completion, cancel);
+
+    assert_eq!(cancel_status, 202);
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers().get(GENERATION_ID_HEADER).unwrap(), generation_id.as_str());
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["x_cancelled"], true);
+    assert!(body["choices"][0]["message"]["content"].as_str().unwrap().starts_with("hi"));
+    let completion_tokens = body["usage"]["completion_tokens"].as_u64().unwrap();
+    assert!(completion_tokens > 0 && completion_tokens < 100000);
+    assert!(service.generations().is_empty());
+}
+
+#[actix_web::test]
+async fn test_cancel_unknown_generation_returns_404() {
+    let (service, _) = service().await;
+    let app =
+        test::init_service(App::new().app_data(service).route(
+            "/v1/chat/completions/{generation_id}/cancel",
+            web::post().to(cancel_completion),
+        ))
+        .await;
+
+    let req = test::TestRequest::post().uri("/v1/chat/completions/missing/cancel").to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 404);
+}
+
+#[actix_web::test]
+async fn test_generation_id_is_assigned_by_the_server() {
+    let (service, _) = service().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(service.clone())
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    // 客户端指定的ID被忽略
+    let req = request(2).insert_header((GENERATION_ID_HEADER, "client-id")).to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 200);
+    let generation_id = resp.headers().get(GENERATION_ID_HEADER).unwrap().to_str().unwrap();
+    assert!(uuid::Uuid::parse_str(generation_id).is_ok());
+    assert!(service.generations().is_empty());
+}
+
+#[actix_web::test]
+async fn test_dropped_request_releases_generation_id() {
+    let (service, model) = service().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(service.clone())
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    // 生成开始后丢弃请求，模拟客户端断开
+    let completion = Box::pin(test::call_service(&app, request(100000).to_request()));
+    let started = Box::pin(async {
+        while model.steps() < 3 {
+            tokio::task::yield_now().await;
+        }
+    });
+    futures::future::select(completion, started).await;
+
+    assert!(service.generations().is_empty());
+}
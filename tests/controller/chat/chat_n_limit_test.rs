@@ -0,0 +1,62 @@
+use actix_web::{test, web, App};
+use coder_openapi::controller::chat::chat::chat_completions;
+use coder_openapi::controller::chat::chat_completion::chat_completion;
+use coder_openapi::error::AppError;
+use coder_openapi::service::chat::chat_completion::{validate_n, ChatCompletionService};
+use coder_openapi::service::chat::ChatService;
+use coder_openapi::service::models::ModelManager;
+use coder_openapi::utils::config::get_config;
+use serde_json::json;
+
+fn request_body(n: usize) -> serde_json::Value {
+    json!({
+        "model": "yi-coder",
+        "messages": [{"role": "user", "content": "hi"}],
+        "n": n
+    })
+}
+
+#[actix_web::test]
+async fn test_validate_n() {
+    assert!(validate_n(None, 4).is_ok());
+    assert!(validate_n(Some(4), 4).is_ok());
+    assert!(matches!(validate_n(Some(5), 4), Err(AppError::InvalidParameter(_))));
+    assert!(matches!(validate_n(Some(0), 4), Err(AppError::InvalidParameter(_))));
+}
+
+#[actix_web::test]
+async fn test_n_above_max_returns_400() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(ChatCompletionService::new(ModelManager::new())))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .set_json(request_body(get_config().chat.max_n + 1))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 400);
+}
+
+#[actix_web::test]
+async fn test_legacy_endpoint_n_above_max_returns_400() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(ModelManager::new()))
+            .app_data(web::Data::new(ChatService::new()))
+            .service(chat_completions),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/chat/completions")
+        .set_json(request_body(get_config().chat.max_n + 1))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 400);
+}
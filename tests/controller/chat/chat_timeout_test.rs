@@ -0,0 +1,79 @@
+#[path = "../../common/mod.rs"]
+mod common;
+
+use actix_web::{test, web, App};
+use coder_openapi::controller::chat::chat_completion::{chat_completion, MAX_DURATION_HEADER};
+use coder_openapi::service::chat::chat_completion::ChatCompletionService;
+use coder_openapi::service::models::ModelManager;
+use common::FakeModel;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+const MODEL_ID: &str = "slow-model";
+
+async fn chat_service() -> ChatCompletionService {
+    let manager = ModelManager::new();
+    // 每步前向传播耗时固定时间，且从不生成EOS
+    let model = FakeModel::endless(MODEL_ID).with_step_delay(Duration::from_millis(20));
+    manager.register_model(MODEL_ID, Arc::new(model)).await;
+    ChatCompletionService::new(manager)
+}
+
+fn request_body() -> Value {
+    json!({
+        "model": MODEL_ID,
+        "messages": [{"role": "user", "content": "hi"}],
+        "temperature": 0.5,
+        "max_tokens": 1000,
+        "stream": false
+    })
+}
+
+#[actix_web::test]
+async fn test_timeout_returns_partial_completion() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(chat_service().await))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .insert_header((MAX_DURATION_HEADER, "100"))
+        .set_json(request_body())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["x_timeout"], true);
+    assert_eq!(body["choices"][0]["finish_reason"], "length");
+    let content = body["choices"][0]["message"]["content"].as_str().unwrap();
+    assert!(content.starts_with("hi"));
+    let completion_tokens = body["usage"]["completion_tokens"].as_u64().unwrap();
+    assert!(completion_tokens > 0 && completion_tokens < 1000);
+}
+
+#[actix_web::test]
+async fn test_invalid_max_duration_header_is_rejected() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(chat_service().await))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .insert_header((MAX_DURATION_HEADER, "soon"))
+        .set_json(request_body())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error_code"], "invalid_parameter");
+    assert!(body["message"].as_str().unwrap().contains(MAX_DURATION_HEADER));
+}
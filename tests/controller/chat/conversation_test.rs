@@ -0,0 +1,174 @@
+#[path = "../../common/mod.rs"]
+mod common;
+
+use actix_web::{test, web, App};
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use coder_openapi::controller::chat::chat_completion::{chat_completion, delete_conversation};
+use coder_openapi::error::AppError;
+use coder_openapi::service::chat::chat_completion::{ChatCompletionParams, ChatCompletionService};
+use coder_openapi::service::models::{
+    CompletionModel, FinishReason, GenerationOutput, ModelManager,
+};
+use common::word_level_tokenizer;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use tokenizers::Tokenizer;
+
+const MODEL_ID: &str = "recording-model";
+
+/// 记录收到的提示词并固定回复 `pong` 的模型
+struct RecordingModel {
+    tokenizer: Tokenizer,
+    prompts: Mutex<Vec<String>>,
+}
+
+#[async_trait]
+impl CompletionModel for RecordingModel {
+    fn model_id(&self) -> &str {
+        MODEL_ID
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    fn forward_logits(&self, _input_ids: &[u32]) -> Result<Tensor, AppError> {
+        Ok(Tensor::new(&[1f32, 0.0], &Device::Cpu)?)
+    }
+
+    async fn generate(
+        &self,
+        prompt: &str,
+        _params: &ChatCompletionParams,
+    ) -> Result<GenerationOutput, AppError> {
+        self.prompts.lock().unwrap().push(prompt.to_string());
+        Ok(GenerationOutput {
+            text: "pong".to_string(),
+            token_ids: vec![0],
+            prompt_tokens: 0,
+            finish_reason: FinishReason::Stop,
+            timed_out: false,
+            cancelled: false,
+            clamped_max_tokens: None,
+            system_fingerprint: None,
+            sampling: None,
+        })
+    }
+}
+
+async fn service() -> (web::Data<ChatCompletionService>, Arc<RecordingModel>) {
+    let model = Arc::new(RecordingModel {
+        tokenizer: word_level_tokenizer(&["<eos>", "<unk>"]),
+        prompts: Mutex::new(Vec::new()),
+    });
+    let manager = ModelManager::new();
+    manager.register_model(MODEL_ID, model.clone()).await;
+    (web::Data::new(ChatCompletionService::new(manager)), model)
+}
+
+fn request_body(content: &str, conversation_id: Option<&str>) -> serde_json::Value {
+    let mut body = json!({
+        "model": MODEL_ID,
+        "messages": [{"role": "user", "content": content}]
+    });
+    if let Some(conversation_id) = conversation_id {
+        body["conversation_id"] = json!(conversation_id);
+    }
+    body
+}
+
+#[actix_web::test]
+async fn test_second_request_sees_prior_turn() {
+    let (service, model) = service().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(service.clone())
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    for content in ["ping", "again"] {
+        let req = test::TestRequest::post()
+            .uri("/v1/chat/completions")
+            .set_json(request_body(content, Some("conv-1")))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+    }
+
+    let prompts = model.prompts.lock().unwrap();
+    assert_eq!(prompts[0], "user: ping");
+    assert_eq!(prompts[1], "user: ping\nassistant: pong\nuser: again");
+    assert_eq!(service.conversations().history("conv-1").len(), 4);
+}
+
+#[actix_web::test]
+async fn test_stateless_request_not_stored() {
+    let (service, model) = service().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(service.clone())
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    for content in ["ping", "again"] {
+        let req = test::TestRequest::post()
+            .uri("/v1/chat/completions")
+            .set_json(request_body(content, None))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+    }
+
+    assert_eq!(model.prompts.lock().unwrap()[1], "user: again");
+    assert!(service.conversations().is_empty());
+}
+
+#[actix_web::test]
+async fn test_delete_conversation() {
+    let (service, _model) = service().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(service.clone())
+            .route("/v1/chat/completions", web::post().to(chat_completion))
+            .route("/v1/conversations/{conversation_id}", web::delete().to(delete_conversation)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .set_json(request_body("ping", Some("conv-2")))
+        .to_request();
+    test::call_service(&app, req).await;
+
+    let delete = || test::TestRequest::delete().uri("/v1/conversations/conv-2").to_request();
+    assert_eq!(test::call_service(&app, delete()).await.status(), 204);
+    assert!(service.conversations().is_empty());
+    assert_eq!(test::call_service(&app, delete()).await.status(), 404);
+}
+
+#[actix_web::test]
+async fn test_conversation_with_stream_is_rejected() {
+    let (service, _model) = service().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(service.clone())
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let mut body = request_body("ping", Some("conv-3"));
+    body["stream"] = json!(true);
+    let req = test::TestRequest::post().uri("/v1/chat/completions").set_json(body).to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["error_code"], "invalid_parameter");
+    assert!(body["message"].as_str().unwrap().contains("conversation_id is not supported"));
+    assert!(service.conversations().is_empty());
+}
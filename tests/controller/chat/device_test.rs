@@ -0,0 +1,148 @@
+#[path = "../../common/mod.rs"]
+mod common;
+
+use actix_web::{test, web, App};
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use coder_openapi::controller::chat::chat_completion::{chat_completion, DEVICE_HEADER};
+use coder_openapi::error::AppError;
+use coder_openapi::middleware::authentication::Authentication;
+use coder_openapi::service::chat::chat_completion::ChatCompletionService;
+use coder_openapi::service::models::{CompletionModel, ModelManager};
+use common::word_level_tokenizer;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokenizers::Tokenizer;
+
+const MODEL_ID: &str = "pinnable-model";
+const VOCAB: [&str; 7] = ["<eos>", "user", ":", "hi", "plain", "pinned", "<unk>"];
+const API_KEY: &str = "device-key";
+
+/// 默认总是生成 `plain`，`to_device` 返回的副本总是生成 `pinned`
+struct PinnableModel {
+    tokenizer: Tokenizer,
+    pinned: bool,
+    replicas: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl CompletionModel for PinnableModel {
+    fn model_id(&self) -> &str {
+        MODEL_ID
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    async fn to_device(&self, _device: &Device) -> Result<Arc<dyn CompletionModel>, AppError> {
+        self.replicas.fetch_add(1, Ordering::SeqCst);
+        Ok(Arc::new(PinnableModel {
+            tokenizer: self.tokenizer.clone(),
+            pinned: true,
+            replicas: self.replicas.clone(),
+        }))
+    }
+
+    fn forward_logits(&self, _input_ids: &[u32]) -> Result<Tensor, AppError> {
+        let logits = if self.pinned {
+            [0f32, 0.0, 0.0, 0.0, 0.0, 10.0, 0.0]
+        } else {
+            [0f32, 0.0, 0.0, 0.0, 10.0, 0.0, 0.0]
+        };
+        Ok(Tensor::new(&logits, &Device::Cpu)?)
+    }
+}
+
+/// 对话接口设为公开路径，`X-Device` 仍需要API key
+fn public_chat_auth() -> Authentication {
+    Authentication::new(vec!["/v1/chat".to_string()]).with_api_key(API_KEY)
+}
+
+async fn app_service(replicas: Arc<AtomicUsize>) -> web::Data<ChatCompletionService> {
+    let manager = ModelManager::new();
+    let model = PinnableModel { tokenizer: word_level_tokenizer(&VOCAB), pinned: false, replicas };
+    manager.register_model(MODEL_ID, Arc::new(model)).await;
+    web::Data::new(ChatCompletionService::new(manager))
+}
+
+fn request(device: Option<&str>, authorized: bool) -> test::TestRequest {
+    let mut req = test::TestRequest::post().uri("/v1/chat/completions").set_json(json!({
+        "model": MODEL_ID,
+        "messages": [{"role": "user", "content": "hi"}],
+        "temperature": 0.0,
+        "max_tokens": 1
+    }));
+    if let Some(device) = device {
+        req = req.insert_header((DEVICE_HEADER, device));
+    }
+    if authorized {
+        req = req.insert_header(("Authorization", format!("Bearer {}", API_KEY)));
+    }
+    req
+}
+
+#[actix_web::test]
+async fn test_device_header_routes_inference_to_a_replica() {
+    let replicas = Arc::new(AtomicUsize::new(0));
+    let app = test::init_service(
+        App::new()
+            .wrap(public_chat_auth())
+            .app_data(app_service(replicas.clone()).await)
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    for _ in 0..2 {
+        let resp = test::call_service(&app, request(Some("cpu"), true).to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["choices"][0]["message"]["content"], "pinned");
+    }
+    // 同一设备上的副本只创建一次
+    assert_eq!(replicas.load(Ordering::SeqCst), 1);
+
+    let resp = test::call_service(&app, request(None, false).to_request()).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["choices"][0]["message"]["content"], "plain");
+}
+
+#[actix_web::test]
+async fn test_invalid_device_is_rejected() {
+    let replicas = Arc::new(AtomicUsize::new(0));
+    let app = test::init_service(
+        App::new()
+            .wrap(public_chat_auth())
+            .app_data(app_service(replicas.clone()).await)
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    for device in ["gpu:1", "auto", "cuda:99"] {
+        let resp = test::call_service(&app, request(Some(device), true).to_request()).await;
+        assert_eq!(resp.status(), 400, "{}", device);
+    }
+    assert_eq!(replicas.load(Ordering::SeqCst), 0);
+}
+
+#[actix_web::test]
+async fn test_device_header_requires_api_key() {
+    let app = test::init_service(
+        App::new()
+            .wrap(public_chat_auth())
+            .app_data(app_service(Arc::new(AtomicUsize::new(0))).await)
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let resp = test::call_service(&app, request(Some("cpu"), false).to_request()).await;
+    assert_eq!(resp.status(), 401);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error_code"], "unauthorized");
+}
@@ -0,0 +1,223 @@
+use actix_web::{test, web, App};
+use coder_openapi::controller::chat::chat_completion::{chat_completion, LEAN_RESPONSE_HEADER};
+use coder_openapi::service::chat::chat_completion::ChatCompletionService;
+use coder_openapi::service::models::echo::ECHO_MODEL_ID;
+use coder_openapi::service::models::ModelManager;
+use serde_json::{json, Value};
+
+const CONTENT: &str = "fn main() {\n    println!(\"hi\");\n}";
+
+async fn post(body: Value) -> actix_web::dev::ServiceResponse {
+    let service = ChatCompletionService::new(ModelManager::new());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+    let req = test::TestRequest::post().uri("/v1/chat/completions").set_json(body).to_request();
+    test::call_service(&app, req).await
+}
+
+fn messages() -> Value {
+    json!([
+        {"role": "system", "content": "You are a helpful assistant"},
+        {"role": "user", "content": CONTENT}
+    ])
+}
+
+#[actix_web::test]
+async fn test_echo_model_returns_last_user_message() {
+    let resp = post(json!({"model": ECHO_MODEL_ID, "messages": messages()})).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+
+    assert_eq!(body["model"], ECHO_MODEL_ID);
+    assert_eq!(body["choices"][0]["message"]["content"], CONTENT);
+    assert_eq!(body["choices"][0]["finish_reason"], "stop");
+    // 每个空白分隔的词计为一个token
+    assert_eq!(body["usage"]["prompt_tokens"], 5);
+    assert_eq!(body["usage"]["completion_tokens"], 5);
+    assert_eq!(body["usage"]["total_tokens"], 10);
+}
+
+#[actix_web::test]
+async fn test_echo_option_prepends_prompt() {
+    let resp = post(json!({"model": ECHO_MODEL_ID, "messages": messages(), "echo": true})).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+
+    // 回显模型的提示词即最后一条user消息，回复为提示词加上生成的文本
+    assert_eq!(body["choices"][0]["message"]["content"], format!("{}{}", CONTENT, CONTENT));
+    // 回显的提示词不计入completion_tokens
+    assert_eq!(body["usage"]["completion_tokens"], 5);
+}
+
+#[actix_web::test]
+async fn test_lean_response_omits_usage() {
+    let service = ChatCompletionService::new(ModelManager::new());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .insert_header((LEAN_RESPONSE_HEADER, "true"))
+        .set_json(json!({"model": ECHO_MODEL_ID, "messages": messages()}))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(body["choices"][0]["message"]["content"], CONTENT);
+    assert!(body.get("usage").is_none());
+    assert!(body.get("created").is_none());
+    assert!(body.get("object").is_none());
+
+    let body: Value =
+        test::read_body_json(post(json!({"model": ECHO_MODEL_ID, "messages": messages()})).await)
+            .await;
+    assert!(body.get("usage").is_some());
+    assert_eq!(body["object"], "chat.completion");
+}
+
+#[actix_web::test]
+async fn test_invalid_lean_response_header_is_rejected() {
+    let service = ChatCompletionService::new(ModelManager::new());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .insert_header((LEAN_RESPONSE_HEADER, "yes"))
+        .set_json(json!({"model": ECHO_MODEL_ID, "messages": messages()}))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error_code"], "invalid_parameter");
+    assert!(body["message"].as_str().unwrap().contains(LEAN_RESPONSE_HEADER));
+}
+
+#[actix_web::test]
+async fn test_echo_model_streams_token_by_token() {
+    let resp = post(json!({"model": ECHO_MODEL_ID, "messages": messages(), "stream": true})).await;
+    assert_eq!(resp.status(), 200);
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+
+    let deltas: Vec<String> = body
+        .split("\n\n")
+        .filter_map(|frame| frame.strip_prefix("data: "))
+        .filter(|data| *data != "[DONE]")
+        .map(|data| serde_json::from_str::<Value>(data).unwrap())
+        .filter_map(|chunk| chunk["choices"][0]["delta"]["content"].as_str().map(String::from))
+        .collect();
+    assert_eq!(deltas, ["fn", " main()", " {", "\n    println!(\"hi\");", "\n}"]);
+}
+
+#[actix_web::test]
+async fn test_stream_final_frame_carries_length_finish_reason() {
+    let resp = post(json!({
+        "model": ECHO_MODEL_ID,
+        "messages": messages(),
+        "stream": true,
+        "max_tokens": 2
+    }))
+    .await;
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+
+    let frames: Vec<&str> =
+        body.split("\n\n").filter_map(|frame| frame.strip_prefix("data: ")).collect();
+    assert_eq!(frames.last(), Some(&"[DONE]"));
+    // [DONE]之前的最后一帧只携带finish_reason，不再携带内容
+    let last: Value = serde_json::from_str(frames[frames.len() - 2]).unwrap();
+    assert_eq!(last["choices"][0]["finish_reason"], "length");
+    assert_eq!(last["choices"][0]["delta"], json!({}));
+    let content_frames = frames.iter().filter(|frame| frame.contains("\"content\"")).count();
+    assert_eq!(content_frames, 2);
+}
+
+#[actix_web::test]
+async fn test_stream_with_n_interleaves_choices_by_index() {
+    let resp = post(json!({
+        "model": ECHO_MODEL_ID,
+        "messages": messages(),
+        "stream": true,
+        "n": 2
+    }))
+    .await;
+    assert_eq!(resp.status(), 200);
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+
+    let chunks: Vec<Value> = body
+        .split("\n\n")
+        .filter_map(|frame| frame.strip_prefix("data: "))
+        .filter(|data| *data != "[DONE]")
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+    for index in [0, 1] {
+        let choice_chunks: Vec<&Value> =
+            chunks.iter().map(|c| &c["choices"][0]).filter(|c| c["index"] == index).collect();
+        let content: String =
+            choice_chunks.iter().filter_map(|c| c["delta"]["content"].as_str()).collect();
+        assert_eq!(content, CONTENT, "choice {}", index);
+        assert_eq!(choice_chunks[0]["delta"]["role"], "assistant");
+        assert_eq!(choice_chunks.last().unwrap()["finish_reason"], "stop");
+    }
+    assert!(body.ends_with("data: [DONE]\n\n"));
+}
+
+#[actix_web::test]
+async fn test_max_completion_tokens_limits_completion() {
+    let resp = post(json!({
+        "model": ECHO_MODEL_ID,
+        "messages": messages(),
+        "max_completion_tokens": 2
+    }))
+    .await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+
+    assert_eq!(body["choices"][0]["message"]["content"], "fn main()");
+    assert_eq!(body["choices"][0]["finish_reason"], "length");
+    assert_eq!(body["usage"]["completion_tokens"], 2);
+}
+
+#[actix_web::test]
+async fn test_max_completion_tokens_takes_precedence_over_max_tokens() {
+    let resp = post(json!({
+        "model": ECHO_MODEL_ID,
+        "messages": messages(),
+        "max_tokens": 4,
+        "max_completion_tokens": 1
+    }))
+    .await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+
+    assert_eq!(body["choices"][0]["message"]["content"], "fn");
+    assert_eq!(body["usage"]["completion_tokens"], 1);
+}
+
+#[actix_web::test]
+async fn test_responses_from_same_model_share_system_fingerprint() {
+    let first: Value =
+        test::read_body_json(post(json!({"model": ECHO_MODEL_ID, "messages": messages()})).await)
+            .await;
+    let second: Value = test::read_body_json(
+        post(json!({
+            "model": ECHO_MODEL_ID,
+            "messages": [{"role": "user", "content": "something else"}]
+        }))
+        .await,
+    )
+    .await;
+
+    let fingerprint = first["system_fingerprint"].as_str().unwrap();
+    assert!(fingerprint.starts_with("fp_"));
+    assert_eq!(second["system_fingerprint"], fingerprint);
+}
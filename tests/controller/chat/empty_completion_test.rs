@@ -0,0 +1,68 @@
+#[path = "../../common/mod.rs"]
+mod common;
+
+use actix_web::{test, web, App};
+use coder_openapi::controller::chat::chat_completion::chat_completion;
+use coder_openapi::service::chat::chat_completion::ChatCompletionService;
+use coder_openapi::service::models::ModelManager;
+use common::FakeModel;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+const MODEL_ID: &str = "yi-coder";
+
+async fn post(stream: bool) -> actix_web::dev::ServiceResponse {
+    let manager = ModelManager::new();
+    // 第一个token就生成EOS
+    let model = FakeModel::fixed_reply(MODEL_ID, "<eos>");
+    manager.register_model(MODEL_ID, Arc::new(model)).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(ChatCompletionService::new(manager)))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .set_json(json!({
+            "model": MODEL_ID,
+            "messages": [{"role": "user", "content": "ping"}],
+            "temperature": 0.0,
+            "stream": stream
+        }))
+        .to_request();
+    test::call_service(&app, req).await
+}
+
+#[actix_web::test]
+async fn test_eos_as_first_token_returns_empty_choice() {
+    let resp = post(false).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+
+    assert_eq!(body["choices"][0]["message"]["role"], "assistant");
+    assert_eq!(body["choices"][0]["message"]["content"], "");
+    assert_eq!(body["choices"][0]["finish_reason"], "stop");
+    assert_eq!(body["usage"]["completion_tokens"], 0);
+}
+
+#[actix_web::test]
+async fn test_eos_as_first_token_streams_empty_choice() {
+    let resp = post(true).await;
+    assert_eq!(resp.status(), 200);
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+
+    let events: Vec<Value> = body
+        .split("\n\n")
+        .filter_map(|frame| frame.strip_prefix("data: "))
+        .filter(|data| *data != "[DONE]")
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+    assert_eq!(events.len(), 1, "{}", body);
+    let choice = &events[0]["choices"][0];
+    assert_eq!(choice["delta"]["role"], "assistant");
+    assert_eq!(choice["delta"]["content"], "");
+    assert_eq!(choice["finish_reason"], "stop");
+    assert!(body.ends_with("data: [DONE]\n\n"));
+}
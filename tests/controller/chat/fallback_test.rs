@@ -0,0 +1,84 @@
+#[path = "../../common/mod.rs"]
+mod common;
+
+use actix_web::{test, web, App};
+use coder_openapi::controller::chat::chat_completion::chat_completion;
+use coder_openapi::error::AppError;
+use coder_openapi::service::chat::chat_completion::ChatCompletionService;
+use coder_openapi::service::models::ModelManager;
+use common::FakeModel;
+use serde_json::json;
+use std::sync::Arc;
+
+const FALLBACK_MODEL: &str = "fallback-model";
+const MISSING_MODEL: &str = "missing-model";
+
+async fn manager() -> ModelManager {
+    let manager = ModelManager::new();
+    let model = Arc::new(FakeModel::fixed_reply(FALLBACK_MODEL, "<eos>"));
+    manager.register_model(FALLBACK_MODEL, model).await;
+    manager
+}
+
+fn request_body(model: &str) -> serde_json::Value {
+    json!({
+        "model": model,
+        "messages": [{"role": "user", "content": "hi"}]
+    })
+}
+
+#[actix_web::test]
+async fn test_unavailable_model_uses_fallback() {
+    let service =
+        ChatCompletionService::new(manager().await).with_fallback(MISSING_MODEL, FALLBACK_MODEL);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .set_json(request_body(MISSING_MODEL))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["x_fallback_model"], FALLBACK_MODEL);
+}
+
+#[actix_web::test]
+async fn test_available_model_does_not_report_fallback() {
+    let service =
+        ChatCompletionService::new(manager().await).with_fallback(FALLBACK_MODEL, MISSING_MODEL);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .set_json(request_body(FALLBACK_MODEL))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert!(body.get("x_fallback_model").is_none());
+}
+
+#[tokio::test]
+async fn test_fallback_is_not_chained() {
+    let service = ChatCompletionService::new(manager().await)
+        .with_fallback(MISSING_MODEL, "other-missing-model")
+        .with_fallback("other-missing-model", FALLBACK_MODEL);
+    let messages = serde_json::from_value(request_body(MISSING_MODEL)["messages"].clone()).unwrap();
+
+    let result = service.complete_with_fallback(MISSING_MODEL, messages, Default::default()).await;
+
+    assert!(matches!(result, Err(AppError::InvalidModel(_))));
+}
@@ -0,0 +1,91 @@
+use actix_web::{test, web, App};
+use coder_openapi::controller::chat::chat_completion::chat_completion;
+use coder_openapi::controller::json::json_config;
+use coder_openapi::service::chat::chat_completion::ChatCompletionService;
+use coder_openapi::service::models::ModelManager;
+use serde_json::json;
+
+#[actix_web::test]
+async fn test_invalid_field_type_names_field() {
+    let app = test::init_service(
+        App::new()
+            .app_data(json_config())
+            .app_data(web::Data::new(ChatCompletionService::new(ModelManager::new())))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .set_json(json!({
+            "model": "yi-coder",
+            "messages": [{"role": "user", "content": "hi"}],
+            "temperature": "hot"
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], 400);
+    let message = body["message"].as_str().unwrap();
+    assert!(message.contains("temperature"), "unexpected message: {}", message);
+    assert!(message.contains("f32"), "unexpected message: {}", message);
+}
+
+#[actix_web::test]
+async fn test_nested_field_path_reported() {
+    let app = test::init_service(
+        App::new()
+            .app_data(json_config())
+            .app_data(web::Data::new(ChatCompletionService::new(ModelManager::new())))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .set_json(json!({
+            "model": "yi-coder",
+            "messages": [{"role": "user", "content": 1}]
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert!(body["message"].as_str().unwrap().contains("messages[0].content"));
+}
+
+#[actix_web::test]
+async fn test_non_json_content_type_is_rejected() {
+    let app = test::init_service(
+        App::new()
+            .app_data(json_config())
+            .app_data(web::Data::new(ChatCompletionService::new(ModelManager::new())))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let body = json!({"model": "echo", "messages": [{"role": "user", "content": "hi"}]});
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .insert_header(("Content-Type", "text/plain"))
+        .set_payload(body.to_string())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 415);
+    let error: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(error["code"], 415);
+    assert_eq!(error["error_code"], "unsupported_media_type");
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .insert_header(("Content-Type", "application/json; charset=utf-8"))
+        .set_payload(body.to_string())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 200);
+}
@@ -0,0 +1,195 @@
+#[path = "../../common/mod.rs"]
+mod common;
+
+use actix_web::{test, web, App};
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use coder_openapi::controller::chat::chat_completion::{chat_completion, RETURN_PARAMS_HEADER};
+use coder_openapi::error::AppError;
+use coder_openapi::service::chat::chat_completion::ChatCompletionService;
+use coder_openapi::service::models::completion_model::MaxOutputTokensPolicy;
+use coder_openapi::service::models::{CompletionModel, ModelManager};
+use common::word_level_tokenizer;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokenizers::Tokenizer;
+
+const MODEL_ID: &str = "yi-coder";
+const MAX_OUTPUT_TOKENS: usize = 3;
+
+/// 一直输出 `pong`、从不结束的模型
+struct PongModel {
+    tokenizer: Tokenizer,
+    policy: MaxOutputTokensPolicy,
+}
+
+#[async_trait]
+impl CompletionModel for PongModel {
+    fn model_id(&self) -> &str {
+        MODEL_ID
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    fn max_output_tokens(&self) -> Option<usize> {
+        Some(MAX_OUTPUT_TOKENS)
+    }
+
+    fn max_output_tokens_policy(&self) -> MaxOutputTokensPolicy {
+        self.policy
+    }
+
+    fn forward_logits(&self, _input_ids: &[u32]) -> Result<Tensor, AppError> {
+        Ok(Tensor::new(&[0f32, 0.0, 10.0], &Device::Cpu)?)
+    }
+}
+
+async fn post(policy: MaxOutputTokensPolicy, max_tokens: usize) -> actix_web::dev::ServiceResponse {
+    post_with_cap(policy, max_tokens, None).await
+}
+
+async fn post_with_cap(
+    policy: MaxOutputTokensPolicy,
+    max_tokens: usize,
+    absolute_max_tokens: Option<usize>,
+) -> actix_web::dev::ServiceResponse {
+    send(policy, max_tokens, absolute_max_tokens, false).await
+}
+
+async fn send(
+    policy: MaxOutputTokensPolicy,
+    max_tokens: usize,
+    absolute_max_tokens: Option<usize>,
+    return_params: bool,
+) -> actix_web::dev::ServiceResponse {
+    let body = json!({
+        "model": MODEL_ID,
+        "messages": [{"role": "user", "content": "ping"}],
+        "temperature": 0.0,
+        "max_tokens": max_tokens
+    });
+    send_body(policy, body, absolute_max_tokens, return_params).await
+}
+
+async fn send_body(
+    policy: MaxOutputTokensPolicy,
+    body: Value,
+    absolute_max_tokens: Option<usize>,
+    return_params: bool,
+) -> actix_web::dev::ServiceResponse {
+    let manager = ModelManager::new();
+    let model = PongModel { tokenizer: word_level_tokenizer(&["<eos>", "<unk>", "pong"]), policy };
+    manager.register_model(MODEL_ID, Arc::new(model)).await;
+    let mut service = ChatCompletionService::new(manager);
+    if let Some(cap) = absolute_max_tokens {
+        service = service.with_absolute_max_tokens(cap);
+    }
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let mut req = test::TestRequest::post().uri("/v1/chat/completions").set_json(body);
+    if return_params {
+        req = req.insert_header((RETURN_PARAMS_HEADER, "true"));
+    }
+    test::call_service(&app, req.to_request()).await
+}
+
+#[actix_web::test]
+async fn test_max_tokens_above_ceiling_is_clamped() {
+    let resp = post(MaxOutputTokensPolicy::Clamp, 10).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+
+    assert_eq!(body["usage"]["completion_tokens"], MAX_OUTPUT_TOKENS);
+    assert_eq!(body["choices"][0]["finish_reason"], "length");
+    assert_eq!(body["x_max_tokens"], MAX_OUTPUT_TOKENS);
+}
+
+#[actix_web::test]
+async fn test_max_tokens_within_ceiling_is_not_reported() {
+    let resp = post(MaxOutputTokensPolicy::Clamp, 2).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+
+    assert_eq!(body["usage"]["completion_tokens"], 2);
+    assert!(body.get("x_max_tokens").is_none());
+}
+
+#[actix_web::test]
+async fn test_max_tokens_above_ceiling_is_rejected() {
+    let resp = post(MaxOutputTokensPolicy::Reject, 10).await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[actix_web::test]
+async fn test_absolute_cap_clamps_regardless_of_policy() {
+    // 绝对上限低于模型上限，即使模型的策略为reject也降低到绝对上限
+    let resp = post_with_cap(MaxOutputTokensPolicy::Reject, 10, Some(2)).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+
+    assert_eq!(body["usage"]["completion_tokens"], 2);
+    assert_eq!(body["choices"][0]["finish_reason"], "length");
+    assert_eq!(body["x_max_tokens"], 2);
+}
+
+#[actix_web::test]
+async fn test_absolute_cap_above_ceiling_keeps_model_ceiling() {
+    let resp = post_with_cap(MaxOutputTokensPolicy::Clamp, 10, Some(100)).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+
+    assert_eq!(body["usage"]["completion_tokens"], MAX_OUTPUT_TOKENS);
+    assert_eq!(body["x_max_tokens"], MAX_OUTPUT_TOKENS);
+}
+
+#[actix_web::test]
+async fn test_returned_params_report_clamped_max_tokens() {
+    let resp = send(MaxOutputTokensPolicy::Reject, 10, Some(2), true).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+
+    // 请求的max_tokens为10，返回实际使用的2；未设置的参数返回默认值
+    let params = &body["x_params"];
+    assert_eq!(params["max_tokens"], 2);
+    assert_eq!(params["temperature"], Value::Null);
+    assert_eq!(params["presence_penalty"], 0.0);
+}
+
+#[actix_web::test]
+async fn test_returned_params_report_clamped_sampling_params() {
+    let body = json!({
+        "model": MODEL_ID,
+        "messages": [{"role": "user", "content": "ping"}],
+        "temperature": 5.0,
+        "top_p": 1.5,
+        "max_tokens": 2
+    });
+    let resp = send_body(MaxOutputTokensPolicy::Clamp, body, None, true).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+
+    // 超出范围的temperature与top_p按截断后的值采样并返回
+    let params = &body["x_params"];
+    assert_eq!(params["temperature"], 2.0);
+    assert_eq!(params["top_p"], 1.0);
+}
+
+#[actix_web::test]
+async fn test_params_are_not_returned_by_default() {
+    let resp = post_with_cap(MaxOutputTokensPolicy::Clamp, 10, Some(2)).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+
+    assert!(body.get("x_params").is_none());
+}
@@ -0,0 +1,71 @@
+use actix_web::{test, web, App};
+use coder_openapi::controller::chat::chat_completion::chat_completion;
+use coder_openapi::service::chat::chat_completion::{ChatCompletionParams, ChatCompletionService};
+use coder_openapi::service::models::mock::{MockModel, MOCK_MODEL_ENV, MOCK_MODEL_ID};
+use coder_openapi::service::models::{CompletionModel, ModelManager};
+use serde_json::{json, Value};
+
+fn mock_service() -> ChatCompletionService {
+    std::env::set_var(MOCK_MODEL_ENV, "1");
+    ChatCompletionService::new(ModelManager::new())
+}
+
+async fn complete(service: web::Data<ChatCompletionService>) -> Value {
+    let app = test::init_service(
+        App::new().app_data(service).route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .set_json(json!({
+            "model": MOCK_MODEL_ID,
+            "messages": [{"role": "user", "content": "hello world"}],
+            "temperature": 0.0,
+            "max_tokens": 8
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    test::read_body_json(resp).await
+}
+
+#[actix_web::test]
+async fn test_mock_model_completes_end_to_end_deterministically() {
+    let first = complete(web::Data::new(mock_service())).await;
+    let second = complete(web::Data::new(mock_service())).await;
+
+    let content = first["choices"][0]["message"]["content"].as_str().unwrap();
+    assert_eq!(content, second["choices"][0]["message"]["content"].as_str().unwrap());
+    assert_eq!(first["model"], MOCK_MODEL_ID);
+
+    // 与直接调用模拟模型的结果一致
+    let params =
+        ChatCompletionParams { temperature: Some(0.0), max_tokens: Some(8), ..Default::default() };
+    let output = MockModel::new(0).unwrap().generate("user: hello world", &params).await.unwrap();
+    assert_eq!(content, output.text);
+    assert_eq!(first["usage"]["completion_tokens"], output.completion_tokens());
+}
+
+#[tokio::test]
+async fn test_mock_model_seed_changes_logits() {
+    let input_ids = [2, 5, 19, 20];
+    let a =
+        MockModel::new(1).unwrap().forward_logits(&input_ids).unwrap().to_vec1::<f32>().unwrap();
+    let b =
+        MockModel::new(1).unwrap().forward_logits(&input_ids).unwrap().to_vec1::<f32>().unwrap();
+    let c =
+        MockModel::new(2).unwrap().forward_logits(&input_ids).unwrap().to_vec1::<f32>().unwrap();
+
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+}
+
+#[actix_web::test]
+async fn test_created_is_unix_timestamp() {
+    let before = chrono::Utc::now().timestamp();
+    let body = complete(web::Data::new(mock_service())).await;
+    let after = chrono::Utc::now().timestamp();
+
+    let created = body["created"].as_i64().expect("created should be an integer");
+    assert!(before <= created && created <= after);
+}
@@ -0,0 +1,122 @@
+#[path = "../../common/mod.rs"]
+mod common;
+
+use actix_web::{test, web, App};
+use coder_openapi::controller::chat::chat_completion::chat_completion;
+use coder_openapi::controller::models::routes;
+use coder_openapi::entities::object;
+use coder_openapi::service::chat::chat_completion::ChatCompletionService;
+use coder_openapi::service::models::ModelManager;
+use common::FakeModel;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+const MODEL_ID: &str = "yi-coder";
+const ALIAS: &str = "gpt-3.5-turbo";
+
+async fn manager() -> ModelManager {
+    let manager = ModelManager::new().with_alias(ALIAS, MODEL_ID);
+    let model = FakeModel::fixed_reply(MODEL_ID, "pong");
+    manager.register_model(MODEL_ID, Arc::new(model)).await;
+    manager
+}
+
+fn request_body(model: &str) -> Value {
+    json!({
+        "model": model,
+        "messages": [{"role": "user", "content": "ping"}],
+        "temperature": 0.0,
+        "max_tokens": 4
+    })
+}
+
+#[actix_web::test]
+async fn test_alias_routes_to_underlying_model() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(ChatCompletionService::new(manager().await)))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .set_json(request_body(ALIAS))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["model"], MODEL_ID);
+    assert_eq!(body["object"], object::CHAT_COMPLETION);
+    assert_eq!(body["choices"][0]["message"]["content"], "pong");
+}
+
+#[actix_web::test]
+async fn test_alias_stream_reports_canonical_model() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(ChatCompletionService::new(manager().await)))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let mut body = request_body(ALIAS);
+    body["stream"] = json!(true);
+    let req = test::TestRequest::post().uri("/v1/chat/completions").set_json(body).to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    let body = String::from_utf8(body.to_vec()).unwrap();
+
+    let chunks: Vec<Value> = body
+        .split("\n\n")
+        .filter_map(|frame| frame.strip_prefix("data: "))
+        .filter(|data| *data != "[DONE]")
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+    assert!(!chunks.is_empty());
+    for chunk in &chunks {
+        assert_eq!(chunk["model"], MODEL_ID);
+        assert_eq!(chunk["object"], object::CHAT_COMPLETION_CHUNK);
+    }
+}
+
+#[actix_web::test]
+async fn test_unknown_model_name_is_still_rejected() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(ChatCompletionService::new(manager().await)))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .set_json(request_body("gpt-4"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 400);
+}
+
+#[actix_web::test]
+async fn test_models_endpoints_resolve_aliases() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(manager().await))
+            .service(web::scope("/models").configure(routes)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/models").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let model = body["models"].as_array().unwrap().iter().find(|m| m["id"] == MODEL_ID).unwrap();
+    assert_eq!(model["aliases"], json!([ALIAS]));
+
+    let req = test::TestRequest::get().uri(&format!("/models/{}/files", ALIAS)).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["model_id"], MODEL_ID);
+
+    let req = test::TestRequest::get().uri("/models/gpt-4/files").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 404);
+}
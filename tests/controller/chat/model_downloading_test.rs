@@ -0,0 +1,128 @@
+use actix_web::http::header;
+use actix_web::{test, web, App};
+use coder_openapi::controller::chat::chat::chat_completions;
+use coder_openapi::controller::chat::chat_completion::chat_completion;
+use coder_openapi::service::chat::chat_completion::ChatCompletionService;
+use coder_openapi::service::chat::ChatService;
+use coder_openapi::service::models::echo::EchoModel;
+use coder_openapi::service::models::{CompletionModel, ModelLoaderFn, ModelManager};
+use coder_openapi::utils::config::get_config;
+use futures::future::join_all;
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn request_body() -> serde_json::Value {
+    json!({
+        "model": "yi-coder",
+        "messages": [{"role": "user", "content": "hi"}]
+    })
+}
+
+#[actix_web::test]
+async fn test_downloading_model_returns_503() {
+    let manager = ModelManager::new();
+    let _download = manager.begin_download("yi-coder");
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(ChatCompletionService::new(manager.clone())))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let req =
+        test::TestRequest::post().uri("/v1/chat/completions").set_json(request_body()).to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 503);
+    assert!(resp.headers().contains_key(header::RETRY_AFTER));
+    assert!(manager.get_model_status("yi-coder").await.unwrap().is_downloading);
+}
+
+#[actix_web::test]
+async fn test_legacy_endpoint_downloading_model_returns_503() {
+    let manager = ModelManager::new();
+    let _download = manager.begin_download("yi-coder");
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(manager.clone()))
+            .app_data(web::Data::new(ChatService::new()))
+            .service(chat_completions),
+    )
+    .await;
+
+    let req =
+        test::TestRequest::post().uri("/chat/completions").set_json(request_body()).to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 503);
+    assert!(resp.headers().contains_key(header::RETRY_AFTER));
+}
+
+#[actix_web::test]
+async fn test_download_guard_clears_status() {
+    let manager = ModelManager::new();
+    let download = manager.begin_download("deepseek-coder");
+    assert!(manager.is_downloading("deepseek-coder"));
+
+    drop(download);
+
+    assert!(!manager.is_downloading("deepseek-coder"));
+}
+
+/// 缓存目录中文件齐全的 `yi-coder`，返回缓存目录
+fn cached_yi_coder() -> std::path::PathBuf {
+    let cache_dir = std::env::temp_dir().join(format!("cached-model-{}", uuid::Uuid::new_v4()));
+    let model_config = get_config().models.get("yi-coder").unwrap();
+    let model_dir = cache_dir.join(&model_config.hf_hub_id);
+    std::fs::create_dir_all(&model_dir).unwrap();
+    for name in model_config.model_files.all() {
+        std::fs::write(model_dir.join(name), "{}").unwrap();
+    }
+    cache_dir
+}
+
+/// 记录调用次数、耗时一段时间才完成的模型构造函数
+fn slow_loader(counter: Arc<AtomicUsize>) -> ModelLoaderFn {
+    Arc::new(move || {
+        let counter = counter.clone();
+        Box::pin(async move {
+            counter.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let model: Arc<dyn CompletionModel> = Arc::new(EchoModel::new().unwrap());
+            Ok(model)
+        })
+    })
+}
+
+#[actix_web::test]
+async fn test_concurrent_first_requests_wait_for_cached_model_initialization() {
+    let cache_dir = cached_yi_coder();
+    let counter = Arc::new(AtomicUsize::new(0));
+    let manager = ModelManager::new()
+        .with_models_cache_dir(&cache_dir)
+        .with_model_loader("yi-coder", slow_loader(counter.clone()));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(ChatCompletionService::new(manager.clone())))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    // 文件已在缓存中，初始化期间到达的请求等待初始化完成而不是返回503
+    let requests = (0..4).map(|_| {
+        let req = test::TestRequest::post()
+            .uri("/v1/chat/completions")
+            .set_json(request_body())
+            .to_request();
+        test::call_service(&app, req)
+    });
+    let statuses: Vec<u16> =
+        join_all(requests).await.iter().map(|resp| resp.status().as_u16()).collect();
+    std::fs::remove_dir_all(&cache_dir).unwrap();
+
+    assert_eq!(statuses, vec![200; 4]);
+    assert_eq!(counter.load(Ordering::SeqCst), 1);
+    assert!(!manager.is_downloading("yi-coder"));
+}
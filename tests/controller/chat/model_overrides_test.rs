@@ -0,0 +1,155 @@
+#[path = "../../common/mod.rs"]
+mod common;
+
+use actix_web::{test, web, App};
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use coder_openapi::controller::chat::chat_completion::{chat_completion, MODEL_OVERRIDES_HEADER};
+use coder_openapi::error::AppError;
+use coder_openapi::middleware::authentication::Authentication;
+use coder_openapi::service::chat::chat_completion::ChatCompletionService;
+use coder_openapi::service::models::overrides::ModelOverrides;
+use coder_openapi::service::models::{CompletionModel, ModelManager};
+use common::word_level_tokenizer;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokenizers::Tokenizer;
+
+const MODEL_ID: &str = "tunable-model";
+const VOCAB: [&str; 7] = ["<eos>", "user", ":", "hi", "plain", "tweaked", "<unk>"];
+const API_KEY: &str = "overrides-key";
+
+/// 默认总是生成 `plain`，设置了 `hidden_clamp` 覆盖的副本总是生成 `tweaked`
+struct TunableModel {
+    tokenizer: Tokenizer,
+    overrides: Option<ModelOverrides>,
+}
+
+#[async_trait]
+impl CompletionModel for TunableModel {
+    fn model_id(&self) -> &str {
+        MODEL_ID
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    fn with_overrides(
+        &self,
+        overrides: &ModelOverrides,
+    ) -> Result<Arc<dyn CompletionModel>, AppError> {
+        Ok(Arc::new(TunableModel {
+            tokenizer: self.tokenizer.clone(),
+            overrides: Some(overrides.clone()),
+        }))
+    }
+
+    fn forward_logits(&self, _input_ids: &[u32]) -> Result<Tensor, AppError> {
+        let tweaked = self.overrides.as_ref().is_some_and(|o| o.hidden_clamp.is_some());
+        let logits = if tweaked {
+            [0f32, 0.0, 0.0, 0.0, 0.0, 10.0, 0.0]
+        } else {
+            [0f32, 0.0, 0.0, 0.0, 10.0, 0.0, 0.0]
+        };
+        Ok(Tensor::new(&logits, &Device::Cpu)?)
+    }
+}
+
+/// 对话接口设为公开路径，`X-Model-Overrides` 仍需要API key
+fn public_chat_auth() -> Authentication {
+    Authentication::new(vec!["/v1/chat".to_string()]).with_api_key(API_KEY)
+}
+
+async fn app_service() -> web::Data<ChatCompletionService> {
+    let manager = ModelManager::new();
+    let model = TunableModel { tokenizer: word_level_tokenizer(&VOCAB), overrides: None };
+    manager.register_model(MODEL_ID, Arc::new(model)).await;
+    web::Data::new(ChatCompletionService::new(manager))
+}
+
+fn body() -> Value {
+    json!({
+        "model": MODEL_ID,
+        "messages": [{"role": "user", "content": "hi"}],
+        "temperature": 0.0,
+        "max_tokens": 1
+    })
+}
+
+#[actix_web::test]
+async fn test_override_applies_to_one_request_only() {
+    let app = test::init_service(
+        App::new()
+            .wrap(public_chat_auth())
+            .app_data(app_service().await)
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .insert_header(("Authorization", format!("Bearer {}", API_KEY)))
+        .insert_header((MODEL_OVERRIDES_HEADER, r#"{"hidden_clamp": 1.0}"#))
+        .set_json(body())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let overridden: Value = test::read_body_json(resp).await;
+    assert_eq!(overridden["choices"][0]["message"]["content"], "tweaked");
+
+    let req = test::TestRequest::post().uri("/v1/chat/completions").set_json(body()).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let normal: Value = test::read_body_json(resp).await;
+    assert_eq!(normal["choices"][0]["message"]["content"], "plain");
+}
+
+#[actix_web::test]
+async fn test_override_requires_api_key() {
+    let app = test::init_service(
+        App::new()
+            .wrap(public_chat_auth())
+            .app_data(app_service().await)
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .insert_header((MODEL_OVERRIDES_HEADER, r#"{"hidden_clamp": 1.0}"#))
+        .set_json(body())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 401);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error_code"], "unauthorized");
+}
+
+#[actix_web::test]
+async fn test_field_outside_allowlist_is_rejected() {
+    let app = test::init_service(
+        App::new()
+            .wrap(public_chat_auth())
+            .app_data(app_service().await)
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .insert_header(("Authorization", format!("Bearer {}", API_KEY)))
+        .insert_header((MODEL_OVERRIDES_HEADER, r#"{"num_hidden_layers": 1}"#))
+        .set_json(body())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error_code"], "invalid_parameter");
+    let message = body["message"].as_str().unwrap();
+    assert!(message.contains("num_hidden_layers"), "{}", message);
+}
@@ -0,0 +1,83 @@
+use actix_web::{test, web, App};
+use coder_openapi::controller::chat::chat_completion::chat_completion;
+use coder_openapi::controller::json::json_config;
+use coder_openapi::service::chat::chat_completion::ChatCompletionService;
+use coder_openapi::service::models::ModelManager;
+use coder_openapi::utils::config::get_config;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+async fn post(messages: Value) -> (u16, String) {
+    let service = ChatCompletionService::new(ModelManager::new()).with_prompt_limits(3, 20);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .set_json(json!({ "model": "yi-coder", "messages": messages }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let status = resp.status().as_u16();
+    let body: Value = test::read_body_json(resp).await;
+    (status, body["message"].as_str().unwrap_or_default().to_string())
+}
+
+#[actix_web::test]
+async fn test_too_many_messages_is_rejected() {
+    let messages: Vec<Value> = (0..4).map(|_| json!({"role": "user", "content": "hi"})).collect();
+
+    let (status, message) = post(json!(messages)).await;
+
+    assert_eq!(status, 400);
+    assert!(message.contains("messages has 4 entries, exceeding the limit of 3"), "{}", message);
+}
+
+#[actix_web::test]
+async fn test_too_many_prompt_chars_is_rejected() {
+    let messages = json!([
+        {"role": "system", "content": "0123456789"},
+        {"role": "user", "content": "我想要十一个字符的问题呀"}
+    ]);
+
+    let (status, message) = post(messages).await;
+
+    assert_eq!(status, 400);
+    assert!(
+        message.contains("messages content has 22 characters, exceeding the limit of 20"),
+        "{}",
+        message
+    );
+}
+
+#[actix_web::test]
+async fn test_huge_message_array_is_rejected_while_parsing() {
+    let app = test::init_service(
+        App::new()
+            .app_data(json_config())
+            .app_data(web::Data::new(ChatCompletionService::new(ModelManager::new())))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+    let max_messages = get_config().chat.max_messages;
+    let messages: Vec<Value> =
+        (0..10_000).map(|_| json!({"role": "user", "content": "hi"})).collect();
+    assert!(messages.len() > max_messages);
+
+    let start = Instant::now();
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .set_json(json!({ "model": "yi-coder", "messages": messages }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 400);
+    assert!(start.elapsed() < Duration::from_secs(1), "took {:?}", start.elapsed());
+    let body: Value = test::read_body_json(resp).await;
+    let message = body["message"].as_str().unwrap();
+    let expected = format!("messages: 10000 entries exceeds the limit of {}", max_messages);
+    assert!(message.contains(&expected), "{}", message);
+}
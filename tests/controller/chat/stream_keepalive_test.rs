@@ -0,0 +1,131 @@
+#[path = "../../common/mod.rs"]
+mod common;
+
+use actix_web::{test, web, App};
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use coder_openapi::controller::chat::chat_completion::chat_completion;
+use coder_openapi::controller::chat::sse::{DONE_FRAME, KEEPALIVE_FRAME};
+use coder_openapi::error::AppError;
+use coder_openapi::service::chat::chat_completion::{ChatCompletionParams, ChatCompletionService};
+use coder_openapi::service::models::{
+    CompletionModel, FinishReason, GenerationOutput, ModelManager,
+};
+use common::word_level_tokenizer;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokenizers::Tokenizer;
+use tokio::sync::mpsc;
+
+const MODEL_ID: &str = "slow-first-token";
+
+/// 第一个token前等待一段时间，之后立即输出 `hello world`
+struct SlowFirstTokenModel {
+    tokenizer: Tokenizer,
+}
+
+#[async_trait]
+impl CompletionModel for SlowFirstTokenModel {
+    fn model_id(&self) -> &str {
+        MODEL_ID
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    fn forward_logits(&self, _input_ids: &[u32]) -> Result<Tensor, AppError> {
+        Ok(Tensor::new(&[10f32, 0.0, 0.0, 0.0], &Device::Cpu)?)
+    }
+
+    async fn generate_stream(
+        &self,
+        _prompt: &str,
+        _params: &ChatCompletionParams,
+        sender: Option<&mpsc::Sender<String>>,
+    ) -> Result<GenerationOutput, AppError> {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        for token in ["hello", " world"] {
+            if let Some(sender) = sender {
+                sender.send(token.to_string()).await.unwrap();
+            }
+        }
+        Ok(GenerationOutput {
+            text: "hello world".to_string(),
+            token_ids: vec![1, 2],
+            prompt_tokens: 1,
+            finish_reason: FinishReason::Stop,
+            timed_out: false,
+            cancelled: false,
+            clamped_max_tokens: None,
+            system_fingerprint: None,
+            sampling: None,
+        })
+    }
+}
+
+async fn stream_body(keepalive: Duration) -> String {
+    let manager = ModelManager::new();
+    let model = SlowFirstTokenModel {
+        tokenizer: word_level_tokenizer(&["<eos>", "hello", "world", "<unk>"]),
+    };
+    manager.register_model(MODEL_ID, Arc::new(model)).await;
+    let service = ChatCompletionService::new(manager).with_stream_keepalive(keepalive);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .set_json(json!({
+            "model": MODEL_ID,
+            "messages": [{"role": "user", "content": "hi"}],
+            "stream": true
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers().get("content-type").unwrap(), "text/event-stream");
+    String::from_utf8(test::read_body(resp).await.to_vec()).unwrap()
+}
+
+#[actix_web::test]
+async fn test_keepalive_comments_precede_first_data_frame() {
+    let body = stream_body(Duration::from_millis(30)).await;
+
+    let first_data = body.find("data: ").unwrap();
+    let keepalives = body[..first_data].matches(KEEPALIVE_FRAME).count();
+    assert!(keepalives >= 2, "expected keepalives before first token, got body: {}", body);
+    // token开始输出后不再发送keepalive
+    assert!(!body[first_data..].contains(KEEPALIVE_FRAME));
+    assert!(body.ends_with(DONE_FRAME));
+}
+
+#[actix_web::test]
+async fn test_stream_frames_carry_tokens_and_finish_reason() {
+    let body = stream_body(Duration::from_secs(5)).await;
+
+    assert!(!body.contains(KEEPALIVE_FRAME));
+    let chunks: Vec<Value> = body
+        .split("\n\n")
+        .filter_map(|frame| frame.strip_prefix("data: "))
+        .filter(|data| *data != "[DONE]")
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+    let content: String =
+        chunks.iter().filter_map(|c| c["choices"][0]["delta"]["content"].as_str()).collect();
+    assert_eq!(content, "hello world");
+    assert_eq!(chunks[0]["choices"][0]["delta"]["role"], "assistant");
+    assert_eq!(chunks.last().unwrap()["choices"][0]["finish_reason"], "stop");
+    assert!(chunks
+        .iter()
+        .all(|c| c["object"] == "chat.completion.chunk" && c["model"] == MODEL_ID));
+}
@@ -0,0 +1,96 @@
+#[path = "../../common/mod.rs"]
+mod common;
+
+use actix_web::{test, web, App};
+use coder_openapi::controller::chat::chat_completion::{
+    cancel_completion, chat_completion, GENERATION_ID_HEADER,
+};
+use coder_openapi::service::chat::chat_completion::ChatCompletionService;
+use coder_openapi::service::models::ModelManager;
+use common::FakeModel;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+const MODEL_ID: &str = "endless-model";
+
+/// 记录info及以上级别的日志
+struct CaptureLogger {
+    lines: Mutex<Vec<String>>,
+}
+
+impl log::Log for CaptureLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Info
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            self.lines.lock().unwrap().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[actix_web::test]
+async fn test_cancelled_stream_reports_partial_usage() {
+    let logger: &'static CaptureLogger =
+        Box::leak(Box::new(CaptureLogger { lines: Mutex::new(Vec::new()) }));
+    log::set_logger(logger).unwrap();
+    log::set_max_level(log::LevelFilter::Info);
+
+    let model = Arc::new(FakeModel::endless(MODEL_ID));
+    let manager = ModelManager::new();
+    manager.register_model(MODEL_ID, model.clone()).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(ChatCompletionService::new(manager)))
+            .route("/v1/chat/completions", web::post().to(chat_completion))
+            .route(
+                "/v1/chat/completions/{generation_id}/cancel",
+                web::post().to(cancel_completion),
+            ),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .set_json(json!({
+            "model": MODEL_ID,
+            "messages": [{"role": "user", "content": "hi"}],
+            "temperature": 0.0,
+            "max_tokens": 100000,
+            "stream": true
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let generation_id =
+        resp.headers().get(GENERATION_ID_HEADER).unwrap().to_str().unwrap().to_string();
+    while model.steps() < 3 {
+        tokio::task::yield_now().await;
+    }
+    let req = test::TestRequest::post()
+        .uri(&format!("/v1/chat/completions/{}/cancel", generation_id))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 202);
+
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    let chunks: Vec<Value> = body
+        .split("\n\n")
+        .filter_map(|frame| frame.strip_prefix("data: "))
+        .filter(|data| *data != "[DONE]")
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+    // 客户端实际收到的token数
+    let streamed =
+        chunks.iter().filter(|chunk| chunk["choices"][0]["delta"]["content"].is_string()).count();
+    assert!(streamed >= 3);
+
+    let usage = &chunks.last().unwrap()["usage"];
+    assert_eq!(usage["completion_tokens"], streamed);
+    assert!(chunks.last().unwrap()["choices"].as_array().unwrap().is_empty());
+
+    let expected = format!("completion_tokens={}", streamed);
+    let lines = logger.lines.lock().unwrap();
+    assert!(lines.iter().any(|line| line.contains(&generation_id) && line.ends_with(&expected)));
+}
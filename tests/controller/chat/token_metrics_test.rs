@@ -0,0 +1,61 @@
+use actix_web::{test, web, App};
+use coder_openapi::controller::chat::chat_completion::{
+    chat_completion, COMPLETION_TOKENS_HISTOGRAM, PROMPT_TOKENS_HISTOGRAM,
+};
+use coder_openapi::service::chat::chat_completion::ChatCompletionService;
+use coder_openapi::service::metrics::metrics;
+use coder_openapi::service::models::echo::ECHO_MODEL_ID;
+use coder_openapi::service::models::ModelManager;
+use serde_json::json;
+
+fn observations(name: &str) -> u64 {
+    metrics().histogram(name).map(|histogram| histogram.count()).unwrap_or(0)
+}
+
+#[actix_web::test]
+async fn test_request_records_token_histograms() {
+    let service = ChatCompletionService::new(ModelManager::new());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+    let prompt_before = observations(PROMPT_TOKENS_HISTOGRAM);
+    let completion_before = observations(COMPLETION_TOKENS_HISTOGRAM);
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .set_json(json!({
+            "model": ECHO_MODEL_ID,
+            "messages": [{"role": "user", "content": "one two three"}]
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    assert!(observations(PROMPT_TOKENS_HISTOGRAM) > prompt_before);
+    assert!(observations(COMPLETION_TOKENS_HISTOGRAM) > completion_before);
+    let histogram = metrics().histogram(PROMPT_TOKENS_HISTOGRAM).unwrap();
+    // 回显模型每个空白分隔的词计为一个token，3个token落在最小的桶中
+    assert!(histogram.buckets().next().unwrap().1 > 0);
+}
+
+#[actix_web::test]
+async fn test_histogram_buckets_are_cumulative_in_render() {
+    let name = "test_cumulative_histogram";
+    metrics().observe_histogram(name, &[10.0, 100.0], 5.0);
+    metrics().observe_histogram(name, &[10.0, 100.0], 50.0);
+    metrics().observe_histogram(name, &[10.0, 100.0], 500.0);
+
+    let histogram = metrics().histogram(name).unwrap();
+    assert_eq!(histogram.buckets().collect::<Vec<_>>(), [(10.0, 1), (100.0, 2)]);
+    assert_eq!(histogram.count(), 3);
+    assert_eq!(histogram.sum(), 555.0);
+
+    let output = metrics().render();
+    assert!(output.contains("# TYPE test_cumulative_histogram histogram"));
+    assert!(output.contains("test_cumulative_histogram_bucket{le=\"10\"} 1"));
+    assert!(output.contains("test_cumulative_histogram_bucket{le=\"+Inf\"} 3"));
+    assert!(output.contains("test_cumulative_histogram_sum 555"));
+    assert!(output.contains("test_cumulative_histogram_count 3"));
+}
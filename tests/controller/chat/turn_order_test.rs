@@ -0,0 +1,70 @@
+use actix_web::{test, web, App};
+use coder_openapi::controller::chat::chat_completion::chat_completion;
+use coder_openapi::service::chat::chat_completion::ChatCompletionService;
+use coder_openapi::service::models::echo::ECHO_MODEL_ID;
+use coder_openapi::service::models::ModelManager;
+use serde_json::{json, Value};
+
+async fn post(strict: bool, messages: Value) -> (u16, Value) {
+    let service = ChatCompletionService::new(ModelManager::new()).with_strict_turn_order(strict);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/chat/completions")
+        .set_json(json!({ "model": ECHO_MODEL_ID, "messages": messages }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let status = resp.status().as_u16();
+    (status, test::read_body_json(resp).await)
+}
+
+fn double_assistant() -> Value {
+    json!([
+        {"role": "user", "content": "write a sort"},
+        {"role": "assistant", "content": "def sort(xs):"},
+        {"role": "assistant", "content": "    return sorted(xs)"},
+        {"role": "user", "content": "now in rust"}
+    ])
+}
+
+#[actix_web::test]
+async fn test_alternating_conversation_passes_in_strict_mode() {
+    let (status, _) = post(
+        true,
+        json!([
+            {"role": "system", "content": "You are a coding assistant"},
+            {"role": "user", "content": "write a sort"},
+            {"role": "assistant", "content": "def sort(xs): return sorted(xs)"},
+            {"role": "user", "content": "now in rust"}
+        ]),
+    )
+    .await;
+
+    assert_eq!(status, 200);
+}
+
+#[actix_web::test]
+async fn test_double_assistant_is_rejected_in_strict_mode() {
+    let (status, body) = post(true, double_assistant()).await;
+
+    assert_eq!(status, 400);
+    assert_eq!(body["error_code"], "validation_error");
+    let message = body["message"].as_str().unwrap();
+    assert!(
+        message.contains("messages[2] is a second consecutive assistant message"),
+        "{}",
+        message
+    );
+}
+
+#[actix_web::test]
+async fn test_double_assistant_is_accepted_by_default() {
+    let (status, _) = post(false, double_assistant()).await;
+
+    assert_eq!(status, 200);
+}
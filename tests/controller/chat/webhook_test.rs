@@ -0,0 +1,118 @@
+use actix_web::{test, web, App, HttpResponse};
+use coder_openapi::controller::chat::chat_completion::chat_completion;
+use coder_openapi::service::chat::chat_completion::ChatCompletionService;
+use coder_openapi::service::chat::webhook::WebhookAllowlist;
+use coder_openapi::service::models::echo::ECHO_MODEL_ID;
+use coder_openapi::service::models::ModelManager;
+use serde_json::{json, Value};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const CONTENT: &str = "fn main() {}";
+
+type Received = web::Data<Mutex<Vec<Value>>>;
+
+async fn receive(received: Received, body: web::Json<Value>) -> HttpResponse {
+    received.lock().unwrap().push(body.into_inner());
+    HttpResponse::Ok().finish()
+}
+
+/// 本地的webhook接收端，记录收到的所有事件
+fn mock_webhook() -> (actix_test::TestServer, Received) {
+    let received: Received = web::Data::new(Mutex::new(Vec::new()));
+    let data = received.clone();
+    let server = actix_test::start(move || {
+        App::new().app_data(data.clone()).route("/hook", web::post().to(receive))
+    });
+    (server, received)
+}
+
+async fn post(body: Value) -> actix_web::dev::ServiceResponse {
+    let service = ChatCompletionService::new(ModelManager::new())
+        .with_webhook_allowlist(WebhookAllowlist::new(&["http"], &["127.0.0.1"]));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(service))
+            .route("/v1/chat/completions", web::post().to(chat_completion)),
+    )
+    .await;
+    let req = test::TestRequest::post().uri("/v1/chat/completions").set_json(body).to_request();
+    test::call_service(&app, req).await
+}
+
+#[actix_web::test]
+async fn test_webhook_receives_chunks_and_final_event() {
+    let (server, received) = mock_webhook();
+    let resp = post(json!({
+        "model": ECHO_MODEL_ID,
+        "messages": [{"role": "user", "content": CONTENT}],
+        "webhook_url": server.url("/hook")
+    }))
+    .await;
+    assert_eq!(resp.status(), 202);
+    let body: Value = test::read_body_json(resp).await;
+    let id = body["id"].as_str().unwrap().to_string();
+
+    // 等待最终的chat.completion事件
+    let events = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let events = received.lock().unwrap().clone();
+            if events.iter().any(|event| event["object"] == "chat.completion") {
+                return events;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    let (last, chunks) = events.split_last().unwrap();
+    assert!(!chunks.is_empty());
+    assert!(chunks.iter().all(|chunk| chunk["object"] == "chat.completion.chunk"));
+    assert!(chunks.iter().all(|chunk| chunk["id"] == id));
+    assert_eq!(chunks[0]["choices"][0]["delta"]["role"], "assistant");
+    let streamed: String = chunks
+        .iter()
+        .map(|chunk| chunk["choices"][0]["delta"]["content"].as_str().unwrap())
+        .collect();
+    assert_eq!(streamed, CONTENT);
+
+    assert_eq!(last["id"], id);
+    assert_eq!(last["choices"][0]["message"]["content"], CONTENT);
+    assert_eq!(last["choices"][0]["finish_reason"], "stop");
+}
+
+#[actix_web::test]
+async fn test_webhook_host_outside_allowlist_is_rejected() {
+    let resp = post(json!({
+        "model": ECHO_MODEL_ID,
+        "messages": [{"role": "user", "content": CONTENT}],
+        "webhook_url": "http://169.254.169.254/latest/meta-data"
+    }))
+    .await;
+    assert_eq!(resp.status(), 400);
+
+    let resp = post(json!({
+        "model": ECHO_MODEL_ID,
+        "messages": [{"role": "user", "content": CONTENT}],
+        "webhook_url": "ftp://127.0.0.1/hook"
+    }))
+    .await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[actix_web::test]
+async fn test_webhook_with_stream_is_rejected() {
+    let resp = post(json!({
+        "model": ECHO_MODEL_ID,
+        "messages": [{"role": "user", "content": CONTENT}],
+        "webhook_url": "http://127.0.0.1/hook",
+        "stream": true
+    }))
+    .await;
+
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error_code"], "invalid_parameter");
+    assert!(body["message"].as_str().unwrap().contains("stream"));
+}
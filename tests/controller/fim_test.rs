@@ -0,0 +1,105 @@
+use actix_web::{test, web, App};
+use async_trait::async_trait;
+use candle_core::Tensor;
+use coder_openapi::controller::fim::routes;
+use coder_openapi::error::AppError;
+use coder_openapi::service::chat::chat_completion::{ChatCompletionParams, ChatCompletionService};
+use coder_openapi::service::models::echo::{EchoModel, ECHO_MODEL_ID};
+use coder_openapi::service::models::{CompletionModel, GenerationOutput, ModelManager};
+use coder_openapi::utils::config::FimTokens;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokenizers::Tokenizer;
+use tokio::sync::mpsc;
+
+const MODEL_ID: &str = "fim-echo";
+
+/// 配置了FIM哨兵token的回显模型，回复即渲染后的提示词
+struct FimEchoModel {
+    echo: EchoModel,
+}
+
+#[async_trait]
+impl CompletionModel for FimEchoModel {
+    fn model_id(&self) -> &str {
+        MODEL_ID
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        self.echo.tokenizer()
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        self.echo.eos_token_id()
+    }
+
+    fn fim_tokens(&self) -> Option<FimTokens> {
+        Some(FimTokens {
+            prefix: "<PRE>".to_string(),
+            suffix: " <SUF>".to_string(),
+            middle: " <MID>".to_string(),
+        })
+    }
+
+    fn forward_logits(&self, input_ids: &[u32]) -> Result<Tensor, AppError> {
+        self.echo.forward_logits(input_ids)
+    }
+
+    async fn generate_stream(
+        &self,
+        prompt: &str,
+        params: &ChatCompletionParams,
+        sender: Option<&mpsc::Sender<String>>,
+    ) -> Result<GenerationOutput, AppError> {
+        self.echo.generate_stream(prompt, params, sender).await
+    }
+}
+
+async fn fim_service() -> web::Data<ChatCompletionService> {
+    let manager = ModelManager::new();
+    let model = Arc::new(FimEchoModel { echo: EchoModel::new().unwrap() });
+    manager.register_model(MODEL_ID, model).await;
+    web::Data::new(ChatCompletionService::new(manager))
+}
+
+#[actix_web::test]
+async fn test_prefix_and_suffix_are_wrapped_with_fim_tokens() {
+    let app = test::init_service(
+        App::new().app_data(fim_service().await).service(web::scope("/v1/fim").configure(routes)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/fim/completions")
+        .set_json(json!({
+            "model": MODEL_ID,
+            "prefix": "fn add(a: i32, b: i32) -> i32 {",
+            "suffix": "}"
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["object"], "text_completion");
+    assert_eq!(body["model"], MODEL_ID);
+    assert_eq!(body["choices"][0]["index"], 0);
+    assert_eq!(body["choices"][0]["text"], "<PRE>fn add(a: i32, b: i32) -> i32 { <SUF>} <MID>");
+    assert_eq!(body["choices"][0]["finish_reason"], "stop");
+    assert!(body["usage"]["total_tokens"].as_u64().unwrap() > 0);
+}
+
+#[actix_web::test]
+async fn test_model_without_fim_tokens_is_rejected() {
+    let app = test::init_service(
+        App::new().app_data(fim_service().await).service(web::scope("/v1/fim").configure(routes)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/fim/completions")
+        .set_json(json!({"model": ECHO_MODEL_ID, "prefix": "fn main() {", "suffix": "}"}))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+}
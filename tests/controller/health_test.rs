@@ -0,0 +1,31 @@
+use actix_web::{test, web, App};
+use coder_openapi::controller::health::routes;
+use coder_openapi::service::models::echo::ECHO_MODEL_ID;
+use coder_openapi::service::models::ModelManager;
+use serde_json::Value;
+
+#[actix_web::test]
+async fn test_health_detail_reports_device_and_loaded_models() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(ModelManager::new()))
+            .service(web::scope("/health").configure(routes)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/health").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["status"], "ok");
+
+    let req = test::TestRequest::get().uri("/health/detail").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+
+    let device = body["device"].as_str().unwrap();
+    assert!(device == "cpu" || device.starts_with("cuda:") || device.starts_with("metal:"));
+    assert!(body["cuda_active"].is_boolean());
+    assert!(body["metal_active"].is_boolean());
+    let loaded: Vec<&str> =
+        body["loaded_models"].as_array().unwrap().iter().filter_map(Value::as_str).collect();
+    assert!(loaded.contains(&ECHO_MODEL_ID));
+    assert!(body["memory"].get("resident_bytes").is_some());
+}
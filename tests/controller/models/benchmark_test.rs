@@ -0,0 +1,75 @@
+use actix_web::{test, web, App};
+use coder_openapi::controller::models::routes;
+use coder_openapi::middleware::authentication::Authentication;
+use coder_openapi::service::models::mock::{MockModel, MOCK_MODEL_ID};
+use coder_openapi::service::models::ModelManager;
+use std::sync::Arc;
+
+async fn mock_manager() -> ModelManager {
+    let manager = ModelManager::new();
+    manager.register_model(MOCK_MODEL_ID, Arc::new(MockModel::new(0).unwrap())).await;
+    manager
+}
+
+#[actix_web::test]
+async fn test_benchmark_returns_positive_throughput() {
+    let app = test::init_service(
+        App::new()
+            .wrap(Authentication::new(vec![]).with_api_key("benchmark-key"))
+            .app_data(web::Data::new(mock_manager().await))
+            .service(web::scope("/models").configure(routes)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/models/mock-model/benchmark")
+        .insert_header(("Authorization", "Bearer benchmark-key"))
+        .set_json(serde_json::json!({"prompt_tokens": 16, "max_tokens": 8}))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(body["model_id"], MOCK_MODEL_ID);
+    assert_eq!(body["prompt_tokens"], 16);
+    assert_eq!(body["generated_tokens"], 8);
+    assert!(body["prompt_tokens_per_second"].as_f64().unwrap() > 0.0);
+    assert!(body["generation_tokens_per_second"].as_f64().unwrap() > 0.0);
+}
+
+#[actix_web::test]
+async fn test_benchmark_requires_api_key() {
+    let app = test::init_service(
+        App::new()
+            .wrap(Authentication::new(vec![]).with_api_key("benchmark-key"))
+            .app_data(web::Data::new(mock_manager().await))
+            .service(web::scope("/models").configure(routes)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/models/mock-model/benchmark")
+        .set_json(serde_json::json!({}))
+        .to_request();
+    let err = test::try_call_service(&app, req).await.unwrap_err();
+
+    assert_eq!(err.as_response_error().status_code(), 401);
+}
+
+#[actix_web::test]
+async fn test_benchmark_rejects_zero_tokens() {
+    let app = test::init_service(
+        App::new()
+            .wrap(Authentication::new(vec![]).with_api_key("benchmark-key"))
+            .app_data(web::Data::new(mock_manager().await))
+            .service(web::scope("/models").configure(routes)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/models/mock-model/benchmark")
+        .insert_header(("Authorization", "Bearer benchmark-key"))
+        .set_json(serde_json::json!({"max_tokens": 0}))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status().as_u16(), 400);
+}
@@ -0,0 +1,140 @@
+#[path = "../../common/mod.rs"]
+mod common;
+
+use actix_web::{test, web, App};
+use coder_openapi::controller::models::routes;
+use coder_openapi::service::models::{
+    read_generation_defaults, read_special_tokens, scan_model_files, GenerationDefaults,
+    ModelManager, SpecialToken,
+};
+use coder_openapi::utils::config::ModelFiles;
+use common::word_level_tokenizer;
+
+fn tiny_model_files() -> ModelFiles {
+    ModelFiles {
+        weights: vec!["model.safetensors".to_string()],
+        config: "config.json".to_string(),
+        tokenizer: "tokenizer.json".to_string(),
+        tokenizer_config: "tokenizer_config.json".to_string(),
+        generation_config: "generation_config.json".to_string(),
+    }
+}
+
+#[actix_web::test]
+async fn test_partially_downloaded_model_flags_missing_files() {
+    let model_dir = std::env::temp_dir().join(format!("model-files-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&model_dir).unwrap();
+    std::fs::write(model_dir.join("model.safetensors"), [0u8; 16]).unwrap();
+    std::fs::write(model_dir.join("config.json"), "{}").unwrap();
+
+    let files = scan_model_files(&model_dir, &tiny_model_files());
+    std::fs::remove_dir_all(&model_dir).unwrap();
+
+    let weights = files.iter().find(|f| f.name == "model.safetensors").unwrap();
+    assert!(weights.present);
+    assert_eq!(weights.size_bytes, 16);
+
+    let tokenizer = files.iter().find(|f| f.name == "tokenizer.json").unwrap();
+    assert!(!tokenizer.present);
+    assert_eq!(tokenizer.size_bytes, 0);
+    assert_eq!(files.len(), 5);
+}
+
+#[actix_web::test]
+async fn test_model_files_endpoint() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(ModelManager::new()))
+            .service(web::scope("/models").configure(routes)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/models/yi-coder/files").to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["model_id"], "yi-coder");
+    assert!(body["files"].as_array().unwrap().iter().all(|f| f.get("present").is_some()));
+
+    let req = test::TestRequest::get().uri("/models/unknown-model/files").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 404);
+}
+
+#[actix_web::test]
+async fn test_generation_defaults_read_from_config_file() {
+    let model_dir =
+        std::env::temp_dir().join(format!("generation-config-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&model_dir).unwrap();
+    let path = model_dir.join("generation_config.json");
+    std::fs::write(
+        &path,
+        r#"{"bos_token_id": 1, "eos_token_id": 2, "temperature": 0.5, "top_p": 0.75, "max_new_tokens": 512}"#,
+    )
+    .unwrap();
+
+    let defaults = read_generation_defaults(&path).unwrap();
+    std::fs::remove_dir_all(&model_dir).unwrap();
+
+    assert_eq!(
+        defaults,
+        GenerationDefaults {
+            temperature: Some(0.5),
+            top_p: Some(0.75),
+            top_k: None,
+            max_tokens: Some(512)
+        }
+    );
+    let value = serde_json::to_value(&defaults).unwrap();
+    assert_eq!(value, serde_json::json!({"temperature": 0.5, "top_p": 0.75, "max_tokens": 512}));
+}
+
+#[actix_web::test]
+async fn test_generation_config_endpoint_unknown_model() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(ModelManager::new()))
+            .service(web::scope("/models").configure(routes)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/models/unknown-model/generation_config").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 404);
+}
+
+#[actix_web::test]
+async fn test_special_tokens_read_from_tokenizer_config() {
+    let model_dir = std::env::temp_dir().join(format!("special-tokens-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&model_dir).unwrap();
+    let tokenizer_path = model_dir.join("tokenizer.json");
+    word_level_tokenizer(&["<s>", "</s>", "hello", "<unk>"]).save(&tokenizer_path, false).unwrap();
+    let config_path = model_dir.join("tokenizer_config.json");
+    std::fs::write(
+        &config_path,
+        r#"{"bos_token": "<s>", "eos_token": {"__type": "AddedToken", "content": "</s>"}, "pad_token": null, "unk_token": "<unk>"}"#,
+    )
+    .unwrap();
+
+    let tokens = read_special_tokens(&config_path, &tokenizer_path).unwrap();
+    std::fs::remove_dir_all(&model_dir).unwrap();
+
+    assert_eq!(tokens.eos, Some(SpecialToken { id: Some(1), content: "</s>".to_string() }));
+    assert_eq!(tokens.bos, Some(SpecialToken { id: Some(0), content: "<s>".to_string() }));
+    assert_eq!(tokens.unk, Some(SpecialToken { id: Some(3), content: "<unk>".to_string() }));
+    assert_eq!(tokens.pad, None);
+    let value = serde_json::to_value(&tokens).unwrap();
+    assert_eq!(value["eos"], serde_json::json!({"id": 1, "content": "</s>"}));
+}
+
+#[actix_web::test]
+async fn test_special_tokens_endpoint_unknown_model() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(ModelManager::new()))
+            .service(web::scope("/models").configure(routes)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/models/unknown-model/special_tokens").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status().as_u16(), 404);
+}
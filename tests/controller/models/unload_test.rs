@@ -0,0 +1,102 @@
+use actix_web::{test, web, App};
+use coder_openapi::controller::models::routes;
+use coder_openapi::middleware::authentication::Authentication;
+use coder_openapi::service::models::mock::MockModel;
+use coder_openapi::service::models::ModelManager;
+use coder_openapi::utils::config::get_config;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const MODEL_ID: &str = "yi-coder";
+
+/// 在临时缓存目录中写入yi-coder的全部模型文件，并注册一个已加载的模型实例
+async fn cached_manager() -> (ModelManager, PathBuf) {
+    let cache_dir = std::env::temp_dir().join(format!("model-unload-{}", uuid::Uuid::new_v4()));
+    let model_config = get_config().models.get(MODEL_ID).unwrap();
+    let model_dir = cache_dir.join(&model_config.hf_hub_id);
+    std::fs::create_dir_all(&model_dir).unwrap();
+    for name in model_config.model_files.all() {
+        std::fs::write(model_dir.join(name), "{}").unwrap();
+    }
+
+    let manager = ModelManager::new().with_models_cache_dir(&cache_dir);
+    manager.register_model(MODEL_ID, Arc::new(MockModel::new(0).unwrap())).await;
+    (manager, cache_dir)
+}
+
+#[actix_web::test]
+async fn test_unload_keeps_files_on_disk() {
+    let (manager, cache_dir) = cached_manager().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(Authentication::new(vec![]).with_api_key("unload-key"))
+            .app_data(web::Data::new(manager.clone()))
+            .service(web::scope("/models").configure(routes)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/models/yi-coder/unload")
+        .insert_header(("Authorization", "Bearer unload-key"))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(body["unloaded"], true);
+    assert!(manager.get_model(MODEL_ID).await.is_none());
+    let files = manager.get_model_files(MODEL_ID).unwrap();
+    assert!(files.iter().all(|file| file.present));
+    let status = manager.get_model_status(MODEL_ID).await.unwrap();
+    assert!(status.is_cached);
+    std::fs::remove_dir_all(&cache_dir).unwrap();
+}
+
+#[actix_web::test]
+async fn test_delete_with_confirm_removes_files() {
+    let (manager, cache_dir) = cached_manager().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(Authentication::new(vec![]).with_api_key("unload-key"))
+            .app_data(web::Data::new(manager.clone()))
+            .service(web::scope("/models").configure(routes)),
+    )
+    .await;
+
+    let req = test::TestRequest::delete()
+        .uri("/models/yi-coder/files?confirm=true")
+        .insert_header(("Authorization", "Bearer unload-key"))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+    let expected = get_config().models.get(MODEL_ID).unwrap().model_files.all().len();
+    assert_eq!(body["deleted_files"].as_array().unwrap().len(), expected);
+    assert!(manager.get_model(MODEL_ID).await.is_none());
+    let files = manager.get_model_files(MODEL_ID).unwrap();
+    assert!(files.iter().all(|file| !file.present));
+    let status = manager.get_model_status(MODEL_ID).await.unwrap();
+    assert!(!status.is_cached);
+    std::fs::remove_dir_all(&cache_dir).unwrap();
+}
+
+#[actix_web::test]
+async fn test_delete_without_confirm_is_rejected() {
+    let (manager, cache_dir) = cached_manager().await;
+    let app = test::init_service(
+        App::new()
+            .wrap(Authentication::new(vec![]).with_api_key("unload-key"))
+            .app_data(web::Data::new(manager.clone()))
+            .service(web::scope("/models").configure(routes)),
+    )
+    .await;
+
+    let req = test::TestRequest::delete()
+        .uri("/models/yi-coder/files")
+        .insert_header(("Authorization", "Bearer unload-key"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status().as_u16(), 400);
+    assert!(manager.get_model(MODEL_ID).await.is_some());
+    let files = manager.get_model_files(MODEL_ID).unwrap();
+    assert!(files.iter().all(|file| file.present));
+    std::fs::remove_dir_all(&cache_dir).unwrap();
+}
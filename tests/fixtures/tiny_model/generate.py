@@ -0,0 +1,126 @@
+"""生成集成测试使用的微型Yi-Coder模型 (hidden_size 16, 2层, vocab 100)
+
+只依赖标准库，权重使用固定种子的随机数，重复运行得到相同的文件:
+
+    python3 tests/fixtures/tiny_model/generate.py
+"""
+import json
+import os
+import random
+import struct
+
+HIDDEN_SIZE = 16
+NUM_LAYERS = 2
+NUM_HEADS = 2
+INTERMEDIATE_SIZE = 32
+VOCAB_SIZE = 100
+MAX_POSITION_EMBEDDINGS = 64
+EOS_TOKEN_ID = 0
+
+OUT_DIR = os.path.dirname(os.path.abspath(__file__))
+rng = random.Random(2159)
+
+
+def randn(n, scale=0.2):
+    return [rng.uniform(-scale, scale) for _ in range(n)]
+
+
+def tensors():
+    yield "model.embeddings.word_embeddings", [VOCAB_SIZE, HIDDEN_SIZE], randn(
+        VOCAB_SIZE * HIDDEN_SIZE, 1.0
+    )
+    for i in range(NUM_LAYERS):
+        prefix = f"layer_{i}"
+        for name in ("query", "key", "value", "out"):
+            yield f"{prefix}.attention.{name}.weight", [HIDDEN_SIZE, HIDDEN_SIZE], randn(
+                HIDDEN_SIZE * HIDDEN_SIZE
+            )
+            yield f"{prefix}.attention.{name}.bias", [HIDDEN_SIZE], randn(HIDDEN_SIZE)
+        yield f"{prefix}.ffn.fc1.weight", [INTERMEDIATE_SIZE, HIDDEN_SIZE], randn(
+            INTERMEDIATE_SIZE * HIDDEN_SIZE
+        )
+        yield f"{prefix}.ffn.fc1.bias", [INTERMEDIATE_SIZE], randn(INTERMEDIATE_SIZE)
+        yield f"{prefix}.ffn.fc2.weight", [HIDDEN_SIZE, INTERMEDIATE_SIZE], randn(
+            HIDDEN_SIZE * INTERMEDIATE_SIZE
+        )
+        yield f"{prefix}.ffn.fc2.bias", [HIDDEN_SIZE], randn(HIDDEN_SIZE)
+        for norm in ("input_layernorm", "post_attention_layernorm"):
+            yield f"{prefix}.{norm}.weight", [HIDDEN_SIZE], [1.0] * HIDDEN_SIZE
+            yield f"{prefix}.{norm}.bias", [HIDDEN_SIZE], [0.0] * HIDDEN_SIZE
+    yield "model.norm.weight", [HIDDEN_SIZE], [1.0] * HIDDEN_SIZE
+    yield "model.norm.bias", [HIDDEN_SIZE], [0.0] * HIDDEN_SIZE
+
+
+def write_safetensors(path):
+    header, data = {}, bytearray()
+    for name, shape, values in tensors():
+        start = len(data)
+        data += struct.pack(f"<{len(values)}f", *values)
+        header[name] = {"dtype": "F32", "shape": shape, "data_offsets": [start, len(data)]}
+    header = json.dumps(header, separators=(",", ":")).encode()
+    # 头部按8字节对齐，与safetensors库的输出一致
+    header += b" " * (-len(header) % 8)
+    with open(path, "wb") as f:
+        f.write(struct.pack("<Q", len(header)))
+        f.write(header)
+        f.write(data)
+
+
+def write_json(name, value):
+    with open(os.path.join(OUT_DIR, name), "w") as f:
+        json.dump(value, f, indent=2)
+        f.write("\n")
+
+
+def main():
+    write_safetensors(os.path.join(OUT_DIR, "model.safetensors"))
+    write_json(
+        "config.json",
+        {
+            "bos_token_id": 1,
+            "eos_token_id": EOS_TOKEN_ID,
+            "pad_token_id": EOS_TOKEN_ID,
+            "hidden_size": HIDDEN_SIZE,
+            "num_attention_heads": NUM_HEADS,
+            "intermediate_size": INTERMEDIATE_SIZE,
+            "num_hidden_layers": NUM_LAYERS,
+            "layer_norm_eps": 1e-5,
+            "vocab_size": VOCAB_SIZE,
+            "max_position_embeddings": MAX_POSITION_EMBEDDINGS,
+        },
+    )
+    write_json("generation_config.json", {"bos_token_id": 1, "eos_token_id": EOS_TOKEN_ID})
+    vocab = ["<eos>", "<unk>"] + [f"tok{i}" for i in range(2, VOCAB_SIZE)]
+    write_json(
+        "tokenizer.json",
+        {
+            "version": "1.0",
+            "truncation": None,
+            "padding": None,
+            "added_tokens": [
+                {
+                    "id": 0,
+                    "content": "<eos>",
+                    "single_word": False,
+                    "lstrip": False,
+                    "rstrip": False,
+                    "normalized": False,
+                    "special": True,
+                }
+            ],
+            "normalizer": None,
+            "pre_tokenizer": {"type": "Whitespace"},
+            "post_processor": None,
+            "decoder": None,
+            "model": {
+                "type": "WordLevel",
+                "vocab": {token: i for i, token in enumerate(vocab)},
+                "unk_token": "<unk>",
+            },
+        },
+    )
+    write_json("tokenizer_config.json", {"eos_token": "<eos>", "unk_token": "<unk>"})
+
+
+if __name__ == "__main__":
+    main()
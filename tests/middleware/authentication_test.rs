@@ -0,0 +1,62 @@
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::{test, web, App, HttpResponse};
+use coder_openapi::middleware::authentication::Authentication;
+
+const API_KEY: &str = "auth-key";
+
+/// `/health` 与 `/metrics` 公开，其余路径需要API key
+async fn app(
+) -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error> {
+    let public_paths = vec!["/health".to_string(), "/metrics".to_string()];
+    test::init_service(
+        App::new()
+            .wrap(Authentication::new(public_paths).with_api_key(API_KEY))
+            .route("/health", web::get().to(HttpResponse::Ok))
+            .route("/healthz", web::get().to(HttpResponse::Ok))
+            .route("/v1/models", web::get().to(HttpResponse::Ok)),
+    )
+    .await
+}
+
+#[actix_web::test]
+async fn test_public_path_reachable_without_key() {
+    let app = app().await;
+
+    let req = test::TestRequest::get().uri("/health").to_request();
+    let resp = test::try_call_service(&app, req).await.unwrap();
+
+    assert_eq!(resp.status(), 200);
+}
+
+#[actix_web::test]
+async fn test_protected_path_requires_key() {
+    let app = app().await;
+
+    let req = test::TestRequest::get().uri("/v1/models").to_request();
+    let err = test::try_call_service(&app, req).await.unwrap_err();
+
+    assert_eq!(err.as_response_error().status_code(), 401);
+}
+
+#[actix_web::test]
+async fn test_protected_path_accepts_valid_key() {
+    let app = app().await;
+
+    let req = test::TestRequest::get()
+        .uri("/v1/models")
+        .insert_header(("Authorization", format!("Bearer {}", API_KEY)))
+        .to_request();
+    let resp = test::try_call_service(&app, req).await.unwrap();
+
+    assert_eq!(resp.status(), 200);
+}
+
+#[actix_web::test]
+async fn test_public_prefix_matches_whole_segments() {
+    let app = app().await;
+
+    let req = test::TestRequest::get().uri("/healthz").to_request();
+    let err = test::try_call_service(&app, req).await.unwrap_err();
+
+    assert_eq!(err.as_response_error().status_code(), 401);
+}
@@ -0,0 +1,58 @@
+use actix_web::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING};
+use actix_web::middleware::Compress;
+use actix_web::{test, web, App, HttpResponse};
+use coder_openapi::middleware::compression::{EventStreamIdentity, EVENT_STREAM};
+use serde_json::json;
+
+async fn large_json() -> HttpResponse {
+    let choices: Vec<_> = (0..200)
+        .map(|i| json!({"index": i, "message": {"role": "assistant", "content": "fn main() {}"}}))
+        .collect();
+    HttpResponse::Ok().json(json!({ "choices": choices }))
+}
+
+async fn events() -> HttpResponse {
+    HttpResponse::Ok().content_type(EVENT_STREAM).body("data: hello\n\n".repeat(200))
+}
+
+#[actix_web::test]
+async fn test_large_json_is_gzip_encoded() {
+    let app = test::init_service(
+        App::new()
+            .wrap(EventStreamIdentity)
+            .wrap(Compress::default())
+            .route("/large", web::get().to(large_json)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/large")
+        .insert_header((ACCEPT_ENCODING, "gzip"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+}
+
+#[actix_web::test]
+async fn test_event_stream_is_not_compressed() {
+    let app = test::init_service(
+        App::new()
+            .wrap(EventStreamIdentity)
+            .wrap(Compress::default())
+            .route("/events", web::get().to(events)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/events")
+        .insert_header((ACCEPT_ENCODING, "gzip"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers().get(CONTENT_ENCODING).unwrap(), "identity");
+    let body = test::read_body(resp).await;
+    assert!(body.starts_with(b"data: hello\n\n"));
+}
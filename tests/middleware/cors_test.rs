@@ -0,0 +1,43 @@
+use actix_web::http::header;
+use actix_web::{test, web, App, HttpResponse};
+use coder_openapi::middleware::cors::cors;
+use coder_openapi::utils::config::CorsConfig;
+
+fn preflight() -> test::TestRequest {
+    test::TestRequest::default()
+        .method(actix_web::http::Method::OPTIONS)
+        .uri("/v1/models")
+        .insert_header((header::ORIGIN, "https://example.com"))
+        .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "POST"))
+}
+
+#[actix_web::test]
+async fn test_preflight_includes_configured_max_age() {
+    let config = CorsConfig { enabled: true, max_age_secs: Some(600), ..Default::default() };
+    let app = test::init_service(
+        App::new().wrap(cors(&config)).route("/v1/models", web::post().to(HttpResponse::Ok)),
+    )
+    .await;
+
+    let resp = test::call_service(&app, preflight().to_request()).await;
+
+    assert!(resp.status().is_success());
+    assert_eq!(resp.headers().get(header::ACCESS_CONTROL_MAX_AGE).unwrap(), "600");
+    assert_eq!(
+        resp.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+        "https://example.com"
+    );
+}
+
+#[actix_web::test]
+async fn test_disabled_cors_adds_no_headers() {
+    let config = CorsConfig { max_age_secs: Some(600), ..Default::default() };
+    let app = test::init_service(
+        App::new().wrap(cors(&config)).route("/v1/models", web::post().to(HttpResponse::Ok)),
+    )
+    .await;
+
+    let resp = test::call_service(&app, preflight().to_request()).await;
+
+    assert!(resp.headers().get(header::ACCESS_CONTROL_MAX_AGE).is_none());
+}
@@ -0,0 +1,39 @@
+use actix_web::{test, web, App, HttpResponse};
+use coder_openapi::middleware::draining::{DrainState, Draining};
+
+#[actix_web::test]
+async fn test_draining_rejects_new_v1_requests_but_not_health() {
+    let drain = DrainState::new();
+    let app = test::init_service(
+        App::new()
+            .wrap(Draining::new(drain.clone()))
+            .route("/health", web::get().to(HttpResponse::Ok))
+            .route("/v1/models", web::get().to(HttpResponse::Ok)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/v1/models").to_request();
+    assert_eq!(test::try_call_service(&app, req).await.unwrap().status(), 200);
+
+    drain.start();
+
+    let req = test::TestRequest::get().uri("/v1/models").to_request();
+    let err = test::try_call_service(&app, req).await.unwrap_err();
+    assert_eq!(err.as_response_error().status_code(), 503);
+
+    let req = test::TestRequest::get().uri("/health").to_request();
+    assert_eq!(test::try_call_service(&app, req).await.unwrap().status(), 200);
+}
+
+#[actix_web::test]
+async fn test_prefix_match_is_by_path_segment() {
+    let drain = DrainState::new();
+    drain.start();
+    let app = test::init_service(
+        App::new().wrap(Draining::new(drain)).route("/v1beta", web::get().to(HttpResponse::Ok)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/v1beta").to_request();
+    assert_eq!(test::try_call_service(&app, req).await.unwrap().status(), 200);
+}
@@ -0,0 +1,53 @@
+use actix_web::{test, web, App, HttpMessage, HttpRequest, HttpResponse};
+use coder_openapi::middleware::logging::{Logging, RequestId, REQUEST_ID_HEADER};
+
+/// 返回处理函数看到的请求ID
+async fn request_id(req: HttpRequest) -> HttpResponse {
+    let id = req.extensions().get::<RequestId>().map(|id| id.0.clone()).unwrap_or_default();
+    HttpResponse::Ok().body(id)
+}
+
+async fn call(logging: Logging, incoming: Option<&str>) -> (String, String) {
+    let app =
+        test::init_service(App::new().wrap(logging).route("/id", web::get().to(request_id))).await;
+    let mut req = test::TestRequest::get().uri("/id");
+    if let Some(id) = incoming {
+        req = req.insert_header((REQUEST_ID_HEADER, id));
+    }
+    let resp = test::call_service(&app, req.to_request()).await;
+    let header = resp.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap().to_string();
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    (header, body)
+}
+
+#[actix_web::test]
+async fn test_incoming_request_id_is_echoed_unchanged() {
+    let (header, seen_by_handler) = call(Logging::default(), Some("gw-7f3a.01:x_y")).await;
+
+    assert_eq!(header, "gw-7f3a.01:x_y");
+    assert_eq!(seen_by_handler, "gw-7f3a.01:x_y");
+}
+
+#[actix_web::test]
+async fn test_request_id_is_generated_when_absent() {
+    let (header, seen_by_handler) = call(Logging::default(), None).await;
+
+    assert!(uuid::Uuid::parse_str(&header).is_ok(), "{}", header);
+    assert_eq!(seen_by_handler, header);
+}
+
+#[actix_web::test]
+async fn test_invalid_request_id_is_replaced() {
+    for invalid in ["has space", "", &"a".repeat(129)] {
+        let (header, _) = call(Logging::default(), Some(invalid)).await;
+        assert!(uuid::Uuid::parse_str(&header).is_ok(), "{:?} -> {}", invalid, header);
+    }
+}
+
+#[actix_web::test]
+async fn test_untrusted_request_id_is_replaced() {
+    let (header, _) = call(Logging::new(false), Some("gateway-id")).await;
+
+    assert_ne!(header, "gateway-id");
+    assert!(uuid::Uuid::parse_str(&header).is_ok());
+}
@@ -0,0 +1,435 @@
+#[path = "../common/mod.rs"]
+mod common;
+
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use coder_openapi::error::AppError;
+use coder_openapi::service::chat::chat_completion::ChatCompletionParams;
+use coder_openapi::service::models::completion_model::check_vocab_size;
+use coder_openapi::service::models::deepseek_coder::DeepseekCoder;
+use coder_openapi::service::models::sampling::NanPolicy;
+use coder_openapi::service::models::stream_decoder::invalid_utf8_tokens;
+use coder_openapi::service::models::yi_coder::YiCoder;
+use coder_openapi::service::models::{read_stop_token_ids, CompletionModel, FinishReason};
+use common::word_level_tokenizer;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokenizers::Tokenizer;
+
+const VOCAB: [&str; 4] = ["<eos>", "hello", "world", "<unk>"];
+
+/// 按固定序列依次输出token的模型，序列结束后输出EOS
+struct ScriptedModel {
+    tokenizer: Tokenizer,
+    script: Vec<u32>,
+}
+
+#[async_trait]
+impl CompletionModel for ScriptedModel {
+    fn model_id(&self) -> &str {
+        "scripted"
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    fn forward_logits(&self, input_ids: &[u32]) -> Result<Tensor, AppError> {
+        // 第一次调用时input_ids只包含提示词
+        let step = input_ids.len() - 1;
+        let next = self.script.get(step).copied().unwrap_or(0) as usize;
+        let logits: Vec<f32> =
+            (0..VOCAB.len()).map(|i| if i == next { 10.0 } else { 0.0 }).collect();
+        Ok(Tensor::new(logits, &Device::Cpu)?)
+    }
+}
+
+/// 第一步输出的logits中含有NaN，之后输出EOS的模型
+struct NanModel {
+    tokenizer: Tokenizer,
+    policy: NanPolicy,
+}
+
+#[async_trait]
+impl CompletionModel for NanModel {
+    fn model_id(&self) -> &str {
+        "nan"
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    fn nan_policy(&self) -> NanPolicy {
+        self.policy
+    }
+
+    fn forward_logits(&self, input_ids: &[u32]) -> Result<Tensor, AppError> {
+        let logits = match input_ids.len() {
+            1 => vec![0.0, f32::NAN, 10.0, 0.0],
+            _ => vec![10.0, 0.0, 0.0, 0.0],
+        };
+        Ok(Tensor::new(logits, &Device::Cpu)?)
+    }
+}
+
+/// 始终最倾向于输出EOS的模型
+struct EagerEosModel {
+    tokenizer: Tokenizer,
+}
+
+#[async_trait]
+impl CompletionModel for EagerEosModel {
+    fn model_id(&self) -> &str {
+        "eager-eos"
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    fn forward_logits(&self, _input_ids: &[u32]) -> Result<Tensor, AppError> {
+        Ok(Tensor::new(&[10f32, 5.0, 0.0, 0.0], &Device::Cpu)?)
+    }
+}
+
+/// 输出 `hello` 后输出 `stop`，停止token来自 `generation_config.json`
+struct MultiStopModel {
+    tokenizer: Tokenizer,
+    stop_token_ids: Vec<u32>,
+    stop: u32,
+}
+
+#[async_trait]
+impl CompletionModel for MultiStopModel {
+    fn model_id(&self) -> &str {
+        "multi-stop"
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    fn stop_token_ids(&self) -> Vec<u32> {
+        self.stop_token_ids.clone()
+    }
+
+    fn forward_logits(&self, input_ids: &[u32]) -> Result<Tensor, AppError> {
+        let next = if input_ids.len() == 1 { 1 } else { self.stop as usize };
+        let logits: Vec<f32> =
+            (0..VOCAB.len()).map(|i| if i == next { 10.0 } else { 0.0 }).collect();
+        Ok(Tensor::new(logits, &Device::Cpu)?)
+    }
+}
+
+fn assert_completion_model<T: CompletionModel + 'static>() {}
+
+#[test]
+fn test_models_implement_completion_model() {
+    assert_completion_model::<YiCoder>();
+    assert_completion_model::<DeepseekCoder>();
+}
+
+#[tokio::test]
+async fn test_generate_stops_on_eos() {
+    let model: Arc<dyn CompletionModel> =
+        Arc::new(ScriptedModel { tokenizer: word_level_tokenizer(&VOCAB), script: vec![1, 2] });
+
+    let output = model.generate("hello", &ChatCompletionParams::default()).await.unwrap();
+
+    assert_eq!(output.token_ids, vec![1, 2]);
+    assert_eq!(output.text, "hello world");
+    assert_eq!(output.prompt_tokens, 1);
+    assert_eq!(output.completion_tokens(), 2);
+    assert_eq!(output.finish_reason, FinishReason::Stop);
+}
+
+#[tokio::test]
+async fn test_generate_respects_max_tokens() {
+    let model = ScriptedModel { tokenizer: word_level_tokenizer(&VOCAB), script: vec![1, 2, 1, 2] };
+    let params = ChatCompletionParams { max_tokens: Some(3), ..Default::default() };
+
+    let output = model.generate("hello", &params).await.unwrap();
+
+    assert_eq!(output.completion_tokens(), 3);
+    assert_eq!(output.finish_reason, FinishReason::Length);
+}
+
+#[tokio::test]
+async fn test_generate_stream_sends_each_token() {
+    let model = ScriptedModel { tokenizer: word_level_tokenizer(&VOCAB), script: vec![2, 1] };
+    let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+
+    let output =
+        model.generate_stream("world", &ChatCompletionParams::default(), Some(&tx)).await.unwrap();
+    drop(tx);
+
+    let mut pieces = Vec::new();
+    while let Some(piece) = rx.recv().await {
+        pieces.push(piece);
+    }
+    assert_eq!(pieces, vec!["world", "hello"]);
+    assert_eq!(output.finish_reason, FinishReason::Stop);
+}
+
+/// 只含字节token的BPE tokenizer，ID 1..=3 依次为“你”的UTF-8字节 E4 BD A0
+fn byte_fallback_tokenizer() -> Tokenizer {
+    let json = serde_json::json!({
+        "version": "1.0",
+        "truncation": null,
+        "padding": null,
+        "added_tokens": [],
+        "normalizer": null,
+        "pre_tokenizer": null,
+        "post_processor": null,
+        "decoder": {"type": "ByteFallback"},
+        "model": {
+            "type": "BPE",
+            "dropout": null,
+            "unk_token": null,
+            "continuing_subword_prefix": null,
+            "end_of_word_suffix": null,
+            "fuse_unk": false,
+            "byte_fallback": true,
+            "vocab": {"<eos>": 0, "<0xE4>": 1, "<0xBD>": 2, "<0xA0>": 3},
+            "merges": []
+        }
+    });
+    Tokenizer::from_str(&json.to_string()).unwrap()
+}
+
+#[tokio::test]
+async fn test_generate_stream_waits_for_complete_utf8_chars() {
+    // 提示词“你”编码为3个字节token，第一次前向传播时step为2
+    let model = ScriptedModel { tokenizer: byte_fallback_tokenizer(), script: vec![0, 0, 1, 2, 3] };
+    let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+
+    let output =
+        model.generate_stream("你", &ChatCompletionParams::default(), Some(&tx)).await.unwrap();
+    drop(tx);
+
+    let mut pieces = Vec::new();
+    while let Some(piece) = rx.recv().await {
+        pieces.push(piece);
+    }
+    assert_eq!(output.token_ids, vec![1, 2, 3]);
+    assert!(pieces.iter().all(|piece| !piece.contains(char::REPLACEMENT_CHARACTER)));
+    assert_eq!(pieces.concat(), "你");
+    assert_eq!(output.text, "你");
+}
+
+#[test]
+fn test_complete_utf8_output_passes_validation() {
+    let tokenizer = byte_fallback_tokenizer();
+
+    let invalid = invalid_utf8_tokens(&tokenizer, &[1, 2, 3], "你").unwrap();
+
+    assert!(invalid.is_empty());
+}
+
+#[test]
+fn test_dangling_byte_token_is_flagged() {
+    let tokenizer = byte_fallback_tokenizer();
+    // “你”之后多出一个孤立的续字节 0xBD
+    let token_ids = [1, 2, 3, 2];
+    let text = tokenizer.decode(&token_ids, true).unwrap();
+    assert!(text.contains(char::REPLACEMENT_CHARACTER));
+
+    let invalid = invalid_utf8_tokens(&tokenizer, &token_ids, &text).unwrap();
+
+    assert_eq!(invalid, vec![2]);
+}
+
+#[tokio::test]
+async fn test_invalid_temperature_is_rejected() {
+    let model = ScriptedModel { tokenizer: word_level_tokenizer(&VOCAB), script: vec![1] };
+    let params = ChatCompletionParams { temperature: Some(f32::NAN), ..Default::default() };
+
+    let result = model.generate("hello", &params).await;
+
+    assert!(matches!(result, Err(AppError::InvalidParameter(_))));
+}
+
+#[tokio::test]
+async fn test_nan_logit_clamped_returns_completion() {
+    let model = NanModel { tokenizer: word_level_tokenizer(&VOCAB), policy: NanPolicy::Clamp };
+    let params =
+        ChatCompletionParams { temperature: Some(0.7), seed: Some(1), ..Default::default() };
+
+    let output = model.generate("hello", &params).await.unwrap();
+
+    assert_eq!(output.finish_reason, FinishReason::Stop);
+    assert!(!output.token_ids.is_empty());
+}
+
+#[tokio::test]
+async fn test_nan_logit_fails_with_error_policy() {
+    let model = NanModel { tokenizer: word_level_tokenizer(&VOCAB), policy: NanPolicy::Error };
+    let params =
+        ChatCompletionParams { temperature: Some(0.7), seed: Some(1), ..Default::default() };
+
+    assert!(model.generate("hello", &params).await.is_err());
+}
+
+#[tokio::test]
+async fn test_min_tokens_suppresses_early_eos() {
+    let model = EagerEosModel { tokenizer: word_level_tokenizer(&VOCAB) };
+    let params =
+        ChatCompletionParams { min_tokens: Some(10), max_tokens: Some(20), ..Default::default() };
+
+    let output = model.generate("hello", &params).await.unwrap();
+
+    assert_eq!(output.completion_tokens(), 10);
+    assert_eq!(output.finish_reason, FinishReason::Stop);
+}
+
+#[tokio::test]
+async fn test_min_tokens_suppresses_early_eos_when_sampling() {
+    let model = EagerEosModel { tokenizer: word_level_tokenizer(&VOCAB) };
+    let params = ChatCompletionParams {
+        temperature: Some(1.0),
+        seed: Some(5),
+        min_tokens: Some(10),
+        max_tokens: Some(20),
+        ..Default::default()
+    };
+
+    let output = model.generate("hello", &params).await.unwrap();
+
+    assert!(output.completion_tokens() >= 10);
+}
+
+/// 编码时在开头添加 `<s>` (id 4) 的tokenizer
+fn bos_tokenizer() -> Tokenizer {
+    let json = serde_json::json!({
+        "version": "1.0",
+        "truncation": null,
+        "padding": null,
+        "added_tokens": [{
+            "id": 4, "content": "<s>", "single_word": false, "lstrip": false,
+            "rstrip": false, "normalized": false, "special": true
+        }],
+        "normalizer": null,
+        "pre_tokenizer": {"type": "Whitespace"},
+        "post_processor": {
+            "type": "TemplateProcessing",
+            "single": [{"SpecialToken": {"id": "<s>", "type_id": 0}}, {"Sequence": {"id": "A", "type_id": 0}}],
+            "pair": [{"SpecialToken": {"id": "<s>", "type_id": 0}}, {"Sequence": {"id": "A", "type_id": 0}}, {"Sequence": {"id": "B", "type_id": 1}}],
+            "special_tokens": {"<s>": {"id": "<s>", "ids": [4], "tokens": ["<s>"]}}
+        },
+        "decoder": null,
+        "model": {
+            "type": "WordLevel",
+            "vocab": {"<eos>": 0, "hello": 1, "world": 2, "<unk>": 3, "<s>": 4},
+            "unk_token": "<unk>"
+        }
+    });
+    Tokenizer::from_str(&json.to_string()).unwrap()
+}
+
+/// 由字段决定是否添加特殊token的模型
+struct BosModel {
+    tokenizer: Tokenizer,
+    add_special_tokens: bool,
+}
+
+#[async_trait]
+impl CompletionModel for BosModel {
+    fn model_id(&self) -> &str {
+        "bos"
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    fn add_special_tokens(&self) -> bool {
+        self.add_special_tokens
+    }
+
+    fn forward_logits(&self, _input_ids: &[u32]) -> Result<Tensor, AppError> {
+        Ok(Tensor::new(&[10f32, 0.0, 0.0, 0.0, 0.0], &Device::Cpu)?)
+    }
+}
+
+#[test]
+fn test_add_special_tokens_toggles_bos() {
+    let with_bos = BosModel { tokenizer: bos_tokenizer(), add_special_tokens: true };
+    let without_bos = BosModel { tokenizer: bos_tokenizer(), add_special_tokens: false };
+
+    assert_eq!(with_bos.encode_prompt("hello world").unwrap(), vec![4, 1, 2]);
+    assert_eq!(without_bos.encode_prompt("hello world").unwrap(), vec![1, 2]);
+}
+
+#[test]
+fn test_unconfigured_model_adds_special_tokens() {
+    let model = ScriptedModel { tokenizer: bos_tokenizer(), script: vec![] };
+    assert_eq!(model.encode_prompt("hello").unwrap(), vec![4, 1]);
+}
+
+#[test]
+fn test_tokenizer_larger_than_vocab_size_is_rejected() {
+    let tokenizer = word_level_tokenizer(&VOCAB);
+
+    let result = check_vocab_size(&tokenizer, VOCAB.len() - 1);
+
+    assert!(matches!(result, Err(AppError::ConfigError(_))));
+    // embedding填充得比词表大、或未声明vocab_size时通过
+    assert!(check_vocab_size(&tokenizer, VOCAB.len()).is_ok());
+    assert!(check_vocab_size(&tokenizer, VOCAB.len() + 60).is_ok());
+    assert!(check_vocab_size(&tokenizer, 0).is_ok());
+}
+
+#[tokio::test]
+async fn test_any_eos_from_generation_config_stops_generation() {
+    let path =
+        std::env::temp_dir().join(format!("generation-config-{}.json", uuid::Uuid::new_v4()));
+    std::fs::write(&path, r#"{"eos_token_id": [2, 3], "temperature": 0.7}"#).unwrap();
+    let stop_token_ids = read_stop_token_ids(&path, 0);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(stop_token_ids, vec![2, 3]);
+
+    for stop in [2, 3] {
+        let model = MultiStopModel {
+            tokenizer: word_level_tokenizer(&VOCAB),
+            stop_token_ids: stop_token_ids.clone(),
+            stop,
+        };
+
+        let output = model.generate("hello", &ChatCompletionParams::default()).await.unwrap();
+
+        assert_eq!(output.token_ids, vec![1], "stop token {}", stop);
+        assert_eq!(output.finish_reason, FinishReason::Stop);
+    }
+}
+
+#[test]
+fn test_stop_tokens_fall_back_to_config_eos() {
+    let path =
+        std::env::temp_dir().join(format!("generation-config-{}.json", uuid::Uuid::new_v4()));
+    std::fs::write(&path, r#"{"eos_token_id": 7}"#).unwrap();
+    assert_eq!(read_stop_token_ids(&path, 0), vec![7]);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(read_stop_token_ids(&path, 5), vec![5]);
+}
@@ -0,0 +1,32 @@
+#[path = "../common/mod.rs"]
+mod common;
+
+use coder_openapi::service::chat::chat_completion::ChatCompletionParams;
+use coder_openapi::service::models::{CompletionModel, FinishReason};
+use common::FakeModel;
+
+const CONTEXT_LENGTH: usize = 512;
+
+#[tokio::test]
+async fn test_omitted_max_tokens_uses_remaining_context() {
+    let model = FakeModel::endless("endless").with_context_length(CONTEXT_LENGTH);
+    let params = ChatCompletionParams { temperature: Some(0.0), ..Default::default() };
+
+    let output = model.generate("user : hi", &params).await.unwrap();
+
+    assert_eq!(output.prompt_tokens, 3);
+    assert!(output.token_ids.len() > 100);
+    assert_eq!(output.token_ids.len(), CONTEXT_LENGTH - 3);
+    assert_eq!(output.finish_reason, FinishReason::Length);
+}
+
+#[tokio::test]
+async fn test_explicit_max_tokens_is_respected() {
+    let model = FakeModel::endless("endless").with_context_length(CONTEXT_LENGTH);
+    let params =
+        ChatCompletionParams { temperature: Some(0.0), max_tokens: Some(5), ..Default::default() };
+
+    let output = model.generate("user : hi", &params).await.unwrap();
+
+    assert_eq!(output.token_ids.len(), 5);
+}
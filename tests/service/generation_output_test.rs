@@ -0,0 +1,98 @@
+#[path = "../common/mod.rs"]
+mod common;
+
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use coder_openapi::entities::chat_completion_message::ChatCompletionMessage;
+use coder_openapi::error::AppError;
+use coder_openapi::service::chat::chat_completion::{ChatCompletionParams, ChatCompletionService};
+use coder_openapi::service::models::{
+    CompletionModel, FinishReason, GenerationOutput, ModelManager,
+};
+use common::word_level_tokenizer;
+use std::sync::Arc;
+use tokenizers::Tokenizer;
+
+const MODEL_ID: &str = "seeded-model";
+
+/// 生成 `seed` 个token，达到 `max_tokens` 时截断，各choice的种子不同因此长度不同
+struct SeededModel {
+    tokenizer: Tokenizer,
+}
+
+#[async_trait]
+impl CompletionModel for SeededModel {
+    fn model_id(&self) -> &str {
+        MODEL_ID
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    fn forward_logits(&self, _input_ids: &[u32]) -> Result<Tensor, AppError> {
+        Ok(Tensor::new(&[1f32, 0.0], &Device::Cpu)?)
+    }
+
+    async fn generate(
+        &self,
+        _prompt: &str,
+        params: &ChatCompletionParams,
+    ) -> Result<GenerationOutput, AppError> {
+        let wanted = params.seed.unwrap_or(0) as usize;
+        let max_tokens = params.max_tokens.unwrap_or(usize::MAX);
+        let generated = wanted.min(max_tokens);
+        Ok(GenerationOutput {
+            text: "x ".repeat(generated),
+            token_ids: vec![1; generated],
+            prompt_tokens: 3,
+            finish_reason: if wanted > max_tokens {
+                FinishReason::Length
+            } else {
+                FinishReason::Stop
+            },
+            timed_out: false,
+            cancelled: false,
+            clamped_max_tokens: None,
+            system_fingerprint: None,
+            sampling: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_each_choice_carries_its_own_finish_reason_and_tokens() {
+    let manager = ModelManager::new();
+    let model = SeededModel { tokenizer: word_level_tokenizer(&["<eos>", "<unk>"]) };
+    manager.register_model(MODEL_ID, Arc::new(model)).await;
+    let service = ChatCompletionService::new(manager);
+    let messages = vec![ChatCompletionMessage {
+        role: "user".to_string(),
+        content: "count".to_string(),
+        name: None,
+    }];
+    // 三个choice的种子依次为1、2、3
+    let params = ChatCompletionParams {
+        n: Some(3),
+        seed: Some(1),
+        max_tokens: Some(2),
+        ..Default::default()
+    };
+
+    let completion = service.complete_with_fallback(MODEL_ID, messages, params).await.unwrap();
+
+    let outputs = &completion.outputs;
+    assert_eq!(outputs.len(), 3);
+    let finish_reasons: Vec<FinishReason> =
+        outputs.iter().map(|output| output.finish_reason).collect();
+    assert_eq!(finish_reasons, vec![FinishReason::Stop, FinishReason::Stop, FinishReason::Length]);
+    let completion_tokens: Vec<usize> =
+        outputs.iter().map(GenerationOutput::completion_tokens).collect();
+    assert_eq!(completion_tokens, vec![1, 2, 2]);
+    assert!(outputs.iter().all(|output| output.prompt_tokens == 3));
+    assert_eq!(completion.model, MODEL_ID);
+}
@@ -0,0 +1,56 @@
+use candle_core::Device;
+use coder_openapi::entities::chat_completion_message::ChatCompletionMessage;
+use coder_openapi::service::models::yi_coder::config::ModelConfig;
+use coder_openapi::service::models::yi_coder::inference::YiCoderInference;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+const MESSAGES: usize = 20;
+
+fn message(index: usize) -> ChatCompletionMessage {
+    ChatCompletionMessage {
+        role: "assistant".to_string(),
+        content: format!("token-{}", index),
+        name: None,
+    }
+}
+
+#[tokio::test]
+async fn test_slow_consumer_receives_all_messages_in_order() {
+    let config: ModelConfig = serde_json::from_str("{}").unwrap();
+    let inference = Arc::new(YiCoderInference::new(&config, &Device::Cpu));
+    // 缓冲区只有1条，生产者必须等待消费者读取
+    let (tx, mut rx) = mpsc::channel(1);
+    inference.set_stream_sender(tx);
+
+    let producer = {
+        let inference = inference.clone();
+        tokio::spawn(async move {
+            for index in 0..MESSAGES {
+                inference.send_stream_response(&message(index)).await.unwrap();
+            }
+        })
+    };
+
+    let mut received = Vec::new();
+    while received.len() < MESSAGES {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        received.push(rx.recv().await.unwrap().content);
+    }
+    producer.await.unwrap();
+
+    let expected: Vec<String> = (0..MESSAGES).map(|index| message(index).content).collect();
+    assert_eq!(received, expected);
+}
+
+#[tokio::test]
+async fn test_send_fails_once_consumer_is_gone() {
+    let config: ModelConfig = serde_json::from_str("{}").unwrap();
+    let inference = YiCoderInference::new(&config, &Device::Cpu);
+    let (tx, rx) = mpsc::channel(1);
+    inference.set_stream_sender(tx);
+    drop(rx);
+
+    assert!(inference.send_stream_response(&message(0)).await.is_err());
+}
@@ -0,0 +1,130 @@
+#[path = "../common/mod.rs"]
+mod common;
+
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use coder_openapi::error::AppError;
+use coder_openapi::service::chat::chat_completion::ChatCompletionParams;
+use coder_openapi::service::models::input_buffer::InputBuffer;
+use coder_openapi::service::models::CompletionModel;
+use common::word_level_tokenizer;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokenizers::Tokenizer;
+
+const VOCAB: [&str; 8] = ["a", "b", "c", "d", "e", "f", "g", "<unk>"];
+const GENERATED_TOKENS: usize = 128;
+
+#[test]
+fn test_generation_allocates_buffer_once() {
+    let mut input_ids = vec![1, 2, 3, 4];
+    let mut buffer = InputBuffer::with_capacity(&Device::Cpu, input_ids.len() + GENERATED_TOKENS);
+
+    for step in 0..GENERATED_TOKENS {
+        let input = buffer.update(&input_ids).unwrap();
+        assert_eq!(input.to_vec1::<u32>().unwrap(), input_ids);
+        input_ids.push(step as u32 % 7);
+    }
+
+    assert_eq!(buffer.allocations(), 1);
+}
+
+#[test]
+fn test_buffer_grows_by_doubling() {
+    let mut input_ids = vec![0];
+    let mut buffer = InputBuffer::with_capacity(&Device::Cpu, 2);
+
+    for step in 0..100 {
+        let input = buffer.update(&input_ids).unwrap();
+        assert_eq!(input.to_vec1::<u32>().unwrap(), input_ids);
+        input_ids.push(step);
+    }
+
+    // 2 -> 4 -> 8 -> ... -> 128
+    assert_eq!(buffer.allocations(), 7);
+    assert_eq!(buffer.capacity(), 128);
+}
+
+#[test]
+fn test_buffer_rewrites_changed_prefix() {
+    let mut buffer = InputBuffer::with_capacity(&Device::Cpu, 8);
+
+    buffer.update(&[1, 2, 3]).unwrap();
+    let input = buffer.update(&[1, 5]).unwrap();
+
+    assert_eq!(input.to_vec1::<u32>().unwrap(), vec![1, 5]);
+    assert_eq!(buffer.allocations(), 1);
+}
+
+/// logits只取决于输入序列的模型，`buffered` 时通过输入缓冲区执行前向传播
+struct HashModel {
+    tokenizer: Tokenizer,
+    device: Device,
+    buffered: bool,
+    buffered_calls: AtomicUsize,
+}
+
+impl HashModel {
+    fn new(buffered: bool) -> Self {
+        Self {
+            tokenizer: word_level_tokenizer(&VOCAB),
+            device: Device::Cpu,
+            buffered,
+            buffered_calls: AtomicUsize::new(0),
+        }
+    }
+}
+
+#[async_trait]
+impl CompletionModel for HashModel {
+    fn model_id(&self) -> &str {
+        "hash"
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        None
+    }
+
+    fn forward_logits(&self, input_ids: &[u32]) -> Result<Tensor, AppError> {
+        let hash = input_ids
+            .iter()
+            .fold(7u64, |hash, &id| hash.wrapping_mul(31).wrapping_add(id as u64 + 1));
+        let logits: Vec<f32> = (0..VOCAB.len() as u64)
+            .map(|i| (hash.wrapping_add(i * 17) % 13) as f32 / 4.0)
+            .collect();
+        Ok(Tensor::new(logits, &Device::Cpu)?)
+    }
+
+    fn input_device(&self) -> Option<&Device> {
+        self.buffered.then_some(&self.device)
+    }
+
+    fn forward_input(&self, input: &Tensor) -> Result<Tensor, AppError> {
+        self.buffered_calls.fetch_add(1, Ordering::SeqCst);
+        self.forward_logits(&input.to_vec1::<u32>()?)
+    }
+}
+
+#[tokio::test]
+async fn test_buffered_generation_matches_naive_loop() {
+    let params = ChatCompletionParams {
+        temperature: Some(0.8),
+        seed: Some(7),
+        max_tokens: Some(GENERATED_TOKENS),
+        ..Default::default()
+    };
+    let naive = HashModel::new(false);
+    let buffered = HashModel::new(true);
+
+    let expected = naive.generate("a b c", &params).await.unwrap();
+    let output = buffered.generate("a b c", &params).await.unwrap();
+
+    assert_eq!(expected.completion_tokens(), GENERATED_TOKENS);
+    assert_eq!(output.token_ids, expected.token_ids);
+    assert_eq!(output.text, expected.text);
+    assert_eq!(naive.buffered_calls.load(Ordering::SeqCst), 0);
+    assert_eq!(buffered.buffered_calls.load(Ordering::SeqCst), GENERATED_TOKENS);
+}
@@ -0,0 +1,115 @@
+#[path = "../common/mod.rs"]
+mod common;
+
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use coder_openapi::error::AppError;
+use coder_openapi::service::chat::chat_completion::ChatCompletionParams;
+use coder_openapi::service::models::json_schema::{JsonSchemaFormat, ResponseFormat};
+use coder_openapi::service::models::{CompletionModel, FinishReason};
+use common::word_level_tokenizer;
+use serde_json::{json, Value};
+use tokenizers::Tokenizer;
+
+const VOCAB: [&str; 9] = ["<eos>", "{", "\"answer\"", ":", "\"hi\"", "}", "42", "hello", "<unk>"];
+
+/// 最倾向于输出 `42` 和 `hello` 这类不符合schema的token的模型
+struct UnstructuredModel {
+    tokenizer: Tokenizer,
+}
+
+#[async_trait]
+impl CompletionModel for UnstructuredModel {
+    fn model_id(&self) -> &str {
+        "unstructured"
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    fn forward_logits(&self, _input_ids: &[u32]) -> Result<Tensor, AppError> {
+        Ok(Tensor::new(&[0f32, 0.0, 0.0, 0.0, 1.0, 0.0, 5.0, 10.0, 0.0], &Device::Cpu)?)
+    }
+}
+
+fn params(schema: Value) -> ChatCompletionParams {
+    ChatCompletionParams {
+        max_tokens: Some(16),
+        response_format: Some(ResponseFormat::JsonSchema {
+            json_schema: JsonSchemaFormat { name: Some("reply".to_string()), schema },
+        }),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_output_conforms_to_schema() {
+    let model = UnstructuredModel { tokenizer: word_level_tokenizer(&VOCAB) };
+    let schema = json!({
+        "type": "object",
+        "properties": {"answer": {"type": "string"}}
+    });
+
+    let output = model.generate("hello", &params(schema)).await.unwrap();
+
+    let value: Value = serde_json::from_str(&output.text).unwrap();
+    assert!(value["answer"].is_string(), "unexpected output: {}", output.text);
+    assert_eq!(value.as_object().unwrap().len(), 1);
+    assert_eq!(output.finish_reason, FinishReason::Stop);
+}
+
+#[tokio::test]
+async fn test_number_and_enum_properties() {
+    let model = UnstructuredModel { tokenizer: word_level_tokenizer(&VOCAB) };
+    let schema = json!({
+        "type": "object",
+        "properties": {"answer": {"enum": ["hi"]}}
+    });
+
+    let output = model.generate("hello", &params(schema)).await.unwrap();
+    let value: Value = serde_json::from_str(&output.text).unwrap();
+    assert_eq!(value["answer"], "hi");
+
+    let output = model.generate("hello", &params(json!({"type": "number"}))).await.unwrap();
+    assert_eq!(output.text, "42");
+}
+
+#[tokio::test]
+async fn test_unsatisfiable_schema_returns_chat_error() {
+    let model = UnstructuredModel { tokenizer: word_level_tokenizer(&VOCAB) };
+    let schema = json!({
+        "type": "object",
+        "properties": {"count": {"type": "number"}}
+    });
+
+    let result = model.generate("hello", &params(schema)).await;
+
+    assert!(matches!(result, Err(AppError::Chat(_))));
+}
+
+#[tokio::test]
+async fn test_unsupported_schema_is_rejected() {
+    let model = UnstructuredModel { tokenizer: word_level_tokenizer(&VOCAB) };
+
+    let result = model.generate("hello", &params(json!({"type": "array"}))).await;
+
+    assert!(matches!(result, Err(AppError::InvalidParameter(_))));
+}
+
+#[test]
+fn test_response_format_deserializes() {
+    let format: ResponseFormat = serde_json::from_value(json!({
+        "type": "json_schema",
+        "json_schema": {"name": "reply", "schema": {"type": "string"}}
+    }))
+    .unwrap();
+    assert_eq!(format.schema(), Some(&json!({"type": "string"})));
+
+    let format: ResponseFormat = serde_json::from_value(json!({"type": "text"})).unwrap();
+    assert!(format.schema().is_none());
+}
@@ -0,0 +1,100 @@
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use coder_openapi::service::models::yi_coder::config::ModelConfig;
+use coder_openapi::service::models::yi_coder::loader::load_safetensors;
+use coder_openapi::service::models::yi_coder::transformer::YiCoderTransformer;
+use coder_openapi::utils::config::{ArchitectureConfig, NumericalStabilityConfig};
+use std::collections::HashMap;
+
+/// 权重目录中 `config.json` 的HuggingFace字段
+const CONFIG_JSON: &str = r#"{
+    "hidden_size": 8,
+    "num_attention_heads": 2,
+    "intermediate_size": 16,
+    "num_hidden_layers": 2,
+    "layer_norm_eps": 1e-5,
+    "vocab_size": 10,
+    "max_position_embeddings": 64
+}"#;
+
+fn build(config: &ModelConfig) -> YiCoderTransformer {
+    YiCoderTransformer::new(config, VarBuilder::zeros(DType::F32, &Device::Cpu)).unwrap()
+}
+
+#[test]
+fn test_num_hidden_layers_is_read_from_config_json() {
+    let config: ModelConfig = serde_json::from_str(CONFIG_JSON).unwrap();
+
+    assert_eq!(config.num_layers, 2);
+    assert_eq!(build(&config).num_layers(), 2);
+}
+
+#[test]
+fn test_architecture_override_sets_layer_count() {
+    let architecture: ArchitectureConfig =
+        serde_yaml::from_str("num_hidden_layers: 3\nmax_position_embeddings: 128").unwrap();
+    let config =
+        serde_json::from_str::<ModelConfig>(CONFIG_JSON).unwrap().with_architecture(&architecture);
+
+    assert_eq!(build(&config).num_layers(), 3);
+    assert_eq!(config.max_position_embeddings, 128);
+    // 未覆盖的字段保留config.json中的值
+    assert_eq!(config.hidden_size, 8);
+}
+
+#[test]
+fn test_hidden_clamp_bounds_forward_output() {
+    // 没有Transformer层时输出为最终LayerNorm的bias，bias设为100
+    let config: ModelConfig = serde_json::from_str(CONFIG_JSON).unwrap();
+    let config = config.with_architecture(&ArchitectureConfig {
+        num_hidden_layers: Some(0),
+        ..Default::default()
+    });
+    let forward = |stability: &NumericalStabilityConfig| {
+        let tensors = HashMap::from([
+            ("model.norm.weight".to_string(), Tensor::ones(8, DType::F32, &Device::Cpu).unwrap()),
+            ("model.norm.bias".to_string(), Tensor::new(&[100f32; 8], &Device::Cpu).unwrap()),
+        ]);
+        let vb = VarBuilder::from_tensors(tensors, DType::F32, &Device::Cpu);
+        let transformer =
+            YiCoderTransformer::new(&config.clone().with_numerical_stability(stability), vb)
+                .unwrap();
+        let input = Tensor::new(&[1u32, 2, 3], &Device::Cpu).unwrap();
+        transformer.forward(&input).unwrap().flatten_all().unwrap().to_vec1::<f32>().unwrap()
+    };
+
+    let default_output = forward(&NumericalStabilityConfig::default());
+    assert!(default_output.iter().all(|&x| (x - 100.0).abs() < 1e-3), "{:?}", default_output);
+
+    let tight = NumericalStabilityConfig { hidden_clamp: 10.0, ..Default::default() };
+    let tight_output = forward(&tight);
+    assert!(tight_output.iter().all(|&x| x.abs() <= 10.0), "{:?}", tight_output);
+}
+
+#[tokio::test]
+async fn test_forward_without_strict_validation_matches_strict() {
+    let config = ModelConfig::from_file("tests/fixtures/tiny_model/config.json").unwrap();
+    let tensors = load_safetensors(
+        vec!["tests/fixtures/tiny_model/model.safetensors".into()],
+        Device::Cpu,
+        true,
+    )
+    .await
+    .unwrap();
+    let forward = |strict_validation: bool| {
+        let vb = VarBuilder::from_tensors(tensors.clone(), DType::F32, &Device::Cpu);
+        let transformer =
+            YiCoderTransformer::new(&config, vb).unwrap().with_strict_validation(strict_validation);
+        let input = Tensor::new(&[2u32, 3, 4, 5], &Device::Cpu).unwrap();
+        transformer.forward(&input).unwrap()
+    };
+
+    let strict = forward(true);
+    let relaxed = forward(false);
+    assert_eq!(strict.dims(), &[4, 16]);
+    assert_eq!(relaxed.dims(), strict.dims());
+    assert_eq!(
+        relaxed.flatten_all().unwrap().to_vec1::<f32>().unwrap(),
+        strict.flatten_all().unwrap().to_vec1::<f32>().unwrap()
+    );
+}
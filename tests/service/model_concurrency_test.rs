@@ -0,0 +1,176 @@
+#[path = "../common/mod.rs"]
+mod common;
+
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use coder_openapi::entities::chat_completion_message::ChatCompletionMessage;
+use coder_openapi::error::AppError;
+use coder_openapi::service::chat::chat_completion::{ChatCompletionParams, ChatCompletionService};
+use coder_openapi::service::models::scheduler::{Priority, PriorityLimiter};
+use coder_openapi::service::models::{CompletionModel, ModelManager};
+use coder_openapi::utils::config::AppConfig;
+use common::word_level_tokenizer;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokenizers::Tokenizer;
+
+const MODEL_ID: &str = "slow-model";
+const VOCAB: [&str; 5] = ["<eos>", "user", ":", "hi", "<unk>"];
+
+/// 每步前向传播耗时固定时间，并记录同时运行的推理数量峰值
+struct SlowModel {
+    tokenizer: Tokenizer,
+    step_delay: Duration,
+    in_flight: AtomicUsize,
+    peak_in_flight: AtomicUsize,
+}
+
+impl SlowModel {
+    fn new(step_delay: Duration) -> Self {
+        Self {
+            tokenizer: word_level_tokenizer(&VOCAB),
+            step_delay,
+            in_flight: AtomicUsize::new(0),
+            peak_in_flight: AtomicUsize::new(0),
+        }
+    }
+}
+
+#[async_trait]
+impl CompletionModel for SlowModel {
+    fn model_id(&self) -> &str {
+        MODEL_ID
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    fn forward_logits(&self, _input_ids: &[u32]) -> Result<Tensor, AppError> {
+        let current = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak_in_flight.fetch_max(current, Ordering::SeqCst);
+        std::thread::sleep(self.step_delay);
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        Ok(Tensor::new(&[0f32, 0.0, 0.0, 10.0, 0.0], &Device::Cpu)?)
+    }
+}
+
+fn messages() -> Vec<ChatCompletionMessage> {
+    vec![ChatCompletionMessage { role: "user".to_string(), content: "hi".to_string(), name: None }]
+}
+
+fn params() -> ChatCompletionParams {
+    ChatCompletionParams { max_tokens: Some(2), ..Default::default() }
+}
+
+async fn service_with_limit(
+    model: Arc<SlowModel>,
+    queue_timeout: Duration,
+) -> Arc<ChatCompletionService> {
+    let manager =
+        ModelManager::new().with_concurrency_limit(MODEL_ID, 1).with_queue_timeout(queue_timeout);
+    manager.register_model(MODEL_ID, model).await;
+    Arc::new(ChatCompletionService::new(manager))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_max_concurrent_one_serializes_requests() {
+    let model = Arc::new(SlowModel::new(Duration::from_millis(50)));
+    let service = service_with_limit(model.clone(), Duration::from_secs(5)).await;
+
+    let first = tokio::spawn({
+        let service = service.clone();
+        async move { service.complete(MODEL_ID, messages(), params()).await }
+    });
+    let second = tokio::spawn({
+        let service = service.clone();
+        async move { service.complete(MODEL_ID, messages(), params()).await }
+    });
+
+    assert!(first.await.unwrap().is_ok());
+    assert!(second.await.unwrap().is_ok());
+    assert_eq!(model.peak_in_flight.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_queue_timeout_returns_service_unavailable() {
+    let model = Arc::new(SlowModel::new(Duration::from_millis(200)));
+    let service = service_with_limit(model, Duration::from_millis(20)).await;
+
+    let first = tokio::spawn({
+        let service = service.clone();
+        async move { service.complete(MODEL_ID, messages(), params()).await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let second = service.complete(MODEL_ID, messages(), params()).await;
+
+    assert!(matches!(second, Err(AppError::ServiceUnavailable(_))));
+    assert!(first.await.unwrap().is_ok());
+}
+
+#[test]
+fn test_zero_max_concurrent_is_rejected() {
+    let yaml = "server:\n  host: 127.0.0.1\n  port: 8080\n  shutdown_timeout: 30\n\
+                locales:\n  path: locales\n  default: zh\n\
+                models_cache_dir: models_cache\n\
+                models:\n  yi-coder:\n    hf_hub_id: 01-ai/Yi-Coder-1.5B-Chat\n    max_concurrent: 0\n\
+                \x20   model_files:\n\
+                \x20     weights: [model.safetensors]\n      config: config.json\n\
+                \x20     tokenizer: tokenizer.json\n      tokenizer_config: tokenizer_config.json\n\
+                \x20     generation_config: generation_config.json\n\
+                chat:\n  defaults:\n    temperature: 0.7\n    top_p: 0.9\n    n: 1\n    max_tokens: 16\n    stream: false\n";
+    let path = std::env::temp_dir().join(format!("app-{}.yml", uuid::Uuid::new_v4()));
+    std::fs::write(&path, yaml).unwrap();
+    let result = AppConfig::load(path.to_str().unwrap());
+    std::fs::remove_file(&path).unwrap();
+
+    let message = result.unwrap_err().to_string();
+    assert!(message.contains("models.yi-coder.max_concurrent must be at least 1"), "{}", message);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_high_priority_acquires_slot_before_earlier_low_priority() {
+    let manager = ModelManager::new()
+        .with_concurrency_limit(MODEL_ID, 1)
+        .with_queue_timeout(Duration::from_secs(5));
+    let held = manager.acquire_permit(MODEL_ID, Priority::Normal).await.unwrap();
+
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let enqueue = |priority: Priority| {
+        let manager = manager.clone();
+        let order = order.clone();
+        tokio::spawn(async move {
+            let _permit = manager.acquire_permit(MODEL_ID, priority).await.unwrap();
+            order.lock().unwrap().push(priority);
+        })
+    };
+    let low = enqueue(Priority::Low);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let high = enqueue(Priority::High);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    drop(held);
+    low.await.unwrap();
+    high.await.unwrap();
+    assert_eq!(*order.lock().unwrap(), vec![Priority::High, Priority::Low]);
+}
+
+#[tokio::test]
+async fn test_abandoned_waiter_does_not_leak_permit() {
+    let limiter = PriorityLimiter::new(1);
+    let held = limiter.acquire(Priority::Normal).await;
+
+    let abandoned =
+        tokio::time::timeout(Duration::from_millis(20), limiter.acquire(Priority::High)).await;
+    assert!(abandoned.is_err());
+
+    drop(held);
+    assert_eq!(limiter.available_permits(), 1);
+    let _permit = limiter.acquire(Priority::Low).await;
+    assert_eq!(limiter.available_permits(), 0);
+}
@@ -0,0 +1,50 @@
+use candle_core::Device;
+use coder_openapi::service::models::yi_coder::loader::load_safetensors;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// 写入一个包含单个F32张量 (shape [2]) 的safetensors文件
+fn write_safetensors() -> PathBuf {
+    let header = br#"{"weight":{"dtype":"F32","shape":[2],"data_offsets":[0,8]}}"#;
+    let mut bytes = (header.len() as u64).to_le_bytes().to_vec();
+    bytes.extend_from_slice(header);
+    bytes.extend_from_slice(&1.0f32.to_le_bytes());
+    bytes.extend_from_slice(&2.0f32.to_le_bytes());
+
+    let path = std::env::temp_dir().join(format!("loader-{}.safetensors", uuid::Uuid::new_v4()));
+    std::fs::write(&path, bytes).unwrap();
+    path
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn test_load_does_not_block_runtime() {
+    let path = write_safetensors();
+    let ticks = Arc::new(AtomicUsize::new(0));
+    let ticker = {
+        let ticks = ticks.clone();
+        tokio::spawn(async move {
+            loop {
+                ticks.fetch_add(1, Ordering::SeqCst);
+                tokio::task::yield_now().await;
+            }
+        })
+    };
+
+    // 单线程运行时中，只有加载让出运行时，计数任务才能在加载完成前执行
+    let tensors = load_safetensors(vec![path.clone()], Device::Cpu, true).await;
+    let progressed = ticks.load(Ordering::SeqCst);
+    ticker.abort();
+    std::fs::remove_file(&path).unwrap();
+
+    let tensors = tensors.unwrap();
+    assert!(progressed > 0);
+    assert_eq!(tensors["weight"].to_vec1::<f32>().unwrap(), vec![1.0, 2.0]);
+}
+
+#[tokio::test]
+async fn test_load_skips_non_safetensors_files() {
+    let path = std::env::temp_dir().join(format!("loader-{}.json", uuid::Uuid::new_v4()));
+    let tensors = load_safetensors(vec![path], Device::Cpu, true).await.unwrap();
+    assert!(tensors.is_empty());
+}
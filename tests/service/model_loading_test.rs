@@ -0,0 +1,164 @@
+use coder_openapi::service::models::echo::EchoModel;
+use coder_openapi::service::models::{CompletionModel, ModelError, ModelLoaderFn, ModelManager};
+use futures::future::join_all;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+const MODEL_ID: &str = "lazy-model";
+
+/// 记录调用次数、耗时一段时间才完成的模型构造函数，前 `failures` 次调用失败
+fn counting_loader(counter: Arc<AtomicUsize>, failures: usize) -> ModelLoaderFn {
+    Arc::new(move || {
+        let counter = counter.clone();
+        Box::pin(async move {
+            let calls = counter.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            if calls < failures {
+                return Err(ModelError::InitializationFailed("flaky".to_string()));
+            }
+            let model: Arc<dyn CompletionModel> = Arc::new(EchoModel::new().unwrap());
+            Ok(model)
+        })
+    })
+}
+
+#[tokio::test]
+async fn test_concurrent_first_requests_construct_model_once() {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let manager =
+        ModelManager::new().with_model_loader(MODEL_ID, counting_loader(counter.clone(), 0));
+
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let manager = manager.clone();
+            tokio::spawn(async move { manager.get_or_load_model(MODEL_ID).await.map(|_| ()) })
+        })
+        .collect();
+    for result in join_all(handles).await {
+        assert!(result.unwrap().is_ok());
+    }
+
+    assert_eq!(counter.load(Ordering::SeqCst), 1);
+    assert!(manager.is_model_available(MODEL_ID).await);
+    assert!(!manager.is_downloading(MODEL_ID));
+}
+
+#[tokio::test]
+async fn test_failed_initialization_is_retried() {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let manager =
+        ModelManager::new().with_model_loader(MODEL_ID, counting_loader(counter.clone(), 1));
+
+    assert!(manager.get_or_load_model(MODEL_ID).await.is_err());
+    assert!(manager.get_or_load_model(MODEL_ID).await.is_ok());
+    assert_eq!(counter.load(Ordering::SeqCst), 2);
+}
+
+/// 等待 `release` 通知后才完成的模型构造函数
+fn gated_loader(release: Arc<tokio::sync::Notify>) -> ModelLoaderFn {
+    Arc::new(move || {
+        let release = release.clone();
+        Box::pin(async move {
+            release.notified().await;
+            let model: Arc<dyn CompletionModel> = Arc::new(EchoModel::new().unwrap());
+            Ok(model)
+        })
+    })
+}
+
+#[tokio::test]
+async fn test_second_download_waits_for_first_when_limited() {
+    let release = Arc::new(tokio::sync::Notify::new());
+    let counter = Arc::new(AtomicUsize::new(0));
+    let manager = ModelManager::new()
+        .with_max_concurrent_downloads(1)
+        .with_model_loader("first-model", gated_loader(release.clone()))
+        .with_model_loader("second-model", counting_loader(counter.clone(), 0));
+
+    let first = tokio::spawn({
+        let manager = manager.clone();
+        async move { manager.get_or_load_model("first-model").await.map(|_| ()) }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let second = tokio::spawn({
+        let manager = manager.clone();
+        async move { manager.get_or_load_model("second-model").await.map(|_| ()) }
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(counter.load(Ordering::SeqCst), 0);
+    assert!(manager.is_downloading("first-model"));
+    // 排队中的模型尚未开始下载
+    assert!(!manager.is_downloading("second-model"));
+    assert!(manager.is_download_pending("second-model"));
+    assert_eq!(manager.download_queue_position("second-model"), Some(1));
+    assert_eq!(manager.download_queue_position("first-model"), None);
+
+    release.notify_one();
+    assert!(first.await.unwrap().is_ok());
+    assert!(second.await.unwrap().is_ok());
+    assert_eq!(counter.load(Ordering::SeqCst), 1);
+    assert_eq!(manager.download_queue_position("second-model"), None);
+}
+
+#[tokio::test]
+async fn test_status_reads_proceed_while_another_model_downloads() {
+    let release = Arc::new(tokio::sync::Notify::new());
+    let manager =
+        ModelManager::new().with_model_loader("slow-model", gated_loader(release.clone()));
+
+    let download = tokio::spawn({
+        let manager = manager.clone();
+        async move { manager.get_or_load_model("slow-model").await.map(|_| ()) }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(manager.is_downloading("slow-model"));
+
+    // 下载期间不持有model_status的锁，其他模型的状态读取不会被阻塞
+    let status = tokio::time::timeout(Duration::from_secs(1), manager.get_model_status("echo"))
+        .await
+        .expect("status read blocked by an unrelated download");
+    assert!(status.unwrap().is_enabled);
+    tokio::time::timeout(Duration::from_secs(1), manager.get_all_model_status())
+        .await
+        .expect("status listing blocked by an unrelated download");
+
+    release.notify_one();
+    assert!(download.await.unwrap().is_ok());
+}
+
+#[tokio::test]
+async fn test_idle_model_is_unloaded_and_reloaded_on_next_request() {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let manager = ModelManager::new()
+        .with_model_loader(MODEL_ID, counting_loader(counter.clone(), 0))
+        .with_idle_ttl(Duration::from_millis(100));
+    let unloader = manager.spawn_idle_unloader().unwrap();
+
+    manager.get_or_load_model(MODEL_ID).await.unwrap();
+    assert!(manager.get_model(MODEL_ID).await.is_some());
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(manager.get_model(MODEL_ID).await.is_none());
+    // 内置模型无法重新加载，不会被卸载
+    assert!(manager.get_model("echo").await.is_some());
+
+    manager.get_or_load_model(MODEL_ID).await.unwrap();
+    assert_eq!(counter.load(Ordering::SeqCst), 2);
+    unloader.abort();
+}
+
+#[tokio::test]
+async fn test_recently_used_model_stays_loaded() {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let manager = ModelManager::new()
+        .with_model_loader(MODEL_ID, counting_loader(counter.clone(), 0))
+        .with_idle_ttl(Duration::from_secs(60));
+
+    manager.get_or_load_model(MODEL_ID).await.unwrap();
+
+    assert!(manager.unload_idle_models().await.is_empty());
+    assert!(manager.get_model(MODEL_ID).await.is_some());
+    assert_eq!(counter.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,94 @@
+#[path = "../common/mod.rs"]
+mod common;
+
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use coder_openapi::error::AppError;
+use coder_openapi::service::chat::chat_completion::ChatCompletionParams;
+use coder_openapi::service::models::sampling::repeated_ngram_tokens;
+use coder_openapi::service::models::{CompletionModel, FinishReason};
+use common::word_level_tokenizer;
+use tokenizers::Tokenizer;
+
+const VOCAB: [&str; 4] = ["<eos>", "hello", "world", "<unk>"];
+const HELLO: u32 = 1;
+const WORLD: u32 = 2;
+
+/// 总是倾向于交替输出 `hello world`，且从不输出EOS的模型
+struct LoopingModel {
+    tokenizer: Tokenizer,
+}
+
+#[async_trait]
+impl CompletionModel for LoopingModel {
+    fn model_id(&self) -> &str {
+        "looping"
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    fn forward_logits(&self, input_ids: &[u32]) -> Result<Tensor, AppError> {
+        let logits = match input_ids.last() {
+            Some(&HELLO) => [f32::NEG_INFINITY, 5.0, 10.0, f32::NEG_INFINITY],
+            _ => [f32::NEG_INFINITY, 10.0, 5.0, f32::NEG_INFINITY],
+        };
+        Ok(Tensor::new(&logits, &Device::Cpu)?)
+    }
+}
+
+fn params(no_repeat_ngram_size: Option<usize>) -> ChatCompletionParams {
+    ChatCompletionParams {
+        temperature: Some(0.0),
+        max_tokens: Some(10),
+        no_repeat_ngram_size,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_repeated_ngram_tokens() {
+    let tokens = [HELLO, WORLD, HELLO];
+    assert_eq!(repeated_ngram_tokens(&tokens, 2), vec![WORLD]);
+    assert_eq!(repeated_ngram_tokens(&tokens, 3), Vec::<u32>::new());
+    assert_eq!(repeated_ngram_tokens(&tokens, 1), vec![HELLO, WORLD, HELLO]);
+}
+
+#[tokio::test]
+async fn test_repetition_loops_until_max_tokens_by_default() {
+    let model = LoopingModel { tokenizer: word_level_tokenizer(&VOCAB) };
+
+    let output = model.generate("hello", &params(None)).await.unwrap();
+
+    assert_eq!(output.token_ids.len(), 10);
+    assert_eq!(output.finish_reason, FinishReason::Length);
+}
+
+#[tokio::test]
+async fn test_no_repeat_ngram_size_prevents_repeated_bigrams() {
+    let model = LoopingModel { tokenizer: word_level_tokenizer(&VOCAB) };
+
+    let output = model.generate("hello", &params(Some(2))).await.unwrap();
+
+    // 第二次 `world hello` 被屏蔽后改选 `world world`，之后没有可选token
+    assert_eq!(output.token_ids, vec![WORLD, HELLO, WORLD, WORLD]);
+    assert_eq!(output.finish_reason, FinishReason::Stop);
+    let bigrams: Vec<_> = output.token_ids.windows(2).collect();
+    for (i, bigram) in bigrams.iter().enumerate() {
+        assert!(!bigrams[i + 1..].contains(bigram), "repeated bigram {:?}", bigram);
+    }
+}
+
+#[tokio::test]
+async fn test_zero_no_repeat_ngram_size_is_rejected() {
+    let model = LoopingModel { tokenizer: word_level_tokenizer(&VOCAB) };
+
+    let result = model.generate("hello", &params(Some(0))).await;
+
+    assert!(matches!(result, Err(AppError::InvalidParameter(_))));
+}
@@ -0,0 +1,75 @@
+use coder_openapi::service::chat::chat_completion::ChatCompletionParams;
+use coder_openapi::service::models::GenerationDefaults;
+use coder_openapi::utils::config::ChatDefaults;
+
+fn app_defaults() -> ChatDefaults {
+    ChatDefaults { temperature: 0.7, top_p: 0.9, n: 1, stream: false }
+}
+
+fn model_defaults() -> GenerationDefaults {
+    GenerationDefaults {
+        temperature: Some(0.3),
+        top_p: Some(0.8),
+        top_k: Some(40),
+        max_tokens: Some(512),
+    }
+}
+
+#[test]
+fn test_request_params_take_precedence() {
+    let request = ChatCompletionParams {
+        temperature: Some(1.2),
+        top_p: Some(0.5),
+        top_k: Some(5),
+        max_tokens: Some(8),
+        n: Some(3),
+        stream: Some(true),
+        ..Default::default()
+    };
+
+    let params = request.with_defaults(&model_defaults(), &app_defaults());
+
+    assert_eq!(params.temperature, Some(1.2));
+    assert_eq!(params.top_p, Some(0.5));
+    assert_eq!(params.top_k, Some(5));
+    assert_eq!(params.max_tokens, Some(8));
+    assert_eq!(params.n, Some(3));
+    assert_eq!(params.stream, Some(true));
+}
+
+#[test]
+fn test_generation_config_overrides_app_defaults() {
+    let params = ChatCompletionParams::default().with_defaults(&model_defaults(), &app_defaults());
+
+    assert_eq!(params.temperature, Some(0.3));
+    assert_eq!(params.top_p, Some(0.8));
+    assert_eq!(params.top_k, Some(40));
+    assert_eq!(params.max_tokens, Some(512));
+    // generation_config.json中没有n与stream
+    assert_eq!(params.n, Some(1));
+    assert_eq!(params.stream, Some(false));
+}
+
+#[test]
+fn test_app_defaults_apply_last() {
+    let params = ChatCompletionParams::default()
+        .with_defaults(&GenerationDefaults::default(), &app_defaults());
+
+    assert_eq!(params.temperature, Some(0.7));
+    assert_eq!(params.top_p, Some(0.9));
+    // chat.defaults中没有top_k与max_tokens
+    assert_eq!(params.top_k, None);
+    assert_eq!(params.max_tokens, None);
+}
+
+#[test]
+fn test_sources_are_resolved_per_field() {
+    let request = ChatCompletionParams { top_p: Some(0.95), ..Default::default() };
+    let model = GenerationDefaults { temperature: Some(0.2), ..Default::default() };
+
+    let params = request.with_defaults(&model, &app_defaults());
+
+    assert_eq!(params.top_p, Some(0.95));
+    assert_eq!(params.temperature, Some(0.2));
+    assert_eq!(params.n, Some(1));
+}
@@ -0,0 +1,106 @@
+#[path = "../common/mod.rs"]
+mod common;
+
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use coder_openapi::entities::chat_completion_message::ChatCompletionMessage;
+use coder_openapi::error::AppError;
+use coder_openapi::service::chat::chat_completion::{ChatCompletionParams, ChatCompletionService};
+use coder_openapi::service::chat::prompt::assistant_prefill;
+use coder_openapi::service::models::{
+    CompletionModel, FinishReason, GenerationOutput, ModelManager,
+};
+use common::word_level_tokenizer;
+use std::sync::{Arc, Mutex};
+use tokenizers::Tokenizer;
+
+const MODEL_ID: &str = "continuing-model";
+
+/// 记录收到的提示词并固定生成 `() {}` 的模型
+struct ContinuingModel {
+    tokenizer: Tokenizer,
+    prompts: Mutex<Vec<String>>,
+}
+
+#[async_trait]
+impl CompletionModel for ContinuingModel {
+    fn model_id(&self) -> &str {
+        MODEL_ID
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    fn forward_logits(&self, _input_ids: &[u32]) -> Result<Tensor, AppError> {
+        Ok(Tensor::new(&[1f32, 0.0], &Device::Cpu)?)
+    }
+
+    async fn generate(
+        &self,
+        prompt: &str,
+        _params: &ChatCompletionParams,
+    ) -> Result<GenerationOutput, AppError> {
+        self.prompts.lock().unwrap().push(prompt.to_string());
+        Ok(GenerationOutput {
+            text: "() {}".to_string(),
+            token_ids: vec![1, 1],
+            prompt_tokens: 0,
+            finish_reason: FinishReason::Stop,
+            timed_out: false,
+            cancelled: false,
+            clamped_max_tokens: None,
+            system_fingerprint: None,
+            sampling: None,
+        })
+    }
+}
+
+fn message(role: &str, content: &str) -> ChatCompletionMessage {
+    ChatCompletionMessage { role: role.to_string(), content: content.to_string(), name: None }
+}
+
+async fn service() -> (ChatCompletionService, Arc<ContinuingModel>) {
+    let model = Arc::new(ContinuingModel {
+        tokenizer: word_level_tokenizer(&["<eos>", "<unk>"]),
+        prompts: Mutex::new(Vec::new()),
+    });
+    let manager = ModelManager::new();
+    manager.register_model(MODEL_ID, model.clone()).await;
+    (ChatCompletionService::new(manager), model)
+}
+
+#[tokio::test]
+async fn test_trailing_assistant_message_is_continued() {
+    let (service, model) = service().await;
+    let messages = vec![message("user", "write main"), message("assistant", "fn main")];
+
+    let outputs =
+        service.complete(MODEL_ID, messages, ChatCompletionParams::default()).await.unwrap();
+
+    assert_eq!(model.prompts.lock().unwrap()[0], "user: write main\nassistant: fn main");
+    assert_eq!(outputs[0].text, "fn main() {}");
+    assert_eq!(outputs[0].completion_tokens(), 2);
+}
+
+#[tokio::test]
+async fn test_trailing_user_message_is_not_prefilled() {
+    let (service, _model) = service().await;
+    let messages = vec![message("assistant", "hello"), message("user", "write main")];
+
+    let outputs =
+        service.complete(MODEL_ID, messages, ChatCompletionParams::default()).await.unwrap();
+
+    assert_eq!(outputs[0].text, "() {}");
+}
+
+#[test]
+fn test_assistant_prefill_only_for_last_message() {
+    assert_eq!(assistant_prefill(&[message("assistant", "fn")]), Some("fn"));
+    assert_eq!(assistant_prefill(&[message("assistant", "fn"), message("user", "hi")]), None);
+    assert_eq!(assistant_prefill(&[]), None);
+}
@@ -0,0 +1,142 @@
+#[path = "../common/mod.rs"]
+mod common;
+
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use coder_openapi::entities::chat_completion_message::ChatCompletionMessage;
+use coder_openapi::error::AppError;
+use coder_openapi::service::chat::chat_completion::{ChatCompletionParams, ChatCompletionService};
+use coder_openapi::service::chat::prompt_cache::{is_cacheable, PromptCache, PromptCacheKey};
+use coder_openapi::service::metrics::metrics;
+use coder_openapi::service::models::{CompletionModel, ModelManager};
+use common::word_level_tokenizer;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokenizers::Tokenizer;
+
+const MODEL_ID: &str = "counting-model";
+const VOCAB: [&str; 5] = ["<eos>", "user", ":", "hi", "<unk>"];
+
+/// 记录前向传播次数的模型，使用自己的角色标记渲染提示词
+struct CountingModel {
+    tokenizer: Tokenizer,
+    forward_calls: AtomicUsize,
+}
+
+#[async_trait]
+impl CompletionModel for CountingModel {
+    fn model_id(&self) -> &str {
+        MODEL_ID
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    fn render_prompt(&self, messages: &[ChatCompletionMessage]) -> String {
+        messages.iter().map(|m| format!("<|{}|>{}", m.role, m.content)).collect()
+    }
+
+    fn forward_logits(&self, _input_ids: &[u32]) -> Result<Tensor, AppError> {
+        self.forward_calls.fetch_add(1, Ordering::SeqCst);
+        Ok(Tensor::new(&[1f32, 0.5, 0.5, 2.0, 0.0], &Device::Cpu)?)
+    }
+}
+
+fn messages() -> Vec<ChatCompletionMessage> {
+    vec![ChatCompletionMessage { role: "user".to_string(), content: "hi".to_string(), name: None }]
+}
+
+async fn service_with_cache() -> (ChatCompletionService, Arc<CountingModel>) {
+    let model = Arc::new(CountingModel {
+        tokenizer: word_level_tokenizer(&VOCAB),
+        forward_calls: AtomicUsize::new(0),
+    });
+    let manager = ModelManager::new();
+    manager.register_model(MODEL_ID, model.clone()).await;
+    (ChatCompletionService::new(manager).with_prompt_cache(16), model)
+}
+
+#[tokio::test]
+async fn test_seeded_request_served_from_cache() {
+    let (service, model) = service_with_cache().await;
+    let params = ChatCompletionParams {
+        temperature: Some(0.7),
+        max_tokens: Some(4),
+        seed: Some(42),
+        ..Default::default()
+    };
+    let hits_before = metrics().counter("prompt_cache_hits_total");
+
+    let first = service.complete(MODEL_ID, messages(), params.clone()).await.unwrap();
+    let calls_after_first = model.forward_calls.load(Ordering::SeqCst);
+    let second = service.complete(MODEL_ID, messages(), params).await.unwrap();
+
+    assert!(calls_after_first > 0);
+    assert_eq!(model.forward_calls.load(Ordering::SeqCst), calls_after_first);
+    assert_eq!(first[0].token_ids, second[0].token_ids);
+    let cache = service.prompt_cache().unwrap();
+    assert_eq!(cache.hits(), 1);
+    assert_eq!(cache.hit_rate(), 0.5);
+    assert!(metrics().counter("prompt_cache_hits_total") > hits_before);
+}
+
+#[tokio::test]
+async fn test_cache_key_uses_the_model_rendered_prompt() {
+    let (service, model) = service_with_cache().await;
+    let params = ChatCompletionParams { seed: Some(42), max_tokens: Some(2), ..Default::default() };
+    let message = |content: &str| ChatCompletionMessage {
+        role: "user".to_string(),
+        content: content.to_string(),
+        name: None,
+    };
+
+    // 按默认格式两者都渲染为 "user: a\nuser: b"，按模型的角色标记则不同
+    service.complete(MODEL_ID, vec![message("a\nuser: b")], params.clone()).await.unwrap();
+    let calls_after_first = model.forward_calls.load(Ordering::SeqCst);
+    service.complete(MODEL_ID, vec![message("a"), message("b")], params).await.unwrap();
+
+    assert!(model.forward_calls.load(Ordering::SeqCst) > calls_after_first);
+    assert_eq!(service.prompt_cache().unwrap().hits(), 0);
+}
+
+#[tokio::test]
+async fn test_nondeterministic_request_not_cached() {
+    let (service, model) = service_with_cache().await;
+    let params =
+        ChatCompletionParams { temperature: Some(0.7), max_tokens: Some(2), ..Default::default() };
+
+    service.complete(MODEL_ID, messages(), params.clone()).await.unwrap();
+    let calls_after_first = model.forward_calls.load(Ordering::SeqCst);
+    service.complete(MODEL_ID, messages(), params).await.unwrap();
+
+    assert!(model.forward_calls.load(Ordering::SeqCst) > calls_after_first);
+    assert!(service.prompt_cache().unwrap().is_empty());
+}
+
+#[test]
+fn test_streaming_request_not_cacheable() {
+    let params = ChatCompletionParams { seed: Some(1), stream: Some(true), ..Default::default() };
+    assert!(!is_cacheable(&params));
+    assert!(is_cacheable(&ChatCompletionParams { temperature: Some(0.0), ..Default::default() }));
+}
+
+#[test]
+fn test_cache_evicts_least_recently_used() {
+    let cache = PromptCache::new(2);
+    let params = ChatCompletionParams::default();
+    let key = |prompt: &str| PromptCacheKey::new(MODEL_ID, prompt, &params);
+
+    cache.insert(key("a"), Vec::new());
+    cache.insert(key("b"), Vec::new());
+    assert!(cache.get(&key("a")).is_some());
+    cache.insert(key("c"), Vec::new());
+
+    assert!(cache.get(&key("a")).is_some());
+    assert!(cache.get(&key("b")).is_none());
+    assert_eq!(cache.len(), 2);
+}
@@ -0,0 +1,125 @@
+use coder_openapi::entities::chat_completion_message::ChatCompletionMessage;
+use coder_openapi::service::chat::chat_completion::ChatCompletionService;
+use coder_openapi::service::chat::prompt::{render_fim_prompt, render_prompt, render_prompt_with};
+use coder_openapi::service::models::ModelManager;
+use coder_openapi::utils::config::{FimTokens, RoleMarkers};
+use serde_json::json;
+
+#[test]
+fn test_named_message_renders_name() {
+    let messages: Vec<ChatCompletionMessage> = serde_json::from_value(json!([
+        {"role": "system", "content": "You are a reviewer."},
+        {"role": "user", "name": "alice", "content": "Review this diff."}
+    ]))
+    .unwrap();
+
+    let prompt = render_prompt(&messages);
+
+    assert_eq!(prompt, "system: You are a reviewer.\nuser name=alice: Review this diff.");
+}
+
+#[test]
+fn test_name_round_trips() {
+    let message: ChatCompletionMessage =
+        serde_json::from_value(json!({"role": "tool", "name": "search", "content": "3 results"}))
+            .unwrap();
+    assert_eq!(message.name.as_deref(), Some("search"));
+
+    let value = serde_json::to_value(&message).unwrap();
+    assert_eq!(value, json!({"role": "tool", "name": "search", "content": "3 results"}));
+}
+
+#[test]
+fn test_name_is_optional() {
+    let message: ChatCompletionMessage =
+        serde_json::from_value(json!({"role": "user", "content": "hi"})).unwrap();
+    assert!(message.name.is_none());
+
+    let value = serde_json::to_value(&message).unwrap();
+    assert!(value.get("name").is_none());
+}
+
+#[test]
+fn test_role_markers_from_config_change_rendered_prompt() {
+    let messages: Vec<ChatCompletionMessage> = serde_json::from_value(json!([
+        {"role": "system", "content": "You are a reviewer."},
+        {"role": "user", "content": "Review this diff."}
+    ]))
+    .unwrap();
+    let markers: RoleMarkers = serde_yaml::from_str("user: \"### Instruction:\\n\"").unwrap();
+
+    let prompt = render_prompt_with(&messages, &markers);
+
+    // 只替换配置了前缀的角色
+    assert_eq!(prompt, "system: You are a reviewer.\n### Instruction:\nReview this diff.");
+    assert_eq!(render_prompt_with(&messages, &RoleMarkers::default()), render_prompt(&messages));
+}
+
+#[test]
+fn test_content_parts_render_same_prompt_as_string() {
+    let plain: Vec<ChatCompletionMessage> = serde_json::from_value(json!([
+        {"role": "system", "content": "You are a reviewer."},
+        {"role": "user", "content": "Review this diff.\nKeep it short."}
+    ]))
+    .unwrap();
+    let parts: Vec<ChatCompletionMessage> = serde_json::from_value(json!([
+        {"role": "system", "content": [{"type": "text", "text": "You are a reviewer."}]},
+        {"role": "user", "content": [
+            {"type": "text", "text": "Review this diff."},
+            {"type": "image_url", "image_url": {"url": "https://example.com/diff.png"}},
+            {"type": "text", "text": "Keep it short."}
+        ]}
+    ]))
+    .unwrap();
+
+    assert_eq!(parts[1].content, "Review this diff.\nKeep it short.");
+    assert_eq!(render_prompt(&parts), render_prompt(&plain));
+}
+
+#[test]
+fn test_invalid_content_is_rejected() {
+    let result = serde_json::from_value::<ChatCompletionMessage>(
+        json!({"role": "user", "content": {"text": "hi"}}),
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_fim_prompt_wraps_prefix_and_suffix() {
+    let tokens = FimTokens {
+        prefix: "<PRE>".to_string(),
+        suffix: "<SUF>".to_string(),
+        middle: "<MID>".to_string(),
+    };
+
+    let prompt = render_fim_prompt("def add(a, b):\n", "\n    return c", &tokens);
+
+    assert_eq!(prompt, "<PRE>def add(a, b):\n<SUF>\n    return c<MID>");
+}
+
+fn repeated_user_messages() -> Vec<ChatCompletionMessage> {
+    serde_json::from_value(json!([
+        {"role": "user", "content": "Fix the bug."},
+        {"role": "user", "content": "Fix the bug."},
+        {"role": "assistant", "content": "Done."},
+        {"role": "user", "content": "Fix the bug."}
+    ]))
+    .unwrap()
+}
+
+#[test]
+fn test_dedupe_consecutive_collapses_repeated_messages() {
+    let service = ChatCompletionService::new(ModelManager::new()).with_dedupe_consecutive(true);
+
+    let prompt = render_prompt(&service.dedupe_messages(repeated_user_messages()));
+
+    // 只合并相邻的重复消息
+    assert_eq!(prompt, "user: Fix the bug.\nassistant: Done.\nuser: Fix the bug.");
+}
+
+#[test]
+fn test_dedupe_consecutive_disabled_keeps_messages() {
+    let service = ChatCompletionService::new(ModelManager::new()).with_dedupe_consecutive(false);
+
+    assert_eq!(service.dedupe_messages(repeated_user_messages()).len(), 4);
+}
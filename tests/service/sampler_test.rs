@@ -0,0 +1,121 @@
+use candle_core::{Device, Tensor};
+use coder_openapi::service::chat::chat_completion::ChatCompletionParams;
+use coder_openapi::service::models::sampler::{
+    sampler_for, GreedySampler, Sampler, TemperatureSampler, TopKTopPSampler,
+};
+use coder_openapi::service::models::sampling::{SamplingConfig, MIN_ANNEALED_TEMPERATURE};
+use std::collections::HashSet;
+
+/// 概率从高到低依次为token 1、2、0、3
+fn logits() -> Tensor {
+    Tensor::new(&[0.5f32, 2.5, 2.4, -1.0], &Device::Cpu).unwrap()
+}
+
+fn samples(sampler: &dyn Sampler, count: usize) -> HashSet<u32> {
+    (0..count).map(|_| sampler.sample(&logits(), &[]).unwrap()).collect()
+}
+
+#[test]
+fn test_greedy_sampler_picks_argmax() {
+    let sampler = GreedySampler;
+
+    assert_eq!(sampler.sample(&logits(), &[]).unwrap(), 1);
+    assert_eq!(sampler.sample(&logits(), &[1, 1, 1]).unwrap(), 1);
+}
+
+#[test]
+fn test_temperature_sampler_covers_all_tokens() {
+    let sampler = TemperatureSampler::new(5.0, Some(0));
+
+    assert_eq!(samples(&sampler, 500), HashSet::from([0, 1, 2, 3]));
+}
+
+#[test]
+fn test_temperature_sampler_is_reproducible_with_seed() {
+    let run = || {
+        let sampler = TemperatureSampler::new(1.0, Some(3));
+        (0..20).map(|_| sampler.sample(&logits(), &[]).unwrap()).collect::<Vec<_>>()
+    };
+
+    assert_eq!(run(), run());
+}
+
+#[test]
+fn test_top_k_limits_candidates() {
+    let sampler = TopKTopPSampler::new(5.0, Some(2), 1.0, Some(0));
+
+    assert_eq!(samples(&sampler, 500), HashSet::from([1, 2]));
+}
+
+#[test]
+fn test_top_k_of_one_is_greedy() {
+    let sampler = TopKTopPSampler::new(100.0, Some(1), 1.0, None);
+
+    assert_eq!(samples(&sampler, 50), HashSet::from([1]));
+}
+
+#[test]
+fn test_top_p_keeps_smallest_set_reaching_probability() {
+    // token 1的概率约为0.48，加上token 2约为0.92
+    let sampler = TopKTopPSampler::new(1.0, None, 0.8, Some(0));
+    assert_eq!(samples(&sampler, 500), HashSet::from([1, 2]));
+
+    let sampler = TopKTopPSampler::new(1.0, None, 0.1, Some(0));
+    assert_eq!(samples(&sampler, 50), HashSet::from([1]));
+}
+
+#[test]
+fn test_sampler_is_selected_from_params() {
+    let sampler =
+        |params: ChatCompletionParams| sampler_for(&SamplingConfig::try_new(&params).unwrap());
+
+    // temperature为0时贪心解码
+    let greedy = sampler(ChatCompletionParams { temperature: Some(0.0), ..Default::default() });
+    assert_eq!(samples(greedy.as_ref(), 50), HashSet::from([1]));
+
+    // 设置top_k时只在前k个token中采样
+    let top_k = sampler(ChatCompletionParams {
+        temperature: Some(5.0),
+        top_k: Some(1),
+        seed: Some(0),
+        ..Default::default()
+    });
+    assert_eq!(samples(top_k.as_ref(), 50), HashSet::from([1]));
+
+    let temperature = sampler(ChatCompletionParams {
+        temperature: Some(5.0),
+        seed: Some(0),
+        ..Default::default()
+    });
+    assert_eq!(samples(temperature.as_ref(), 500).len(), 4);
+}
+
+#[test]
+fn test_temperature_decay_lowers_temperature_over_the_sequence() {
+    let params = ChatCompletionParams {
+        temperature: Some(1.0),
+        temperature_decay: Some(0.01),
+        max_tokens: Some(50),
+        ..Default::default()
+    };
+    let config = SamplingConfig::try_new(&params).unwrap();
+
+    let first = config.temperature_at(0).unwrap();
+    let last = config.temperature_at(config.max_tokens() - 1).unwrap();
+    assert_eq!(first, 1.0);
+    assert!(first > last);
+    // 不低于下限
+    assert_eq!(config.temperature_at(10_000), Some(MIN_ANNEALED_TEMPERATURE));
+}
+
+#[test]
+fn test_annealed_sampler_narrows_later_steps() {
+    let sampler = TemperatureSampler::new(5.0, Some(0)).with_temperature_decay(1.0);
+    let history = vec![0u32; 100];
+
+    assert_eq!(samples(&sampler, 500), HashSet::from([0, 1, 2, 3]));
+    // temperature退火到下限后，logit低得多的token 0和3不再被采样
+    let late: HashSet<u32> =
+        (0..500).map(|_| sampler.sample(&logits(), &history).unwrap()).collect();
+    assert!(late.is_subset(&HashSet::from([1, 2])));
+}
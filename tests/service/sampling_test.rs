@@ -0,0 +1,227 @@
+use candle_core::{Device, Tensor};
+use coder_openapi::error::AppError;
+use coder_openapi::service::chat::chat_completion::ChatCompletionParams;
+use coder_openapi::service::models::completion_model::DEFAULT_MAX_TOKENS;
+use coder_openapi::service::models::sampling::{
+    apply_penalties, sample_next_token, sanitize_logits, NanPolicy, SamplingConfig, MAX_TEMPERATURE,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+fn logits() -> Tensor {
+    Tensor::new(&[0.5f32, 2.5, 2.4, -1.0], &Device::Cpu).unwrap()
+}
+
+#[test]
+fn test_zero_temperature_is_greedy() {
+    let logits = logits();
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..20 {
+        assert_eq!(sample_next_token(&logits, Some(0.0), &mut rng).unwrap(), 1);
+    }
+}
+
+#[test]
+fn test_negative_temperature_is_greedy() {
+    let mut rng = rand::thread_rng();
+    assert_eq!(sample_next_token(&logits(), Some(-0.5), &mut rng).unwrap(), 1);
+}
+
+#[test]
+fn test_greedy_does_not_consume_randomness() {
+    let mut greedy_rng = StdRng::seed_from_u64(7);
+    let mut untouched_rng = StdRng::seed_from_u64(7);
+
+    sample_next_token(&logits(), Some(0.0), &mut greedy_rng).unwrap();
+
+    assert_eq!(greedy_rng.gen::<u64>(), untouched_rng.gen::<u64>());
+}
+
+#[test]
+fn test_seeded_sampling_is_reproducible() {
+    let logits = logits();
+    let sample = |seed| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..10)
+            .map(|_| sample_next_token(&logits, Some(1.0), &mut rng).unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(sample(3), sample(3));
+}
+
+#[test]
+fn test_nan_temperature_is_rejected() {
+    let mut rng = rand::thread_rng();
+    let result = sample_next_token(&logits(), Some(f32::NAN), &mut rng);
+    assert!(matches!(result, Err(AppError::InvalidParameter(_))));
+}
+
+#[test]
+fn test_clamp_replaces_non_finite_logits() {
+    let logits = Tensor::new(&[f32::NAN, 2.0, f32::INFINITY, -1.0], &Device::Cpu).unwrap();
+
+    let values: Vec<f32> = sanitize_logits(&logits, NanPolicy::Clamp).unwrap().to_vec1().unwrap();
+
+    assert_eq!(values, vec![-1.0, 2.0, 2.0, -1.0]);
+}
+
+#[test]
+fn test_skip_excludes_non_finite_logits() {
+    let logits = Tensor::new(&[f32::INFINITY, 0.5, f32::NAN], &Device::Cpu).unwrap();
+    let sanitized = sanitize_logits(&logits, NanPolicy::Skip).unwrap();
+    let mut rng = StdRng::seed_from_u64(0);
+
+    for _ in 0..20 {
+        assert_eq!(sample_next_token(&sanitized, Some(1.0), &mut rng).unwrap(), 1);
+    }
+}
+
+#[test]
+fn test_all_non_finite_logits_are_rejected() {
+    let logits = Tensor::new(&[f32::NAN, f32::NEG_INFINITY], &Device::Cpu).unwrap();
+    assert!(sanitize_logits(&logits, NanPolicy::Clamp).is_err());
+}
+
+fn invalid_parameter(params: ChatCompletionParams) -> String {
+    match SamplingConfig::try_new(&params) {
+        Err(AppError::InvalidParameter(message)) => message,
+        other => panic!("expected InvalidParameter, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_sampling_config_defaults() {
+    let config = SamplingConfig::try_new(&ChatCompletionParams::default()).unwrap();
+
+    assert_eq!(config.temperature(), None);
+    assert_eq!(config.top_p(), 1.0);
+    assert_eq!(config.top_k(), None);
+    assert_eq!(config.presence_penalty(), 0.0);
+    assert_eq!(config.frequency_penalty(), 0.0);
+    assert_eq!(config.max_tokens(), DEFAULT_MAX_TOKENS);
+}
+
+#[test]
+fn test_sampling_config_non_positive_temperature_is_greedy() {
+    let params = ChatCompletionParams { temperature: Some(-0.5), ..Default::default() };
+    assert_eq!(SamplingConfig::try_new(&params).unwrap().temperature(), None);
+}
+
+#[test]
+fn test_sampling_config_rejects_non_finite_temperature() {
+    let message = invalid_parameter(ChatCompletionParams {
+        temperature: Some(f32::INFINITY),
+        ..Default::default()
+    });
+    assert_eq!(message, "temperature must be a finite number, got inf");
+}
+
+#[test]
+fn test_sampling_config_rejects_negative_temperature_decay() {
+    let message = invalid_parameter(ChatCompletionParams {
+        temperature_decay: Some(-0.1),
+        ..Default::default()
+    });
+    assert_eq!(message, "temperature_decay must be a non-negative number, got -0.1");
+}
+
+#[test]
+fn test_sampling_config_clamps_temperature() {
+    let params = ChatCompletionParams { temperature: Some(5.0), ..Default::default() };
+    assert_eq!(SamplingConfig::try_new(&params).unwrap().temperature(), Some(MAX_TEMPERATURE));
+
+    let params = ChatCompletionParams { temperature: Some(0.7), ..Default::default() };
+    assert_eq!(SamplingConfig::try_new(&params).unwrap().temperature(), Some(0.7));
+}
+
+#[test]
+fn test_sampling_config_clamps_top_p() {
+    let params = ChatCompletionParams { top_p: Some(1.5), ..Default::default() };
+    assert_eq!(SamplingConfig::try_new(&params).unwrap().top_p(), 1.0);
+
+    let params = ChatCompletionParams { top_p: Some(-0.5), ..Default::default() };
+    assert_eq!(SamplingConfig::try_new(&params).unwrap().top_p(), 0.0);
+}
+
+#[test]
+fn test_sampling_config_rejects_nan_top_p() {
+    let message =
+        invalid_parameter(ChatCompletionParams { top_p: Some(f32::NAN), ..Default::default() });
+    assert_eq!(message, "top_p must be a number, got NaN");
+}
+
+#[test]
+fn test_sampling_config_rejects_zero_top_k() {
+    let message = invalid_parameter(ChatCompletionParams { top_k: Some(0), ..Default::default() });
+    assert_eq!(message, "top_k must be at least 1, got 0");
+}
+
+#[test]
+fn test_sampling_config_rejects_presence_penalty_out_of_range() {
+    let message = invalid_parameter(ChatCompletionParams {
+        presence_penalty: Some(2.5),
+        ..Default::default()
+    });
+    assert_eq!(message, "presence_penalty must be between -2 and 2, got 2.5");
+}
+
+#[test]
+fn test_sampling_config_rejects_frequency_penalty_out_of_range() {
+    let message = invalid_parameter(ChatCompletionParams {
+        frequency_penalty: Some(f32::NAN),
+        ..Default::default()
+    });
+    assert_eq!(message, "frequency_penalty must be between -2 and 2, got NaN");
+}
+
+#[test]
+fn test_sampling_config_rejects_zero_max_tokens() {
+    let message =
+        invalid_parameter(ChatCompletionParams { max_tokens: Some(0), ..Default::default() });
+    assert_eq!(message, "max_tokens must be at least 1, got 0");
+}
+
+#[test]
+fn test_sampling_config_rejects_min_tokens_above_max_tokens() {
+    let message = invalid_parameter(ChatCompletionParams {
+        min_tokens: Some(8),
+        max_tokens: Some(4),
+        ..Default::default()
+    });
+    assert_eq!(message, "min_tokens must not exceed max_tokens (4), got 8");
+}
+
+#[test]
+fn test_frequency_penalty_monotonically_lowers_repeated_token() {
+    // token 1 生成了3次，token 2 生成了1次
+    let tokens = [1, 2, 1, 1];
+    let penalized = |frequency_penalty: f32| -> Vec<f32> {
+        apply_penalties(&logits(), &tokens, 0.0, frequency_penalty).unwrap().to_vec1().unwrap()
+    };
+
+    let mut previous = penalized(0.0);
+    assert_eq!(previous, vec![0.5, 2.5, 2.4, -1.0]);
+    for frequency_penalty in [0.5, 1.0, 1.5, 2.0] {
+        let values = penalized(frequency_penalty);
+        assert!(values[1] < previous[1]);
+        assert!((values[1] - (2.5 - 3.0 * frequency_penalty)).abs() < 1e-6);
+        // 未生成过的token不受影响
+        assert_eq!(values[0], 0.5);
+        previous = values;
+    }
+    // 惩罚足够大时改变贪心解码的结果
+    let mut rng = rand::thread_rng();
+    let logits = apply_penalties(&logits(), &tokens, 0.0, 0.5).unwrap();
+    assert_eq!(sample_next_token(&logits, None, &mut rng).unwrap(), 2);
+}
+
+#[test]
+fn test_presence_penalty_ignores_counts() {
+    let values: Vec<f32> =
+        apply_penalties(&logits(), &[1, 1, 1, 2], 1.0, 0.0).unwrap().to_vec1().unwrap();
+
+    let expected = [0.5, 1.5, 1.4, -1.0];
+    assert!(values.iter().zip(expected).all(|(value, expected)| (value - expected).abs() < 1e-6));
+}
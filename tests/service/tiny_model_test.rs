@@ -0,0 +1,58 @@
+//! 使用 `tests/fixtures/tiny_model` 中的微型模型 (hidden_size 16, 2层, vocab 100)
+//! 走完真实的加载与前向传播路径；fixture由同目录下的 `generate.py` 生成
+use coder_openapi::service::chat::chat_completion::ChatCompletionParams;
+use coder_openapi::service::models::overrides::ModelOverrides;
+use coder_openapi::service::models::yi_coder::YiCoder;
+use coder_openapi::service::models::CompletionModel;
+
+const CONFIG_PATH: &str = "tests/fixtures/tiny_model/app.yml";
+const HIDDEN_SIZE: usize = 16;
+
+#[tokio::test]
+async fn test_tiny_model_loads_from_fixture_dir() {
+    let model = YiCoder::load(CONFIG_PATH).await.unwrap();
+
+    assert_eq!(model.tokenizer().get_vocab_size(true), 100);
+    assert_eq!(model.stop_token_ids(), vec![0]);
+    assert_eq!(model.context_length(), Some(64));
+}
+
+#[tokio::test]
+async fn test_tiny_model_forward_returns_finite_logits() {
+    let model = YiCoder::load(CONFIG_PATH).await.unwrap();
+
+    let logits = model.forward_logits(&[2, 3, 4, 5]).unwrap();
+    let values = logits.to_vec1::<f32>().unwrap();
+    assert_eq!(values.len(), HIDDEN_SIZE);
+    assert!(values.iter().all(|value| value.is_finite()));
+}
+
+#[tokio::test]
+async fn test_tiny_model_greedy_generation_is_deterministic() {
+    let model = YiCoder::load(CONFIG_PATH).await.unwrap();
+    let params =
+        ChatCompletionParams { temperature: Some(0.0), max_tokens: Some(8), ..Default::default() };
+
+    let first = model.generate("tok2 tok3 tok4", &params).await.unwrap();
+    let second = model.generate("tok2 tok3 tok4", &params).await.unwrap();
+
+    assert_eq!(first.prompt_tokens, 3);
+    assert!(first.completion_tokens() <= 8);
+    assert_eq!(first.token_ids, second.token_ids);
+    assert_eq!(first.text, second.text);
+}
+
+#[tokio::test]
+async fn test_tiny_model_overrides_apply_to_a_copy() {
+    let model = YiCoder::load(CONFIG_PATH).await.unwrap();
+    let overrides = ModelOverrides { embedding_clamp: Some(1e-6), ..Default::default() };
+
+    let overridden = model.with_overrides(&overrides).unwrap();
+
+    let base = model.forward_logits(&[2, 3, 4, 5]).unwrap().to_vec1::<f32>().unwrap();
+    let tweaked = overridden.forward_logits(&[2, 3, 4, 5]).unwrap().to_vec1::<f32>().unwrap();
+    assert_ne!(base, tweaked);
+    // 原模型不受影响
+    let again = model.forward_logits(&[2, 3, 4, 5]).unwrap().to_vec1::<f32>().unwrap();
+    assert_eq!(base, again);
+}
@@ -0,0 +1,59 @@
+#[path = "../common/mod.rs"]
+mod common;
+
+use coder_openapi::error::AppError;
+use coder_openapi::service::models::tokenizer_file::load_tokenizer_file_with_retry;
+use common::word_level_tokenizer;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+fn tokenizer_path() -> PathBuf {
+    std::env::temp_dir().join(format!("tokenizer-{}.json", uuid::Uuid::new_v4()))
+}
+
+fn tokenizer_json() -> String {
+    word_level_tokenizer(&["<unk>", "a", "b"]).to_string(false).unwrap()
+}
+
+#[tokio::test]
+async fn test_retries_until_file_is_fully_written() {
+    let path = tokenizer_path();
+    // 模拟写到一半的文件
+    let json = tokenizer_json();
+    std::fs::write(&path, &json[..json.len() / 2]).unwrap();
+
+    let writer_path = path.clone();
+    let writer = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        tokio::fs::write(writer_path, json).await.unwrap();
+    });
+
+    let tokenizer =
+        load_tokenizer_file_with_retry(&path, 5, Duration::from_millis(200)).await.unwrap();
+    writer.await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(tokenizer.encode("a b", false).unwrap().get_ids(), [1, 2]);
+}
+
+#[tokio::test]
+async fn test_missing_file_fails_without_retry() {
+    let start = Instant::now();
+    let err = load_tokenizer_file_with_retry(tokenizer_path(), 5, Duration::from_secs(10))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, AppError::InvalidParameter(_)));
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+#[tokio::test]
+async fn test_gives_up_after_max_attempts() {
+    let path = tokenizer_path();
+    std::fs::write(&path, "{").unwrap();
+
+    let err = load_tokenizer_file_with_retry(&path, 2, Duration::from_millis(1)).await.unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(err, AppError::TokenizerError(_)));
+}
@@ -0,0 +1,52 @@
+#[path = "../common/mod.rs"]
+mod common;
+
+use coder_openapi::service::models::tokenizer_settings::apply_tokenizer_settings;
+use coder_openapi::utils::config::{TokenizerSettings, TokenizerSide};
+use common::word_level_tokenizer;
+use tokenizers::Tokenizer;
+
+fn tokenizer(settings: &str) -> Tokenizer {
+    let settings: TokenizerSettings = serde_yaml::from_str(settings).unwrap();
+    let mut tokenizer = word_level_tokenizer(&["<pad>", "<unk>", "a", "b", "c", "d"]);
+    apply_tokenizer_settings(&mut tokenizer, &settings).unwrap();
+    tokenizer
+}
+
+fn encode(tokenizer: &Tokenizer, text: &str) -> Vec<u32> {
+    tokenizer.encode(text, false).unwrap().get_ids().to_vec()
+}
+
+#[test]
+fn test_defaults_leave_tokenizer_unchanged() {
+    let settings = TokenizerSettings::default();
+    assert_eq!(settings.truncation_side, TokenizerSide::Right);
+
+    let tokenizer = tokenizer("{}");
+    assert!(tokenizer.get_truncation().is_none());
+    assert!(tokenizer.get_padding().is_none());
+    assert_eq!(encode(&tokenizer, "a b c d"), [2, 3, 4, 5]);
+}
+
+#[test]
+fn test_left_truncation_keeps_the_end_of_the_sequence() {
+    let tokenizer = tokenizer("truncation_side: left\nmax_length: 2");
+
+    assert_eq!(encode(&tokenizer, "a b c d"), [4, 5]);
+}
+
+#[test]
+fn test_right_truncation_keeps_the_start_of_the_sequence() {
+    let tokenizer = tokenizer("max_length: 2");
+
+    assert_eq!(encode(&tokenizer, "a b c d"), [2, 3]);
+}
+
+#[test]
+fn test_left_padding_pads_shorter_sequences_at_the_start() {
+    let tokenizer = tokenizer("padding_side: left");
+
+    let encodings = tokenizer.encode_batch(vec!["a b c", "d"], false).unwrap();
+    assert_eq!(encodings[1].get_ids(), [0, 0, 5]);
+    assert_eq!(encodings[1].get_attention_mask(), [0, 0, 1]);
+}
@@ -0,0 +1,112 @@
+#[path = "../common/mod.rs"]
+mod common;
+
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use coder_openapi::error::AppError;
+use coder_openapi::service::chat::chat_completion::ChatCompletionParams;
+use coder_openapi::service::models::completion_model::truncate_prompt;
+use coder_openapi::service::models::{CompletionModel, TruncationStrategy};
+use common::word_level_tokenizer;
+use std::sync::Mutex;
+use tokenizers::Tokenizer;
+
+const VOCAB: [&str; 11] = ["<eos>", "a", "b", "c", "d", "e", "f", "g", "h", "i", "<unk>"];
+/// 10个token的提示词
+const LONG_PROMPT: &str = "a b c d e f g h i a";
+
+/// 上下文长度为8、记录首次前向传播输入的模型
+struct ShortContextModel {
+    tokenizer: Tokenizer,
+    first_input: Mutex<Option<Vec<u32>>>,
+}
+
+impl ShortContextModel {
+    fn new() -> Self {
+        Self { tokenizer: word_level_tokenizer(&VOCAB), first_input: Mutex::new(None) }
+    }
+}
+
+#[async_trait]
+impl CompletionModel for ShortContextModel {
+    fn model_id(&self) -> &str {
+        "short-context"
+    }
+
+    fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    fn eos_token_id(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    fn context_length(&self) -> Option<usize> {
+        Some(8)
+    }
+
+    fn forward_logits(&self, input_ids: &[u32]) -> Result<Tensor, AppError> {
+        self.first_input.lock().unwrap().get_or_insert_with(|| input_ids.to_vec());
+        let mut logits = vec![0f32; VOCAB.len()];
+        logits[0] = 10.0;
+        Ok(Tensor::new(logits, &Device::Cpu)?)
+    }
+}
+
+fn params(truncation: Option<TruncationStrategy>) -> ChatCompletionParams {
+    ChatCompletionParams { max_tokens: Some(2), truncation, ..Default::default() }
+}
+
+#[tokio::test]
+async fn test_left_truncation_keeps_prompt_end() {
+    let model = ShortContextModel::new();
+
+    let output =
+        model.generate(LONG_PROMPT, &params(Some(TruncationStrategy::Left))).await.unwrap();
+
+    assert_eq!(output.prompt_tokens, 6);
+    assert_eq!(model.first_input.lock().unwrap().clone().unwrap(), vec![5, 6, 7, 8, 9, 1]);
+}
+
+#[tokio::test]
+async fn test_right_truncation_keeps_prompt_start() {
+    let model = ShortContextModel::new();
+
+    let output =
+        model.generate(LONG_PROMPT, &params(Some(TruncationStrategy::Right))).await.unwrap();
+
+    assert_eq!(output.prompt_tokens, 6);
+    assert_eq!(model.first_input.lock().unwrap().clone().unwrap(), vec![1, 2, 3, 4, 5, 6]);
+}
+
+#[tokio::test]
+async fn test_error_truncation_rejects_long_prompt() {
+    let model = ShortContextModel::new();
+
+    let result = model.generate(LONG_PROMPT, &params(Some(TruncationStrategy::Error))).await;
+
+    assert!(matches!(result, Err(AppError::InvalidParameter(_))));
+    assert!(model.first_input.lock().unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_no_truncation_by_default() {
+    let model = ShortContextModel::new();
+
+    let output = model.generate(LONG_PROMPT, &params(None)).await.unwrap();
+
+    assert_eq!(output.prompt_tokens, 10);
+}
+
+#[test]
+fn test_truncation_strategy_deserializes_lowercase() {
+    let strategy: TruncationStrategy = serde_json::from_str("\"left\"").unwrap();
+    assert_eq!(strategy, TruncationStrategy::Left);
+    assert!(serde_json::from_str::<TruncationStrategy>("\"middle\"").is_err());
+}
+
+#[test]
+fn test_short_prompt_is_untouched() {
+    let ids = truncate_prompt(vec![1, 2, 3], 6, TruncationStrategy::Error).unwrap();
+    assert_eq!(ids, vec![1, 2, 3]);
+}
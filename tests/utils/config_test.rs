@@ -0,0 +1,61 @@
+use coder_openapi::utils::config::AppConfig;
+use std::time::Duration;
+
+/// 写入一个只包含 `server` 段差异的最小配置文件
+fn write_config(server_extra: &str) -> std::path::PathBuf {
+    let yaml = format!(
+        "server:\n  host: 127.0.0.1\n  port: 8080\n  shutdown_timeout: 30\n{}\
+         locales:\n  path: locales\n  default: zh\n\
+         models_cache_dir: models_cache\n\
+         models: {{}}\n\
+         chat:\n  defaults:\n    temperature: 0.7\n    top_p: 0.9\n    n: 1\n    max_tokens: 16\n    stream: false\n",
+        server_extra
+    );
+    let path = std::env::temp_dir().join(format!("app-{}.yml", uuid::Uuid::new_v4()));
+    std::fs::write(&path, yaml).unwrap();
+    path
+}
+
+#[test]
+fn test_server_timeouts_are_read_from_config() {
+    let path = write_config("  client_request_timeout_secs: 120\n  keep_alive_secs: 0\n");
+    let config = AppConfig::load(path.to_str().unwrap());
+    std::fs::remove_file(&path).unwrap();
+    let config = config.unwrap();
+
+    assert_eq!(config.server.client_request_timeout(), Duration::from_secs(120));
+    assert_eq!(config.server.keep_alive(), None);
+}
+
+#[test]
+fn test_server_timeouts_default_when_omitted() {
+    let path = write_config("");
+    let config = AppConfig::load(path.to_str().unwrap());
+    std::fs::remove_file(&path).unwrap();
+    let config = config.unwrap();
+
+    assert_eq!(config.server.client_request_timeout(), Duration::from_secs(30));
+    assert_eq!(config.server.keep_alive(), Some(Duration::from_secs(5)));
+}
+
+#[test]
+fn test_model_aliases_are_read_alongside_model_configs() {
+    let yaml = "server:\n  host: 127.0.0.1\n  port: 8080\n  shutdown_timeout: 30\n\
+                locales:\n  path: locales\n  default: zh\n\
+                models_cache_dir: models_cache\n\
+                models:\n  aliases:\n    gpt-3.5-turbo: yi-coder\n\
+                \x20 yi-coder:\n    hf_hub_id: 01-ai/Yi-Coder-1.5B-Chat\n    model_files:\n\
+                \x20     weights: [model.safetensors]\n      config: config.json\n\
+                \x20     tokenizer: tokenizer.json\n      tokenizer_config: tokenizer_config.json\n\
+                \x20     generation_config: generation_config.json\n\
+                chat:\n  defaults:\n    temperature: 0.7\n    top_p: 0.9\n    n: 1\n    max_tokens: 16\n    stream: false\n";
+    let path = std::env::temp_dir().join(format!("app-{}.yml", uuid::Uuid::new_v4()));
+    std::fs::write(&path, yaml).unwrap();
+    let config = AppConfig::load(path.to_str().unwrap());
+    std::fs::remove_file(&path).unwrap();
+    let config = config.unwrap();
+
+    assert_eq!(config.models.aliases.get("gpt-3.5-turbo").map(String::as_str), Some("yi-coder"));
+    assert_eq!(config.models.len(), 1);
+    assert!(config.models.contains_key("yi-coder"));
+}
@@ -0,0 +1,49 @@
+use candle_core::Device;
+use coder_openapi::error::AppError;
+use coder_openapi::utils::device::{resolve_device, DeviceSpec};
+
+#[test]
+fn test_parse_device_spec() {
+    assert_eq!("auto".parse::<DeviceSpec>().unwrap(), DeviceSpec::Auto);
+    assert_eq!("cpu".parse::<DeviceSpec>().unwrap(), DeviceSpec::Cpu);
+    assert_eq!("cuda".parse::<DeviceSpec>().unwrap(), DeviceSpec::Cuda(0));
+    assert_eq!("cuda:1".parse::<DeviceSpec>().unwrap(), DeviceSpec::Cuda(1));
+    assert!(matches!("tpu".parse::<DeviceSpec>(), Err(AppError::ConfigError(_))));
+}
+
+#[test]
+fn test_explicit_cuda_unavailable_returns_config_error() {
+    if candle_core::utils::cuda_is_available() {
+        return;
+    }
+
+    let result = resolve_device("cuda:0");
+    assert!(matches!(result, Err(AppError::ConfigError(_))));
+}
+
+#[test]
+fn test_auto_falls_back_to_cpu() {
+    if candle_core::utils::cuda_is_available() {
+        return;
+    }
+
+    let device = resolve_device("auto").unwrap();
+    assert!(matches!(device, Device::Cpu));
+}
+
+#[test]
+fn test_parse_metal_device_spec() {
+    assert_eq!("metal".parse::<DeviceSpec>().unwrap(), DeviceSpec::Metal(0));
+    assert_eq!("metal:1".parse::<DeviceSpec>().unwrap(), DeviceSpec::Metal(1));
+    assert!(matches!("metal:x".parse::<DeviceSpec>(), Err(AppError::ConfigError(_))));
+}
+
+#[test]
+fn test_explicit_metal_unavailable_returns_config_error() {
+    if candle_core::utils::metal_is_available() {
+        return;
+    }
+
+    let result = resolve_device("metal");
+    assert!(matches!(result, Err(AppError::ConfigError(_))));
+}
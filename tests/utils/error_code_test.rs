@@ -0,0 +1,32 @@
+use actix_web::body::to_bytes;
+use actix_web::ResponseError;
+use coder_openapi::error::AppError;
+use serde_json::Value;
+
+#[test]
+fn test_variants_map_to_error_codes() {
+    let cases = [
+        (AppError::InvalidModel("gpt-4".to_string()), "model_not_found"),
+        (AppError::InvalidParameter("top_p".to_string()), "invalid_parameter"),
+        (AppError::ValidationError("messages".to_string()), "validation_error"),
+        (AppError::ModelDownloading("yi-coder".to_string()), "model_downloading"),
+        (AppError::ServiceUnavailable("busy".to_string()), "service_unavailable"),
+        (AppError::NotFound, "not_found"),
+        (AppError::UnsupportedMediaType("text/plain".to_string()), "unsupported_media_type"),
+        (AppError::Generic("boom".to_string()), "internal_error"),
+    ];
+    for (error, code) in cases {
+        assert_eq!(error.error_code(), code, "{:?}", error);
+    }
+}
+
+#[actix_web::test]
+async fn test_error_response_includes_error_code() {
+    let resp = AppError::InvalidModel("gpt-4".to_string()).error_response();
+    assert_eq!(resp.status(), 400);
+
+    let body: Value = serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+    assert_eq!(body["code"], 400);
+    assert_eq!(body["error_code"], "model_not_found");
+    assert_eq!(body["message"], "Model not found: gpt-4");
+}
@@ -0,0 +1,56 @@
+use coder_openapi::error::AppError;
+use coder_openapi::utils::config::AppConfig;
+use coder_openapi::utils::log_filter::LogFilters;
+use log::{Level, LevelFilter};
+
+const TRANSFORMER: &str = "coder_openapi::service::models::yi_coder::transformer";
+const CONTROLLER: &str = "coder_openapi::controller::chat::chat_completion";
+
+#[test]
+fn test_filters_suppress_transformer_logs_while_controller_logs_pass() {
+    let filters = LogFilters::parse(&format!("debug,{}=warn", TRANSFORMER)).unwrap();
+
+    assert_eq!(filters.default_level(), Some(LevelFilter::Debug));
+    assert!(!filters.enabled(TRANSFORMER, Level::Debug));
+    assert!(!filters.enabled(&format!("{}::attention", TRANSFORMER), Level::Info));
+    assert!(filters.enabled(TRANSFORMER, Level::Warn));
+    assert!(filters.enabled(CONTROLLER, Level::Debug));
+    assert!(!filters.enabled(CONTROLLER, Level::Trace));
+}
+
+#[test]
+fn test_longest_matching_directive_wins() {
+    let filters = LogFilters::parse(
+        "coder_openapi=error, coder_openapi::service=info,coder_openapi::service::models=trace",
+    )
+    .unwrap();
+
+    assert_eq!(filters.level_for("coder_openapi::controller"), LevelFilter::Error);
+    assert_eq!(filters.level_for("coder_openapi::service::chat"), LevelFilter::Info);
+    assert_eq!(filters.level_for(TRANSFORMER), LevelFilter::Trace);
+    // 只按模块路径边界匹配
+    assert_eq!(filters.level_for("coder_openapi_extra"), LevelFilter::Trace);
+}
+
+#[test]
+fn test_invalid_level_is_rejected() {
+    let result = LogFilters::parse("info,coder_openapi=loud");
+    assert!(matches!(result, Err(AppError::ConfigError(_))));
+}
+
+#[test]
+fn test_logging_filters_are_read_from_config() {
+    let yaml = "server:\n  host: 127.0.0.1\n  port: 8080\n  shutdown_timeout: 30\n\
+                logging:\n  filters: \"info,coder_openapi::service=warn\"\n\
+                locales:\n  path: locales\n  default: zh\n\
+                models_cache_dir: models_cache\n\
+                models: {}\n\
+                chat:\n  defaults:\n    temperature: 0.7\n    top_p: 0.9\n    n: 1\n    max_tokens: 16\n    stream: false\n";
+    let path = std::env::temp_dir().join(format!("app-{}.yml", uuid::Uuid::new_v4()));
+    std::fs::write(&path, yaml).unwrap();
+    let config = AppConfig::load(path.to_str().unwrap());
+    std::fs::remove_file(&path).unwrap();
+
+    let config = config.unwrap();
+    assert_eq!(config.logging.filters.as_deref(), Some("info,coder_openapi::service=warn"));
+}
@@ -0,0 +1,55 @@
+use candle_core::{DType, Tensor};
+use candle_nn::{Module, VarBuilder};
+use coder_openapi::service::models::deepseek_coder::config::ModelConfig;
+use coder_openapi::service::models::deepseek_coder::transformer::DeepseekCoderTransformer;
+use coder_openapi::service::models::sampling::sample_next_token;
+use coder_openapi::utils::device::resolve_device;
+
+fn tiny_config() -> ModelConfig {
+    serde_json::from_str(
+        r#"{
+            "models_cache_dir": "models_cache",
+            "hf_hub_id": "test/tiny",
+            "model_files": {
+                "weights": [],
+                "config": "config.json",
+                "tokenizer": "tokenizer.json",
+                "tokenizer_config": "tokenizer_config.json",
+                "generation_config": "generation_config.json"
+            },
+            "hidden_size": 8,
+            "num_attention_heads": 2,
+            "intermediate_size": 16,
+            "num_layers": 2,
+            "layer_norm_eps": 1e-5
+        }"#,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_forward_pass_on_metal() {
+    let device = resolve_device("metal").unwrap();
+    assert!(device.is_metal());
+
+    let config = tiny_config();
+    let vb = VarBuilder::zeros(DType::F32, &device);
+    let transformer = DeepseekCoderTransformer::new(&config, vb).unwrap();
+    let input = Tensor::randn(0f32, 1f32, (1, 4, config.hidden_size), &device).unwrap();
+
+    let output = transformer.forward(&input).unwrap();
+
+    assert!(output.device().is_metal());
+    assert_eq!(output.dims(), &[1, 4, config.hidden_size]);
+}
+
+#[test]
+fn test_sampling_metal_logits() {
+    let device = resolve_device("metal").unwrap();
+    let logits = Tensor::new(&[0.1f32, 3.0, 0.2], &device).unwrap();
+
+    let mut rng = rand::thread_rng();
+
+    assert_eq!(sample_next_token(&logits, None, &mut rng).unwrap(), 1);
+    assert!(sample_next_token(&logits, Some(0.7), &mut rng).unwrap() < 3);
+}
@@ -0,0 +1,43 @@
+use coder_openapi::service::models::echo::EchoModel;
+use coder_openapi::service::models::{CompletionModel, ModelError, ModelLoaderFn, ModelManager};
+use coder_openapi::utils::config::PreloadFailure;
+use coder_openapi::utils::init::preload_models;
+use std::sync::Arc;
+
+const MODEL_ID: &str = "preloaded-model";
+
+fn echo_loader() -> ModelLoaderFn {
+    Arc::new(|| {
+        Box::pin(async {
+            let model: Arc<dyn CompletionModel> = Arc::new(EchoModel::new().unwrap());
+            Ok(model)
+        })
+    })
+}
+
+fn failing_loader() -> ModelLoaderFn {
+    Arc::new(|| {
+        Box::pin(async { Err(ModelError::InitializationFailed("missing weights".to_string())) })
+    })
+}
+
+#[tokio::test]
+async fn test_preloaded_model_is_enabled_after_init() {
+    let manager = ModelManager::new().with_model_loader(MODEL_ID, echo_loader());
+
+    preload_models(&manager, &[MODEL_ID.to_string()], PreloadFailure::Fatal).await.unwrap();
+
+    let status = manager.get_model_status(MODEL_ID).await.unwrap();
+    assert!(status.is_enabled);
+    assert!(manager.get_model(MODEL_ID).await.is_some());
+}
+
+#[tokio::test]
+async fn test_preload_failure_policy() {
+    let manager = ModelManager::new().with_model_loader(MODEL_ID, failing_loader());
+    let model_ids = [MODEL_ID.to_string()];
+
+    assert!(preload_models(&manager, &model_ids, PreloadFailure::Fatal).await.is_err());
+    assert!(preload_models(&manager, &model_ids, PreloadFailure::Warn).await.is_ok());
+    assert!(manager.get_model(MODEL_ID).await.is_none());
+}
@@ -0,0 +1,45 @@
+use coder_openapi::utils::config::{ServerConfig, TlsConfig};
+use coder_openapi::utils::tls::Listener;
+use std::path::PathBuf;
+
+const SERVER_YAML: &str = "host: 127.0.0.1\nport: 8443\nshutdown_timeout: 30\n";
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tls").join(name)
+}
+
+fn server_config(tls: Option<TlsConfig>) -> ServerConfig {
+    let mut config: ServerConfig = serde_yaml::from_str(SERVER_YAML).unwrap();
+    config.tls = tls;
+    config
+}
+
+#[test]
+fn test_plaintext_is_default() {
+    let config: ServerConfig = serde_yaml::from_str(SERVER_YAML).unwrap();
+
+    assert_eq!(config.tls, None);
+    assert!(matches!(Listener::from_config(&config).unwrap(), Listener::Plaintext));
+}
+
+#[test]
+fn test_tls_config_switches_to_tls_listener() {
+    let yaml = format!(
+        "{}tls:\n  cert: {}\n  key: {}\n",
+        SERVER_YAML,
+        fixture("cert.pem").display(),
+        fixture("key.pem").display()
+    );
+    let config: ServerConfig = serde_yaml::from_str(&yaml).unwrap();
+
+    assert!(matches!(Listener::from_config(&config).unwrap(), Listener::Tls(_)));
+}
+
+#[test]
+fn test_missing_certificate_fails_to_start() {
+    let missing = fixture("missing.pem");
+    let config = server_config(Some(TlsConfig { cert: missing.clone(), key: fixture("key.pem") }));
+
+    let err = Listener::from_config(&config).unwrap_err();
+    assert!(err.to_string().contains(&missing.display().to_string()), "{}", err);
+}
@@ -0,0 +1,43 @@
+#[path = "../common/mod.rs"]
+mod common;
+
+use coder_openapi::utils::config::AppConfig;
+use coder_openapi::utils::init::apply_tokenizer_parallelism;
+use common::word_level_tokenizer;
+use tokenizers::utils::parallelism::get_parallelism;
+
+fn load_config(extra: &str) -> AppConfig {
+    let yaml = format!(
+        "server:\n  host: 127.0.0.1\n  port: 8080\n  shutdown_timeout: 30\n\
+         locales:\n  path: locales\n  default: zh\n\
+         models_cache_dir: models_cache\n\
+         models: {{}}\n\
+         chat:\n  defaults:\n    temperature: 0.7\n    top_p: 0.9\n    n: 1\n    max_tokens: 16\n    stream: false\n{}",
+        extra
+    );
+    let path = std::env::temp_dir().join(format!("app-{}.yml", uuid::Uuid::new_v4()));
+    std::fs::write(&path, yaml).unwrap();
+    let config = AppConfig::load(path.to_str().unwrap());
+    std::fs::remove_file(&path).unwrap();
+    config.unwrap()
+}
+
+#[test]
+fn test_parallelism_defaults_to_enabled() {
+    assert!(load_config("").tokenizer.parallelism);
+}
+
+#[test]
+fn test_configured_parallelism_is_applied_before_encoding() {
+    let config = load_config("tokenizer:\n  parallelism: false\n");
+
+    apply_tokenizer_parallelism(&config.tokenizer);
+
+    assert!(!get_parallelism());
+    assert_eq!(std::env::var("TOKENIZERS_PARALLELISM").as_deref(), Ok("false"));
+    // 编码不会改变已设置的值
+    let tokenizer = word_level_tokenizer(&["hello", "world", "<unk>"]);
+    let encodings = tokenizer.encode_batch(vec!["hello world", "world hello"], false).unwrap();
+    assert_eq!(encodings[0].get_ids(), &[0, 1]);
+    assert!(!get_parallelism());
+}
@@ -0,0 +1,73 @@
+use coder_openapi::error::AppError;
+use coder_openapi::utils::weights::verify_safetensors_file;
+use std::path::PathBuf;
+
+/// 写入一个包含单个F32张量 (shape [2]) 的safetensors文件，数据区保留 `data_len` 字节
+fn write_safetensors(data_len: usize) -> PathBuf {
+    let header = br#"{"weight":{"dtype":"F32","shape":[2],"data_offsets":[0,8]}}"#;
+    let mut bytes = (header.len() as u64).to_le_bytes().to_vec();
+    bytes.extend_from_slice(header);
+    bytes.extend(std::iter::repeat_n(0u8, data_len));
+
+    let path = std::env::temp_dir().join(format!("weights-{}.safetensors", uuid::Uuid::new_v4()));
+    std::fs::write(&path, bytes).unwrap();
+    path
+}
+
+#[test]
+fn test_complete_file_passes() {
+    let path = write_safetensors(8);
+    let result = verify_safetensors_file(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(result.is_ok());
+}
+
+#[test]
+fn test_truncated_file_is_rejected() {
+    let path = write_safetensors(4);
+    let result = verify_safetensors_file(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    match result {
+        Err(AppError::SafeTensor(e)) => {
+            let message = e.to_string();
+            assert!(message.contains(&path.display().to_string()));
+            assert!(message.contains("weight"));
+        }
+        other => panic!("expected SafeTensor error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_truncated_header_is_rejected() {
+    let path = std::env::temp_dir().join(format!("weights-{}.safetensors", uuid::Uuid::new_v4()));
+    std::fs::write(&path, 1024u64.to_le_bytes()).unwrap();
+    let result = verify_safetensors_file(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    match result {
+        Err(AppError::SafeTensor(e)) => {
+            let message = e.to_string();
+            assert!(message.contains(&path.display().to_string()), "{}", message);
+            assert!(message.contains("InvalidHeaderLength"), "{}", message);
+        }
+        other => panic!("expected SafeTensor error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_short_file_error_names_the_file() {
+    let path = std::env::temp_dir().join(format!("weights-{}.safetensors", uuid::Uuid::new_v4()));
+    std::fs::write(&path, [0u8; 4]).unwrap();
+    let result = verify_safetensors_file(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    match result {
+        Err(AppError::SafeTensor(e)) => {
+            let message = e.to_string();
+            assert!(message.contains(&path.display().to_string()), "{}", message);
+            assert!(message.contains("HeaderTooSmall"), "{}", message);
+        }
+        other => panic!("expected SafeTensor error, got {:?}", other),
+    }
+}